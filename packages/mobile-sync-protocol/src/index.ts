@@ -50,6 +50,8 @@ export const PairingPayloadV1Schema = z.object({
   host: z.string().min(1),
   port: z.number().int().positive(),
   expiresAt: z.string(),
+  minSupportedVersion: z.number().int().positive().optional(),
+  maxSupportedVersion: z.number().int().positive().optional(),
 });
 
 export type SnapshotV1 = z.infer<typeof SnapshotV1Schema>;
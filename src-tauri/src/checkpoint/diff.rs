@@ -0,0 +1,167 @@
+//! Line diffs between two versions of a file, rendered as unified diffs.
+//!
+//! Uses the linear-space variant of Myers' algorithm, so memory stays proportional
+//! to the file sizes. The additions and deletions it reports are those of a shortest
+//! edit script and always match the `+`/`-` lines of the rendered hunks.
+
+use similar::{capture_diff_slices_deadline, Algorithm, DiffTag};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Lines of unchanged context kept around each change
+const CONTEXT_LINES: usize = 3;
+
+/// How long to search for a shortest edit script before settling for a longer one
+const DIFF_DEADLINE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A unified diff together with its line counts
+#[derive(Debug, Default)]
+pub struct UnifiedDiff {
+    /// The rendered diff; empty when the two versions have the same lines
+    pub text: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Diff `before` against `after`, labelling the file as `path`
+pub fn unified_diff(path: &Path, before: &str, after: &str) -> UnifiedDiff {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let lines = shortest_edit(&old, &new);
+
+    let mut additions = 0;
+    let mut deletions = 0;
+    // Group changed lines, with their context, into hunks of `lines` indices
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        match line {
+            DiffLine::Context(_) => continue,
+            DiffLine::Removed(_) => deletions += 1,
+            DiffLine::Added(_) => additions += 1,
+        }
+
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    if hunks.is_empty() {
+        return UnifiedDiff::default();
+    }
+
+    let path = path.display();
+    let mut text = format!("--- a/{}\n+++ b/{}\n", path, path);
+    for (start, end) in hunks {
+        // Lines of each version that come before the hunk
+        let old_before = lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_before = lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+
+        text.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_len),
+            hunk_range(new_before, new_len)
+        ));
+        for line in hunk {
+            let (prefix, content) = match line {
+                DiffLine::Context(content) => (' ', content),
+                DiffLine::Removed(content) => ('-', content),
+                DiffLine::Added(content) => ('+', content),
+            };
+            text.push(prefix);
+            text.push_str(content);
+            text.push('\n');
+        }
+    }
+
+    UnifiedDiff {
+        text,
+        additions,
+        deletions,
+    }
+}
+
+/// A hunk header range. An empty range names the line before it, as `diff -u` does.
+fn hunk_range(lines_before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", lines_before),
+        1 => format!("{}", lines_before + 1),
+        _ => format!("{},{}", lines_before + 1, len),
+    }
+}
+
+/// Myers' shortest edit script from `old` to `new`
+fn shortest_edit<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let deadline = Instant::now() + DIFF_DEADLINE;
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    for op in capture_diff_slices_deadline(Algorithm::Myers, old, new, Some(deadline)) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let (old_lines, new_lines) = (&old[old_range], &new[new_range]);
+        let removed = old_lines.iter().copied().map(DiffLine::Removed);
+        let added = new_lines.iter().copied().map(DiffLine::Added);
+        match tag {
+            DiffTag::Equal => lines.extend(old_lines.iter().copied().map(DiffLine::Context)),
+            DiffTag::Delete => lines.extend(removed),
+            DiffTag::Insert => lines.extend(added),
+            DiffTag::Replace => lines.extend(removed.chain(added)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::FileDiff;
+    use std::path::PathBuf;
+
+    #[test]
+    fn file_diff_renders_unified_hunks_for_modified_snapshots() {
+        let before = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n\nfn helper() {}\nfn other() {}\nfn last() {}\nfn tail() {}\nfn end() {}\n";
+        let after = "fn main() {\n    let a = 1;\n    let b = 3;\n    println!(\"{}\", a + b);\n}\n\nfn helper() {}\nfn other() {}\nfn last() {}\nfn tail() {}\nfn end() {}\nfn added() {}\n";
+
+        let diff = FileDiff::between(PathBuf::from("src/main.rs"), before, after);
+        let text = diff.diff_content.unwrap();
+
+        assert_eq!(
+            text,
+            "--- a/src/main.rs\n+++ b/src/main.rs\n\
+             @@ -1,6 +1,6 @@\n fn main() {\n     let a = 1;\n-    let b = 2;\n+    let b = 3;\n     println!(\"{}\", a + b);\n }\n \n\
+             @@ -9,3 +9,4 @@\n fn last() {}\n fn tail() {}\n fn end() {}\n+fn added() {}\n"
+        );
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(text.lines().filter(|l| l.starts_with("@@")).count(), 2);
+    }
+
+    #[test]
+    fn identical_lines_have_no_diff_content() {
+        let diff = FileDiff::between(PathBuf::from("a.txt"), "same\n", "same");
+
+        assert!(diff.diff_content.is_none());
+        assert_eq!((diff.additions, diff.deletions), (0, 0));
+    }
+}
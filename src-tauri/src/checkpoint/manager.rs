@@ -2,16 +2,81 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use super::{
     storage::{self, CheckpointStorage},
-    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
-    FileSnapshot, FileState, FileTracker, SessionTimeline,
+    Checkpoint, CheckpointControl, CheckpointDiff, CheckpointMetadata, CheckpointPaths,
+    CheckpointResult, CheckpointStrategy, FileDiff, FileSnapshot, FileState, FileTracker,
+    SessionTimeline,
 };
 
+/// `from_checkpoint_id` of the diff a restore reports
+pub const WORKING_TREE_ID: &str = "working-tree";
+
+/// Diff from the working tree as it was before a restore to the restored checkpoint's
+/// files. `before` holds every pre-restore file by relative path, with its content
+/// only when the restore overwrote it with something different.
+fn restore_diff(
+    checkpoint_id: &str,
+    before: &HashMap<PathBuf, Option<String>>,
+    snapshots: &[FileSnapshot],
+) -> CheckpointDiff {
+    let mut modified_files = Vec::new();
+    let mut added_files = Vec::new();
+    let mut restored = std::collections::HashSet::new();
+
+    for snapshot in snapshots.iter().filter(|snapshot| !snapshot.is_deleted) {
+        restored.insert(&snapshot.file_path);
+        match before.get(&snapshot.file_path) {
+            Some(Some(content)) => modified_files.push(FileDiff::between(
+                snapshot.file_path.clone(),
+                content,
+                &snapshot.content,
+            )),
+            Some(None) => {}
+            None => added_files.push(snapshot.file_path.clone()),
+        }
+    }
+    let mut deleted_files: Vec<PathBuf> = before
+        .keys()
+        .filter(|path| !restored.contains(path))
+        .cloned()
+        .collect();
+
+    modified_files.sort_by(|a, b| a.path.cmp(&b.path));
+    added_files.sort();
+    deleted_files.sort();
+    CheckpointDiff {
+        from_checkpoint_id: WORKING_TREE_ID.to_string(),
+        to_checkpoint_id: checkpoint_id.to_string(),
+        modified_files,
+        added_files,
+        deleted_files,
+        token_delta: 0,
+    }
+}
+
+/// Reject exclude globs that don't parse, before they're saved
+fn validate_exclude_globs(globs: &[String]) -> Result<()> {
+    for pattern in globs {
+        glob::Pattern::new(pattern)
+            .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+    }
+    Ok(())
+}
+
+/// Whether a project-relative path, or any directory containing it, matches an
+/// exclude pattern, so `target` excludes everything under `target/`
+fn is_excluded(patterns: &[glob::Pattern], rel_path: &Path) -> bool {
+    rel_path
+        .ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .any(|path| patterns.iter().any(|pattern| pattern.matches_path(path)))
+}
+
 /// Manages checkpoint operations for a session
 pub struct CheckpointManager {
     project_id: String,
@@ -21,6 +86,9 @@ pub struct CheckpointManager {
     pub storage: Arc<CheckpointStorage>,
     timeline: Arc<RwLock<SessionTimeline>>,
     current_messages: Arc<RwLock<Vec<String>>>, // JSONL messages
+    /// When this manager last saved a checkpoint, or first saw activity; drives
+    /// the `time_interval` strategy
+    last_checkpoint_at: Arc<RwLock<Option<DateTime<Utc>>>>,
 }
 
 impl CheckpointManager {
@@ -56,9 +124,35 @@ impl CheckpointManager {
             storage,
             timeline: Arc::new(RwLock::new(timeline)),
             current_messages: Arc::new(RwLock::new(Vec::new())),
+            last_checkpoint_at: Arc::new(RwLock::new(None)),
         })
     }
 
+    /// Re-read the timeline after it was changed on disk, e.g. by an imported bundle
+    pub async fn reload_timeline(&self) -> Result<()> {
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        let timeline = self.storage.load_timeline(&paths.timeline_file)?;
+        *self.timeline.write().await = timeline;
+        Ok(())
+    }
+
+    /// The session's exclude patterns; invalid ones are skipped
+    async fn exclude_patterns(&self) -> Vec<glob::Pattern> {
+        let timeline = self.timeline.read().await;
+        timeline
+            .exclude_globs
+            .iter()
+            .filter_map(|pattern| match glob::Pattern::new(pattern.trim()) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid checkpoint exclude {:?}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Track a new message in the session
     pub async fn track_message(&self, jsonl_message: String) -> Result<()> {
         let mut messages = self.current_messages.write().await;
@@ -188,6 +282,22 @@ impl CheckpointManager {
         &self,
         description: Option<String>,
         parent_checkpoint_id: Option<String>,
+    ) -> Result<CheckpointResult> {
+        self.create_checkpoint_with(
+            description,
+            parent_checkpoint_id,
+            CheckpointControl::default(),
+        )
+        .await
+    }
+
+    /// Create a checkpoint that reports progress and can be cancelled through `control`.
+    /// A cancelled checkpoint fails with `CheckpointCancelled` and leaves nothing saved.
+    pub async fn create_checkpoint_with(
+        &self,
+        description: Option<String>,
+        parent_checkpoint_id: Option<String>,
+        control: CheckpointControl<'_>,
     ) -> Result<CheckpointResult> {
         let messages = self.current_messages.read().await;
         let message_index = messages.len().saturating_sub(1);
@@ -226,7 +336,10 @@ impl CheckpointManager {
         let mut all_files = Vec::new();
         let project_dir = &self.project_path;
         let _ = collect_files(project_dir.as_path(), project_dir.as_path(), &mut all_files);
+        let excludes = self.exclude_patterns().await;
+        all_files.retain(|rel| !is_excluded(&excludes, rel));
         for rel in all_files {
+            control.check()?;
             if let Some(p) = rel.to_str() {
                 // Track each file for snapshot
                 let _ = self.track_file_modification(p).await;
@@ -237,7 +350,7 @@ impl CheckpointManager {
         let checkpoint_id = storage::CheckpointStorage::generate_checkpoint_id();
 
         // Create file snapshots
-        let file_snapshots = self.create_file_snapshots(&checkpoint_id).await?;
+        let file_snapshots = self.create_file_snapshots(&checkpoint_id, control).await?;
 
         // Generate checkpoint struct
         let checkpoint = Checkpoint {
@@ -269,6 +382,7 @@ impl CheckpointManager {
         };
 
         // Save checkpoint
+        control.check()?;
         let messages_content = messages.join("\n");
         let result = self.storage.save_checkpoint(
             &self.project_id,
@@ -276,6 +390,7 @@ impl CheckpointManager {
             &checkpoint,
             file_snapshots,
             &messages_content,
+            control,
         )?;
 
         // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
@@ -291,6 +406,8 @@ impl CheckpointManager {
         let mut timeline = self.timeline.write().await;
         timeline.current_checkpoint_id = Some(checkpoint_id);
 
+        *self.last_checkpoint_at.write().await = Some(Utc::now());
+
         // Reset file tracker
         let mut tracker = self.file_tracker.write().await;
         for (_, state) in tracker.tracked_files.iter_mut() {
@@ -397,15 +514,26 @@ impl CheckpointManager {
     }
 
     /// Create file snapshots for all tracked modified files
-    async fn create_file_snapshots(&self, checkpoint_id: &str) -> Result<Vec<FileSnapshot>> {
+    async fn create_file_snapshots(
+        &self,
+        checkpoint_id: &str,
+        control: CheckpointControl<'_>,
+    ) -> Result<Vec<FileSnapshot>> {
+        let excludes = self.exclude_patterns().await;
         let tracker = self.file_tracker.read().await;
         let mut snapshots = Vec::new();
+        let total_files = tracker
+            .tracked_files
+            .iter()
+            .filter(|(rel_path, state)| state.is_modified && !is_excluded(&excludes, rel_path))
+            .count();
 
         for (rel_path, state) in &tracker.tracked_files {
-            // Skip files that haven't been modified
-            if !state.is_modified {
+            // Skip files that haven't been modified, and excluded ones even if a tool edited them
+            if !state.is_modified || is_excluded(&excludes, rel_path) {
                 continue;
             }
+            control.check()?;
 
             let full_path = self.project_path.join(rel_path);
 
@@ -442,6 +570,7 @@ impl CheckpointManager {
                 permissions,
                 size,
             });
+            control.progress(snapshots.len(), total_files);
         }
 
         Ok(snapshots)
@@ -450,10 +579,14 @@ impl CheckpointManager {
     /// Restore a checkpoint
     pub async fn restore_checkpoint(&self, checkpoint_id: &str) -> Result<CheckpointResult> {
         // Load checkpoint data
-        let (checkpoint, file_snapshots, messages) =
+        let (checkpoint, mut file_snapshots, messages) =
             self.storage
                 .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
 
+        // Excluded paths are left alone, even if an older checkpoint captured them
+        let excludes = self.exclude_patterns().await;
+        file_snapshots.retain(|snapshot| !is_excluded(&excludes, &snapshot.file_path));
+
         // First, collect all files currently in the project to handle deletions
         fn collect_all_project_files(
             dir: &std::path::Path,
@@ -484,6 +617,27 @@ impl CheckpointManager {
         let mut current_files = Vec::new();
         let _ =
             collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
+        current_files.retain(|rel| !is_excluded(&excludes, rel));
+
+        // Remember the working tree so the result can say what the restore changed.
+        // Only files it overwrites with different content are kept in memory.
+        let restored_hashes: HashMap<&PathBuf, &str> = file_snapshots
+            .iter()
+            .filter(|snapshot| !snapshot.is_deleted)
+            .map(|snapshot| (&snapshot.file_path, snapshot.hash.as_str()))
+            .collect();
+        let before: HashMap<PathBuf, Option<String>> = current_files
+            .iter()
+            .map(|rel| {
+                let content = restored_hashes.get(rel).and_then(|hash| {
+                    let bytes = fs::read(self.project_path.join(rel)).ok()?;
+                    let content = String::from_utf8_lossy(&bytes).into_owned();
+                    (storage::CheckpointStorage::calculate_file_hash(&content) != *hash)
+                        .then_some(content)
+                });
+                (rel.clone(), content)
+            })
+            .collect();
 
         // Create a set of files that should exist after restore
         let mut checkpoint_files = std::collections::HashSet::new();
@@ -594,6 +748,7 @@ impl CheckpointManager {
             checkpoint: checkpoint.clone(),
             files_processed,
             warnings,
+            diff: Some(restore_diff(checkpoint_id, &before, &file_snapshots)),
         })
     }
 
@@ -688,6 +843,22 @@ impl CheckpointManager {
 
         match timeline.checkpoint_strategy {
             CheckpointStrategy::Manual => false,
+            CheckpointStrategy::TimeInterval => {
+                let Some(interval_secs) = timeline.checkpoint_interval_secs.filter(|s| *s > 0)
+                else {
+                    return false;
+                };
+                let now = Utc::now();
+                let mut last_checkpoint_at = self.last_checkpoint_at.write().await;
+                match *last_checkpoint_at {
+                    Some(last) => now - last >= chrono::Duration::seconds(interval_secs as i64),
+                    None => {
+                        // The clock starts with the first activity this manager sees
+                        *last_checkpoint_at = Some(now);
+                        false
+                    }
+                }
+            }
             CheckpointStrategy::PerPrompt => {
                 // Check if message is a user prompt
                 if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
@@ -749,10 +920,34 @@ impl CheckpointManager {
         &self,
         auto_checkpoint_enabled: bool,
         checkpoint_strategy: CheckpointStrategy,
+        checkpoint_interval_secs: Option<u64>,
+        exclude_globs: Option<Vec<String>>,
     ) -> Result<()> {
+        let checkpoint_interval_secs = checkpoint_interval_secs.filter(|secs| *secs > 0);
+        if matches!(checkpoint_strategy, CheckpointStrategy::TimeInterval)
+            && checkpoint_interval_secs.is_none()
+        {
+            anyhow::bail!("The time_interval strategy needs a positive interval_seconds");
+        }
+
+        let exclude_globs: Option<Vec<String>> = exclude_globs.map(|globs| {
+            globs
+                .into_iter()
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect()
+        });
+        if let Some(exclude_globs) = &exclude_globs {
+            validate_exclude_globs(exclude_globs)?;
+        }
+
         let mut timeline = self.timeline.write().await;
         timeline.auto_checkpoint_enabled = auto_checkpoint_enabled;
         timeline.checkpoint_strategy = checkpoint_strategy;
+        timeline.checkpoint_interval_secs = checkpoint_interval_secs;
+        if let Some(exclude_globs) = exclude_globs {
+            timeline.exclude_globs = exclude_globs;
+        }
 
         // Save updated timeline
         let claude_dir = self.storage.claude_dir.clone();
@@ -784,3 +979,213 @@ impl CheckpointManager {
             .max()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn restore_reports_what_it_changed_on_disk() {
+        let claude_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        fs::write(project.path().join("kept.txt"), "same\n").unwrap();
+        fs::write(project.path().join("edited.txt"), "one\ntwo\n").unwrap();
+        fs::write(project.path().join("removed.txt"), "gone soon\n").unwrap();
+
+        let manager = CheckpointManager::new(
+            "project".to_string(),
+            "session".to_string(),
+            project.path().to_path_buf(),
+            claude_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        let created = manager.create_checkpoint(None, None).await.unwrap();
+        assert!(created.diff.is_none());
+
+        fs::write(project.path().join("edited.txt"), "one\nTWO\nthree\n").unwrap();
+        fs::remove_file(project.path().join("removed.txt")).unwrap();
+        fs::write(project.path().join("new.txt"), "scratch\n").unwrap();
+
+        let restored = manager
+            .restore_checkpoint(&created.checkpoint.id)
+            .await
+            .unwrap();
+        let diff = restored.diff.unwrap();
+        assert_eq!(diff.from_checkpoint_id, WORKING_TREE_ID);
+        assert_eq!(diff.to_checkpoint_id, created.checkpoint.id);
+        assert_eq!(diff.added_files, vec![PathBuf::from("removed.txt")]);
+        assert_eq!(diff.deleted_files, vec![PathBuf::from("new.txt")]);
+        assert_eq!(diff.modified_files.len(), 1);
+        assert_eq!(diff.modified_files[0].path, PathBuf::from("edited.txt"));
+        assert_eq!(diff.modified_files[0].additions, 1);
+        assert_eq!(diff.modified_files[0].deletions, 2);
+        assert_eq!(
+            fs::read_to_string(project.path().join("edited.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn excluded_paths_are_not_snapshotted_or_restored() {
+        let claude_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        fs::create_dir_all(project.path().join("target/debug")).unwrap();
+        fs::write(project.path().join("target/debug/app"), "binary v1").unwrap();
+        fs::write(project.path().join("build.log"), "log v1").unwrap();
+        fs::write(project.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let manager = CheckpointManager::new(
+            "project".to_string(),
+            "session".to_string(),
+            project.path().to_path_buf(),
+            claude_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+        assert!(manager
+            .update_settings(
+                true,
+                CheckpointStrategy::PerPrompt,
+                None,
+                Some(vec!["[".to_string()])
+            )
+            .await
+            .is_err());
+        assert!(!manager.get_timeline().await.auto_checkpoint_enabled);
+        manager
+            .update_settings(
+                false,
+                CheckpointStrategy::Manual,
+                None,
+                Some(vec!["target".to_string(), " *.log ".to_string()]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_timeline().await.exclude_globs,
+            vec!["target", "*.log"]
+        );
+
+        let created = manager.create_checkpoint(None, None).await.unwrap();
+        let (_, snapshots, _) = manager
+            .storage
+            .load_checkpoint("project", "session", &created.checkpoint.id)
+            .unwrap();
+        let snapshotted: Vec<&PathBuf> = snapshots.iter().map(|s| &s.file_path).collect();
+        assert_eq!(snapshotted, vec![&PathBuf::from("main.rs")]);
+
+        fs::write(project.path().join("target/debug/app"), "binary v2").unwrap();
+        fs::write(project.path().join("build.log"), "log v2").unwrap();
+        fs::write(project.path().join("main.rs"), "fn main() { edited }").unwrap();
+        let restored = manager
+            .restore_checkpoint(&created.checkpoint.id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(project.path().join("main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert_eq!(
+            fs::read_to_string(project.path().join("target/debug/app")).unwrap(),
+            "binary v2"
+        );
+        assert_eq!(
+            fs::read_to_string(project.path().join("build.log")).unwrap(),
+            "log v2"
+        );
+        let diff = restored.diff.unwrap();
+        assert!(diff.deleted_files.is_empty());
+        assert_eq!(diff.modified_files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn time_interval_checkpoints_once_the_interval_passes() {
+        let claude_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        fs::write(project.path().join("main.rs"), "fn main() {}").unwrap();
+        let manager = CheckpointManager::new(
+            "project".to_string(),
+            "session".to_string(),
+            project.path().to_path_buf(),
+            claude_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        for interval in [None, Some(0)] {
+            assert!(manager
+                .update_settings(true, CheckpointStrategy::TimeInterval, interval, None)
+                .await
+                .is_err());
+        }
+        manager
+            .update_settings(true, CheckpointStrategy::TimeInterval, Some(60), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_timeline().await.checkpoint_interval_secs,
+            Some(60)
+        );
+
+        let message = r#"{"type":"assistant"}"#;
+        // The first activity starts the clock
+        assert!(!manager.should_auto_checkpoint(message).await);
+        assert!(!manager.should_auto_checkpoint(message).await);
+
+        *manager.last_checkpoint_at.write().await =
+            Some(Utc::now() - chrono::Duration::seconds(61));
+        assert!(manager.should_auto_checkpoint(message).await);
+
+        manager.create_checkpoint(None, None).await.unwrap();
+        assert!(!manager.should_auto_checkpoint(message).await);
+    }
+
+    #[tokio::test]
+    async fn cancelled_checkpoint_leaves_nothing_behind() {
+        let claude_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        for i in 0..5 {
+            fs::write(project.path().join(format!("file{}.txt", i)), i.to_string()).unwrap();
+        }
+        let manager = CheckpointManager::new(
+            "project".to_string(),
+            "session".to_string(),
+            project.path().to_path_buf(),
+            claude_dir.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+
+        // Cancel once the second file has been snapshotted
+        let cancelled = std::sync::atomic::AtomicBool::new(false);
+        let progress = std::sync::Mutex::new(Vec::new());
+        let on_progress = |done: usize, total: usize| {
+            progress.lock().unwrap().push((done, total));
+            if done == 2 {
+                cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        };
+        let control = CheckpointControl {
+            cancelled: Some(&cancelled),
+            on_progress: Some(&on_progress),
+        };
+
+        let error = manager
+            .create_checkpoint_with(None, None, control)
+            .await
+            .unwrap_err();
+        assert!(error.is::<crate::checkpoint::CheckpointCancelled>());
+        assert_eq!(*progress.lock().unwrap(), vec![(1, 5), (2, 5)]);
+
+        let paths = CheckpointPaths::new(&claude_dir.path().to_path_buf(), "project", "session");
+        assert_eq!(fs::read_dir(&paths.checkpoints_dir).unwrap().count(), 0);
+        assert!(manager.list_checkpoints().await.is_empty());
+
+        // The next uncancelled checkpoint still snapshots every file
+        let created = manager.create_checkpoint(None, None).await.unwrap();
+        assert_eq!(created.files_processed, 5);
+    }
+}
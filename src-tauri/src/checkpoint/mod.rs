@@ -1,8 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+pub mod diff;
 pub mod manager;
 pub mod state;
 pub mod storage;
@@ -91,6 +94,13 @@ pub struct SessionTimeline {
     pub auto_checkpoint_enabled: bool,
     /// Strategy for automatic checkpoints
     pub checkpoint_strategy: CheckpointStrategy,
+    /// Seconds between checkpoints for the `time_interval` strategy
+    #[serde(default)]
+    pub checkpoint_interval_secs: Option<u64>,
+    /// Glob patterns (relative to the project root) of paths checkpoints never
+    /// snapshot or restore, e.g. `target` or `*.log`
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
     /// Total number of checkpoints in timeline
     pub total_checkpoints: usize,
 }
@@ -107,6 +117,8 @@ pub enum CheckpointStrategy {
     PerToolUse,
     /// Create checkpoint after destructive operations
     Smart,
+    /// Create checkpoint once `checkpoint_interval_secs` have passed since the last one
+    TimeInterval,
 }
 
 /// Tracks the state of files for checkpointing
@@ -138,6 +150,48 @@ pub struct CheckpointResult {
     pub files_processed: usize,
     /// Any warnings during the operation
     pub warnings: Vec<String>,
+    /// What a restore changed on disk, from the pre-restore working tree to the checkpoint
+    #[serde(default)]
+    pub diff: Option<CheckpointDiff>,
+}
+
+/// Error (through `anyhow`) for a checkpoint cancelled before it was saved
+#[derive(Debug)]
+pub struct CheckpointCancelled;
+
+impl fmt::Display for CheckpointCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Checkpoint cancelled")
+    }
+}
+
+impl std::error::Error for CheckpointCancelled {}
+
+/// Optional hooks for a long checkpoint: a cancellation flag checked per file and a
+/// callback invoked after each snapshotted file with (files_snapshotted, total_files)
+#[derive(Default, Clone, Copy)]
+pub struct CheckpointControl<'a> {
+    pub cancelled: Option<&'a AtomicBool>,
+    pub on_progress: Option<&'a (dyn Fn(usize, usize) + Send + Sync)>,
+}
+
+impl CheckpointControl<'_> {
+    /// Fail with `CheckpointCancelled` once the flag is set
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self
+            .cancelled
+            .is_some_and(|flag| flag.load(Ordering::SeqCst))
+        {
+            return Err(CheckpointCancelled.into());
+        }
+        Ok(())
+    }
+
+    fn progress(&self, files_snapshotted: usize, total_files: usize) {
+        if let Some(on_progress) = self.on_progress {
+            on_progress(files_snapshotted, total_files);
+        }
+    }
 }
 
 /// Diff between two checkpoints
@@ -170,6 +224,21 @@ pub struct FileDiff {
     pub diff_content: Option<String>,
 }
 
+impl FileDiff {
+    /// Unified diff between two versions of a file, with line counts taken from
+    /// its hunks
+    pub fn between(path: PathBuf, before: &str, after: &str) -> Self {
+        let diff = diff::unified_diff(&path, before, after);
+
+        Self {
+            path,
+            additions: diff.additions,
+            deletions: diff.deletions,
+            diff_content: (!diff.text.is_empty()).then_some(diff.text),
+        }
+    }
+}
+
 impl Default for CheckpointStrategy {
     fn default() -> Self {
         CheckpointStrategy::Smart
@@ -185,6 +254,8 @@ impl SessionTimeline {
             current_checkpoint_id: None,
             auto_checkpoint_enabled: false,
             checkpoint_strategy: CheckpointStrategy::default(),
+            checkpoint_interval_secs: None,
+            exclude_globs: Vec::new(),
             total_checkpoints: 0,
         }
     }
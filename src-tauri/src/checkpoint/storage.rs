@@ -1,14 +1,36 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
 use uuid::Uuid;
-use zstd::stream::{decode_all, encode_all};
+use zstd::stream::{decode_all, encode_all, Decoder};
 
 use super::{
-    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
+    Checkpoint, CheckpointControl, CheckpointPaths, CheckpointResult, FileSnapshot,
+    SessionTimeline, TimelineNode,
 };
 
+/// First line of every exported checkpoint bundle, followed by `/` and the version
+const CHECKPOINT_BUNDLE_MAGIC: &str = "opcode-checkpoint-bundle";
+
+/// Checkpoint bundle version written by export and accepted by import
+pub const CHECKPOINT_BUNDLE_VERSION: u32 = 1;
+
+/// Largest checkpoint bundle accepted for import, both as a file and once decompressed
+pub const MAX_CHECKPOINT_BUNDLE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Everything needed to recreate a checkpoint: its metadata, file snapshots and the
+/// messages up to it. Stored zstd-compressed after the bundle header line.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckpointBundle {
+    checkpoint: Checkpoint,
+    file_snapshots: Vec<FileSnapshot>,
+    messages: String,
+}
+
 /// Manages checkpoint storage operations
 pub struct CheckpointStorage {
     pub claude_dir: PathBuf,
@@ -42,7 +64,8 @@ impl CheckpointStorage {
         Ok(())
     }
 
-    /// Save a checkpoint to disk
+    /// Save a checkpoint to disk. A cancellation through `control` removes whatever
+    /// was already written, so the checkpoint never appears half-saved.
     pub fn save_checkpoint(
         &self,
         project_id: &str,
@@ -50,6 +73,7 @@ impl CheckpointStorage {
         checkpoint: &Checkpoint,
         file_snapshots: Vec<FileSnapshot>,
         messages: &str, // JSONL content up to checkpoint
+        control: CheckpointControl<'_>,
     ) -> Result<CheckpointResult> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
         let checkpoint_dir = paths.checkpoint_dir(&checkpoint.id);
@@ -75,6 +99,11 @@ impl CheckpointStorage {
         let mut files_processed = 0;
 
         for snapshot in &file_snapshots {
+            if let Err(cancelled) = control.check() {
+                self.remove_checkpoint(&paths, &checkpoint.id)?;
+                self.garbage_collect_content(project_id, session_id)?;
+                return Err(cancelled);
+            }
             match self.save_file_snapshot(&paths, snapshot) {
                 Ok(_) => files_processed += 1,
                 Err(e) => warnings.push(format!(
@@ -92,6 +121,7 @@ impl CheckpointStorage {
             checkpoint: checkpoint.clone(),
             files_processed,
             warnings,
+            diff: None,
         })
     }
 
@@ -377,6 +407,57 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 
+    /// Every checkpoint of every session in a project, oldest first. Each carries
+    /// its session id and snapshot size.
+    pub fn list_project_checkpoints(&self, project_id: &str) -> Result<Vec<Checkpoint>> {
+        // The id must name a single directory under `projects`
+        let mut components = Path::new(project_id).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            anyhow::bail!("Invalid project id: {}", project_id);
+        }
+
+        let timelines_dir = self
+            .claude_dir
+            .join("projects")
+            .join(project_id)
+            .join(".timelines");
+        if !timelines_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut checkpoints = Vec::new();
+        for entry in fs::read_dir(&timelines_dir).context("Failed to read timelines directory")? {
+            let session_dir = entry?;
+            let Some(session_id) = session_dir.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let paths = CheckpointPaths::new(&self.claude_dir, project_id, &session_id);
+            if !paths.timeline_file.exists() {
+                continue;
+            }
+            match self.load_timeline(&paths.timeline_file) {
+                Ok(timeline) => {
+                    if let Some(root) = &timeline.root_node {
+                        Self::collect_checkpoints(root, &mut checkpoints);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Skipping unreadable timeline for session {}: {}",
+                        session_id,
+                        e
+                    );
+                }
+            }
+        }
+
+        checkpoints.sort_by_key(|checkpoint| checkpoint.timestamp);
+        Ok(checkpoints)
+    }
+
     /// Collect all checkpoints from the tree in order
     fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
         checkpoints.push(node.checkpoint.clone());
@@ -385,6 +466,152 @@ impl CheckpointStorage {
         }
     }
 
+    /// Package a checkpoint into a single bundle that another machine can import
+    pub fn export_bundle(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<u8>> {
+        let (checkpoint, file_snapshots, messages) =
+            self.load_checkpoint(project_id, session_id, checkpoint_id)?;
+        let bundle = CheckpointBundle {
+            checkpoint,
+            file_snapshots,
+            messages,
+        };
+
+        let json = serde_json::to_vec(&bundle).context("Failed to serialize checkpoint bundle")?;
+        let mut bytes = format!(
+            "{}/{}\n",
+            CHECKPOINT_BUNDLE_MAGIC, CHECKPOINT_BUNDLE_VERSION
+        )
+        .into_bytes();
+        bytes.extend(
+            encode_all(&json[..], self.compression_level)
+                .context("Failed to compress checkpoint bundle")?,
+        );
+        Ok(bytes)
+    }
+
+    /// Save the checkpoint in an exported bundle as a new checkpoint of `session_id`,
+    /// on top of the session's current checkpoint
+    pub fn import_bundle(
+        &self,
+        bundle: &[u8],
+        project_id: &str,
+        session_id: &str,
+    ) -> Result<Checkpoint> {
+        let payload = Self::bundle_payload(bundle)?;
+        let json = Self::decompress_bundle_payload(payload, MAX_CHECKPOINT_BUNDLE_BYTES)?;
+        let CheckpointBundle {
+            mut checkpoint,
+            mut file_snapshots,
+            messages,
+        } = serde_json::from_slice(&json).context("Failed to parse checkpoint bundle")?;
+
+        for snapshot in &file_snapshots {
+            // Restores join these onto the project root, so anything that could
+            // escape it is refused before it reaches storage
+            let relative = snapshot
+                .file_path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+            if !relative || snapshot.file_path.as_os_str().is_empty() {
+                anyhow::bail!(
+                    "Checkpoint bundle has an unsafe file path: {}",
+                    snapshot.file_path.display()
+                );
+            }
+            if !snapshot.is_deleted && Self::calculate_file_hash(&snapshot.content) != snapshot.hash
+            {
+                anyhow::bail!(
+                    "Checkpoint bundle is corrupt: {} doesn't match its hash",
+                    snapshot.file_path.display()
+                );
+            }
+        }
+
+        self.init_storage(project_id, session_id)?;
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let timeline = self.load_timeline(&paths.timeline_file)?;
+
+        checkpoint.id = Self::generate_checkpoint_id();
+        checkpoint.session_id = session_id.to_string();
+        checkpoint.project_id = project_id.to_string();
+        checkpoint.parent_checkpoint_id = timeline.current_checkpoint_id;
+        for snapshot in &mut file_snapshots {
+            snapshot.checkpoint_id = checkpoint.id.clone();
+        }
+
+        let result = self.save_checkpoint(
+            project_id,
+            session_id,
+            &checkpoint,
+            file_snapshots,
+            &messages,
+            CheckpointControl::default(),
+        )?;
+        Ok(result.checkpoint)
+    }
+
+    /// Decompress a bundle payload, refusing to inflate it past `max_bytes`
+    fn decompress_bundle_payload(payload: &[u8], max_bytes: u64) -> Result<Vec<u8>> {
+        let mut json = Vec::new();
+        Decoder::new(payload)
+            .context("Failed to decompress checkpoint bundle")?
+            .take(max_bytes + 1)
+            .read_to_end(&mut json)
+            .context("Failed to decompress checkpoint bundle")?;
+        if json.len() as u64 > max_bytes {
+            anyhow::bail!(
+                "Checkpoint bundle is larger than {} bytes once decompressed",
+                max_bytes
+            );
+        }
+        Ok(json)
+    }
+
+    /// The compressed part of a bundle, once its header names a supported version
+    fn bundle_payload(bundle: &[u8]) -> Result<&[u8]> {
+        let header_end = bundle
+            .iter()
+            .take(64)
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| anyhow::anyhow!("Not a checkpoint bundle: missing header"))?;
+        let header = std::str::from_utf8(&bundle[..header_end])
+            .ok()
+            .and_then(|header| header.strip_prefix(CHECKPOINT_BUNDLE_MAGIC))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .ok_or_else(|| anyhow::anyhow!("Not a checkpoint bundle: unrecognized header"))?;
+        let version: u32 = header
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid checkpoint bundle version: {:?}", header))?;
+        if version != CHECKPOINT_BUNDLE_VERSION {
+            anyhow::bail!(
+                "Unsupported checkpoint bundle version {} (this build reads version {})",
+                version,
+                CHECKPOINT_BUNDLE_VERSION
+            );
+        }
+        Ok(&bundle[header_end + 1..])
+    }
+
+    /// Remove all checkpoint storage for a session: its timeline, checkpoints and
+    /// file snapshots. Returns the removed directory, or `None` if there was none.
+    pub fn delete_session(&self, project_id: &str, session_id: &str) -> Result<Option<PathBuf>> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let Some(session_dir) = paths.timeline_file.parent().map(Path::to_path_buf) else {
+            return Ok(None);
+        };
+        if !session_dir.exists() {
+            return Ok(None);
+        }
+
+        fs::remove_dir_all(&session_dir).context("Failed to remove session checkpoints")?;
+        Ok(Some(session_dir))
+    }
+
     /// Remove a checkpoint and its associated files
     fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<()> {
         // Remove checkpoint metadata directory
@@ -458,3 +685,228 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    fn save_test_checkpoint(storage: &CheckpointStorage, session_id: &str, minute: u32) -> String {
+        storage.init_storage("project", session_id).unwrap();
+        let checkpoint = Checkpoint {
+            id: CheckpointStorage::generate_checkpoint_id(),
+            session_id: session_id.to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, 12, minute, 0).unwrap(),
+            description: None,
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "sonnet".to_string(),
+                user_prompt: String::new(),
+                file_changes: 0,
+                snapshot_size: u64::from(minute) * 100,
+            },
+        };
+        storage
+            .save_checkpoint(
+                "project",
+                session_id,
+                &checkpoint,
+                Vec::new(),
+                "",
+                CheckpointControl::default(),
+            )
+            .unwrap();
+        checkpoint.id
+    }
+
+    #[test]
+    fn exported_bundles_import_into_another_session() {
+        let source_dir = TempDir::new().unwrap();
+        let source = CheckpointStorage::new(source_dir.path().to_path_buf());
+        source.init_storage("project", "source").unwrap();
+        let checkpoint_id = CheckpointStorage::generate_checkpoint_id();
+        let content = "fn main() {}\n".to_string();
+        let checkpoint = Checkpoint {
+            id: checkpoint_id.clone(),
+            session_id: "source".to_string(),
+            project_id: "project".to_string(),
+            message_index: 1,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(),
+            description: Some("before refactor".to_string()),
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 42,
+                model_used: "sonnet".to_string(),
+                user_prompt: "refactor".to_string(),
+                file_changes: 1,
+                snapshot_size: content.len() as u64,
+            },
+        };
+        let snapshot = FileSnapshot {
+            checkpoint_id: checkpoint_id.clone(),
+            file_path: PathBuf::from("src/main.rs"),
+            hash: CheckpointStorage::calculate_file_hash(&content),
+            size: content.len() as u64,
+            content,
+            is_deleted: false,
+            permissions: Some(0o644),
+        };
+        source
+            .save_checkpoint(
+                "project",
+                "source",
+                &checkpoint,
+                vec![snapshot],
+                "{\"type\":\"user\"}\n",
+                CheckpointControl::default(),
+            )
+            .unwrap();
+
+        let bundle = source
+            .export_bundle("project", "source", &checkpoint_id)
+            .unwrap();
+        assert!(bundle.starts_with(b"opcode-checkpoint-bundle/1\n"));
+
+        let target_dir = TempDir::new().unwrap();
+        let target = CheckpointStorage::new(target_dir.path().to_path_buf());
+        let existing_id = save_test_checkpoint(&target, "target", 0);
+        let imported = target.import_bundle(&bundle, "project", "target").unwrap();
+        assert_ne!(imported.id, checkpoint_id);
+        assert_eq!(imported.session_id, "target");
+        assert_eq!(
+            imported.parent_checkpoint_id.as_deref(),
+            Some(existing_id.as_str())
+        );
+        assert_eq!(imported.description.as_deref(), Some("before refactor"));
+
+        let (_, files, messages) = target
+            .load_checkpoint("project", "target", &imported.id)
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].content, "fn main() {}\n");
+        assert_eq!(messages, "{\"type\":\"user\"}\n");
+
+        let mut future = b"opcode-checkpoint-bundle/2\n".to_vec();
+        future.extend_from_slice(&bundle[b"opcode-checkpoint-bundle/1\n".len()..]);
+        let err = target
+            .import_bundle(&future, "project", "target")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported checkpoint bundle version 2"));
+        assert!(target
+            .import_bundle(b"PK\x03\x04", "project", "target")
+            .is_err());
+    }
+
+    #[test]
+    fn bundle_payloads_are_decompressed_up_to_the_limit() {
+        let payload = encode_all(&[b'x'; 4096][..], 0).unwrap();
+
+        let json = CheckpointStorage::decompress_bundle_payload(&payload, 4096).unwrap();
+        assert_eq!(json.len(), 4096);
+        let err = CheckpointStorage::decompress_bundle_payload(&payload, 4095).unwrap_err();
+        assert!(err.to_string().contains("larger than 4095 bytes"));
+    }
+
+    #[test]
+    fn bundles_with_paths_outside_the_project_are_rejected() {
+        let claude_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(claude_dir.path().to_path_buf());
+        let checkpoint_id = save_test_checkpoint(&storage, "source", 0);
+        let (checkpoint, _, _) = storage
+            .load_checkpoint("project", "source", &checkpoint_id)
+            .unwrap();
+
+        for path in ["../../.bashrc", "/etc/passwd", "src/../../outside.rs"] {
+            let content = "echo pwned\n".to_string();
+            let bundle = CheckpointBundle {
+                checkpoint: checkpoint.clone(),
+                file_snapshots: vec![FileSnapshot {
+                    checkpoint_id: checkpoint_id.clone(),
+                    file_path: PathBuf::from(path),
+                    hash: CheckpointStorage::calculate_file_hash(&content),
+                    size: content.len() as u64,
+                    content,
+                    is_deleted: false,
+                    permissions: None,
+                }],
+                messages: String::new(),
+            };
+            let json = serde_json::to_vec(&bundle).unwrap();
+            let mut bytes = format!(
+                "{}/{}\n",
+                CHECKPOINT_BUNDLE_MAGIC, CHECKPOINT_BUNDLE_VERSION
+            )
+            .into_bytes();
+            bytes.extend(encode_all(json.as_slice(), 0).unwrap());
+
+            let err = storage
+                .import_bundle(&bytes, "project", "target")
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("unsafe file path"),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn project_checkpoints_span_every_session() {
+        let claude_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(claude_dir.path().to_path_buf());
+        assert!(storage
+            .list_project_checkpoints("project")
+            .unwrap()
+            .is_empty());
+
+        let second = save_test_checkpoint(&storage, "session-a", 2);
+        let first = save_test_checkpoint(&storage, "session-b", 1);
+        // A session with no checkpoints yet contributes nothing
+        storage.init_storage("project", "session-c").unwrap();
+
+        let listed = storage.list_project_checkpoints("project").unwrap();
+        let summary: Vec<(&str, &str, u64)> = listed
+            .iter()
+            .map(|c| {
+                (
+                    c.id.as_str(),
+                    c.session_id.as_str(),
+                    c.metadata.snapshot_size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (first.as_str(), "session-b", 100),
+                (second.as_str(), "session-a", 200)
+            ]
+        );
+        assert!(storage
+            .list_project_checkpoints("other")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn project_checkpoints_reject_ids_outside_projects() {
+        let claude_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(claude_dir.path().to_path_buf());
+
+        for project_id in ["", "..", ".", "a/b", "/etc"] {
+            assert!(
+                storage.list_project_checkpoints(project_id).is_err(),
+                "{:?} should be rejected",
+                project_id
+            );
+        }
+    }
+}
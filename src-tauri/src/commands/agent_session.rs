@@ -4,7 +4,8 @@ use tauri::{AppHandle, Emitter, Manager};
 use tokio::process::Command;
 
 use crate::providers::runtime::{
-    self, ProviderCapability, ProviderCommandKind, ProviderCommandRequest, ProviderStreamAdapter,
+    self, ProviderCapabilitiesDocument, ProviderCapability, ProviderCommandKind,
+    ProviderCommandRequest, ProviderStreamAdapter, SupportedProvider,
 };
 
 #[tauri::command]
@@ -12,6 +13,20 @@ pub fn list_provider_capabilities() -> Result<Vec<ProviderCapability>, String> {
     Ok(runtime::list_provider_capabilities())
 }
 
+/// Every supported provider with its install command and auth requirements,
+/// for onboarding
+#[tauri::command]
+pub fn list_supported_providers() -> Result<Vec<SupportedProvider>, String> {
+    Ok(runtime::list_supported_providers())
+}
+
+/// Provider capabilities with models, reasoning efforts and auth env vars,
+/// as one versioned document for the UI and external scripts
+#[tauri::command]
+pub fn export_provider_capabilities_schema() -> Result<ProviderCapabilitiesDocument, String> {
+    Ok(runtime::provider_capabilities_document())
+}
+
 /// Execute a new session with any detected CLI agent.
 ///
 /// For Claude, this delegates to provider-session runtime logic.
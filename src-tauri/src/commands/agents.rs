@@ -5,16 +5,46 @@ use reqwest;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::env;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
+use crate::error::OpcodeError;
+use crate::process::output_lines::CappedLines;
+use crate::process::rate_limit::{self, RateLimitSignal};
+use crate::process::sandbox::{self, SandboxPolicy, SandboxedCommand};
 // Sidecar support removed; using system binary execution only
-use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
+use tokio::io::BufReader as TokioBufReader;
 use tokio::process::Command;
 
+/// Exit code recorded for runs killed because they never produced output
+pub const TIMEOUT_EXIT_CODE: i32 = -1;
+
+/// Status of a run waiting for an execution slot; it has no process yet
+pub const QUEUED_RUN_STATUS: &str = "queued";
+/// Setting key (in `app_settings`) capping how many agent runs execute at once
+pub const MAX_CONCURRENT_RUNS_SETTING: &str = "max_concurrent_agent_runs";
+/// Concurrency cap when the setting is unset or invalid
+pub const DEFAULT_MAX_CONCURRENT_RUNS: usize = 5;
+/// Setting key (in `app_settings`) enabling a checkpoint after each successful run
+pub const CHECKPOINT_AFTER_RUNS_SETTING: &str = "checkpoint_after_agent_runs";
+/// Setting key (in `app_settings`) for a URL to POST to whenever an agent run finishes
+pub const COMPLETION_WEBHOOK_SETTING: &str = "completion_webhook_url";
+/// Completion webhooks are best-effort, so a slow receiver is abandoned quickly
+const COMPLETION_WEBHOOK_TIMEOUT_SECS: u64 = 5;
+
+/// First-output timeout for agents that don't configure one
+pub const DEFAULT_FIRST_OUTPUT_TIMEOUT_SECS: i64 = 30;
+/// How often the monitor checks whether a run has produced output
+const FIRST_OUTPUT_POLL_INTERVAL_MS: u64 = 100;
+
+fn default_first_output_timeout_secs() -> i64 {
+    DEFAULT_FIRST_OUTPUT_TIMEOUT_SECS
+}
+
 fn default_provider_id() -> String {
     "claude".to_string()
 }
@@ -42,6 +72,27 @@ pub struct Agent {
     pub hooks: Option<String>, // JSON string of hooks configuration
     pub created_at: String,
     pub updated_at: String,
+    /// Start time of the most recent run (only populated when requested)
+    #[serde(default)]
+    pub last_run_at: Option<String>,
+    /// Status of the most recent run (only populated when requested)
+    #[serde(default)]
+    pub last_run_status: Option<String>,
+    /// Model used by the most recent run; `model` remains the configured fallback
+    #[serde(default)]
+    pub last_used_model: Option<String>,
+    /// Set while the agent is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    /// Seconds to wait for the first output before killing a run; 0 never times out
+    #[serde(default = "default_first_output_timeout_secs")]
+    pub first_output_timeout_secs: i64,
+    /// JSON array of strings appended verbatim to the provider invocation
+    #[serde(default)]
+    pub extra_args: Option<String>,
+    /// Claude CLI this agent runs with instead of the globally detected one
+    #[serde(default)]
+    pub binary_path: Option<String>,
 }
 
 /// Represents an agent execution run
@@ -58,11 +109,30 @@ pub struct AgentRun {
     pub project_path: String,
     pub session_id: String, // UUID session ID from Claude Code
     pub output: Option<String>,
-    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled'
+    pub status: String,     // 'pending', 'queued', 'running', 'completed', 'failed', 'cancelled'
     pub pid: Option<u32>,
     pub process_started_at: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    /// Process exit code, or `TIMEOUT_EXIT_CODE` when the run was killed for
+    /// never producing output
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// When the process wrote its first stdout line
+    #[serde(default)]
+    pub first_output_at: Option<String>,
+    /// When the monitor saw the process exit (or killed it)
+    #[serde(default)]
+    pub process_ended_at: Option<String>,
+    /// Original run this one automatically retried after a rate limit
+    #[serde(default)]
+    pub retry_of_run_id: Option<i64>,
+    /// Free-form annotation set by the user
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Shared by runs started together by `run_agents_on_task`
+    #[serde(default)]
+    pub comparison_id: Option<String>,
 }
 
 /// Represents runtime metrics calculated from JSONL
@@ -70,8 +140,20 @@ pub struct AgentRun {
 pub struct AgentRunMetrics {
     pub duration_ms: Option<i64>,
     pub total_tokens: Option<i64>,
+    /// Tokens written to the prompt cache; not part of `total_tokens`
+    #[serde(default)]
+    pub cache_creation_tokens: Option<i64>,
+    /// Tokens served from the prompt cache; not part of `total_tokens`
+    #[serde(default)]
+    pub cache_read_tokens: Option<i64>,
     pub cost_usd: Option<f64>,
     pub message_count: Option<i64>,
+    /// Process start to first stdout line
+    #[serde(default)]
+    pub time_to_first_output_ms: Option<i64>,
+    /// Process start to process exit
+    #[serde(default)]
+    pub total_wall_ms: Option<i64>,
 }
 
 /// Combined agent run with real-time metrics
@@ -90,6 +172,22 @@ pub struct AgentExport {
     pub agent: AgentData,
 }
 
+/// Export format holding every agent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentBulkExport {
+    pub version: u32,
+    pub exported_at: String,
+    pub agents: Vec<AgentData>,
+}
+
+/// Agents created by an import: one for a single-agent export, all of them for a bulk export
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ImportedAgents {
+    Single(Box<Agent>),
+    Bulk(Vec<Agent>),
+}
+
 /// Agent data within export
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentData {
@@ -119,77 +217,151 @@ pub struct ProviderRuntimeStatus {
 /// Database connection state
 pub struct AgentDb(pub Mutex<Connection>);
 
-/// Real-time JSONL reading and processing functions
-impl AgentRunMetrics {
-    /// Calculate metrics from JSONL content
-    pub fn from_jsonl(jsonl_content: &str) -> Self {
-        let mut total_tokens = 0i64;
-        let mut cost_usd = 0.0f64;
-        let mut message_count = 0i64;
-        let mut start_time: Option<chrono::DateTime<chrono::Utc>> = None;
-        let mut end_time: Option<chrono::DateTime<chrono::Utc>> = None;
+/// Payload of the `agent-metrics:{run_id}` event emitted while a run streams
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LiveRunMetrics {
+    pub total_tokens: i64,
+    pub cost_usd: f64,
+    pub message_count: i64,
+}
 
-        for line in jsonl_content.lines() {
-            if let Ok(json) = serde_json::from_str::<JsonValue>(line) {
-                message_count += 1;
-
-                // Track timestamps
-                if let Some(timestamp_str) = json.get("timestamp").and_then(|t| t.as_str()) {
-                    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(timestamp_str) {
-                        let utc_time = timestamp.with_timezone(&chrono::Utc);
-                        if start_time.is_none() || utc_time < start_time.unwrap() {
-                            start_time = Some(utc_time);
-                        }
-                        if end_time.is_none() || utc_time > end_time.unwrap() {
-                            end_time = Some(utc_time);
-                        }
-                    }
+/// Running totals behind `AgentRunMetrics`, fed one JSONL line at a time
+#[derive(Debug, Default, Clone)]
+pub struct RunMetricsAccumulator {
+    total_tokens: i64,
+    cache_creation_tokens: i64,
+    cache_read_tokens: i64,
+    cost_usd: f64,
+    /// `total_cost_usd` of Claude's final `result` message, which supersedes `cost_usd`
+    reported_cost_usd: Option<f64>,
+    message_count: i64,
+    start_time: Option<chrono::DateTime<chrono::Utc>>,
+    end_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl RunMetricsAccumulator {
+    /// Fold one JSONL line into the totals. Returns true if it carried a `usage` block.
+    pub fn push_line(&mut self, line: &str) -> bool {
+        let Ok(json) = serde_json::from_str::<JsonValue>(line) else {
+            return false;
+        };
+        self.message_count += 1;
+
+        // Track timestamps
+        if let Some(timestamp_str) = json.get("timestamp").and_then(|t| t.as_str()) {
+            if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(timestamp_str) {
+                let utc_time = timestamp.with_timezone(&chrono::Utc);
+                if self.start_time.is_none_or(|start| utc_time < start) {
+                    self.start_time = Some(utc_time);
                 }
+                if self.end_time.is_none_or(|end| utc_time > end) {
+                    self.end_time = Some(utc_time);
+                }
+            }
+        }
 
-                // Extract token usage - check both top-level and nested message.usage
-                let usage = json
-                    .get("usage")
-                    .or_else(|| json.get("message").and_then(|m| m.get("usage")));
+        // Extract token usage - check both top-level and nested message.usage
+        let usage = json
+            .get("usage")
+            .or_else(|| json.get("message").and_then(|m| m.get("usage")));
 
-                if let Some(usage) = usage {
-                    if let Some(input_tokens) = usage.get("input_tokens").and_then(|t| t.as_i64()) {
-                        total_tokens += input_tokens;
-                    }
-                    if let Some(output_tokens) = usage.get("output_tokens").and_then(|t| t.as_i64())
-                    {
-                        total_tokens += output_tokens;
-                    }
-                }
+        if let Some(usage) = usage {
+            if let Some(input_tokens) = usage.get("input_tokens").and_then(|t| t.as_i64()) {
+                self.total_tokens += input_tokens;
+            }
+            if let Some(output_tokens) = usage.get("output_tokens").and_then(|t| t.as_i64()) {
+                self.total_tokens += output_tokens;
+            }
+            if let Some(tokens) = usage
+                .get("cache_creation_input_tokens")
+                .and_then(|t| t.as_i64())
+            {
+                self.cache_creation_tokens += tokens;
+            }
+            if let Some(tokens) = usage
+                .get("cache_read_input_tokens")
+                .and_then(|t| t.as_i64())
+            {
+                self.cache_read_tokens += tokens;
+            }
+        }
 
-                // Extract cost information
-                if let Some(cost) = json.get("cost").and_then(|c| c.as_f64()) {
-                    cost_usd += cost;
-                }
+        // Extract cost information
+        if let Some(cost) = json.get("cost").and_then(|c| c.as_f64()) {
+            self.cost_usd += cost;
+        }
+        if json.get("type").and_then(|t| t.as_str()) == Some("result") {
+            if let Some(total) = json.get("total_cost_usd").and_then(|c| c.as_f64()) {
+                self.reported_cost_usd = Some(total);
             }
         }
 
-        let duration_ms = match (start_time, end_time) {
+        usage.is_some()
+    }
+
+    fn cost_usd(&self) -> f64 {
+        self.reported_cost_usd.unwrap_or(self.cost_usd)
+    }
+
+    pub fn live(&self) -> LiveRunMetrics {
+        LiveRunMetrics {
+            total_tokens: self.total_tokens,
+            cost_usd: self.cost_usd(),
+            message_count: self.message_count,
+        }
+    }
+
+    pub fn finish(self) -> AgentRunMetrics {
+        let positive = |value: i64| (value > 0).then_some(value);
+        let duration_ms = match (self.start_time, self.end_time) {
             (Some(start), Some(end)) => Some((end - start).num_milliseconds()),
             _ => None,
         };
+        let cost_usd = self.cost_usd();
 
-        Self {
+        AgentRunMetrics {
             duration_ms,
-            total_tokens: if total_tokens > 0 {
-                Some(total_tokens)
-            } else {
-                None
-            },
-            cost_usd: if cost_usd > 0.0 { Some(cost_usd) } else { None },
-            message_count: if message_count > 0 {
-                Some(message_count)
-            } else {
-                None
-            },
+            total_tokens: positive(self.total_tokens),
+            cache_creation_tokens: positive(self.cache_creation_tokens),
+            cache_read_tokens: positive(self.cache_read_tokens),
+            cost_usd: (cost_usd > 0.0).then_some(cost_usd),
+            message_count: positive(self.message_count),
+            time_to_first_output_ms: None,
+            total_wall_ms: None,
+        }
+    }
+}
+
+/// Real-time JSONL reading and processing functions
+impl AgentRunMetrics {
+    /// Calculate metrics from JSONL content
+    pub fn from_jsonl(jsonl_content: &str) -> Self {
+        let mut accumulator = RunMetricsAccumulator::default();
+        for line in jsonl_content.lines() {
+            accumulator.push_line(line);
         }
+        accumulator.finish()
+    }
+
+    /// Fill in the timing breakdown from the process timestamps the monitor recorded
+    pub fn with_run_timing(mut self, run: &AgentRun) -> Self {
+        let started = run.process_started_at.as_deref().and_then(parse_run_timestamp);
+        let elapsed_since_start = |at: Option<&str>| {
+            let at = at.and_then(parse_run_timestamp)?;
+            Some((at - started?).num_milliseconds())
+        };
+        self.time_to_first_output_ms = elapsed_since_start(run.first_output_at.as_deref());
+        self.total_wall_ms = elapsed_since_start(run.process_ended_at.as_deref());
+        self
     }
 }
 
+fn parse_run_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&chrono::Utc))
+}
+
 /// Read JSONL content from a session file
 pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<String, String> {
     let claude_dir = dirs::home_dir()
@@ -222,7 +394,7 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     // Claude sessions can be loaded directly from Claude JSONL files.
     if run.provider_id == "claude" && !run.session_id.is_empty() {
         if let Ok(jsonl_content) = read_session_jsonl(&run.session_id, &run.project_path).await {
-            let metrics = AgentRunMetrics::from_jsonl(&jsonl_content);
+            let metrics = AgentRunMetrics::from_jsonl(&jsonl_content).with_run_timing(&run);
             return AgentRunWithMetrics {
                 run,
                 metrics: Some(metrics),
@@ -231,7 +403,7 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     }
 
     if let Some(output) = db_output {
-        let metrics = AgentRunMetrics::from_jsonl(&output);
+        let metrics = AgentRunMetrics::from_jsonl(&output).with_run_timing(&run);
         AgentRunWithMetrics {
             run,
             metrics: Some(metrics),
@@ -260,7 +432,27 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
 
     let db_path = app_dir.join("agents.db");
     let conn = Connection::open(db_path)?;
+    init_schema(&conn)?;
+
+    fail_interrupted_runs(&conn)?;
 
+    Ok(conn)
+}
+
+/// Fail runs the last app session left unfinished, so they don't hold execution
+/// slots forever. `pending` runs never got a process; `running` ones lost the
+/// monitor that would have recorded how they ended.
+fn fail_interrupted_runs(conn: &Connection) -> SqliteResult<usize> {
+    conn.execute(
+        "UPDATE agent_runs
+         SET status = 'failed', launch_options = NULL, completed_at = CURRENT_TIMESTAMP
+         WHERE status IN ('pending', 'running')",
+        [],
+    )
+}
+
+/// Create or migrate the agents database schema on an open connection
+pub(crate) fn init_schema(conn: &Connection) -> SqliteResult<()> {
     // Create agents table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS agents (
@@ -292,6 +484,14 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     );
     let _ = conn.execute("ALTER TABLE agents ADD COLUMN hooks TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN last_used_model TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN deleted_at TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE agents ADD COLUMN first_output_timeout_secs INTEGER NOT NULL DEFAULT 30",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN extra_args TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN binary_path TEXT", []);
     let _ = conn.execute(
         "ALTER TABLE agents ADD COLUMN enable_file_read BOOLEAN DEFAULT 1",
         [],
@@ -348,6 +548,14 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN exit_code INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN first_output_at TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN process_ended_at TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN retry_of_run_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN notes TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN stderr TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN comparison_id TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN launch_options TEXT", []);
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -452,38 +660,112 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
-    Ok(conn)
+    // Create recent_projects table for projects opened in the app
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recent_projects (
+            id TEXT PRIMARY KEY,
+            opened_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
 }
 
 /// List all agents
 #[tauri::command]
-pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
+pub async fn list_agents(
+    db: State<'_, AgentDb>,
+    with_last_run: Option<bool>,
+) -> Result<Vec<Agent>, OpcodeError> {
+    let conn = db.0.lock()?;
+    query_agents(&conn, with_last_run.unwrap_or(false), false).map_err(OpcodeError::Db)
+}
+
+/// List agents in the trash, most recently created first
+#[tauri::command]
+pub async fn list_deleted_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_agents(&conn, false, true)
+}
 
-    let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
+/// Columns `agent_from_row` reads, in order
+const AGENT_COLUMNS: &str = "id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at, last_used_model, deleted_at, first_output_timeout_secs, extra_args, binary_path";
+
+/// Map a row selected with `AGENT_COLUMNS` into an `Agent`, without last-run details
+fn agent_from_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        system_prompt: row.get(3)?,
+        default_task: row.get(4)?,
+        provider_id: row
+            .get::<_, String>(5)
+            .unwrap_or_else(|_| "claude".to_string()),
+        model: row
+            .get::<_, String>(6)
+            .unwrap_or_else(|_| "sonnet".to_string()),
+        enable_file_read: row.get::<_, bool>(7).unwrap_or(true),
+        enable_file_write: row.get::<_, bool>(8).unwrap_or(true),
+        enable_network: row.get::<_, bool>(9).unwrap_or(false),
+        hooks: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        last_run_at: None,
+        last_run_status: None,
+        last_used_model: row.get(13)?,
+        deleted_at: row.get(14)?,
+        first_output_timeout_secs: row.get(15)?,
+        extra_args: row.get(16)?,
+        binary_path: row.get(17)?,
+    })
+}
+
+fn query_agent_by_id(conn: &Connection, id: i64) -> rusqlite::Result<Agent> {
+    conn.query_row(
+        &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+        params![id],
+        agent_from_row,
+    )
+}
+
+/// Load all agents, optionally joined with their most recent run
+/// Active agents, or only soft-deleted ones when `deleted` is set
+fn query_agents(conn: &Connection, with_last_run: bool, deleted: bool) -> Result<Vec<Agent>, String> {
+    let deleted_filter = if deleted {
+        "deleted_at IS NOT NULL"
+    } else {
+        "deleted_at IS NULL"
+    };
+    let sql = if with_last_run {
+        format!(
+            "SELECT {}, last_run_at, last_run_status FROM (
+                 SELECT a.*, r.created_at AS last_run_at, r.status AS last_run_status
+                 FROM agents a
+                 LEFT JOIN agent_runs r ON r.id = (
+                     SELECT id FROM agent_runs WHERE agent_id = a.id ORDER BY created_at DESC, id DESC LIMIT 1
+                 )
+             )
+             WHERE {}
+             ORDER BY created_at DESC",
+            AGENT_COLUMNS, deleted_filter
+        )
+    } else {
+        format!(
+            "SELECT {}, NULL, NULL FROM agents WHERE {} ORDER BY created_at DESC",
+            AGENT_COLUMNS, deleted_filter
+        )
+    };
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
     let agents = stmt
         .query_map([], |row| {
             Ok(Agent {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                system_prompt: row.get(3)?,
-                default_task: row.get(4)?,
-                provider_id: row
-                    .get::<_, String>(5)
-                    .unwrap_or_else(|_| "claude".to_string()),
-                model: row
-                    .get::<_, String>(6)
-                    .unwrap_or_else(|_| "sonnet".to_string()),
-                enable_file_read: row.get::<_, bool>(7).unwrap_or(true),
-                enable_file_write: row.get::<_, bool>(8).unwrap_or(true),
-                enable_network: row.get::<_, bool>(9).unwrap_or(false),
-                hooks: row.get(10)?,
-                created_at: row.get(11)?,
-                updated_at: row.get(12)?,
+                last_run_at: row.get(18)?,
+                last_run_status: row.get(19)?,
+                ..agent_from_row(row)?
             })
         })
         .map_err(|e| e.to_string())?
@@ -507,50 +789,34 @@ pub async fn create_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    first_output_timeout_secs: Option<i64>,
+    extra_args: Option<String>,
+    binary_path: Option<String>,
 ) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    parse_extra_args(extra_args.as_deref())?;
+    let binary_path = binary_path.filter(|path| !path.trim().is_empty());
+    validate_agent_binary_path(binary_path.as_deref())?;
     let provider_id = provider_id.unwrap_or_else(|| "claude".to_string());
+    validate_provider_id(&provider_id)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
     let enable_file_read = enable_file_read.unwrap_or(true);
     let enable_file_write = enable_file_write.unwrap_or(true);
     let enable_network = enable_network.unwrap_or(false);
+    let first_output_timeout_secs = first_output_timeout_secs
+        .unwrap_or(DEFAULT_FIRST_OUTPUT_TIMEOUT_SECS)
+        .max(0);
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, first_output_timeout_secs, extra_args, binary_path) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, first_output_timeout_secs, extra_args, binary_path],
     )
     .map_err(|e| e.to_string())?;
 
     let id = conn.last_insert_rowid();
 
     // Fetch the created agent
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    provider_id: row
-                        .get::<_, String>(5)
-                        .unwrap_or_else(|_| "claude".to_string()),
-                    model: row.get(6)?,
-                    enable_file_read: row.get(7)?,
-                    enable_file_write: row.get(8)?,
-                    enable_network: row.get(9)?,
-                    hooks: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
-
-    Ok(agent)
+    query_agent_by_id(&conn, id).map_err(|e| e.to_string())
 }
 
 /// Update an existing agent
@@ -568,7 +834,17 @@ pub async fn update_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    first_output_timeout_secs: Option<i64>,
+    extra_args: Option<String>,
+    binary_path: Option<String>,
 ) -> Result<Agent, String> {
+    parse_extra_args(extra_args.as_deref())?;
+    if let Some(binary_path) = binary_path.as_deref().filter(|path| !path.trim().is_empty()) {
+        validate_agent_binary_path(Some(binary_path))?;
+    }
+    if let Some(provider_id) = provider_id.as_deref() {
+        validate_provider_id(provider_id)?;
+    }
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
 
@@ -600,6 +876,23 @@ pub async fn update_agent(
         query.push_str(&format!(", enable_network = ?{}", param_count));
         params_vec.push(Box::new(en));
     }
+    if let Some(timeout) = first_output_timeout_secs {
+        param_count += 1;
+        query.push_str(&format!(", first_output_timeout_secs = ?{}", param_count));
+        params_vec.push(Box::new(timeout.max(0)));
+    }
+    if let Some(extra_args) = extra_args {
+        param_count += 1;
+        query.push_str(&format!(", extra_args = ?{}", param_count));
+        // An empty value clears the extra args
+        params_vec.push(Box::new(Some(extra_args).filter(|args| !args.trim().is_empty())));
+    }
+    if let Some(binary_path) = binary_path {
+        param_count += 1;
+        query.push_str(&format!(", binary_path = ?{}", param_count));
+        // An empty value goes back to the globally detected binary
+        params_vec.push(Box::new(Some(binary_path).filter(|path| !path.trim().is_empty())));
+    }
 
     param_count += 1;
     query.push_str(&format!(" WHERE id = ?{}", param_count));
@@ -612,242 +905,531 @@ pub async fn update_agent(
     .map_err(|e| e.to_string())?;
 
     // Fetch the updated agent
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    provider_id: row
-                        .get::<_, String>(5)
-                        .unwrap_or_else(|_| "claude".to_string()),
-                    model: row.get(6)?,
-                    enable_file_read: row.get(7)?,
-                    enable_file_write: row.get(8)?,
-                    enable_network: row.get(9)?,
-                    hooks: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
-
-    Ok(agent)
+    query_agent_by_id(&conn, id).map_err(|e| e.to_string())
 }
 
 /// Delete an agent
 #[tauri::command]
-pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+pub async fn delete_agent(
+    db: State<'_, AgentDb>,
+    id: i64,
+    hard: Option<bool>,
+) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
+    if hard.unwrap_or(false) {
+        conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+    } else {
+        trash_agent(&conn, id)?;
+    }
 
     Ok(())
 }
 
-/// Get a single agent by ID
-#[tauri::command]
-pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+/// Move an agent to the trash; it stays restorable until purged.
+/// Returns how many agents were trashed.
+fn trash_agent(conn: &Connection, id: i64) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE agents SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?1 AND deleted_at IS NULL",
+        params![id],
+    )
+    .map_err(|e| e.to_string())
+}
 
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+fn restore_trashed_agent(conn: &Connection, id: i64) -> Result<(), String> {
+    let restored = conn
+        .execute(
+            "UPDATE agents SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    provider_id: row
-                        .get::<_, String>(5)
-                        .unwrap_or_else(|_| "claude".to_string()),
-                    model: row.get::<_, String>(6).unwrap_or_else(|_| "sonnet".to_string()),
-                    enable_file_read: row.get::<_, bool>(7).unwrap_or(true),
-                    enable_file_write: row.get::<_, bool>(8).unwrap_or(true),
-                    enable_network: row.get::<_, bool>(9).unwrap_or(false),
-                    hooks: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            },
         )
         .map_err(|e| e.to_string())?;
+    if restored == 0 {
+        return Err(format!("Agent {} is not in the trash", id));
+    }
+    Ok(())
+}
 
-    Ok(agent)
+fn purge_trashed_agent(conn: &Connection, id: i64) -> Result<(), String> {
+    let purged = conn
+        .execute(
+            "DELETE FROM agents WHERE id = ?1 AND deleted_at IS NOT NULL",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    if purged == 0 {
+        return Err(format!("Agent {} is not in the trash", id));
+    }
+    Ok(())
 }
 
-/// List agent runs (optionally filtered by agent_id)
+/// Restore a soft-deleted agent
 #[tauri::command]
-pub async fn list_agent_runs(
-    db: State<'_, AgentDb>,
-    agent_id: Option<i64>,
-) -> Result<Vec<AgentRun>, String> {
+pub async fn restore_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    restore_trashed_agent(&conn, id)
+}
 
-    let query = if agent_id.is_some() {
-        "SELECT id, agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, status, pid, process_started_at, created_at, completed_at
-         FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC"
-    } else {
-        "SELECT id, agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, status, pid, process_started_at, created_at, completed_at
-         FROM agent_runs ORDER BY created_at DESC"
-    };
-
-    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
-
-    let run_mapper = |row: &rusqlite::Row| -> rusqlite::Result<AgentRun> {
-        Ok(AgentRun {
-            id: Some(row.get(0)?),
-            agent_id: row.get(1)?,
-            agent_name: row.get(2)?,
-            agent_icon: row.get(3)?,
-            provider_id: row
-                .get::<_, String>(4)
-                .unwrap_or_else(|_| "claude".to_string()),
-            task: row.get(5)?,
-            model: row.get(6)?,
-            project_path: row.get(7)?,
-            session_id: row.get(8)?,
-            output: row
-                .get::<_, Option<String>>(9)?
-                .filter(|s| !s.is_empty()),
-            status: row
-                .get::<_, String>(10)
-                .unwrap_or_else(|_| "pending".to_string()),
-            pid: row
-                .get::<_, Option<i64>>(11)
-                .ok()
-                .flatten()
-                .map(|p| p as u32),
-            process_started_at: row.get(12)?,
-            created_at: row.get(13)?,
-            completed_at: row.get(14)?,
-        })
-    };
+/// Permanently delete a soft-deleted agent and its runs
+#[tauri::command]
+pub async fn purge_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    purge_trashed_agent(&conn, id)
+}
 
-    let runs = if let Some(aid) = agent_id {
-        stmt.query_map(params![aid], run_mapper)
-    } else {
-        stmt.query_map(params![], run_mapper)
+/// Remember the model a run was started with so the model picker can prefill it
+fn record_last_used_model(conn: &Connection, agent_id: i64, model: &str) -> SqliteResult<usize> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Ok(0);
     }
-    .map_err(|e| e.to_string())?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| e.to_string())?;
-
-    Ok(runs)
+    conn.execute(
+        "UPDATE agents SET last_used_model = ?1 WHERE id = ?2",
+        params![model, agent_id],
+    )
 }
 
-/// Get a single agent run by ID
+/// Delete several agents at once, returning how many were removed. Like
+/// `delete_agent` they go to the trash unless `hard` is set; a hard delete
+/// removes their runs through the agent_runs foreign key cascade.
 #[tauri::command]
-pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+pub async fn delete_agents(
+    db: State<'_, AgentDb>,
+    ids: Vec<i64>,
+    hard: Option<bool>,
+) -> Result<usize, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_agents_in_transaction(&mut conn, &ids, hard.unwrap_or(false))
+}
 
-    let run = conn
-        .query_row(
-            "SELECT id, agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, status, pid, process_started_at, created_at, completed_at
-             FROM agent_runs WHERE id = ?1",
+fn delete_agents_in_transaction(
+    conn: &mut Connection,
+    ids: &[i64],
+    hard: bool,
+) -> Result<usize, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let mut removed = 0;
+    for id in ids {
+        let running_runs: i64 = tx
+            .query_row(
+                "SELECT COUNT(*) FROM agent_runs WHERE agent_id = ?1 AND status = 'running'",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if running_runs > 0 {
+            return Err(format!(
+                "Agent {} has a running run; stop it before deleting",
+                id
+            ));
+        }
+
+        removed += if hard {
+            tx.execute("DELETE FROM agents WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?
+        } else {
+            trash_agent(&tx, *id)?
+        };
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+/// Insert a copy of an agent named "{name} (Copy)", returning the new id.
+/// Runs, usage history and trash state are not copied.
+fn clone_agent_row(conn: &Connection, id: i64) -> Result<i64, String> {
+    let inserted = conn
+        .execute(
+            "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, first_output_timeout_secs, extra_args, binary_path)
+             SELECT name || ' (Copy)', icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, first_output_timeout_secs, extra_args, binary_path
+             FROM agents WHERE id = ?1",
             params![id],
-            |row| {
-                Ok(AgentRun {
-                    id: Some(row.get(0)?),
-                    agent_id: row.get(1)?,
-                    agent_name: row.get(2)?,
-                    agent_icon: row.get(3)?,
-                    provider_id: row
-                        .get::<_, String>(4)
-                        .unwrap_or_else(|_| "claude".to_string()),
-                    task: row.get(5)?,
-                    model: row.get(6)?,
-                    project_path: row.get(7)?,
-                    session_id: row.get(8)?,
-                    output: row
-                        .get::<_, Option<String>>(9)?
-                        .filter(|s| !s.is_empty()),
-                    status: row
-                        .get::<_, String>(10)
-                        .unwrap_or_else(|_| "pending".to_string()),
-                    pid: row.get::<_, Option<i64>>(11).ok().flatten().map(|p| p as u32),
-                    process_started_at: row.get(12)?,
-                    created_at: row.get(13)?,
-                    completed_at: row.get(14)?,
-                })
-            },
         )
         .map_err(|e| e.to_string())?;
-
-    Ok(run)
+    if inserted == 0 {
+        return Err(format!("Agent {} not found", id));
+    }
+    Ok(conn.last_insert_rowid())
 }
 
-/// Get agent run with real-time metrics from JSONL
+/// Duplicate an existing agent
 #[tauri::command]
-pub async fn get_agent_run_with_real_time_metrics(
-    db: State<'_, AgentDb>,
-    id: i64,
-) -> Result<AgentRunWithMetrics, String> {
-    let run = get_agent_run(db, id).await?;
-    Ok(get_agent_run_with_metrics(run).await)
+pub async fn clone_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
+    let clone_id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        clone_agent_row(&conn, id)?
+    };
+    Ok(get_agent(db, clone_id).await?)
 }
 
-/// List agent runs with real-time metrics from JSONL
+/// Get a single agent by ID
 #[tauri::command]
-pub async fn list_agent_runs_with_metrics(
-    db: State<'_, AgentDb>,
-    agent_id: Option<i64>,
-) -> Result<Vec<AgentRunWithMetrics>, String> {
-    let runs = list_agent_runs(db, agent_id).await?;
-    let mut runs_with_metrics = Vec::new();
+pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, OpcodeError> {
+    let conn = db.0.lock()?;
 
-    for run in runs {
-        let run_with_metrics = get_agent_run_with_metrics(run).await;
-        runs_with_metrics.push(run_with_metrics);
+    let agent = query_agent_by_id(&conn, id).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                OpcodeError::NotFound(format!("Agent {} not found", id))
+            }
+            other => OpcodeError::from(other),
+        })?;
+
+    Ok(agent)
+}
+
+/// Load an agent that is about to run; agents in the trash can't be run
+async fn get_runnable_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, OpcodeError> {
+    let agent = get_agent(db, id).await?;
+    if agent.deleted_at.is_some() {
+        return Err(OpcodeError::Validation(format!(
+            "Agent '{}' is in the trash; restore it to run it",
+            agent.name
+        )));
     }
+    Ok(agent)
+}
 
-    Ok(runs_with_metrics)
+/// Columns `agent_run_from_row` reads, in order
+const AGENT_RUN_COLUMNS: &str = "id, agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, status, pid, process_started_at, created_at, completed_at, exit_code, first_output_at, process_ended_at, retry_of_run_id, notes, comparison_id";
+
+/// Map a row selected with `AGENT_RUN_COLUMNS` into an `AgentRun`
+fn agent_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentRun> {
+    Ok(AgentRun {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        agent_name: row.get(2)?,
+        agent_icon: row.get(3)?,
+        provider_id: row
+            .get::<_, String>(4)
+            .unwrap_or_else(|_| "claude".to_string()),
+        task: row.get(5)?,
+        model: row.get(6)?,
+        project_path: row.get(7)?,
+        session_id: row.get(8)?,
+        output: row
+            .get::<_, Option<String>>(9)?
+            .filter(|s| !s.is_empty()),
+        status: row
+            .get::<_, String>(10)
+            .unwrap_or_else(|_| "pending".to_string()),
+        pid: row
+            .get::<_, Option<i64>>(11)
+            .ok()
+            .flatten()
+            .map(|p| p as u32),
+        process_started_at: row.get(12)?,
+        created_at: row.get(13)?,
+        completed_at: row.get(14)?,
+        exit_code: row.get(15)?,
+        first_output_at: row.get(16)?,
+        process_ended_at: row.get(17)?,
+        retry_of_run_id: row.get(18)?,
+        note: row.get(19)?,
+        comparison_id: row.get(20)?,
+    })
 }
 
-fn env_has_value(name: &str) -> bool {
-    env::var(name).map(|v| !v.trim().is_empty()).unwrap_or(false)
+/// Optional filters for `list_agent_runs`; all-None lists every run
+#[derive(Debug, Default)]
+struct AgentRunFilter {
+    agent_id: Option<i64>,
+    status: Option<String>,
+    /// RFC3339 bounds on `created_at`, both inclusive
+    since: Option<String>,
+    until: Option<String>,
 }
 
-fn env_is_truthy(name: &str) -> bool {
-    env::var(name)
-        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
-        .unwrap_or(false)
+/// RFC3339 timestamp in the UTC `YYYY-MM-DD HH:MM:SS` shape SQLite's `datetime()` yields
+fn run_filter_timestamp(name: &str, value: &str) -> Result<String, String> {
+    parse_run_timestamp(value)
+        .map(|timestamp| timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+        .ok_or_else(|| format!("{} must be an RFC3339 timestamp, got '{}'", name, value))
 }
 
-fn gemini_adc_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
+fn query_agent_runs(conn: &Connection, filter: &AgentRunFilter) -> Result<Vec<AgentRun>, String> {
+    let mut sql = format!("SELECT {} FROM agent_runs WHERE 1=1", AGENT_RUN_COLUMNS);
+    let mut sql_params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
-    if let Some(home) = dirs::home_dir() {
-        paths.push(home.join(".config/gcloud/application_default_credentials.json"));
+    if let Some(agent_id) = filter.agent_id {
+        sql.push_str(" AND agent_id = ?");
+        sql_params.push(Box::new(agent_id));
     }
-
-    if let Ok(appdata) = env::var("APPDATA") {
-        paths.push(
-            PathBuf::from(appdata)
-                .join("gcloud")
-                .join("application_default_credentials.json"),
-        );
+    if let Some(status) = &filter.status {
+        sql.push_str(" AND status = ?");
+        sql_params.push(Box::new(status.clone()));
     }
+    // datetime() normalizes both CURRENT_TIMESTAMP and RFC3339 values to UTC
+    if let Some(since) = &filter.since {
+        sql.push_str(" AND datetime(created_at) >= ?");
+        sql_params.push(Box::new(run_filter_timestamp("since", since)?));
+    }
+    if let Some(until) = &filter.until {
+        sql.push_str(" AND datetime(created_at) <= ?");
+        sql_params.push(Box::new(run_filter_timestamp("until", until)?));
+    }
+    sql.push_str(" ORDER BY created_at DESC");
 
-    paths
-}
-
-fn gemini_auth_ready() -> bool {
-    let api_key_ready = env_has_value("GEMINI_API_KEY") || env_has_value("GOOGLE_API_KEY");
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let runs = stmt
+        .query_map(
+            rusqlite::params_from_iter(sql_params.iter().map(|param| param.as_ref())),
+            agent_run_from_row,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
 
-    let vertex_ready = env_is_truthy("GOOGLE_GENAI_USE_VERTEXAI")
-        && env_has_value("GOOGLE_CLOUD_PROJECT")
-        && (env_has_value("GOOGLE_CLOUD_LOCATION") || env_has_value("GOOGLE_CLOUD_REGION"));
+    Ok(runs)
+}
+
+/// List agent runs, optionally filtered by agent, status and creation time (RFC3339)
+#[tauri::command]
+pub async fn list_agent_runs(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    status: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<Vec<AgentRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_agent_runs(
+        &conn,
+        &AgentRunFilter {
+            agent_id,
+            status,
+            since,
+            until,
+        },
+    )
+}
+
+fn query_queued_runs(conn: &Connection) -> Result<Vec<AgentRun>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = ?1 ORDER BY created_at ASC, id ASC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let runs = stmt
+        .query_map(params![QUEUED_RUN_STATUS], agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(runs)
+}
+
+/// Take a run out of the queue; only runs still waiting (never spawned) match
+fn cancel_queued_run_in(conn: &Connection, run_id: i64) -> Result<(), String> {
+    let updated = conn
+        .execute(
+            "UPDATE agent_runs
+             SET status = 'cancelled', launch_options = NULL, completed_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND status = ?2",
+            params![run_id, QUEUED_RUN_STATUS],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Run {} is not queued", run_id));
+    }
+    Ok(())
+}
+
+/// Take every waiting run out of the queue, returning their ids
+pub(crate) fn cancel_all_queued_runs_in(conn: &Connection) -> Result<Vec<i64>, String> {
+    let run_ids: Vec<i64> = query_queued_runs(conn)?
+        .into_iter()
+        .filter_map(|run| run.id)
+        .collect();
+    for run_id in &run_ids {
+        cancel_queued_run_in(conn, *run_id)?;
+    }
+    Ok(run_ids)
+}
+
+/// Cancel every run without a process yet: queued runs, and `pending` ones that
+/// are still starting. A starting run stops at its spawn once it sees the status.
+pub(crate) fn cancel_unstarted_runs_in(conn: &Connection) -> Result<Vec<i64>, String> {
+    let mut run_ids = cancel_all_queued_runs_in(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM agent_runs WHERE status = 'pending' ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let pending = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agent_runs
+         SET status = 'cancelled', launch_options = NULL, completed_at = CURRENT_TIMESTAMP
+         WHERE status = 'pending'",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    run_ids.extend(pending);
+    Ok(run_ids)
+}
+
+/// List runs waiting for a free execution slot, oldest first
+#[tauri::command]
+pub async fn list_queued_runs(db: State<'_, AgentDb>) -> Result<Vec<AgentRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_queued_runs(&conn)
+}
+
+/// Longest run note accepted, in characters
+pub const MAX_RUN_NOTE_CHARS: usize = 4000;
+
+/// Strip control characters (keeping newlines and tabs) and enforce the size cap.
+/// A blank note clears it.
+fn sanitize_run_note(note: &str) -> Result<Option<String>, String> {
+    let cleaned: String = note
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() {
+        return Ok(None);
+    }
+    let length = cleaned.chars().count();
+    if length > MAX_RUN_NOTE_CHARS {
+        return Err(format!(
+            "Run note is {} characters; the limit is {}",
+            length, MAX_RUN_NOTE_CHARS
+        ));
+    }
+    Ok(Some(cleaned.to_string()))
+}
+
+fn set_run_note_in(conn: &Connection, run_id: i64, note: &str) -> Result<Option<String>, String> {
+    let note = sanitize_run_note(note)?;
+    let updated = conn
+        .execute(
+            "UPDATE agent_runs SET notes = ?1 WHERE id = ?2",
+            params![note, run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Agent run {} not found", run_id));
+    }
+    Ok(note)
+}
+
+fn get_run_note_in(conn: &Connection, run_id: i64) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT notes FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Agent run {} not found", run_id),
+        other => other.to_string(),
+    })
+}
+
+/// Annotate a run; returns the stored (sanitized) note, or None when cleared
+#[tauri::command]
+pub async fn set_run_note(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    note: String,
+) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_run_note_in(&conn, run_id, &note)
+}
+
+/// Read a run's note
+#[tauri::command]
+pub async fn get_run_note(db: State<'_, AgentDb>, run_id: i64) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_run_note_in(&conn, run_id)
+}
+
+/// Cancel a queued run so it is never spawned
+#[tauri::command]
+pub async fn cancel_queued_run(db: State<'_, AgentDb>, run_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    cancel_queued_run_in(&conn, run_id)?;
+    tracing::info!("Cancelled queued run {}", run_id);
+    Ok(())
+}
+
+/// Get a single agent run by ID
+#[tauri::command]
+pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
+        params![id],
+        agent_run_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Get agent run with real-time metrics from JSONL
+#[tauri::command]
+pub async fn get_agent_run_with_real_time_metrics(
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<AgentRunWithMetrics, String> {
+    let run = get_agent_run(db, id).await?;
+    Ok(get_agent_run_with_metrics(run).await)
+}
+
+/// List agent runs with real-time metrics from JSONL
+#[tauri::command]
+pub async fn list_agent_runs_with_metrics(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentRunWithMetrics>, String> {
+    let runs = list_agent_runs(db, agent_id, None, None, None).await?;
+    let mut runs_with_metrics = Vec::new();
+
+    for run in runs {
+        let run_with_metrics = get_agent_run_with_metrics(run).await;
+        runs_with_metrics.push(run_with_metrics);
+    }
+
+    Ok(runs_with_metrics)
+}
+
+fn env_has_value(name: &str) -> bool {
+    env::var(name).map(|v| !v.trim().is_empty()).unwrap_or(false)
+}
+
+fn env_is_truthy(name: &str) -> bool {
+    env::var(name)
+        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+fn gemini_adc_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".config/gcloud/application_default_credentials.json"));
+    }
+
+    if let Ok(appdata) = env::var("APPDATA") {
+        paths.push(
+            PathBuf::from(appdata)
+                .join("gcloud")
+                .join("application_default_credentials.json"),
+        );
+    }
+
+    paths
+}
+
+fn gemini_auth_ready() -> bool {
+    let api_key_ready = env_has_value("GEMINI_API_KEY") || env_has_value("GOOGLE_API_KEY");
+
+    let vertex_ready = env_is_truthy("GOOGLE_GENAI_USE_VERTEXAI")
+        && env_has_value("GOOGLE_CLOUD_PROJECT")
+        && (env_has_value("GOOGLE_CLOUD_LOCATION") || env_has_value("GOOGLE_CLOUD_REGION"));
 
     let adc_ready = gemini_adc_paths().into_iter().any(|path| path.exists());
 
@@ -902,18 +1484,15 @@ async fn provider_runtime_status(
         status.auth_ready = gemini_auth_ready();
         if !status.auth_ready {
             status.issues.push("Gemini authentication was not detected.".to_string());
-            status.setup_hints.push(
-                "Set `GEMINI_API_KEY` (or `GOOGLE_API_KEY`) before running Gemini tasks."
-                    .to_string(),
-            );
-            status.setup_hints.push(
-                "Or configure Vertex auth with `GOOGLE_GENAI_USE_VERTEXAI=true`, `GOOGLE_CLOUD_PROJECT`, and `GOOGLE_CLOUD_LOCATION`."
-                    .to_string(),
-            );
-            status.setup_hints.push(
-                "Or run `gcloud auth application-default login` to create ADC credentials."
-                    .to_string(),
-            );
+            if let Some(runtime) = crate::providers::runtime::get_provider_runtime(provider_id) {
+                status.setup_hints.extend(
+                    runtime
+                        .capabilities
+                        .auth_setup_hints
+                        .iter()
+                        .map(|hint| hint.to_string()),
+                );
+            }
         }
     }
 
@@ -953,7 +1532,108 @@ pub async fn check_provider_runtime(
     provider_runtime_status(&app, &provider_id).await
 }
 
-/// Execute a CC agent with streaming output
+/// Actively validates provider credentials before a run is recorded.
+/// Only Gemini API keys can be checked cheaply; other auth modes pass through.
+async fn verify_provider_auth(provider_id: &str) -> Result<(), String> {
+    if provider_id != "gemini" {
+        return Ok(());
+    }
+
+    let api_key = ["GEMINI_API_KEY", "GOOGLE_API_KEY"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty());
+    let Some(api_key) = api_key else {
+        // Vertex / ADC auth has no cheap verification endpoint
+        return Ok(());
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .get("https://generativelanguage.googleapis.com/v1beta/models")
+        .query(&[("key", api_key.as_str()), ("pageSize", "1")])
+        .send()
+        .await
+        .map_err(|e| format!("Gemini auth pre-flight failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Gemini API key was rejected during pre-flight (HTTP {}).",
+            response.status()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fields for a new agent_runs row
+struct PendingRun<'a> {
+    agent_id: i64,
+    agent_name: &'a str,
+    agent_icon: &'a str,
+    provider_id: &'a str,
+    task: &'a str,
+    model: &'a str,
+    project_path: &'a str,
+    session_id: &'a str,
+    retry_of_run_id: Option<i64>,
+    comparison_id: Option<&'a str>,
+}
+
+/// Awaits the pre-flight and only then inserts the run row, so a failed
+/// pre-flight never leaves a useless run record behind.
+async fn record_run_after_preflight<F>(
+    db: &AgentDb,
+    preflight: F,
+    run: &PendingRun<'_>,
+) -> Result<i64, String>
+where
+    F: std::future::Future<Output = Result<(), String>>,
+{
+    preflight.await?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Err(e) = record_last_used_model(&conn, run.agent_id, run.model) {
+        tracing::warn!(
+            "Failed to record last used model for agent {}: {}",
+            run.agent_id,
+            e
+        );
+    }
+    conn.execute(
+        "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, retry_of_run_id, comparison_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            run.agent_id,
+            run.agent_name,
+            run.agent_icon,
+            run.provider_id,
+            run.task,
+            run.model,
+            run.project_path,
+            run.session_id,
+            "",
+            run.retry_of_run_id,
+            run.comparison_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// What `execute_agent` produced: a started run, or the command a dry run would spawn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum AgentExecution {
+    Run(i64),
+    DryRun(String),
+}
+
+/// Execute a CC agent with streaming output. With `dry_run`, nothing is spawned
+/// or recorded and the command line is returned instead.
 #[tauri::command]
 pub async fn execute_agent(
     app: AppHandle,
@@ -962,13 +1642,79 @@ pub async fn execute_agent(
     task: String,
     model: Option<String>,
     reasoning_effort: Option<String>,
+    output_format: Option<String>,
+    verify: Option<bool>,
+    env_overrides: Option<String>,
+    dry_run: Option<bool>,
+    checkpoint_on_complete: Option<bool>,
+    raw_output: Option<bool>,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
-) -> Result<i64, String> {
+) -> Result<AgentExecution, OpcodeError> {
+    let output_format =
+        parse_output_format(output_format.as_deref()).map_err(OpcodeError::Validation)?;
+    let env_overrides =
+        parse_env_overrides(env_overrides.as_deref()).map_err(OpcodeError::Validation)?;
+    if dry_run.unwrap_or(false) {
+        let preview = preview_agent_command(
+            &app,
+            &db,
+            agent_id,
+            &project_path,
+            &task,
+            model,
+            reasoning_effort,
+            output_format,
+        )
+        .await?;
+        return Ok(AgentExecution::DryRun(preview));
+    }
+
+    launch_agent_run(
+        app,
+        agent_id,
+        project_path,
+        task,
+        model,
+        reasoning_effort,
+        output_format,
+        verify,
+        env_overrides,
+        checkpoint_on_complete,
+        raw_output.unwrap_or(false),
+        None,
+        None,
+        db,
+        registry,
+    )
+    .await
+    .map(AgentExecution::Run)
+}
+
+/// Shared body of `execute_agent`; `retry_of_run_id` links an automatic retry
+/// to the run it replaces and `comparison_id` groups runs started by
+/// `run_agents_on_task`. `raw_output` also streams untransformed lines.
+async fn launch_agent_run(
+    app: AppHandle,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    model: Option<String>,
+    reasoning_effort: Option<String>,
+    output_format: OutputFormat,
+    verify: Option<bool>,
+    env_overrides: EnvOverrides,
+    checkpoint_on_complete: Option<bool>,
+    raw_output: bool,
+    retry_of_run_id: Option<i64>,
+    comparison_id: Option<&str>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, OpcodeError> {
     tracing::info!("Executing agent {} with task: {}", agent_id, task);
 
     // Get the agent from database
-    let agent = get_agent(db.clone(), agent_id).await?;
+    let agent = get_runnable_agent(db.clone(), agent_id).await?;
     let provider_id = if agent.provider_id.is_empty() {
         "claude".to_string()
     } else {
@@ -981,16 +1727,28 @@ pub async fn execute_agent(
         format!("{}-run-{}", provider_id, chrono::Utc::now().timestamp_millis())
     };
 
-    // Fail fast on missing provider runtime prerequisites.
-    let runtime_status = provider_runtime_status(&app, &provider_id).await?;
-    if !runtime_status.ready {
-        return Err(provider_runtime_error(&runtime_status));
-    }
-
-    let binary_path = runtime_status
-        .detected_binary
-        .clone()
-        .unwrap_or(resolve_provider_binary(&app, &provider_id).await?);
+    let pinned_binary =
+        pinned_claude_binary(&agent, &provider_id).map_err(OpcodeError::ProviderUnavailable)?;
+    let binary_path = match pinned_binary {
+        Some(path) => path,
+        None => {
+            // Fail fast on missing provider runtime prerequisites.
+            let runtime_status = provider_runtime_status(&app, &provider_id)
+                .await
+                .map_err(OpcodeError::ProviderUnavailable)?;
+            if !runtime_status.ready {
+                return Err(OpcodeError::ProviderUnavailable(provider_runtime_error(
+                    &runtime_status,
+                )));
+            }
+            match runtime_status.detected_binary.clone() {
+                Some(path) => path,
+                None => resolve_provider_binary(&app, &provider_id)
+                    .await
+                    .map_err(OpcodeError::ProviderUnavailable)?,
+            }
+        }
+    };
 
     // Create .claude/settings.json with agent hooks for Claude providers.
     if provider_id == "claude" && agent.hooks.is_some() {
@@ -998,7 +1756,7 @@ pub async fn execute_agent(
             Some(hooks) => hooks,
             None => {
                 tracing::error!("Agent hooks field is None despite is_some() check");
-                return Err("Agent hooks unavailable".into());
+                return Err(OpcodeError::Validation("Agent hooks unavailable".to_string()));
             }
         };
         let claude_dir = std::path::Path::new(&project_path).join(".claude");
@@ -1006,16 +1764,18 @@ pub async fn execute_agent(
 
         // Create .claude directory if it doesn't exist
         if !claude_dir.exists() {
-            std::fs::create_dir_all(&claude_dir)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+            std::fs::create_dir_all(&claude_dir).map_err(|e| {
+                OpcodeError::Io(format!("Failed to create .claude directory: {}", e))
+            })?;
             tracing::info!("Created .claude directory at: {:?}", claude_dir);
         }
 
         // Check if settings.json already exists
         if !settings_path.exists() {
             // Parse the hooks JSON
-            let hooks: serde_json::Value = serde_json::from_str(hooks_json)
-                .map_err(|e| format!("Failed to parse agent hooks: {}", e))?;
+            let hooks: serde_json::Value = serde_json::from_str(hooks_json).map_err(|e| {
+                OpcodeError::Validation(format!("Failed to parse agent hooks: {}", e))
+            })?;
 
             // Create a settings object with just the hooks
             let settings = serde_json::json!({
@@ -1026,8 +1786,9 @@ pub async fn execute_agent(
             let settings_content = serde_json::to_string_pretty(&settings)
                 .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-            std::fs::write(&settings_path, settings_content)
-                .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+            std::fs::write(&settings_path, settings_content).map_err(|e| {
+                OpcodeError::Io(format!("Failed to write settings.json: {}", e))
+            })?;
 
             tracing::info!(
                 "Created settings.json with agent hooks at: {:?}",
@@ -1038,38 +1799,77 @@ pub async fn execute_agent(
         }
     }
 
-    // Create a new run record
-    let run_id = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            params![
-                agent_id,
-                agent.name.clone(),
-                agent.icon.clone(),
-                provider_id.clone(),
-                task.clone(),
-                execution_model.clone(),
-                project_path.clone(),
-                initial_session_id.clone(),
-                "",
-            ],
+    // Create a new run record, after the optional auth pre-flight
+    let preflight = async {
+        if verify.unwrap_or(false) {
+            verify_provider_auth(&provider_id).await
+        } else {
+            Ok(())
+        }
+    };
+    let run_id = record_run_after_preflight(
+        &db,
+        preflight,
+        &PendingRun {
+            agent_id,
+            agent_name: &agent.name,
+            agent_icon: &agent.icon,
+            provider_id: &provider_id,
+            task: &task,
+            model: &execution_model,
+            project_path: &project_path,
+            session_id: &initial_session_id,
+            retry_of_run_id,
+            comparison_id,
+        },
+    )
+    .await?;
+
+    let (reasoning_effort, queued_at) = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let options = RunLaunchOptions {
+            reasoning_effort: resolve_reasoning_effort(
+                &conn,
+                &provider_id,
+                &project_path,
+                reasoning_effort,
+            ),
+            output_format,
+            env_overrides: env_overrides.clone(),
+            checkpoint_on_complete,
+            raw_output,
+        };
+        save_launch_options(&conn, run_id, &options)?;
+        (
+            options.reasoning_effort,
+            queue_run_if_at_limit(&mut conn, run_id)?,
         )
-        .map_err(|e| e.to_string())?;
-        conn.last_insert_rowid()
     };
 
+    // At the concurrency limit: park the run until a finishing run wakes the queue
+    if let Some((in_use, max_concurrent_runs)) = queued_at {
+        tracing::info!(
+            "Queued run {}: {} of {} run slots in use",
+            run_id,
+            in_use,
+            max_concurrent_runs
+        );
+        let _ = app.emit(&format!("agent-queued:{}", run_id), run_id);
+        return Ok(run_id);
+    }
+
     tracing::info!(
         "Running agent '{}' with provider '{}'",
         agent.name, provider_id
     );
-    let args = build_provider_args(
+    let args = build_agent_args(
+        &agent,
         &provider_id,
         &task,
         &execution_model,
-        Some(&agent.system_prompt),
         reasoning_effort.as_deref(),
-    );
+        output_format,
+    )?;
 
     spawn_agent_system(
         app,
@@ -1083,34 +1883,603 @@ pub async fn execute_agent(
         task,
         execution_model,
         initial_session_id,
-        db,
+        agent.first_output_timeout_secs,
+        output_format,
+        &env_overrides,
+        checkpoint_on_complete,
+        SandboxPolicy::for_agent(agent.enable_file_write, agent.enable_network),
+        raw_output,
+        db.clone(),
         registry,
     )
     .await
+    .inspect_err(|e| fail_unstarted_run(&db, run_id, e))
+    .map_err(OpcodeError::from)
 }
 
-async fn resolve_provider_binary(app: &AppHandle, provider_id: &str) -> Result<String, String> {
-    if provider_id == "claude" {
-        return find_claude_binary(app);
-    }
-
-    crate::agent_binary::discover_agent(app, provider_id)
-        .await
-        .map(|a| a.binary_path)
-        .ok_or_else(|| format!("Provider '{}' is not installed or not detected", provider_id))
+/// Per-run launch options, stored with the run so that a queued run or an
+/// automatic retry starts the way the original launch asked for
+#[derive(Default, Serialize, Deserialize)]
+struct RunLaunchOptions {
+    reasoning_effort: Option<String>,
+    #[serde(default)]
+    output_format: OutputFormat,
+    #[serde(default)]
+    env_overrides: EnvOverrides,
+    checkpoint_on_complete: Option<bool>,
+    #[serde(default)]
+    raw_output: bool,
 }
 
-fn build_provider_args(
-    provider_id: &str,
-    task: &str,
-    model: &str,
+fn save_launch_options(
+    conn: &Connection,
+    run_id: i64,
+    options: &RunLaunchOptions,
+) -> Result<(), String> {
+    let json = serde_json::to_string(options).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agent_runs SET launch_options = ?1 WHERE id = ?2",
+        params![json, run_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// A run's stored launch options; `None` for runs recorded before they were kept
+fn load_launch_options(conn: &Connection, run_id: i64) -> Option<RunLaunchOptions> {
+    conn.query_row(
+        "SELECT launch_options FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .ok()
+    .flatten()
+    .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Read a finished run's launch options and clear them, so env overrides are
+/// not kept in the database once the run is over
+fn take_launch_options(conn: &Connection, run_id: i64) -> Option<RunLaunchOptions> {
+    let options = load_launch_options(conn, run_id);
+    let _ = conn.execute(
+        "UPDATE agent_runs SET launch_options = NULL WHERE id = ?1",
+        params![run_id],
+    );
+    options
+}
+
+fn max_concurrent_agent_runs(conn: &Connection) -> usize {
+    read_app_setting(conn, MAX_CONCURRENT_RUNS_SETTING)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_RUNS)
+}
+
+/// Runs holding an execution slot: `pending` runs are starting, `running` ones have a
+/// process. `exclude` leaves out the run being decided on.
+fn runs_holding_slots(conn: &Connection, exclude: i64) -> Result<usize, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM agent_runs WHERE status IN ('pending', 'running') AND id != ?1",
+        params![exclude],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count as usize)
+    .map_err(|e| e.to_string())
+}
+
+/// Queue `run_id` when every execution slot is taken. Counting and queueing happen in
+/// one transaction, so concurrent launches can't both take the last slot. Returns the
+/// slots in use and the limit when the run was queued.
+fn queue_run_if_at_limit(
+    conn: &mut Connection,
+    run_id: i64,
+) -> Result<Option<(usize, usize)>, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let max_concurrent_runs = max_concurrent_agent_runs(&tx);
+    let in_use = runs_holding_slots(&tx, run_id)?;
+    if in_use < max_concurrent_runs {
+        return Ok(None);
+    }
+
+    tx.execute(
+        "UPDATE agent_runs SET status = ?1 WHERE id = ?2",
+        params![QUEUED_RUN_STATUS, run_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(Some((in_use, max_concurrent_runs)))
+}
+
+/// Move the oldest queued run back to `pending` and return it, if a slot is free.
+/// Checking the slot and claiming the run happen in one transaction, so a run is
+/// never started twice and the limit holds.
+fn claim_next_queued_run(conn: &mut Connection) -> Result<Option<AgentRun>, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if runs_holding_slots(&tx, 0)? >= max_concurrent_agent_runs(&tx) {
+        return Ok(None);
+    }
+    let Some(run) = query_queued_runs(&tx)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let run_id = run.id.ok_or("Queued run has no id")?;
+
+    tx.execute(
+        "UPDATE agent_runs SET status = 'pending' WHERE id = ?1 AND status = ?2",
+        params![run_id, QUEUED_RUN_STATUS],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(Some(AgentRun {
+        status: "pending".to_string(),
+        ..run
+    }))
+}
+
+/// Mark a run that never got a process as failed, freeing its execution slot
+fn fail_unstarted_run(db: &AgentDb, run_id: i64, error: &str) {
+    if let Ok(conn) = db.0.lock() {
+        let _ = conn.execute(
+            "UPDATE agent_runs
+             SET status = 'failed', stderr = ?1, launch_options = NULL,
+                 completed_at = CURRENT_TIMESTAMP
+             WHERE id = ?2 AND status = 'pending'",
+            params![error, run_id],
+        );
+    }
+}
+
+/// Wakes the run queue's drain task whenever an execution slot may have freed up
+#[derive(Default)]
+pub struct RunQueueState(tokio::sync::Notify);
+
+/// Signal the drain task that a run finished
+fn wake_run_queue(app: &AppHandle) {
+    if let Some(queue) = app.try_state::<RunQueueState>() {
+        queue.0.notify_one();
+    }
+}
+
+/// Start the long-lived task that starts queued runs, once now and then whenever woken.
+/// `RunQueueState` must already be managed.
+pub fn start_run_queue(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let queue = app.state::<RunQueueState>();
+        // Runs queued by an earlier app session start as soon as slots allow
+        drain_run_queue(&app).await;
+        loop {
+            queue.0.notified().await;
+            drain_run_queue(&app).await;
+        }
+    });
+}
+
+/// Start queued runs while execution slots are free
+async fn drain_run_queue(app: &AppHandle) {
+    let db = app.state::<AgentDb>();
+
+    loop {
+        let next = match db.0.lock() {
+            Ok(mut conn) => claim_next_queued_run(&mut conn),
+            Err(e) => Err(e.to_string()),
+        };
+        let run = match next {
+            Ok(Some(run)) => run,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("❌ Failed to claim a queued run: {}", e);
+                return;
+            }
+        };
+
+        let run_id = run.id.unwrap_or_default();
+        tracing::info!("▶️ Starting queued run {}", run_id);
+        if let Err(e) = start_queued_run(app.clone(), run).await {
+            tracing::error!("❌ Failed to start queued run {}: {}", run_id, e);
+            fail_unstarted_run(&db, run_id, &e);
+            let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+        }
+    }
+}
+
+async fn start_queued_run(app: AppHandle, run: AgentRun) -> Result<i64, String> {
+    let db = app.state::<AgentDb>();
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let run_id = run.id.ok_or("Queued run has no id")?;
+
+    let agent = get_runnable_agent(db.clone(), run.agent_id).await?;
+    let binary_path = match pinned_claude_binary(&agent, &run.provider_id)? {
+        Some(path) => path,
+        None => resolve_provider_binary(&app, &run.provider_id).await?,
+    };
+    let options = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_launch_options(&conn, run_id).unwrap_or_else(|| RunLaunchOptions {
+            reasoning_effort: resolve_reasoning_effort(
+                &conn,
+                &run.provider_id,
+                &run.project_path,
+                None,
+            ),
+            ..RunLaunchOptions::default()
+        })
+    };
+    let RunLaunchOptions {
+        reasoning_effort,
+        output_format,
+        env_overrides,
+        checkpoint_on_complete,
+        raw_output,
+    } = options;
+    let args = build_agent_args(
+        &agent,
+        &run.provider_id,
+        &run.task,
+        &run.model,
+        reasoning_effort.as_deref(),
+        output_format,
+    )?;
+
+    spawn_agent_system(
+        app.clone(),
+        run_id,
+        run.agent_id,
+        run.agent_name,
+        run.provider_id,
+        binary_path,
+        args,
+        run.project_path,
+        run.task,
+        run.model,
+        run.session_id,
+        agent.first_output_timeout_secs,
+        output_format,
+        &env_overrides,
+        checkpoint_on_complete,
+        SandboxPolicy::for_agent(agent.enable_file_write, agent.enable_network),
+        raw_output,
+        db,
+        registry,
+    )
+    .await
+}
+
+/// Full provider argument list for running `agent` on `task`
+fn build_agent_args(
+    agent: &Agent,
+    provider_id: &str,
+    task: &str,
+    execution_model: &str,
+    reasoning_effort: Option<&str>,
+    output_format: OutputFormat,
+) -> Result<Vec<String>, String> {
+    let extra_args = parse_extra_args(agent.extra_args.as_deref())?;
+    let mut args = build_provider_args(
+        provider_id,
+        task,
+        execution_model,
+        Some(&agent.system_prompt),
+        reasoning_effort,
+        &extra_args,
+    );
+    apply_output_format(provider_id, &mut args, output_format);
+    Ok(args)
+}
+
+/// Quote `arg` for a POSIX shell when it contains anything beyond plain word characters
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Command line `execute_agent` would run, ready to paste into a terminal. A
+/// sandbox warning is appended as a shell comment.
+fn format_command_preview(launch: &SandboxedCommand, cwd: &str) -> String {
+    let mut command = format!("cd {} && ", shell_quote(cwd));
+    for (key, value) in &launch.env {
+        command.push_str(&format!("{}={} ", key, shell_quote(value)));
+    }
+    command.push_str(&shell_quote(&launch.program));
+    for arg in &launch.args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    if let Some(warning) = &launch.warning {
+        command.push_str(&format!("\n# {}", warning));
+    }
+    command
+}
+
+/// Build the command a run would spawn without recording a run or starting a process
+async fn preview_agent_command(
+    app: &AppHandle,
+    db: &State<'_, AgentDb>,
+    agent_id: i64,
+    project_path: &str,
+    task: &str,
+    model: Option<String>,
+    reasoning_effort: Option<String>,
+    output_format: OutputFormat,
+) -> Result<String, String> {
+    let agent = get_runnable_agent(db.clone(), agent_id).await?;
+    let provider_id = if agent.provider_id.is_empty() {
+        "claude".to_string()
+    } else {
+        agent.provider_id.clone()
+    };
+    let execution_model = model.unwrap_or(agent.model.clone());
+    let binary_path = match pinned_claude_binary(&agent, &provider_id)? {
+        Some(path) => path,
+        None => resolve_provider_binary(app, &provider_id).await?,
+    };
+    let reasoning_effort = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        resolve_reasoning_effort(&conn, &provider_id, project_path, reasoning_effort)
+    };
+
+    let args = build_agent_args(
+        &agent,
+        &provider_id,
+        task,
+        &execution_model,
+        reasoning_effort.as_deref(),
+        output_format,
+    )?;
+    let launch = sandbox::sandboxed_command(
+        SandboxPolicy::for_agent(agent.enable_file_write, agent.enable_network),
+        &binary_path,
+        args,
+    );
+    Ok(format_command_preview(&launch, project_path))
+}
+
+/// Task used by `smoke_test_agent`; cheap for any provider to answer
+pub const SMOKE_TEST_TASK: &str = "Reply with exactly: OK";
+/// How long a smoke test may run before the agent is killed
+pub const SMOKE_TEST_TIMEOUT_SECS: u64 = 60;
+/// Longest output excerpt returned from a smoke test
+const SMOKE_TEST_EXCERPT_CHARS: usize = 500;
+
+/// Outcome of `smoke_test_agent`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentSmokeTestResult {
+    pub succeeded: bool,
+    pub output_excerpt: String,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+/// How the smoke-test process ended
+#[derive(Debug)]
+enum SmokeTestOutcome {
+    Exited { success: bool, exit_code: Option<i32> },
+    TimedOut,
+    Failed(String),
+}
+
+/// Last `max_chars` characters of `text`
+fn tail_chars(text: &str, max_chars: usize) -> &str {
+    let skip = text.chars().count().saturating_sub(max_chars);
+    match text.char_indices().nth(skip) {
+        Some((index, _)) => &text[index..],
+        None => "",
+    }
+}
+
+/// The final answer when the output ends with a Claude-style `result` event,
+/// otherwise the tail of the raw output
+fn smoke_test_excerpt(stdout: &str) -> String {
+    let result_text = stdout.lines().rev().find_map(|line| {
+        let json = serde_json::from_str::<JsonValue>(line).ok()?;
+        if json.get("type").and_then(|t| t.as_str()) != Some("result") {
+            return None;
+        }
+        json.get("result")
+            .and_then(|r| r.as_str())
+            .map(str::to_string)
+    });
+
+    let text = result_text.unwrap_or_else(|| stdout.trim().to_string());
+    tail_chars(&text, SMOKE_TEST_EXCERPT_CHARS).to_string()
+}
+
+fn smoke_test_result(
+    outcome: SmokeTestOutcome,
+    stdout: &str,
+    stderr: &str,
+    duration_ms: i64,
+) -> AgentSmokeTestResult {
+    let output_excerpt = smoke_test_excerpt(stdout);
+    let error = match outcome {
+        SmokeTestOutcome::Exited { success: true, .. } if output_excerpt.is_empty() => {
+            Some("Agent exited without producing any output".to_string())
+        }
+        SmokeTestOutcome::Exited { success: true, .. } => None,
+        SmokeTestOutcome::Exited { exit_code, .. } => {
+            let code = exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "none (killed by signal)".to_string());
+            match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                Some(last_error) => Some(format!(
+                    "Agent exited with code {}: {}",
+                    code,
+                    last_error.trim()
+                )),
+                None => Some(format!("Agent exited with code {}", code)),
+            }
+        }
+        SmokeTestOutcome::TimedOut => Some(format!(
+            "Agent did not finish within {} seconds",
+            SMOKE_TEST_TIMEOUT_SECS
+        )),
+        SmokeTestOutcome::Failed(message) => Some(message),
+    };
+
+    AgentSmokeTestResult {
+        succeeded: error.is_none(),
+        output_excerpt,
+        duration_ms,
+        error,
+    }
+}
+
+/// Run an agent once with a trivial task in a throwaway directory to check
+/// it works end to end. No run record is written.
+#[tauri::command]
+pub async fn smoke_test_agent(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<AgentSmokeTestResult, String> {
+    let agent = get_runnable_agent(db.clone(), agent_id).await?;
+    let provider_id = if agent.provider_id.is_empty() {
+        "claude".to_string()
+    } else {
+        agent.provider_id.clone()
+    };
+
+    let binary_path = match pinned_claude_binary(&agent, &provider_id) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            let runtime_status = provider_runtime_status(&app, &provider_id).await?;
+            if !runtime_status.ready {
+                return Ok(smoke_test_result(
+                    SmokeTestOutcome::Failed(provider_runtime_error(&runtime_status)),
+                    "",
+                    "",
+                    0,
+                ));
+            }
+            match runtime_status.detected_binary {
+                Some(path) => path,
+                None => resolve_provider_binary(&app, &provider_id).await?,
+            }
+        }
+        Err(e) => return Ok(smoke_test_result(SmokeTestOutcome::Failed(e), "", "", 0)),
+    };
+
+    let work_dir = tempfile::tempdir()
+        .map_err(|e| format!("Failed to create smoke test directory: {}", e))?;
+    let extra_args = parse_extra_args(agent.extra_args.as_deref())?;
+    let args = build_provider_args(
+        &provider_id,
+        SMOKE_TEST_TASK,
+        &agent.model,
+        Some(&agent.system_prompt),
+        None,
+        &extra_args,
+    );
+    let mut cmd = create_agent_system_command(
+        &binary_path,
+        &binary_path,
+        args,
+        &work_dir.path().to_string_lossy(),
+        &EnvOverrides::new(),
+    );
+    // Dropping the wait future on timeout must take the process with it
+    cmd.kill_on_drop(true);
+
+    tracing::info!("Smoke testing agent {} with provider '{}'", agent_id, provider_id);
+    let start = std::time::Instant::now();
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            return Ok(smoke_test_result(
+                SmokeTestOutcome::Failed(format!("Failed to spawn {}: {}", provider_id, e)),
+                "",
+                "",
+                0,
+            ))
+        }
+    };
+
+    let waited = tokio::time::timeout(
+        std::time::Duration::from_secs(SMOKE_TEST_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await;
+    let duration_ms = start.elapsed().as_millis() as i64;
+
+    let result = match waited {
+        Ok(Ok(output)) => smoke_test_result(
+            SmokeTestOutcome::Exited {
+                success: output.status.success(),
+                exit_code: output.status.code(),
+            },
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+            duration_ms,
+        ),
+        Ok(Err(e)) => smoke_test_result(
+            SmokeTestOutcome::Failed(format!("Failed to wait for {}: {}", provider_id, e)),
+            "",
+            "",
+            duration_ms,
+        ),
+        Err(_) => smoke_test_result(SmokeTestOutcome::TimedOut, "", "", duration_ms),
+    };
+    Ok(result)
+}
+
+/// Reject an agent `binary_path` that isn't a runnable Claude CLI
+fn validate_agent_binary_path(path: Option<&str>) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            let validation = validate_claude_binary_path(path);
+            if validation.ok {
+                Ok(())
+            } else {
+                Err(validation.message)
+            }
+        }
+        None => Ok(()),
+    }
+}
+
+/// The Claude binary `agent` is pinned to, re-validated since the file may have
+/// changed since it was saved. `None` for other providers or unpinned agents.
+fn pinned_claude_binary(agent: &Agent, provider_id: &str) -> Result<Option<String>, String> {
+    if provider_id != "claude" {
+        return Ok(None);
+    }
+    let Some(path) = agent.binary_path.as_deref().filter(|path| !path.trim().is_empty()) else {
+        return Ok(None);
+    };
+    validate_agent_binary_path(Some(path)).map_err(|e| {
+        format!("Agent '{}' is pinned to an unusable Claude binary: {}", agent.name, e)
+    })?;
+    Ok(Some(path.to_string()))
+}
+
+async fn resolve_provider_binary(app: &AppHandle, provider_id: &str) -> Result<String, String> {
+    if provider_id == "claude" {
+        return find_claude_binary(app);
+    }
+
+    crate::agent_binary::discover_agent(app, provider_id)
+        .await
+        .map(|a| a.binary_path)
+        .ok_or_else(|| format!("Provider '{}' is not installed or not detected", provider_id))
+}
+
+fn build_provider_args(
+    provider_id: &str,
+    task: &str,
+    model: &str,
     system_prompt: Option<&str>,
     reasoning_effort: Option<&str>,
+    extra_args: &[String],
 ) -> Vec<String> {
     let model = model.trim();
     let has_explicit_model = !model.is_empty() && !model.eq_ignore_ascii_case("default");
 
-    match provider_id {
+    let mut args = match provider_id {
         "claude" => {
             let mut args = vec![
                 "-p".to_string(),
@@ -1153,8 +2522,21 @@ fn build_provider_args(
             if has_explicit_model {
                 args.extend(["--model".to_string(), model.to_string()]);
             }
+            // Aider has no effort setting; high effort switches to architect mode
+            match sanitize_reasoning_effort(reasoning_effort) {
+                Some("high" | "xhigh") => args.push("--architect".to_string()),
+                Some(_) => {}
+                None if reasoning_effort.is_some() => {
+                    tracing::warn!(
+                        "Ignoring invalid aider reasoning effort: {:?}",
+                        reasoning_effort
+                    );
+                }
+                None => {}
+            }
             args
         }
+        // Gemini and opencode expose no reasoning-effort flag, so the effort is not passed on
         "gemini" => {
             let mut args = vec![
                 "--prompt".to_string(),
@@ -1184,13 +2566,122 @@ fn build_provider_args(
             args
         }
         "opencode" => {
-            let mut args = vec!["run".to_string(), task.to_string()];
+            let mut args = vec![
+                "run".to_string(),
+                task.to_string(),
+                "--format".to_string(),
+                "json".to_string(),
+            ];
             if has_explicit_model {
                 args.extend(["--model".to_string(), model.to_string()]);
             }
             args
         }
         _ => vec![task.to_string()],
+    };
+    // Agent-declared extra args go last, verbatim
+    args.extend_from_slice(extra_args);
+    args
+}
+
+/// Reject provider ids the runtime registry doesn't know, so typos fail at save
+/// time instead of on every run
+fn validate_provider_id(provider_id: &str) -> Result<(), String> {
+    if crate::providers::runtime::is_known_provider(provider_id) {
+        return Ok(());
+    }
+
+    let valid_ids = crate::providers::runtime::list_provider_capabilities()
+        .into_iter()
+        .map(|capability| capability.provider_id)
+        .collect::<Vec<_>>();
+    Err(format!(
+        "Unknown provider '{}'. Valid providers: {}",
+        provider_id,
+        valid_ids.join(", ")
+    ))
+}
+
+/// Parse an agent's `extra_args` column: a JSON array of strings, or nothing
+fn parse_extra_args(raw: Option<&str>) -> Result<Vec<String>, String> {
+    match raw.map(str::trim).filter(|raw| !raw.is_empty()) {
+        Some(raw) => serde_json::from_str::<Vec<String>>(raw)
+            .map_err(|e| format!("extra_args must be a JSON array of strings: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Per-run environment overrides; an empty value unsets the variable
+type EnvOverrides = std::collections::BTreeMap<String, String>;
+
+/// Parse `execute_agent`'s `env_overrides`: a JSON object of string values, or nothing
+fn parse_env_overrides(raw: Option<&str>) -> Result<EnvOverrides, String> {
+    let Some(raw) = raw.map(str::trim).filter(|raw| !raw.is_empty()) else {
+        return Ok(EnvOverrides::new());
+    };
+
+    let overrides = serde_json::from_str::<EnvOverrides>(raw)
+        .map_err(|e| format!("env_overrides must be a JSON object of strings: {}", e))?;
+    if let Some(key) = overrides
+        .keys()
+        .find(|key| key.is_empty() || key.contains('=') || key.contains('\0'))
+    {
+        return Err(format!(
+            "Invalid environment variable name in env_overrides: {:?}",
+            key
+        ));
+    }
+    Ok(overrides)
+}
+
+/// How the provider is asked to write its output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OutputFormat {
+    /// Structured events, converted to the Claude stream shape (the default)
+    #[default]
+    StreamJson,
+    /// Plain text; every line is wrapped as assistant text as-is
+    Text,
+}
+
+fn parse_output_format(value: Option<&str>) -> Result<OutputFormat, String> {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        None | Some("stream-json") => Ok(OutputFormat::StreamJson),
+        Some("text") => Ok(OutputFormat::Text),
+        Some(other) => Err(format!(
+            "Unsupported output format '{}'; expected 'stream-json' or 'text'",
+            other
+        )),
+    }
+}
+
+/// Switch provider args built by `build_provider_args` to plain-text output
+/// where the provider supports it. Providers that already print text are left alone.
+fn apply_output_format(provider_id: &str, args: &mut Vec<String>, output_format: OutputFormat) {
+    if output_format == OutputFormat::StreamJson {
+        return;
+    }
+
+    if let Some(index) = args.iter().rposition(|arg| arg == "--output-format") {
+        if let Some(value) = args.get_mut(index + 1) {
+            *value = "text".to_string();
+        }
+    }
+    match provider_id {
+        // --verbose is only required alongside stream-json
+        "claude" => args.retain(|arg| arg != "--verbose"),
+        "codex" => {
+            if let Some(index) = args.iter().position(|arg| arg == "--json") {
+                args.remove(index);
+            }
+        }
+        "opencode" => {
+            if let Some(index) = args.iter().position(|arg| arg == "--format") {
+                args.drain(index..(index + 2).min(args.len()));
+            }
+        }
+        _ => {}
     }
 }
 
@@ -1213,20 +2704,166 @@ fn sanitize_reasoning_effort(reasoning_effort: Option<&str>) -> Option<&'static
     }
 }
 
-fn wrap_as_assistant_text(text: &str) -> String {
-    serde_json::json!({
-        "type": "assistant",
-        "message": {
-            "content": [{ "type": "text", "text": text }]
+/// `app_settings` key prefix for a provider's default reasoning effort,
+/// e.g. `default_reasoning_effort.codex`
+pub const PROVIDER_REASONING_EFFORT_PREFIX: &str = "default_reasoning_effort.";
+/// `app_settings` key prefix for a project's default reasoning effort, keyed by project path
+pub const PROJECT_REASONING_EFFORT_PREFIX: &str = "project_reasoning_effort.";
+
+/// Read a non-blank `app_settings` value
+pub(crate) fn read_app_setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .filter(|value| !value.trim().is_empty())
+}
+
+/// Reasoning effort for a run: the explicit value, else the project default,
+/// else the provider default, else none
+fn resolve_reasoning_effort(
+    conn: &Connection,
+    provider_id: &str,
+    project_path: &str,
+    explicit: Option<String>,
+) -> Option<String> {
+    explicit
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| {
+            read_app_setting(
+                conn,
+                &format!("{}{}", PROJECT_REASONING_EFFORT_PREFIX, project_path),
+            )
+        })
+        .or_else(|| {
+            read_app_setting(
+                conn,
+                &format!("{}{}", PROVIDER_REASONING_EFFORT_PREFIX, provider_id),
+            )
+        })
+}
+
+/// Set or clear (with `None`) the default reasoning effort for a provider,
+/// or for a project when `project_path` is given
+#[tauri::command]
+pub async fn set_default_reasoning_effort(
+    db: State<'_, AgentDb>,
+    provider_id: String,
+    project_path: Option<String>,
+    reasoning_effort: Option<String>,
+) -> Result<(), String> {
+    let key = match project_path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => format!("{}{}", PROJECT_REASONING_EFFORT_PREFIX, path),
+        None => format!("{}{}", PROVIDER_REASONING_EFFORT_PREFIX, provider_id),
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match reasoning_effort.filter(|value| !value.trim().is_empty()) {
+        Some(value) => {
+            let effort = sanitize_reasoning_effort(Some(&value))
+                .ok_or_else(|| format!("Invalid reasoning effort: {}", value))?;
+            conn.execute(
+                "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![key, effort],
+            )
+            .map_err(|e| format!("Failed to save reasoning effort default: {}", e))?;
+        }
+        None => {
+            conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])
+                .map_err(|e| format!("Failed to clear reasoning effort default: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+fn wrap_as_assistant_text(text: &str) -> String {
+    serde_json::json!({
+        "type": "assistant",
+        "message": {
+            "content": [{ "type": "text", "text": text }]
         }
     })
     .to_string()
 }
 
-fn transform_provider_output(provider_id: &str, line: &str) -> Option<String> {
+/// Pipe a provider line was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Payload of the `agent-raw:{run_id}` event: a provider line exactly as printed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RawOutputLine {
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+/// Forwards untransformed provider lines, tagged with their stream, for runs that
+/// opted into raw output. Disabled taps drop lines without allocating.
+#[derive(Clone, Default)]
+struct RawOutputTap(Option<std::sync::Arc<dyn Fn(RawOutputLine) + Send + Sync>>);
+
+impl RawOutputTap {
+    fn for_run(app: &AppHandle, run_id: i64, enabled: bool) -> Self {
+        if !enabled {
+            return Self::default();
+        }
+        let app = app.clone();
+        let event = format!("agent-raw:{}", run_id);
+        Self(Some(std::sync::Arc::new(move |raw| {
+            let _ = app.emit(&event, &raw);
+        })))
+    }
+
+    fn send(&self, stream: OutputStream, line: &str) {
+        if let Some(emit) = &self.0 {
+            emit(RawOutputLine {
+                stream,
+                line: line.to_string(),
+            });
+        }
+    }
+}
+
+/// Record a provider session id for a running agent, in memory and in `agent_runs`
+fn store_extracted_session_id(
+    session_id: &Mutex<String>,
+    db_path: &std::path::Path,
+    run_id: i64,
+    sid: &str,
+) {
+    if let Ok(mut current_session_id) = session_id.lock() {
+        *current_session_id = sid.to_string();
+    }
+    tracing::info!("🔑 Extracted session ID: {}", sid);
+
+    if let Ok(conn) = Connection::open(db_path) {
+        match conn.execute(
+            "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
+            params![sid, run_id],
+        ) {
+            Ok(rows) => {
+                if rows > 0 {
+                    tracing::info!("✅ Updated agent run {} with session ID immediately", run_id);
+                }
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to update session ID immediately: {}", e);
+            }
+        }
+    }
+}
+
+pub(crate) fn transform_provider_output(provider_id: &str, line: &str) -> Option<String> {
     match provider_id {
         "claude" => Some(line.to_string()),
         "codex" => crate::commands::codex_transform::transform_codex_line(line),
+        "opencode" => crate::commands::opencode_transform::transform_opencode_line(line),
         _ => {
             // For unknown provider JSON formats, wrap as text unless it's already
             // in Claude-compatible stream shape.
@@ -1245,13 +2882,52 @@ fn transform_provider_output(provider_id: &str, line: &str) -> Option<String> {
     }
 }
 
-/// Creates a system binary command for agent execution
+fn is_default_model(model: &str) -> bool {
+    let model = model.trim();
+    model.is_empty() || model.eq_ignore_ascii_case("default")
+}
+
+/// Extract the concrete model Claude picked when the run was started with `default`.
+/// The init line carries `model`; the result line carries a `modelUsage` map.
+fn resolve_concrete_model(requested_model: &str, json: &JsonValue) -> Option<String> {
+    if !is_default_model(requested_model) {
+        return None;
+    }
+
+    let resolved = match json.get("type").and_then(|t| t.as_str()) {
+        Some("system") if json.get("subtype").and_then(|s| s.as_str()) == Some("init") => json
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(str::to_string),
+        Some("result") => json
+            .get("modelUsage")
+            .and_then(|usage| usage.as_object())
+            .and_then(|usage| usage.keys().next().cloned()),
+        _ => None,
+    };
+
+    resolved.filter(|model| !is_default_model(model))
+}
+
+/// Replace a run's `default` model with the concrete model reported by the provider
+fn persist_resolved_model(conn: &Connection, run_id: i64, model: &str) -> SqliteResult<usize> {
+    conn.execute(
+        "UPDATE agent_runs SET model = ?1
+         WHERE id = ?2 AND (model = '' OR LOWER(model) = 'default')",
+        params![model, run_id],
+    )
+}
+
+/// Creates a system binary command for agent execution. `program` is what runs,
+/// either the provider binary at `binary_path` or the sandbox wrapping it.
 fn create_agent_system_command(
+    program: &str,
     binary_path: &str,
     args: Vec<String>,
     project_path: &str,
+    env_overrides: &EnvOverrides,
 ) -> Command {
-    let mut cmd = create_command_with_env(binary_path);
+    let mut cmd = create_command_with_env(program, binary_path, env_overrides);
 
     // Add all arguments
     for arg in args {
@@ -1266,6 +2942,15 @@ fn create_agent_system_command(
     cmd
 }
 
+/// Number of polls the monitor makes while waiting for first output.
+/// A timeout of 0 (or less) means never time out, so no polls are made.
+fn first_output_poll_limit(timeout_secs: i64) -> u64 {
+    if timeout_secs <= 0 {
+        return 0;
+    }
+    (timeout_secs as u64).saturating_mul(1000) / FIRST_OUTPUT_POLL_INTERVAL_MS
+}
+
 /// Spawn agent using system binary command
 async fn spawn_agent_system(
     app: AppHandle,
@@ -1279,11 +2964,29 @@ async fn spawn_agent_system(
     task: String,
     execution_model: String,
     initial_session_id: String,
+    first_output_timeout_secs: i64,
+    output_format: OutputFormat,
+    env_overrides: &EnvOverrides,
+    checkpoint_on_complete: Option<bool>,
+    sandbox_policy: SandboxPolicy,
+    raw_output: bool,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
-    // Build the command
-    let mut cmd = create_agent_system_command(&binary_path, args, &project_path);
+    // Build the command, inside a sandbox when the agent's permissions call for one
+    let launch = sandbox::sandboxed_command(sandbox_policy, &binary_path, args);
+    if let Some(warning) = &launch.warning {
+        tracing::warn!("⚠️ Run {}: {}", run_id, warning);
+        let _ = app.emit(&format!("agent-error:{}", run_id), warning);
+    }
+    let mut cmd = create_agent_system_command(
+        &launch.program,
+        &binary_path,
+        launch.args,
+        &project_path,
+        env_overrides,
+    );
+    cmd.envs(launch.env);
 
     // Spawn the process
     tracing::info!("🚀 Spawning {} system process...", provider_id);
@@ -1305,10 +3008,18 @@ async fn spawn_agent_system(
     // Update the database with PID and status
     {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2 WHERE id = ?3",
-            params![pid as i64, now, run_id],
-        ).map_err(|e| e.to_string())?;
+        let started = conn
+            .execute(
+                "UPDATE agent_runs SET status = 'running', pid = ?1, process_started_at = ?2
+                 WHERE id = ?3 AND status = 'pending'",
+                params![pid as i64, now, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if started == 0 {
+            // Cancelled while starting, e.g. by a panic stop
+            let _ = child.start_kill();
+            return Err(format!("Run {} was cancelled before it started", run_id));
+        }
         tracing::info!("📝 Updated database with running status and PID");
     }
 
@@ -1318,6 +3029,10 @@ async fn spawn_agent_system(
     tracing::info!("📡 Set up stdout/stderr readers");
 
     // Create readers
+    let max_line_bytes = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        crate::process::output_lines::load_max_line_bytes(&conn)
+    };
     let stdout_reader = TokioBufReader::new(stdout);
     let stderr_reader = TokioBufReader::new(stderr);
 
@@ -1334,6 +3049,9 @@ async fn spawn_agent_system(
     // Shared state for collecting session ID and live output
     let session_id = std::sync::Arc::new(Mutex::new(initial_session_id.clone()));
     let live_output = std::sync::Arc::new(Mutex::new(String::new()));
+    // Raw stderr, kept apart from live output and persisted when the run ends
+    let stderr_output = std::sync::Arc::new(Mutex::new(String::new()));
+    let rate_limit_signal = std::sync::Arc::new(Mutex::new(None::<RateLimitSignal>));
     let start_time = std::time::Instant::now();
 
     // Non-Claude providers don't emit a Claude-style init event, so emit one ourselves.
@@ -1369,14 +3087,31 @@ async fn spawn_agent_system(
     let first_output_clone = first_output.clone();
     let db_path_for_stdout = db_path.clone(); // Clone the db_path for the stdout task
     let provider_stdout = provider_id.clone();
+    let project_path_stdout = PathBuf::from(&project_path);
+    let model_stdout = execution_model.clone();
+    let rate_limit_stdout = rate_limit_signal.clone();
+    let raw_tap = RawOutputTap::for_run(&app, run_id, raw_output);
+    let raw_tap_stdout = raw_tap.clone();
 
     let stdout_task = tokio::spawn(async move {
         tracing::info!("📖 Starting to read {} stdout...", provider_stdout);
-        let mut lines = stdout_reader.lines();
+        let mut lines = CappedLines::new(stdout_reader, max_line_bytes);
         let mut line_count = 0;
+        let mut model_resolved = !is_default_model(&model_stdout);
+        let mut provider_session_extracted = false;
+        let mut metrics = RunMetricsAccumulator::default();
 
         while let Ok(Some(line)) = lines.next_line().await {
             line_count += 1;
+            raw_tap_stdout.send(OutputStream::Stdout, &line);
+
+            if line_count == 1 {
+                if let Ok(conn) = Connection::open(&db_path_for_stdout) {
+                    if let Err(e) = record_first_output_at(&conn, run_id) {
+                        tracing::error!("❌ Failed to record first output time: {}", e);
+                    }
+                }
+            }
 
             // Log first output
             if !first_output_clone.load(std::sync::atomic::Ordering::Relaxed) {
@@ -1394,56 +3129,121 @@ async fn spawn_agent_system(
                 tracing::debug!("stdout[{}]: {}", line_count, line);
             }
 
-            let Some(emitted_line) = transform_provider_output(&provider_stdout, &line) else {
+            // Codex reports its thread id on an event the transform drops, so check the
+            // raw line; it replaces the placeholder id the run was created with.
+            if provider_stdout == "codex" && !provider_session_extracted {
+                let codex_session = crate::commands::codex_transform::extract_codex_session_id(&line);
+                if let Some(sid) = codex_session {
+                    provider_session_extracted = true;
+                    store_extracted_session_id(
+                        &session_id_clone,
+                        &db_path_for_stdout,
+                        run_id,
+                        &sid,
+                    );
+                }
+            }
+
+            let emitted = match output_format {
+                OutputFormat::StreamJson => transform_provider_output(&provider_stdout, &line),
+                OutputFormat::Text => Some(wrap_as_assistant_text(&line)),
+            };
+            let Some(emitted) = emitted else {
                 continue;
             };
 
-            if let Ok(mut output) = live_output_clone.lock() {
-                output.push_str(&emitted_line);
-                output.push('\n');
+            for edit in crate::commands::file_edits::extract_file_edits(
+                &provider_stdout,
+                &project_path_stdout,
+                &line,
+                &emitted,
+            ) {
+                let _ = app_handle.emit(&format!("file-edit:{}", run_id), &edit);
             }
 
-            let _ = registry_clone.append_live_output(run_id, &emitted_line);
-
-            // Extract session ID from JSONL output
-            if provider_stdout == "claude" {
-                if let Ok(json) = serde_json::from_str::<JsonValue>(&emitted_line) {
-                // Claude Code uses "session_id" (underscore), not "sessionId"
-                    if json.get("type").and_then(|t| t.as_str()) == Some("system")
-                        && json.get("subtype").and_then(|s| s.as_str()) == Some("init")
-                    {
-                        if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
-                            if let Ok(mut current_session_id) = session_id_clone.lock() {
-                                if current_session_id.is_empty() {
-                                    *current_session_id = sid.to_string();
-                                    tracing::info!("🔑 Extracted session ID: {}", sid);
-
-                                    if let Ok(conn) = Connection::open(&db_path_for_stdout) {
-                                        match conn.execute(
-                                            "UPDATE agent_runs SET session_id = ?1 WHERE id = ?2",
-                                            params![sid, run_id],
-                                        ) {
-                                            Ok(rows) => {
-                                                if rows > 0 {
-                                                    tracing::info!("✅ Updated agent run {} with session ID immediately", run_id);
-                                                }
-                                            }
-                                            Err(e) => {
-                                                tracing::error!("❌ Failed to update session ID immediately: {}", e);
-                                            }
-                                        }
+            // A transform may expand one provider line into several stream lines
+            for emitted_line in emitted.lines() {
+                if let Ok(mut output) = live_output_clone.lock() {
+                    output.push_str(emitted_line);
+                    output.push('\n');
+                }
+
+                let _ = registry_clone.append_live_output(run_id, emitted_line);
+
+                if metrics.push_line(emitted_line) {
+                    let _ = app_handle.emit(&format!("agent-metrics:{}", run_id), metrics.live());
+                }
+
+                // Extract session ID from JSONL output
+                if provider_stdout == "claude" {
+                    if let Ok(json) = serde_json::from_str::<JsonValue>(emitted_line) {
+                        if let Some(signal) = rate_limit::detect_rate_limit_in_event(&json) {
+                            note_rate_limit(&rate_limit_stdout, signal);
+                        }
+
+                        // Claude Code uses "session_id" (underscore), not "sessionId"
+                        if json.get("type").and_then(|t| t.as_str()) == Some("system")
+                            && json.get("subtype").and_then(|s| s.as_str()) == Some("init")
+                        {
+                            if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+                                let unset = session_id_clone
+                                    .lock()
+                                    .map(|current| current.is_empty())
+                                    .unwrap_or(false);
+                                if unset {
+                                    store_extracted_session_id(
+                                        &session_id_clone,
+                                        &db_path_for_stdout,
+                                        run_id,
+                                        sid,
+                                    );
+                                }
+                            }
+                        }
+
+                        // Record the concrete model when the run was started with `default`
+                        if !model_resolved {
+                            if let Some(resolved_model) =
+                                resolve_concrete_model(&model_stdout, &json)
+                            {
+                                model_resolved = true;
+                                tracing::info!("🧠 Resolved default model to: {}", resolved_model);
+
+                                if let Ok(conn) = Connection::open(&db_path_for_stdout) {
+                                    if let Err(e) =
+                                        persist_resolved_model(&conn, run_id, &resolved_model)
+                                    {
+                                        tracing::error!("❌ Failed to persist resolved model: {}", e);
                                     }
                                 }
+
+                                let _ = app_handle.emit(
+                                    &format!("agent-model-resolved:{}", run_id),
+                                    &resolved_model,
+                                );
                             }
                         }
                     }
                 }
+
+                // Emit the line to the frontend with run_id for isolation
+                let _ = app_handle.emit(&format!("agent-output:{}", run_id), emitted_line);
+                // Also emit to the generic event for backward compatibility
+                let _ = app_handle.emit("agent-output", emitted_line);
             }
+        }
 
-            // Emit the line to the frontend with run_id for isolation
-            let _ = app_handle.emit(&format!("agent-output:{}", run_id), &emitted_line);
-            // Also emit to the generic event for backward compatibility
-            let _ = app_handle.emit("agent-output", &emitted_line);
+        // opencode reports usage per step, so its run gets a single result line here
+        if provider_stdout == "opencode" && matches!(output_format, OutputFormat::StreamJson) {
+            let result_line =
+                crate::commands::opencode_transform::run_result_line(metrics.live().cost_usd);
+            if let Ok(mut output) = live_output_clone.lock() {
+                output.push_str(&result_line);
+                output.push('\n');
+            }
+            let _ = registry_clone.append_live_output(run_id, &result_line);
+            let _ = app_handle.emit(&format!("agent-output:{}", run_id), &result_line);
+            let _ = app_handle.emit("agent-output", &result_line);
         }
 
         tracing::info!(
@@ -1457,15 +3257,19 @@ async fn spawn_agent_system(
     let first_error_clone = first_error.clone();
     let provider_stderr = provider_id.clone();
     let live_output_stderr = live_output.clone();
+    let stderr_output_reader = stderr_output.clone();
     let registry_stderr = registry.0.clone();
+    let rate_limit_stderr = rate_limit_signal.clone();
+    let raw_tap_stderr = raw_tap;
 
     let stderr_task = tokio::spawn(async move {
         tracing::info!("📖 Starting to read {} stderr...", provider_stderr);
-        let mut lines = stderr_reader.lines();
+        let mut lines = CappedLines::new(stderr_reader, max_line_bytes);
         let mut error_count = 0;
 
         while let Ok(Some(line)) = lines.next_line().await {
             error_count += 1;
+            raw_tap_stderr.send(OutputStream::Stderr, &line);
 
             // Log first error
             if !first_error_clone.load(std::sync::atomic::Ordering::Relaxed) {
@@ -1477,8 +3281,14 @@ async fn spawn_agent_system(
             }
 
             tracing::error!("stderr[{}]: {}", error_count, line);
+            if let Ok(mut stderr) = stderr_output_reader.lock() {
+                append_run_stderr(&mut stderr, &line, MAX_RUN_STDERR_BYTES);
+            }
 
             if provider_stderr == "claude" {
+                if let Some(signal) = rate_limit::detect_rate_limit(&line) {
+                    note_rate_limit(&rate_limit_stderr, signal);
+                }
                 let _ = app_handle_stderr.emit(&format!("agent-error:{}", run_id), &line);
                 let _ = app_handle_stderr.emit("agent-error", &line);
                 continue;
@@ -1512,7 +3322,7 @@ async fn spawn_agent_system(
         .register_sidecar_process(
             run_id,
             agent_id,
-            agent_name,
+            agent_name.clone(),
             pid,
             project_path.clone(),
             task.clone(),
@@ -1523,34 +3333,42 @@ async fn spawn_agent_system(
 
     let db_path_for_monitor = db_path.clone(); // Clone for the monitor task
     let provider_monitor = provider_id.clone();
+    let project_path_monitor = project_path.clone();
+    let agent_name_monitor = agent_name;
+    let checkpoint_on_complete = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        resolve_checkpoint_on_complete(&conn, checkpoint_on_complete)
+    };
     let initial_session_id_monitor = if let Ok(sid) = session_id.lock() {
         sid.clone()
     } else {
         String::new()
     };
     let live_output_monitor = live_output.clone();
+    let stderr_output_monitor = stderr_output.clone();
     let registry_monitor = registry.0.clone();
+    let rate_limit_monitor = rate_limit_signal.clone();
     let mut child_for_wait = child;
 
     // Monitor process status and wait for completion
     tokio::spawn(async move {
         tracing::info!("🕐 Starting process monitoring...");
 
-        // Wait for first output with timeout
-        for i in 0..300 {
-            // 30 seconds (300 * 100ms)
+        // Wait for first output with timeout; a limit of 0 skips the wait entirely
+        let poll_limit = first_output_poll_limit(first_output_timeout_secs);
+        for i in 0..poll_limit {
             if first_output.load(std::sync::atomic::Ordering::Relaxed) {
                 tracing::info!(
                     "✅ Output detected after {}ms, continuing normal execution",
-                    i * 100
+                    i * FIRST_OUTPUT_POLL_INTERVAL_MS
                 );
                 break;
             }
 
-            if i == 299 {
+            if i == poll_limit - 1 {
                 tracing::warn!(
-                    "⏰ TIMEOUT: No output from {} process after 30 seconds",
-                    provider_monitor
+                    "⏰ TIMEOUT: No output from {} process after {} seconds",
+                    provider_monitor, first_output_timeout_secs
                 );
                 tracing::warn!("💡 This usually means:");
                 tracing::warn!("   1. Provider process is waiting for user input");
@@ -1592,19 +3410,44 @@ async fn spawn_agent_system(
                         .unwrap_or_default();
                     let _ = conn.execute(
                         "UPDATE agent_runs
-                         SET output = ?1, status = 'failed', completed_at = CURRENT_TIMESTAMP
-                         WHERE id = ?2 AND status = 'running'",
-                        params![final_output, run_id],
+                         SET output = ?1, status = 'failed', exit_code = ?2,
+                             process_ended_at = ?3, completed_at = CURRENT_TIMESTAMP
+                         WHERE id = ?4 AND status = 'running'",
+                        params![
+                            final_output,
+                            TIMEOUT_EXIT_CODE,
+                            chrono::Utc::now().to_rfc3339(),
+                            run_id
+                        ],
                     );
+                    let stderr = stderr_output_monitor
+                        .lock()
+                        .map(|s| s.clone())
+                        .unwrap_or_default();
+                    let _ = record_run_stderr(&conn, run_id, &stderr);
+                    if let Some((url, payload)) = completion_webhook_request(
+                        &conn,
+                        run_id,
+                        &agent_name_monitor,
+                        start_time.elapsed().as_millis() as i64,
+                        &project_path_monitor,
+                        false,
+                    ) {
+                        tokio::spawn(post_completion_webhook(url, payload));
+                    }
                 }
 
                 let _ = registry_monitor.unregister_process(run_id);
                 let _ = app.emit("agent-complete", false);
                 let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+                wake_run_queue(&app);
                 return;
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            tokio::time::sleep(tokio::time::Duration::from_millis(
+                FIRST_OUTPUT_POLL_INTERVAL_MS,
+            ))
+            .await;
         }
 
         // Wait for reading tasks to complete
@@ -1614,17 +3457,18 @@ async fn spawn_agent_system(
 
         let duration_ms = start_time.elapsed().as_millis() as i64;
         tracing::info!("⏱️ Process execution took {} ms", duration_ms);
-        let process_success = match child_for_wait.wait().await {
+        let (process_success, exit_code) = match child_for_wait.wait().await {
             Ok(status) => {
                 tracing::info!(
                     "✅ {} exited with status: {}",
                     provider_monitor, status
                 );
-                status.success()
+                // `code()` is None when the process was terminated by a signal
+                (status.success(), status.code())
             }
             Err(e) => {
                 tracing::error!("❌ Failed to wait for {} process: {}", provider_monitor, e);
-                false
+                (false, None)
             }
         };
 
@@ -1648,24 +3492,20 @@ async fn spawn_agent_system(
         tracing::info!("✅ {} process execution monitoring complete", provider_monitor);
 
         // Update the run record with session/output and mark as completed.
+        let mut completion_webhook = None;
+        let mut launch_options = None;
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             tracing::info!(
                 "🔄 Updating database with final session ID: {}",
                 final_session_id
             );
-            match conn.execute(
-                "UPDATE agent_runs
-                 SET session_id = ?1,
-                     output = ?2,
-                     status = ?3,
-                     completed_at = CURRENT_TIMESTAMP
-                 WHERE id = ?4 AND status = 'running'",
-                params![
-                    final_session_id,
-                    final_output,
-                    if process_success { "completed" } else { "failed" },
-                    run_id
-                ],
+            match finish_agent_run(
+                &conn,
+                run_id,
+                &final_session_id,
+                &final_output,
+                process_success,
+                exit_code,
             ) {
                 Ok(rows_affected) => {
                     if rows_affected > 0 {
@@ -1678,6 +3518,22 @@ async fn spawn_agent_system(
                     tracing::error!("❌ Failed to update agent run {} metadata: {}", run_id, e);
                 }
             }
+            let stderr = stderr_output_monitor
+                .lock()
+                .map(|s| s.clone())
+                .unwrap_or_default();
+            if let Err(e) = record_run_stderr(&conn, run_id, &stderr) {
+                tracing::error!("❌ Failed to store stderr for agent run {}: {}", run_id, e);
+            }
+            completion_webhook = completion_webhook_request(
+                &conn,
+                run_id,
+                &agent_name_monitor,
+                duration_ms,
+                &project_path_monitor,
+                process_success,
+            );
+            launch_options = take_launch_options(&conn, run_id);
         } else {
             tracing::error!(
                 "❌ Failed to open database to update session ID for run {}",
@@ -1689,997 +3545,4335 @@ async fn spawn_agent_system(
         let _ = registry_monitor.unregister_process(run_id);
         let _ = app.emit("agent-complete", process_success);
         let _ = app.emit(&format!("agent-complete:{}", run_id), process_success);
+        wake_run_queue(&app);
+
+        if let Some((url, payload)) = completion_webhook {
+            tokio::spawn(post_completion_webhook(url, payload));
+        }
+
+        if let Some(request) = completion_checkpoint_request(
+            run_id,
+            process_success,
+            checkpoint_on_complete,
+            &final_session_id,
+            &project_path_monitor,
+        ) {
+            tokio::spawn(create_run_checkpoint(app.clone(), run_id, request));
+        }
+
+        if !process_success {
+            let signal = rate_limit_monitor.lock().ok().and_then(|s| s.clone());
+            if let Some(signal) = signal {
+                schedule_rate_limit_retry(
+                    &app,
+                    &db_path_for_monitor,
+                    run_id,
+                    &signal,
+                    launch_options.unwrap_or_default(),
+                );
+            }
+        }
     });
 
     Ok(run_id)
 }
 
-/// List all currently running agent sessions
-#[tauri::command]
-pub async fn list_running_sessions(
-    db: State<'_, AgentDb>,
-    registry: State<'_, crate::process::ProcessRegistryState>,
-) -> Result<Vec<AgentRun>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    // First get all running sessions from the database
-    let mut stmt = conn.prepare(
-        "SELECT id, agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id, output, status, pid, process_started_at, created_at, completed_at
-         FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC"
-    ).map_err(|e| e.to_string())?;
-
-    let mut runs = stmt
-        .query_map([], |row| {
-            Ok(AgentRun {
-                id: Some(row.get(0)?),
-                agent_id: row.get(1)?,
-                agent_name: row.get(2)?,
-                agent_icon: row.get(3)?,
-                provider_id: row
-                    .get::<_, String>(4)
-                    .unwrap_or_else(|_| "claude".to_string()),
-                task: row.get(5)?,
-                model: row.get(6)?,
-                project_path: row.get(7)?,
-                session_id: row.get(8)?,
-                output: row
-                    .get::<_, Option<String>>(9)?
-                    .filter(|s| !s.is_empty()),
-                status: row
-                    .get::<_, String>(10)
-                    .unwrap_or_else(|_| "pending".to_string()),
-                pid: row
-                    .get::<_, Option<i64>>(11)
-                    .ok()
-                    .flatten()
-                    .map(|p| p as u32),
-                process_started_at: row.get(12)?,
-                created_at: row.get(13)?,
-                completed_at: row.get(14)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+/// Whether a run should be checkpointed when it succeeds: the explicit option,
+/// else the global setting
+fn resolve_checkpoint_on_complete(conn: &Connection, checkpoint_on_complete: Option<bool>) -> bool {
+    checkpoint_on_complete.unwrap_or_else(|| {
+        read_app_setting(conn, CHECKPOINT_AFTER_RUNS_SETTING)
+            .map(|value| value.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    })
+}
 
-    drop(stmt);
-    drop(conn);
+/// Body POSTed to the completion webhook
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct RunCompletionPayload {
+    run_id: i64,
+    agent_name: String,
+    status: String,
+    duration_ms: i64,
+    project_path: String,
+}
 
-    // Cross-check with the process registry to ensure accuracy
-    // Get actually running processes from the registry
-    let registry_processes = registry.0.get_running_agent_processes()?;
-    let registry_run_ids: std::collections::HashSet<i64> =
-        registry_processes.iter().map(|p| p.run_id).collect();
+/// The webhook to notify about a finished run, if one is configured. The status
+/// is read back from the run so runs cancelled while finishing report as such.
+fn completion_webhook_request(
+    conn: &Connection,
+    run_id: i64,
+    agent_name: &str,
+    duration_ms: i64,
+    project_path: &str,
+    success: bool,
+) -> Option<(String, RunCompletionPayload)> {
+    // `read_app_setting` treats a blank or whitespace-only URL as unset
+    let url = read_app_setting(conn, COMPLETION_WEBHOOK_SETTING)?
+        .trim()
+        .to_string();
+    let status = conn
+        .query_row(
+            "SELECT status FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get::<_, String>(0),
+        )
+        .unwrap_or_else(|_| if success { "completed" } else { "failed" }.to_string());
+    Some((
+        url,
+        RunCompletionPayload {
+            run_id,
+            agent_name: agent_name.to_string(),
+            status,
+            duration_ms,
+            project_path: project_path.to_string(),
+        },
+    ))
+}
 
-    // Filter out any database entries that aren't actually running in the registry
-    // This handles cases where processes crashed without updating the database
-    runs.retain(|run| {
-        if let Some(run_id) = run.id {
-            registry_run_ids.contains(&run_id)
-        } else {
-            false
+/// POST a run's completion to the configured webhook. Failures are only logged;
+/// they never affect the run.
+async fn post_completion_webhook(url: String, payload: RunCompletionPayload) {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(COMPLETION_WEBHOOK_TIMEOUT_SECS))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("Failed to create HTTP client for completion webhook: {}", e);
+            return;
         }
-    });
+    };
+    match client.post(&url).json(&payload).send().await {
+        Ok(response) if !response.status().is_success() => {
+            tracing::warn!(
+                "Completion webhook for run {} returned HTTP {}",
+                payload.run_id,
+                response.status()
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!("Completion webhook for run {} failed: {}", payload.run_id, e);
+        }
+    }
+}
 
-    Ok(runs)
+/// Arguments for the checkpoint taken after a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RunCheckpointRequest {
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    description: String,
 }
 
-/// Kill a running agent session
-#[tauri::command]
-pub async fn kill_agent_session(
-    app: AppHandle,
-    db: State<'_, AgentDb>,
-    registry: State<'_, crate::process::ProcessRegistryState>,
+/// The checkpoint to take once a run finishes, if any. Only successful runs with
+/// a session are checkpointed.
+fn completion_checkpoint_request(
     run_id: i64,
-) -> Result<bool, String> {
-    tracing::info!("Attempting to kill agent session {}", run_id);
+    success: bool,
+    checkpoint_on_complete: bool,
+    session_id: &str,
+    project_path: &str,
+) -> Option<RunCheckpointRequest> {
+    if !success || !checkpoint_on_complete || session_id.is_empty() {
+        return None;
+    }
+    Some(RunCheckpointRequest {
+        session_id: session_id.to_string(),
+        project_id: project_path.replace('/', "-"),
+        project_path: project_path.to_string(),
+        description: format!("After run {}", run_id),
+    })
+}
 
-    // First try to kill using the process registry
-    let killed_via_registry = match registry.0.kill_process(run_id).await {
-        Ok(success) => {
-            if success {
-                tracing::info!("Successfully killed process {} via registry", run_id);
-                true
-            } else {
-                tracing::warn!("Process {} not found in registry", run_id);
-                false
-            }
+async fn create_run_checkpoint(app: AppHandle, run_id: i64, request: RunCheckpointRequest) {
+    let checkpoint_state = app.state::<crate::checkpoint::state::CheckpointState>();
+    match crate::commands::claude::create_checkpoint(
+        app.clone(),
+        checkpoint_state,
+        request.session_id,
+        request.project_id,
+        request.project_path,
+        None,
+        Some(request.description),
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(result) => {
+            tracing::info!(
+                "📌 Created checkpoint {} after run {}",
+                result.checkpoint.id,
+                run_id
+            );
+            let _ = app.emit(
+                &format!("agent-checkpoint:{}", run_id),
+                &result.checkpoint.id,
+            );
         }
         Err(e) => {
-            tracing::warn!("Failed to kill process {} via registry: {}", run_id, e);
-            false
+            tracing::error!("❌ Failed to checkpoint run {}: {}", run_id, e);
         }
-    };
-
-    // If registry kill didn't work, try fallback with PID from database
-    if !killed_via_registry {
-        let pid_result = {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
-            conn.query_row(
-                "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
-                params![run_id],
-                |row| row.get::<_, Option<i64>>(0),
-            )
-            .map_err(|e| e.to_string())?
-        };
+    }
+}
 
-        if let Some(pid) = pid_result {
-            tracing::info!("Attempting fallback kill for PID {} from database", pid);
-            let _ = registry.0.kill_process_by_pid(run_id, pid as u32)?;
+/// Keep the first rate-limit signal seen for a run
+fn note_rate_limit(slot: &Mutex<Option<RateLimitSignal>>, signal: RateLimitSignal) {
+    if let Ok(mut current) = slot.lock() {
+        if current.is_none() {
+            tracing::warn!("⏳ Provider reported a rate limit: {:?}", signal);
+            *current = Some(signal);
         }
     }
+}
 
-    // Update the database to mark as cancelled
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let live_output = registry.0.get_live_output(run_id).unwrap_or_default();
-    let updated = conn.execute(
-        "UPDATE agent_runs
-         SET status = 'cancelled',
-             output = CASE WHEN ?2 != '' THEN ?2 ELSE output END,
-             completed_at = CURRENT_TIMESTAMP
-         WHERE id = ?1 AND status = 'running'",
-        params![run_id, live_output],
-    ).map_err(|e| e.to_string())?;
+/// Pending rate-limit retries, keyed by the run they retry, so a kill can abort them
+#[derive(Default)]
+pub struct RateLimitRetryState(Mutex<HashMap<i64, tokio::task::AbortHandle>>);
 
-    // Emit cancellation event with run_id for proper isolation
-    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+impl RateLimitRetryState {
+    /// Abort every pending retry, returning the ids of the runs they would have retried
+    pub fn abort_all(&self) -> Vec<i64> {
+        let Ok(mut retries) = self.0.lock() else {
+            return Vec::new();
+        };
+        retries
+            .drain()
+            .map(|(run_id, retry)| {
+                retry.abort();
+                run_id
+            })
+            .collect()
+    }
+}
 
-    Ok(updated > 0 || killed_via_registry)
+/// Abort the rate-limit retries waiting to start
+pub(crate) fn abort_rate_limit_retries(app: &AppHandle) -> Vec<i64> {
+    app.try_state::<RateLimitRetryState>()
+        .map(|state| state.abort_all())
+        .unwrap_or_default()
 }
 
-/// Get the status of a specific agent session
-#[tauri::command]
-pub async fn get_session_status(
-    db: State<'_, AgentDb>,
-    run_id: i64,
-) -> Result<Option<String>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    match conn.query_row(
+/// Whether the user cancelled a run; cancelled runs are never retried
+fn run_was_cancelled(conn: &Connection, run_id: i64) -> bool {
+    conn.query_row(
         "SELECT status FROM agent_runs WHERE id = ?1",
         params![run_id],
         |row| row.get::<_, String>(0),
-    ) {
-        Ok(status) => Ok(Some(status)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
-    }
+    )
+    .is_ok_and(|status| status == "cancelled")
 }
 
-/// Cleanup finished processes and update their status
-#[tauri::command]
-pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i64>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    // Get all running processes
-    let mut stmt = conn
-        .prepare("SELECT id, pid FROM agent_runs WHERE status = 'running' AND pid IS NOT NULL")
-        .map_err(|e| e.to_string())?;
-
-    let running_processes = stmt
-        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+/// Original run of a retry chain and how many retries it already spawned
+fn rate_limit_retry_state(conn: &Connection, run_id: i64) -> rusqlite::Result<(i64, u32)> {
+    let original_run_id: i64 = conn.query_row(
+        "SELECT COALESCE(retry_of_run_id, id) FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    )?;
+    let retries: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM agent_runs WHERE retry_of_run_id = ?1",
+        params![original_run_id],
+        |row| row.get(0),
+    )?;
+    Ok((original_run_id, retries as u32))
+}
 
-    drop(stmt);
+/// Re-run a rate-limited run after the configured backoff, when retries are
+/// enabled and the original run still has attempts left
+fn schedule_rate_limit_retry(
+    app: &AppHandle,
+    db_path: &std::path::Path,
+    run_id: i64,
+    signal: &RateLimitSignal,
+    options: RunLaunchOptions,
+) {
+    let Ok(conn) = Connection::open(db_path) else {
+        tracing::error!("❌ Failed to open database to schedule retry of run {}", run_id);
+        return;
+    };
+    if run_was_cancelled(&conn, run_id) {
+        return;
+    }
+    let Some(policy) = rate_limit::load_retry_policy(&conn) else {
+        return;
+    };
+    let (original_run_id, retries) = match rate_limit_retry_state(&conn, run_id) {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!("❌ Failed to read retry state for run {}: {}", run_id, e);
+            return;
+        }
+    };
+    let Some(delay) = policy.next_delay(retries, signal) else {
+        tracing::warn!(
+            "⏳ Run {} is still rate limited after {} retries, giving up",
+            original_run_id, retries
+        );
+        return;
+    };
 
-    let mut cleaned_up = Vec::new();
+    tracing::info!(
+        "🔁 Retrying rate-limited run {} in {:?} (attempt {} of {})",
+        run_id,
+        delay,
+        retries + 1,
+        policy.max_attempts
+    );
+    let _ = app.emit(
+        &format!("agent-retry-scheduled:{}", run_id),
+        serde_json::json!({
+            "original_run_id": original_run_id,
+            "attempt": retries + 1,
+            "delay_ms": delay.as_millis() as u64,
+        }),
+    );
 
-    for (run_id, pid) in running_processes {
-        // Check if the process is still running
-        let is_running = if cfg!(target_os = "windows") {
-            // On Windows, use tasklist to check if process exists
-            match std::process::Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid)])
-                .args(["/FO", "CSV"])
-                .output()
-            {
-                Ok(output) => {
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    output_str.lines().count() > 1 // Header + process line if exists
-                }
-                Err(_) => false,
+    let retry_app = app.clone();
+    let retry = async move {
+        tokio::time::sleep(delay).await;
+        if let Some(state) = retry_app.try_state::<RateLimitRetryState>() {
+            if let Ok(mut retries) = state.0.lock() {
+                retries.remove(&run_id);
             }
-        } else {
-            // On Unix-like systems, use kill -0 to check if process exists
-            match std::process::Command::new("kill")
-                .args(["-0", &pid.to_string()])
-                .output()
-            {
-                Ok(output) => output.status.success(),
-                Err(_) => false,
+        }
+        match retry_rate_limited_run(retry_app, run_id, original_run_id, options).await {
+            Ok(retry_run_id) => {
+                tracing::info!("🔁 Run {} retried as run {}", run_id, retry_run_id);
             }
-        };
+            Err(e) => {
+                tracing::error!("❌ Failed to retry rate-limited run {}: {}", run_id, e);
+            }
+        }
+    };
+    // Register the retry while holding the lock, so it can't remove itself first
+    match app.try_state::<RateLimitRetryState>() {
+        Some(state) => {
+            if let Ok(mut retries) = state.0.lock() {
+                retries.insert(run_id, tokio::spawn(retry).abort_handle());
+            }
+        }
+        None => {
+            tokio::spawn(retry);
+        }
+    }
+}
 
-        if !is_running {
-            // Process has finished, update status
-            let updated = conn.execute(
-                "UPDATE agent_runs SET status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
-                params![run_id],
-            ).map_err(|e| e.to_string())?;
+async fn retry_rate_limited_run(
+    app: AppHandle,
+    run_id: i64,
+    original_run_id: i64,
+    options: RunLaunchOptions,
+) -> Result<i64, String> {
+    let db = app.state::<AgentDb>();
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let run = get_agent_run(db.clone(), run_id).await?;
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        ensure_run_agent_available(&conn, run.agent_id, &run.agent_name)?;
+    }
 
-            if updated > 0 {
-                cleaned_up.push(run_id);
-                tracing::info!(
-                    "Marked agent run {} as completed (PID {} no longer running)",
-                    run_id, pid
+    launch_agent_run(
+        app.clone(),
+        run.agent_id,
+        run.project_path,
+        run.task,
+        Some(run.model),
+        options.reasoning_effort,
+        options.output_format,
+        None,
+        options.env_overrides,
+        options.checkpoint_on_complete,
+        options.raw_output,
+        Some(original_run_id),
+        None,
+        db.clone(),
+        registry.clone(),
+    )
+    .await
+    .map_err(String::from)
+}
+
+/// Runs started by `run_agents_on_task`
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentComparison {
+    pub comparison_id: String,
+    pub run_ids: Vec<i64>,
+    /// Agents that failed to launch, as "<agent id>: <error>"
+    pub errors: Vec<String>,
+}
+
+/// Agent ids to compare, in the given order with duplicates dropped
+fn comparison_agent_ids(agent_ids: Vec<i64>) -> Result<Vec<i64>, String> {
+    let mut unique = Vec::with_capacity(agent_ids.len());
+    for agent_id in agent_ids {
+        if !unique.contains(&agent_id) {
+            unique.push(agent_id);
+        }
+    }
+    if unique.is_empty() {
+        return Err("Select at least one agent to compare".to_string());
+    }
+    Ok(unique)
+}
+
+/// Run several agents on the same task, each run recorded with a shared
+/// `comparison_id`. Launches go one at a time so the concurrency limit sees
+/// each run; runs over the limit are queued as usual.
+#[tauri::command]
+pub async fn run_agents_on_task(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    agent_ids: Vec<i64>,
+    project_path: String,
+    task: String,
+) -> Result<AgentComparison, String> {
+    let agent_ids = comparison_agent_ids(agent_ids)?;
+    let comparison_id = uuid::Uuid::new_v4().to_string();
+    let mut run_ids = Vec::new();
+    let mut errors = Vec::new();
+
+    for agent_id in agent_ids {
+        let launched = launch_agent_run(
+            app.clone(),
+            agent_id,
+            project_path.clone(),
+            task.clone(),
+            None,
+            None,
+            OutputFormat::StreamJson,
+            None,
+            EnvOverrides::new(),
+            None,
+            false,
+            None,
+            Some(&comparison_id),
+            db.clone(),
+            registry.clone(),
+        )
+        .await;
+        match launched {
+            Ok(run_id) => run_ids.push(run_id),
+            Err(e) => {
+                tracing::warn!(
+                    "Comparison {}: agent {} failed to launch: {}",
+                    comparison_id,
+                    agent_id,
+                    e
                 );
+                errors.push(format!("{}: {}", agent_id, e));
             }
         }
     }
 
-    Ok(cleaned_up)
+    if run_ids.is_empty() {
+        return Err(format!("No agent could be started: {}", errors.join("; ")));
+    }
+    Ok(AgentComparison {
+        comparison_id,
+        run_ids,
+        errors,
+    })
 }
 
-/// Get live output from a running process
-#[tauri::command]
-pub async fn get_live_session_output(
-    registry: State<'_, crate::process::ProcessRegistryState>,
-    run_id: i64,
-) -> Result<String, String> {
-    registry.0.get_live_output(run_id)
+/// Ensure the agent behind a past run still exists and isn't in the trash
+fn ensure_run_agent_available(
+    conn: &Connection,
+    agent_id: i64,
+    agent_name: &str,
+) -> Result<(), String> {
+    let deleted_at: Option<String> = conn
+        .query_row(
+            "SELECT deleted_at FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!(
+                "Agent '{}' has been deleted, so this run can't be retried",
+                agent_name
+            ),
+            other => other.to_string(),
+        })?;
+    if deleted_at.is_some() {
+        return Err(format!(
+            "Agent '{}' is in the trash; restore it to retry this run",
+            agent_name
+        ));
+    }
+    Ok(())
 }
 
-/// Get real-time output for a running session by reading its JSONL file with live output fallback
+/// Re-execute a past run with the same agent, project, task and model
 #[tauri::command]
-pub async fn get_session_output(
+pub async fn retry_agent_run(
+    app: AppHandle,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
     run_id: i64,
-) -> Result<String, String> {
-    // Get the session information
-    let run = get_agent_run(db, run_id).await?;
-
-    // Persisted output is the most reliable source across restarts/providers.
-    if let Some(output) = &run.output {
-        if !output.is_empty() {
-            return Ok(output.clone());
-        }
+) -> Result<i64, String> {
+    let run = get_agent_run(db.clone(), run_id).await?;
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        ensure_run_agent_available(&conn, run.agent_id, &run.agent_name)?;
     }
 
-    // Non-Claude providers don't write ~/.claude JSONL session files.
-    if run.provider_id != "claude" {
-        return registry.0.get_live_output(run_id);
-    }
+    tracing::info!("Retrying agent run {} (agent {})", run_id, run.agent_id);
+    launch_agent_run(
+        app,
+        run.agent_id,
+        run.project_path,
+        run.task,
+        Some(run.model).filter(|model| !model.is_empty()),
+        None,
+        OutputFormat::StreamJson,
+        None,
+        EnvOverrides::new(),
+        None,
+        false,
+        None,
+        None,
+        db,
+        registry,
+    )
+    .await
+    .map_err(String::from)
+}
 
-    // If no session ID yet, try to get live output from registry
-    if run.session_id.is_empty() {
-        let live_output = registry.0.get_live_output(run_id)?;
-        if !live_output.is_empty() {
-            return Ok(live_output);
-        }
-        return Ok(String::new());
+/// Stamp when a run first produced output; later calls keep the first time
+fn record_first_output_at(conn: &Connection, run_id: i64) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE agent_runs SET first_output_at = ?1 WHERE id = ?2 AND first_output_at IS NULL",
+        params![chrono::Utc::now().to_rfc3339(), run_id],
+    )
+}
+
+/// Record the outcome of a run that is still marked as running
+fn finish_agent_run(
+    conn: &Connection,
+    run_id: i64,
+    session_id: &str,
+    output: &str,
+    success: bool,
+    exit_code: Option<i32>,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE agent_runs
+         SET session_id = ?1,
+             output = ?2,
+             status = ?3,
+             exit_code = ?4,
+             process_ended_at = ?5,
+             completed_at = CURRENT_TIMESTAMP
+         WHERE id = ?6 AND status = 'running'",
+        params![
+            session_id,
+            output,
+            if success { "completed" } else { "failed" },
+            exit_code,
+            chrono::Utc::now().to_rfc3339(),
+            run_id
+        ],
+    )
+}
+
+/// Most raw stderr kept for a run; older lines are dropped first
+const MAX_RUN_STDERR_BYTES: usize = 1024 * 1024;
+
+/// Append a stderr line, dropping whole lines from the front once `stderr` is over
+/// `max_bytes`. The newest line is always kept.
+fn append_run_stderr(stderr: &mut String, line: &str, max_bytes: usize) {
+    let line_start = stderr.len();
+    stderr.push_str(line);
+    stderr.push('\n');
+    if stderr.len() <= max_bytes {
+        return;
     }
+    let excess = stderr.len() - max_bytes;
+    let cut = stderr.as_bytes()[excess..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map(|offset| excess + offset + 1)
+        .filter(|cut| *cut <= line_start)
+        .unwrap_or(line_start);
+    stderr.drain(..cut);
+}
 
-    // Get the Claude directory
-    let claude_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+/// Persist a run's raw stderr; an empty stream is stored as NULL
+fn record_run_stderr(conn: &Connection, run_id: i64, stderr: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE agent_runs SET stderr = ?1 WHERE id = ?2",
+        params![Some(stderr).filter(|s| !s.is_empty()), run_id],
+    )
+}
 
-    // Find the correct project directory by searching for the session file
-    let projects_dir = claude_dir.join("projects");
+fn get_run_stderr_in(conn: &Connection, run_id: i64) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT stderr FROM agent_runs WHERE id = ?1",
+        params![run_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Agent run {} not found", run_id),
+        other => other.to_string(),
+    })
+}
 
-    // Check if projects directory exists
-    if !projects_dir.exists() {
-        tracing::error!("Projects directory not found at: {:?}", projects_dir);
-        return Err("Projects directory not found".to_string());
-    }
+/// Get the raw stderr a finished run produced, or None when it wrote none
+#[tauri::command]
+pub async fn get_agent_run_stderr(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_run_stderr_in(&conn, run_id)
+}
 
-    // Search for the session file in all project directories
-    let mut session_file_path = None;
+/// Build the Claude invocation that resumes a checkpoint-forked session
+fn build_resume_forked_args(
+    session_id: &str,
+    prompt: &str,
+    model: &str,
+) -> Result<Vec<String>, String> {
+    let runtime = crate::providers::runtime::get_provider_runtime("claude")
+        .ok_or_else(|| "Provider 'claude' is not registered".to_string())?;
+
+    (runtime.build_args)(&crate::providers::runtime::ProviderCommandRequest {
+        kind: crate::providers::runtime::ProviderCommandKind::Resume,
+        prompt: prompt.to_string(),
+        model: model.to_string(),
+        session_id: Some(session_id.to_string()),
+        reasoning_effort: None,
+    })
+}
+
+/// Resume execution in a session created by `fork_from_checkpoint`
+#[tauri::command]
+pub async fn resume_forked_session(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    app: AppHandle,
+    project_path: String,
+    new_session_id: String,
+    prompt: String,
+    model: String,
+) -> Result<(), String> {
+    let session_id = new_session_id.trim().to_string();
     tracing::info!(
-        "Searching for session file {} in all project directories",
-        run.session_id
+        "Resuming forked session {} in: {} with model: {}",
+        session_id,
+        project_path,
+        model
     );
 
-    if let Ok(entries) = std::fs::read_dir(&projects_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_dir() {
-                let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
-                tracing::debug!("Checking project directory: {}", dir_name);
+    let args = build_resume_forked_args(&session_id, &prompt, &model)?;
 
-                let potential_session_file = path.join(format!("{}.jsonl", run.session_id));
-                if potential_session_file.exists() {
-                    tracing::info!("Found session file at: {:?}", potential_session_file);
-                    session_file_path = Some(potential_session_file);
-                    break;
-                } else {
-                    tracing::debug!("Session file not found in: {}", dir_name);
-                }
-            }
+    if find_session_jsonl_path(&session_id, &project_path).is_none() {
+        return Err(format!("Forked session file not found: {}", session_id));
+    }
+
+    if registry.0.get_provider_session_by_id(&session_id)?.is_some() {
+        return Err(format!("Session {} is already running", session_id));
+    }
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let running_runs: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM agent_runs WHERE session_id = ?1 AND status = 'running'",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if running_runs > 0 {
+            return Err(format!(
+                "Session {} is already in use by a running agent",
+                session_id
+            ));
         }
-    } else {
-        tracing::error!("Failed to read projects directory");
     }
 
-    // If we found the session file, read it
-    if let Some(session_path) = session_file_path {
-        match tokio::fs::read_to_string(&session_path).await {
-            Ok(content) => Ok(content),
-            Err(e) => {
-                tracing::error!(
-                    "Failed to read session file {}: {}",
-                    session_path.display(),
-                    e
-                );
-                // Fallback to live output if file read fails
-                let live_output = registry.0.get_live_output(run_id)?;
-                Ok(live_output)
-            }
-        }
-    } else {
-        // If session file not found, try the old method as fallback
-        tracing::warn!(
-            "Session file not found for {}, trying legacy method",
-            run.session_id
-        );
-        match read_session_jsonl(&run.session_id, &run.project_path).await {
-            Ok(content) => Ok(content),
-            Err(_) => {
-                // Final fallback to live output
-                let live_output = registry.0.get_live_output(run_id)?;
-                Ok(live_output)
-            }
-        }
-    }
+    let binary_path = find_claude_binary(&app)?;
+    let cmd = crate::commands::provider_session::create_provider_session_system_command(
+        &binary_path,
+        args,
+        &project_path,
+    );
+    crate::commands::provider_session::spawn_provider_session_process(
+        app,
+        cmd,
+        prompt,
+        model,
+        project_path,
+    )
+    .await
 }
 
-/// Stream real-time session output by watching the JSONL file
+/// List all currently running agent sessions
 #[tauri::command]
-pub async fn stream_session_output(
-    app: AppHandle,
+pub async fn list_running_sessions(
     db: State<'_, AgentDb>,
-    run_id: i64,
-) -> Result<(), String> {
-    // Get the session information
-    let run = get_agent_run(db, run_id).await?;
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<Vec<AgentRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    // Non-Claude providers stream directly via agent-output events.
-    if run.provider_id != "claude" {
-        return Ok(());
-    }
+    // First get all running sessions from the database
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
 
-    // If no session ID yet, can't stream
-    if run.session_id.is_empty() {
-        return Err("Session not started yet".to_string());
-    }
+    let mut runs = stmt
+        .query_map([], agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
 
-    let session_id = run.session_id.clone();
-    let project_path = run.project_path.clone();
+    drop(stmt);
+    drop(conn);
 
-    // Spawn a task to monitor the file
-    tokio::spawn(async move {
-        let claude_dir = match dirs::home_dir() {
-            Some(home) => home.join(".claude").join("projects"),
-            None => return,
-        };
+    // Cross-check with the process registry to ensure accuracy
+    // Get actually running processes from the registry
+    let registry_processes = registry.0.get_running_agent_processes()?;
+    let registry_run_ids: std::collections::HashSet<i64> =
+        registry_processes.iter().map(|p| p.run_id).collect();
 
-        let encoded_project = project_path.replace('/', "-");
-        let project_dir = claude_dir.join(&encoded_project);
-        let session_file = project_dir.join(format!("{}.jsonl", session_id));
+    // Filter out any database entries that aren't actually running in the registry
+    // This handles cases where processes crashed without updating the database
+    runs.retain(|run| {
+        if let Some(run_id) = run.id {
+            registry_run_ids.contains(&run_id)
+        } else {
+            false
+        }
+    });
 
-        let mut last_size = 0u64;
+    Ok(runs)
+}
 
-        // Monitor file changes continuously while session is running
-        loop {
-            if session_file.exists() {
-                if let Ok(metadata) = tokio::fs::metadata(&session_file).await {
-                    let current_size = metadata.len();
+/// List running agent runs that have produced no output for longer than `threshold_secs`
+#[tauri::command]
+pub async fn list_stuck_sessions(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    threshold_secs: i64,
+) -> Result<Vec<crate::process::StuckProcess>, String> {
+    if threshold_secs <= 0 {
+        return Err("threshold_secs must be positive".to_string());
+    }
+    registry
+        .0
+        .get_stuck_agent_processes(chrono::Duration::seconds(threshold_secs))
+}
 
-                    if current_size > last_size {
-                        // File has grown, read new content
-                        if let Ok(content) = tokio::fs::read_to_string(&session_file).await {
-                            let _ = app
-                                .emit("session-output-update", &format!("{}:{}", run_id, content));
-                        }
-                        last_size = current_size;
-                    }
-                }
-            } else {
-                // If session file doesn't exist yet, keep waiting
-                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                continue;
-            }
+/// Kill a running agent session
+#[tauri::command]
+pub async fn kill_agent_session(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    tracing::info!("Attempting to kill agent session {}", run_id);
 
-            // Check if the session is still running by querying the database
-            // If the session is no longer running, stop streaming
-            if let Ok(conn) = rusqlite::Connection::open(
-                app.path()
-                    .app_data_dir()
-                    .expect("Failed to get app data dir")
-                    .join("agents.db"),
-            ) {
-                if let Ok(status) = conn.query_row(
-                    "SELECT status FROM agent_runs WHERE id = ?1",
-                    rusqlite::params![run_id],
-                    |row| row.get::<_, String>(0),
-                ) {
-                    if status != "running" {
-                        tracing::debug!("Session {} is no longer running, stopping stream", run_id);
-                        break;
-                    }
-                } else {
-                    // If we can't query the status, assume it's still running
-                    tracing::debug!(
-                        "Could not query session status for {}, continuing stream",
-                        run_id
-                    );
-                }
+    // First try to kill using the process registry
+    let killed_via_registry = match registry.0.kill_process(run_id).await {
+        Ok(success) => {
+            if success {
+                tracing::info!("Successfully killed process {} via registry", run_id);
+                true
+            } else {
+                tracing::warn!("Process {} not found in registry", run_id);
+                false
             }
-
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         }
+        Err(e) => {
+            tracing::warn!("Failed to kill process {} via registry: {}", run_id, e);
+            false
+        }
+    };
 
-        tracing::debug!("Stopped streaming for session {}", run_id);
-    });
+    // If registry kill didn't work, try fallback with PID from database
+    if !killed_via_registry {
+        let pid_result = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
+                params![run_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map_err(|e| e.to_string())?
+        };
 
-    Ok(())
-}
+        if let Some(pid) = pid_result {
+            tracing::info!("Attempting fallback kill for PID {} from database", pid);
+            let _ = registry.0.kill_process_by_pid(run_id, pid as u32)?;
+        }
+    }
 
-/// Export a single agent to JSON format
-#[tauri::command]
-pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, String> {
+    // Update the database to mark as cancelled
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let live_output = registry.0.get_live_output(run_id).unwrap_or_default();
+    let updated = conn.execute(
+        "UPDATE agent_runs
+         SET status = 'cancelled',
+             output = CASE WHEN ?2 != '' THEN ?2 ELSE output END,
+             launch_options = NULL,
+             completed_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'running'",
+        params![run_id, live_output],
+    ).map_err(|e| e.to_string())?;
 
-    // Fetch the agent
-    let agent = conn
-        .query_row(
-            "SELECT name, icon, system_prompt, default_task, provider_id, model, hooks FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(serde_json::json!({
-                    "name": row.get::<_, String>(0)?,
-                    "icon": row.get::<_, String>(1)?,
-                    "system_prompt": row.get::<_, String>(2)?,
-                    "default_task": row.get::<_, Option<String>>(3)?,
-                    "provider_id": row.get::<_, String>(4)?,
-                    "model": row.get::<_, String>(5)?,
-                    "hooks": row.get::<_, Option<String>>(6)?
-                }))
-            },
-        )
-        .map_err(|e| format!("Failed to fetch agent: {}", e))?;
+    // Emit cancellation event with run_id for proper isolation
+    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
 
-    // Create the export wrapper
-    let export_data = serde_json::json!({
-        "version": 1,
-        "exported_at": chrono::Utc::now().to_rfc3339(),
-        "agent": agent
-    });
+    Ok(updated > 0 || killed_via_registry)
+}
 
-    // Convert to pretty JSON string
-    serde_json::to_string_pretty(&export_data)
-        .map_err(|e| format!("Failed to serialize agent: {}", e))
+/// Kill each run in turn, collecting the ones that were cancelled and the
+/// errors, so one stuck process does not keep the rest running
+pub(crate) async fn kill_each_run<F, Fut>(
+    run_ids: impl IntoIterator<Item = i64>,
+    mut kill: F,
+) -> (Vec<i64>, Vec<String>)
+where
+    F: FnMut(i64) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, String>>,
+{
+    let mut cancelled = Vec::new();
+    let mut errors = Vec::new();
+    for run_id in run_ids {
+        match kill(run_id).await {
+            Ok(true) => cancelled.push(run_id),
+            Ok(false) => {}
+            Err(e) => errors.push(format!("Failed to kill agent run {}: {}", run_id, e)),
+        }
+    }
+    (cancelled, errors)
 }
 
-/// Export agent to file with native dialog
+/// Kill every running agent session and cancel the queued ones, returning the
+/// run IDs that were cancelled
 #[tauri::command]
-pub async fn export_agent_to_file(
+pub async fn kill_all_agent_sessions(
+    app: AppHandle,
     db: State<'_, AgentDb>,
-    id: i64,
-    file_path: String,
-) -> Result<(), String> {
-    // Get the JSON data
-    let json_data = export_agent(db, id).await?;
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<Vec<i64>, String> {
+    // Cancel the queue and pending retries first, so slots freed by the kills don't
+    // start them
+    abort_rate_limit_retries(&app);
+    let queued = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        cancel_all_queued_runs_in(&conn)?
+    };
+    let run_ids: Vec<i64> = registry
+        .0
+        .get_running_agent_processes()?
+        .into_iter()
+        .map(|process| process.run_id)
+        .collect();
+    tracing::info!("Killing {} running agent sessions", run_ids.len());
 
-    // Write to file
-    std::fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+    let (cancelled, errors) = kill_each_run(run_ids, |run_id| {
+        kill_agent_session(app.clone(), db.clone(), registry.clone(), run_id)
+    })
+    .await;
+    for error in &errors {
+        tracing::warn!("{}", error);
+    }
 
-    Ok(())
+    Ok(queued.into_iter().chain(cancelled).collect())
 }
 
-/// Get the stored Claude binary path from settings
+/// Get the status of a specific agent session
 #[tauri::command]
-pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<String>, String> {
+pub async fn get_session_status(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<String>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     match conn.query_row(
-        "SELECT value FROM app_settings WHERE key = 'claude_binary_path'",
-        [],
+        "SELECT status FROM agent_runs WHERE id = ?1",
+        params![run_id],
         |row| row.get::<_, String>(0),
     ) {
-        Ok(path) => Ok(Some(path)),
+        Ok(status) => Ok(Some(status)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to get Claude binary path: {}", e)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
-/// Set the Claude binary path in settings
+/// Cleanup finished processes and update their status
 #[tauri::command]
-pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
+pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i64>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    if crate::claude_binary::is_disallowed_claude_path(&path) {
-        return Err(
-            "Selected path points to a GUI app bundle. Please select the Claude CLI binary."
-                .to_string(),
-        );
-    }
+    // Get all running processes
+    let mut stmt = conn
+        .prepare("SELECT id, pid FROM agent_runs WHERE status = 'running' AND pid IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+
+    let running_processes = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    drop(stmt);
+
+    let mut cleaned_up = Vec::new();
+
+    for (run_id, pid) in running_processes {
+        // Check if the process is still running
+        let is_running = if cfg!(target_os = "windows") {
+            // On Windows, use tasklist to check if process exists
+            match std::process::Command::new("tasklist")
+                .args(["/FI", &format!("PID eq {}", pid)])
+                .args(["/FO", "CSV"])
+                .output()
+            {
+                Ok(output) => {
+                    let output_str = String::from_utf8_lossy(&output.stdout);
+                    output_str.lines().count() > 1 // Header + process line if exists
+                }
+                Err(_) => false,
+            }
+        } else {
+            // On Unix-like systems, use kill -0 to check if process exists
+            match std::process::Command::new("kill")
+                .args(["-0", &pid.to_string()])
+                .output()
+            {
+                Ok(output) => output.status.success(),
+                Err(_) => false,
+            }
+        };
 
-    // Validate that the path exists and is executable
-    let path_buf = std::path::PathBuf::from(&path);
-    if !path_buf.exists() {
-        return Err(format!("File does not exist: {}", path));
+        if !is_running {
+            // Process has finished, update status
+            let updated = conn.execute(
+                "UPDATE agent_runs SET status = 'completed', launch_options = NULL, completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                params![run_id],
+            ).map_err(|e| e.to_string())?;
+
+            if updated > 0 {
+                cleaned_up.push(run_id);
+                tracing::info!(
+                    "Marked agent run {} as completed (PID {} no longer running)",
+                    run_id, pid
+                );
+            }
+        }
     }
 
-    // Check if it's executable (on Unix systems)
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let metadata = std::fs::metadata(&path_buf)
-            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
-        let permissions = metadata.permissions();
-        if permissions.mode() & 0o111 == 0 {
-            return Err(format!("File is not executable: {}", path));
+    Ok(cleaned_up)
+}
+
+/// CPU and memory of a running agent run's process tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProcessResourceUsage {
+    pub run_id: i64,
+    pub pid: u32,
+    pub cpu_percent: f64,
+    pub memory_mb: f64,
+}
+
+/// One process in a snapshot of the OS process table
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessSample {
+    pid: u32,
+    parent_pid: u32,
+    cpu_percent: f64,
+    memory_mb: f64,
+}
+
+/// Parse `ps -A -o pid=,ppid=,%cpu=,rss=` output, whose RSS is in KiB
+fn parse_ps_table(output: &str) -> Vec<ProcessSample> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(ProcessSample {
+                pid: fields.next()?.parse().ok()?,
+                parent_pid: fields.next()?.parse().ok()?,
+                cpu_percent: fields.next()?.parse().ok()?,
+                memory_mb: fields.next()?.parse::<f64>().ok()? / 1024.0,
+            })
+        })
+        .collect()
+}
+
+/// Parse `wmic ... get CreatingProcessID,IDProcess,PercentProcessorTime,WorkingSet
+/// /format:csv` output, whose working set is in bytes
+fn parse_wmic_table(output: &str) -> Vec<ProcessSample> {
+    let mut lines = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let header: Vec<&str> = header.split(',').collect();
+    let index = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(pid), Some(parent_pid), Some(cpu), Some(memory)) = (
+        index("IDProcess"),
+        index("CreatingProcessID"),
+        index("PercentProcessorTime"),
+        index("WorkingSet"),
+    ) else {
+        return Vec::new();
+    };
+
+    lines
+        .filter_map(|line| {
+            let row: Vec<&str> = line.split(',').collect();
+            let field = |i: usize| row.get(i).map(|value| value.trim());
+            Some(ProcessSample {
+                pid: field(pid)?.parse().ok()?,
+                parent_pid: field(parent_pid)?.parse().ok()?,
+                cpu_percent: field(cpu)?.parse().ok()?,
+                memory_mb: field(memory)?.parse::<f64>().ok()? / (1024.0 * 1024.0),
+            })
+        })
+        .collect()
+}
+
+/// Summed CPU and memory of `root` and all its descendants; None once `root` has
+/// exited. Runs register the sandbox wrapper's PID, so the provider CLI and the
+/// tools it starts are children.
+fn process_tree_usage(samples: &[ProcessSample], root: u32) -> Option<(f64, f64)> {
+    samples.iter().find(|sample| sample.pid == root)?;
+
+    let mut tree = vec![root];
+    let mut next = 0;
+    while next < tree.len() {
+        let parent = tree[next];
+        for sample in samples {
+            if sample.parent_pid == parent && sample.pid != 0 && !tree.contains(&sample.pid) {
+                tree.push(sample.pid);
+            }
         }
+        next += 1;
     }
 
-    // Insert or update the setting
-    conn.execute(
-        "INSERT INTO app_settings (key, value) VALUES ('claude_binary_path', ?1)
-         ON CONFLICT(key) DO UPDATE SET value = ?1",
-        params![path],
+    Some(
+        samples
+            .iter()
+            .filter(|sample| tree.contains(&sample.pid))
+            .fold((0.0, 0.0), |(cpu, memory), sample| {
+                (cpu + sample.cpu_percent, memory + sample.memory_mb)
+            }),
     )
-    .map_err(|e| format!("Failed to save Claude binary path: {}", e))?;
+}
 
-    Ok(())
+/// Snapshot every process's CPU and memory (in MB)
+async fn query_process_table() -> Result<Vec<ProcessSample>, String> {
+    if cfg!(target_os = "windows") {
+        let output = Command::new("wmic")
+            .args(["path", "Win32_PerfFormattedData_PerfProc_Process"])
+            .args([
+                "get",
+                "CreatingProcessID,IDProcess,PercentProcessorTime,WorkingSet",
+                "/format:csv",
+            ])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run wmic: {}", e))?;
+        Ok(parse_wmic_table(&String::from_utf8_lossy(&output.stdout)))
+    } else {
+        let output = Command::new("ps")
+            .args(["-A", "-o", "pid=,ppid=,%cpu=,rss="])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run ps: {}", e))?;
+        Ok(parse_ps_table(&String::from_utf8_lossy(&output.stdout)))
+    }
 }
 
-/// List all available Claude installations on the system
+/// CPU and memory of each running agent run, summed over its process tree.
+/// Processes that exit before they're queried are left out.
 #[tauri::command]
-pub async fn list_claude_installations(
-    _app: AppHandle,
-) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
-    let installations = crate::claude_binary::discover_claude_installations();
+pub async fn get_running_session_resource_usage(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<Vec<ProcessResourceUsage>, String> {
+    let processes = registry.0.get_running_agent_processes()?;
+    if processes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let samples = query_process_table().await?;
+    Ok(processes
+        .into_iter()
+        .filter(|process| process.pid != 0)
+        .filter_map(|process| {
+            let (cpu_percent, memory_mb) = process_tree_usage(&samples, process.pid)?;
+            Some(ProcessResourceUsage {
+                run_id: process.run_id,
+                pid: process.pid,
+                cpu_percent,
+                memory_mb,
+            })
+        })
+        .collect())
+}
+
+/// Get live output from a running process
+#[tauri::command]
+pub async fn get_live_session_output(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<String, String> {
+    registry.0.get_live_output(run_id)
+}
+
+/// Get a running session's live output appended since `from_byte`, with the
+/// offset to poll from next, so the UI doesn't re-fetch the whole buffer
+#[tauri::command]
+pub async fn get_agent_run_output_tail(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    from_byte: usize,
+) -> Result<(String, usize), String> {
+    registry.0.get_live_output_since(run_id, from_byte)
+}
+
+/// Return the last `lines` lines of a block of text
+fn tail_lines(content: &str, lines: usize) -> String {
+    let all_lines: Vec<&str> = content.lines().collect();
+    let start = all_lines.len().saturating_sub(lines);
+    all_lines[start..].join("\n")
+}
+
+/// Read the last `lines` lines of a file by scanning backwards in chunks,
+/// so large session files don't have to be loaded in full
+fn read_file_tail(path: &std::path::Path, lines: usize) -> Result<String, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    const CHUNK_SIZE: u64 = 8 * 1024;
+
+    if lines == 0 {
+        return Ok(String::new());
+    }
+
+    let mut file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open output file: {}", e))?;
+    let mut position = file
+        .metadata()
+        .map_err(|e| format!("Failed to read output file metadata: {}", e))?
+        .len();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while position > 0 {
+        let read_size = CHUNK_SIZE.min(position);
+        position -= read_size;
+
+        file.seek(SeekFrom::Start(position))
+            .map_err(|e| format!("Failed to seek output file: {}", e))?;
+        let mut chunk = vec![0u8; read_size as usize];
+        file.read_exact(&mut chunk)
+            .map_err(|e| format!("Failed to read output file: {}", e))?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+
+        // One more newline than requested guarantees the first kept line is complete
+        let content_len = if buffer.ends_with(b"\n") {
+            buffer.len() - 1
+        } else {
+            buffer.len()
+        };
+        let newline_count = buffer[..content_len].iter().filter(|b| **b == b'\n').count();
+        if newline_count >= lines {
+            break;
+        }
+    }
+
+    Ok(tail_lines(&String::from_utf8_lossy(&buffer), lines))
+}
+
+/// Locate the Claude JSONL session file for a run, checking the encoded project
+/// directory first and falling back to a scan of all project directories
+fn find_session_jsonl_path(session_id: &str, project_path: &str) -> Option<PathBuf> {
+    let projects_dir = dirs::home_dir()?.join(".claude").join("projects");
+    let file_name = format!("{}.jsonl", session_id);
+
+    let direct = projects_dir
+        .join(project_path.replace('/', "-"))
+        .join(&file_name);
+    if direct.exists() {
+        return Some(direct);
+    }
+
+    scan_projects_for_session(&projects_dir, session_id)
+}
+
+/// Search every project directory under `projects_dir` for `{session_id}.jsonl`
+fn scan_projects_for_session(projects_dir: &std::path::Path, session_id: &str) -> Option<PathBuf> {
+    tracing::info!(
+        "Searching for session file {} in all project directories",
+        session_id
+    );
+    let project_dirs = match crate::commands::claude::project_directories(projects_dir) {
+        Ok(project_dirs) => project_dirs,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return None;
+        }
+    };
+
+    for path in project_dirs {
+        let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
+        tracing::debug!("Checking project directory: {}", dir_name);
+
+        let potential_session_file = path.join(format!("{}.jsonl", session_id));
+        if potential_session_file.exists() {
+            tracing::info!("Found session file at: {:?}", potential_session_file);
+            return Some(potential_session_file);
+        }
+        tracing::debug!("Session file not found in: {}", dir_name);
+    }
+    None
+}
+
+/// Absolute path of a Claude run's session JSONL, for opening it in an external editor.
+/// `None` for other providers or while the file hasn't been written yet.
+#[tauri::command]
+pub async fn get_agent_run_jsonl_path(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<String>, String> {
+    let run = get_agent_run(db, run_id).await?;
+    if run.provider_id != "claude" || run.session_id.is_empty() {
+        return Ok(None);
+    }
+
+    let projects_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude")
+        .join("projects");
+    Ok(scan_projects_for_session(&projects_dir, &run.session_id)
+        .map(|path| path.to_string_lossy().to_string()))
+}
+
+/// Get the last N lines of a run's output without loading it in full
+#[tauri::command]
+pub async fn get_run_output_tail(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    lines: usize,
+) -> Result<String, String> {
+    let run = get_agent_run(db, run_id).await?;
+
+    if let Some(output) = &run.output {
+        return Ok(tail_lines(output, lines));
+    }
+
+    if run.provider_id == "claude" && !run.session_id.is_empty() {
+        if let Some(session_path) = find_session_jsonl_path(&run.session_id, &run.project_path) {
+            return read_file_tail(&session_path, lines);
+        }
+    }
+
+    let live_output = registry.0.get_live_output(run_id)?;
+    Ok(tail_lines(&live_output, lines))
+}
+
+/// Get real-time output for a running session by reading its JSONL file with live output fallback
+#[tauri::command]
+pub async fn get_session_output(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+) -> Result<String, String> {
+    // Get the session information
+    let run = get_agent_run(db, run_id).await?;
+
+    // Persisted output is the most reliable source across restarts/providers.
+    if let Some(output) = &run.output {
+        if !output.is_empty() {
+            return Ok(output.clone());
+        }
+    }
+
+    // Non-Claude providers don't write ~/.claude JSONL session files.
+    if run.provider_id != "claude" {
+        return registry.0.get_live_output(run_id);
+    }
+
+    // If no session ID yet, try to get live output from registry
+    if run.session_id.is_empty() {
+        let live_output = registry.0.get_live_output(run_id)?;
+        if !live_output.is_empty() {
+            return Ok(live_output);
+        }
+        return Ok(String::new());
+    }
+
+    // Get the Claude directory
+    let claude_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+
+    // Find the correct project directory by searching for the session file
+    let projects_dir = claude_dir.join("projects");
+
+    // Check if projects directory exists
+    if !projects_dir.exists() {
+        tracing::error!("Projects directory not found at: {:?}", projects_dir);
+        return Err("Projects directory not found".to_string());
+    }
+
+    // If we found the session file, read it
+    if let Some(session_path) = scan_projects_for_session(&projects_dir, &run.session_id) {
+        match tokio::fs::read_to_string(&session_path).await {
+            Ok(content) => Ok(content),
+            Err(e) => {
+                tracing::error!(
+                    "Failed to read session file {}: {}",
+                    session_path.display(),
+                    e
+                );
+                // Fallback to live output if file read fails
+                let live_output = registry.0.get_live_output(run_id)?;
+                Ok(live_output)
+            }
+        }
+    } else {
+        // If session file not found, try the old method as fallback
+        tracing::warn!(
+            "Session file not found for {}, trying legacy method",
+            run.session_id
+        );
+        match read_session_jsonl(&run.session_id, &run.project_path).await {
+            Ok(content) => Ok(content),
+            Err(_) => {
+                // Final fallback to live output
+                let live_output = registry.0.get_live_output(run_id)?;
+                Ok(live_output)
+            }
+        }
+    }
+}
+
+/// Stream real-time session output by watching the JSONL file
+#[tauri::command]
+pub async fn stream_session_output(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<(), String> {
+    // Get the session information
+    let run = get_agent_run(db, run_id).await?;
+
+    // Non-Claude providers stream directly via agent-output events.
+    if run.provider_id != "claude" {
+        return Ok(());
+    }
+
+    // If no session ID yet, can't stream
+    if run.session_id.is_empty() {
+        return Err("Session not started yet".to_string());
+    }
+
+    let session_id = run.session_id.clone();
+    let project_path = run.project_path.clone();
+
+    // Spawn a task to monitor the file
+    tokio::spawn(async move {
+        let claude_dir = match dirs::home_dir() {
+            Some(home) => home.join(".claude").join("projects"),
+            None => return,
+        };
+
+        let encoded_project = project_path.replace('/', "-");
+        let project_dir = claude_dir.join(&encoded_project);
+        let session_file = project_dir.join(format!("{}.jsonl", session_id));
+
+        let mut last_size = 0u64;
+
+        // Monitor file changes continuously while session is running
+        loop {
+            if session_file.exists() {
+                if let Ok(metadata) = tokio::fs::metadata(&session_file).await {
+                    let current_size = metadata.len();
+
+                    if current_size > last_size {
+                        // File has grown, read new content
+                        if let Ok(content) = tokio::fs::read_to_string(&session_file).await {
+                            let _ = app
+                                .emit("session-output-update", &format!("{}:{}", run_id, content));
+                        }
+                        last_size = current_size;
+                    }
+                }
+            } else {
+                // If session file doesn't exist yet, keep waiting
+                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                continue;
+            }
+
+            // Check if the session is still running by querying the database
+            // If the session is no longer running, stop streaming
+            if let Ok(conn) = rusqlite::Connection::open(
+                app.path()
+                    .app_data_dir()
+                    .expect("Failed to get app data dir")
+                    .join("agents.db"),
+            ) {
+                if let Ok(status) = conn.query_row(
+                    "SELECT status FROM agent_runs WHERE id = ?1",
+                    rusqlite::params![run_id],
+                    |row| row.get::<_, String>(0),
+                ) {
+                    if status != "running" {
+                        tracing::debug!("Session {} is no longer running, stopping stream", run_id);
+                        break;
+                    }
+                } else {
+                    // If we can't query the status, assume it's still running
+                    tracing::debug!(
+                        "Could not query session status for {}, continuing stream",
+                        run_id
+                    );
+                }
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        }
+
+        tracing::debug!("Stopped streaming for session {}", run_id);
+    });
+
+    Ok(())
+}
+
+/// Export a single agent to JSON format
+#[tauri::command]
+pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let export_data = build_agent_export(&conn, id)?;
+
+    // Convert to pretty JSON string
+    serde_json::to_string_pretty(&export_data)
+        .map_err(|e| format!("Failed to serialize agent: {}", e))
+}
+
+/// Export an agent as a JSON string for the clipboard.
+/// `compact` produces a single-line variant suited for pasting into chat.
+#[tauri::command]
+pub async fn export_agent_clipboard(
+    db: State<'_, AgentDb>,
+    id: i64,
+    compact: Option<bool>,
+) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let export_data = build_agent_export(&conn, id)?;
+
+    if compact.unwrap_or(false) {
+        serde_json::to_string(&export_data)
+    } else {
+        serde_json::to_string_pretty(&export_data)
+    }
+    .map_err(|e| format!("Failed to serialize agent: {}", e))
+}
+
+const AGENT_EXPORT_COLUMNS: &str = "name, icon, system_prompt, default_task, provider_id, model, hooks";
+
+/// Exported fields of an agent row selected with `AGENT_EXPORT_COLUMNS`
+fn agent_export_data(row: &rusqlite::Row) -> rusqlite::Result<JsonValue> {
+    Ok(serde_json::json!({
+        "name": row.get::<_, String>(0)?,
+        "icon": row.get::<_, String>(1)?,
+        "system_prompt": row.get::<_, String>(2)?,
+        "default_task": row.get::<_, Option<String>>(3)?,
+        "provider_id": row.get::<_, String>(4)?,
+        "model": row.get::<_, String>(5)?,
+        "hooks": row.get::<_, Option<String>>(6)?
+    }))
+}
+
+/// Build the versioned export wrapper for an agent
+fn build_agent_export(conn: &Connection, id: i64) -> Result<JsonValue, String> {
+    // Fetch the agent
+    let agent = conn
+        .query_row(
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_EXPORT_COLUMNS),
+            params![id],
+            agent_export_data,
+        )
+        .map_err(|e| format!("Failed to fetch agent: {}", e))?;
+
+    // Create the export wrapper
+    Ok(serde_json::json!({
+        "version": 1,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "agent": agent
+    }))
+}
+
+/// Build the versioned export wrapper holding every agent outside the trash
+fn build_all_agents_export(conn: &Connection) -> Result<JsonValue, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agents WHERE deleted_at IS NULL ORDER BY id",
+            AGENT_EXPORT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let agents = stmt
+        .query_map([], agent_export_data)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to fetch agents: {}", e))?;
+
+    Ok(serde_json::json!({
+        "version": 1,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "agents": agents
+    }))
+}
+
+/// Export all agents to a single JSON document
+#[tauri::command]
+pub async fn export_all_agents(db: State<'_, AgentDb>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let export_data = build_all_agents_export(&conn)?;
+
+    serde_json::to_string_pretty(&export_data)
+        .map_err(|e| format!("Failed to serialize agents: {}", e))
+}
+
+/// Export all agents to a single file
+#[tauri::command]
+pub async fn export_all_agents_to_file(
+    db: State<'_, AgentDb>,
+    file_path: String,
+) -> Result<(), String> {
+    let json_data = export_all_agents(db).await?;
+    std::fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(())
+}
+
+/// Export agent to file with native dialog
+#[tauri::command]
+pub async fn export_agent_to_file(
+    db: State<'_, AgentDb>,
+    id: i64,
+    file_path: String,
+) -> Result<(), String> {
+    // Get the JSON data
+    let json_data = export_agent(db, id).await?;
+
+    // Write to file
+    std::fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the stored Claude binary path from settings
+#[tauri::command]
+pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'claude_binary_path'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(path) => Ok(Some(path)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Failed to get Claude binary path: {}", e)),
+    }
+}
+
+/// Why a Claude binary path was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryPathRejection {
+    AppBundle,
+    NotFound,
+    NotAFile,
+    NotExecutable,
+}
+
+/// Outcome of `set_claude_binary_path`; `reason_code` lets the UI tailor its guidance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryPathValidation {
+    pub ok: bool,
+    pub reason_code: Option<BinaryPathRejection>,
+    pub message: String,
+}
+
+impl BinaryPathValidation {
+    fn rejected(reason_code: BinaryPathRejection, message: String) -> Self {
+        Self {
+            ok: false,
+            reason_code: Some(reason_code),
+            message,
+        }
+    }
+}
+
+/// Check that `path` is a runnable Claude CLI binary rather than an app bundle or stray file
+fn validate_claude_binary_path(path: &str) -> BinaryPathValidation {
+    if crate::claude_binary::is_disallowed_claude_path(path) {
+        let message = if path.to_ascii_lowercase().contains("claude.app") {
+            "Claude.app is the desktop app, not the Claude Code CLI. Select the `claude` \
+             binary instead (run `which claude` in a terminal to find it)."
+                .to_string()
+        } else {
+            "Selected path points to a GUI app bundle. Please select the Claude CLI binary."
+                .to_string()
+        };
+        return BinaryPathValidation::rejected(BinaryPathRejection::AppBundle, message);
+    }
+
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return BinaryPathValidation::rejected(
+                BinaryPathRejection::NotFound,
+                format!("File does not exist: {}", path),
+            )
+        }
+    };
+    if !metadata.is_file() {
+        return BinaryPathValidation::rejected(
+            BinaryPathRejection::NotAFile,
+            format!("Path is not a file: {}", path),
+        );
+    }
+
+    // Check if it's executable (on Unix systems)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return BinaryPathValidation::rejected(
+                BinaryPathRejection::NotExecutable,
+                format!("File is not executable: {}", path),
+            );
+        }
+    }
+
+    BinaryPathValidation {
+        ok: true,
+        reason_code: None,
+        message: format!("Claude binary path set to {}", path),
+    }
+}
+
+/// Set the Claude binary path in settings. Invalid paths are reported in the
+/// returned validation rather than as an error.
+#[tauri::command]
+pub async fn set_claude_binary_path(
+    db: State<'_, AgentDb>,
+    path: String,
+) -> Result<BinaryPathValidation, String> {
+    let validation = validate_claude_binary_path(&path);
+    if !validation.ok {
+        return Ok(validation);
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    // Insert or update the setting
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('claude_binary_path', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![path],
+    )
+    .map_err(|e| format!("Failed to save Claude binary path: {}", e))?;
+
+    Ok(validation)
+}
+
+/// List all available Claude installations on the system
+#[tauri::command]
+pub async fn list_claude_installations(
+    _app: AppHandle,
+) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
+    let installations = crate::claude_binary::discover_claude_installations();
+
+    if installations.is_empty() {
+        return Err("No Claude Code installations found on the system".to_string());
+    }
+
+    Ok(installations)
+}
+
+/// Helper function to create a tokio Command with proper environment variables
+/// This ensures commands like Claude can find Node.js and other dependencies.
+/// The environment is worked out for the provider at `binary_path`, even when
+/// `program` is a sandbox wrapping it.
+fn create_command_with_env(
+    program: &str,
+    binary_path: &str,
+    env_overrides: &EnvOverrides,
+) -> Command {
+    // Convert std::process::Command to tokio::process::Command
+    let _std_cmd = crate::claude_binary::create_command_with_env(binary_path);
+
+    // Create a new tokio Command from the program path
+    let mut tokio_cmd = Command::new(program);
+
+    // Inherit full environment so all providers receive auth/config vars.
+    for (key, value) in std::env::vars() {
+        tokio_cmd.env(&key, &value);
+    }
+
+    let existing_path = std::env::var("PATH").unwrap_or_default();
+    let mut paths: Vec<&str> = existing_path.split(':').filter(|p| !p.is_empty()).collect();
+
+    // Add NVM support if the provider is in an NVM directory
+    let node_bin_dir = binary_path
+        .contains("/.nvm/versions/node/")
+        .then(|| std::path::Path::new(binary_path).parent())
+        .flatten()
+        .map(|dir| dir.to_string_lossy().into_owned());
+    if let Some(node_bin_dir) = &node_bin_dir {
+        if !paths.contains(&node_bin_dir.as_str()) {
+            paths.insert(0, node_bin_dir);
+        }
+    }
+
+    // Ensure PATH contains common Homebrew locations
+    for p in ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin", "/bin"] {
+        if !paths.contains(&p) {
+            paths.push(p);
+        }
+    }
+    tokio_cmd.env("PATH", paths.join(":"));
+
+    // Per-run overrides win over everything inherited above
+    for (key, value) in env_overrides {
+        if value.is_empty() {
+            tokio_cmd.env_remove(key);
+        } else {
+            tokio_cmd.env(key, value);
+        }
+    }
+
+    tokio_cmd
+}
+
+/// Validate imported hooks JSON. Invalid hooks are rejected unless `lenient`,
+/// in which case invalid event sections (or an unparseable blob) are dropped.
+fn sanitize_imported_hooks(hooks: Option<String>, lenient: bool) -> Result<Option<String>, String> {
+    let Some(raw) = hooks.filter(|h| !h.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let parsed: JsonValue = match serde_json::from_str(&raw) {
+        Ok(value) => value,
+        Err(e) if lenient => {
+            tracing::warn!("Dropping unparseable hooks from imported agent: {}", e);
+            return Ok(None);
+        }
+        Err(e) => return Err(format!("Invalid hooks JSON in imported agent: {}", e)),
+    };
+
+    let errors = crate::commands::claude::validate_hooks_config(&parsed);
+    if errors.is_empty() {
+        return Ok(Some(raw));
+    }
+    if !lenient {
+        return Err(format!(
+            "Invalid hooks in imported agent: {}",
+            errors.join("; ")
+        ));
+    }
+
+    let JsonValue::Object(mut sections) = parsed else {
+        tracing::warn!("Dropping non-object hooks from imported agent");
+        return Ok(None);
+    };
+    let removed = crate::commands::claude::strip_invalid_hook_sections(&mut sections);
+    tracing::warn!("Dropped invalid hook sections from imported agent: {:?}", removed);
+
+    if sections.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&JsonValue::Object(sections))
+        .map(Some)
+        .map_err(|e| format!("Failed to serialize hooks: {}", e))
+}
+
+/// Import agents from JSON data, either a single-agent export or a bulk
+/// export with an `agents` array. Malformed hooks are rejected unless
+/// `lenient` is set, which strips them instead.
+#[tauri::command]
+pub async fn import_agent(
+    db: State<'_, AgentDb>,
+    json_data: String,
+    lenient: Option<bool>,
+) -> Result<ImportedAgents, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    import_agents_json(&mut conn, &json_data, lenient.unwrap_or(false))
+}
+
+fn check_export_version(version: u32) -> Result<(), String> {
+    if version != 1 {
+        return Err(format!(
+            "Unsupported export version: {}. This version of the app only supports version 1.",
+            version
+        ));
+    }
+    Ok(())
+}
+
+/// Import a single or bulk export; a bulk import is all-or-nothing
+fn import_agents_json(
+    conn: &mut Connection,
+    json_data: &str,
+    lenient: bool,
+) -> Result<ImportedAgents, String> {
+    let parsed: JsonValue =
+        serde_json::from_str(json_data).map_err(|e| format!("Invalid JSON format: {}", e))?;
+    if parsed.get("agents").is_none() {
+        return import_agent_json(conn, json_data, lenient)
+            .map(|agent| ImportedAgents::Single(Box::new(agent)));
+    }
+
+    let export_data: AgentBulkExport =
+        serde_json::from_value(parsed).map_err(|e| format!("Invalid JSON format: {}", e))?;
+    check_export_version(export_data.version)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let agents = export_data
+        .agents
+        .into_iter()
+        .map(|agent_data| insert_imported_agent(&tx, agent_data, lenient))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ImportedAgents::Bulk(agents))
+}
+
+fn import_agent_json(conn: &Connection, json_data: &str, lenient: bool) -> Result<Agent, String> {
+    // Parse the JSON data
+    let export_data: AgentExport =
+        serde_json::from_str(json_data).map_err(|e| format!("Invalid JSON format: {}", e))?;
+
+    // Validate version
+    check_export_version(export_data.version)?;
+
+    insert_imported_agent(conn, export_data.agent, lenient)
+}
+
+/// Insert one imported agent, adding an "(Imported)" suffix when the name is taken
+fn insert_imported_agent(
+    conn: &Connection,
+    agent_data: AgentData,
+    lenient: bool,
+) -> Result<Agent, String> {
+    let hooks = sanitize_imported_hooks(agent_data.hooks, lenient)?;
+
+    // Check if an agent with the same name already exists
+    let existing_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM agents WHERE name = ?1 AND deleted_at IS NULL",
+            params![agent_data.name],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // If agent with same name exists, append a suffix
+    let final_name = if existing_count > 0 {
+        format!("{} (Imported)", agent_data.name)
+    } else {
+        agent_data.name
+    };
+
+    // Create the agent
+    conn.execute(
+        "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, 1, 0, ?7)",
+        params![
+            final_name,
+            agent_data.icon,
+            agent_data.system_prompt,
+            agent_data.default_task,
+            agent_data.provider_id,
+            agent_data.model,
+            hooks
+        ],
+    )
+    .map_err(|e| format!("Failed to create agent: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+
+    // Fetch the created agent
+    query_agent_by_id(&conn, id).map_err(|e| format!("Failed to fetch created agent: {}", e))
+}
+
+/// Import agent from file
+#[tauri::command]
+pub async fn import_agent_from_file(
+    db: State<'_, AgentDb>,
+    file_path: String,
+) -> Result<ImportedAgents, String> {
+    // Read the file
+    let mut json_data =
+        std::fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Normalize potential BOM and whitespace issues
+    if json_data.starts_with('\u{feff}') {
+        json_data = json_data.trim_start_matches('\u{feff}').to_string();
+    }
+    // Also trim leading/trailing whitespace to avoid parse surprises
+    json_data = json_data.trim().to_string();
+
+    // Import the agent
+    import_agent(db, json_data, None).await
+}
+
+// GitHub Agent Import functionality
+
+/// Represents a GitHub agent file from the API
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitHubAgentFile {
+    pub name: String,
+    pub path: String,
+    pub download_url: String,
+    pub size: i64,
+    pub sha: String,
+}
+
+/// Represents the GitHub API response for directory contents
+#[derive(Debug, Deserialize)]
+struct GitHubApiResponse {
+    name: String,
+    path: String,
+    sha: String,
+    size: i64,
+    download_url: Option<String>,
+    #[serde(rename = "type")]
+    file_type: String,
+}
+
+/// Agent library `fetch_github_agents` lists when no repository is given
+const DEFAULT_GITHUB_AGENTS_OWNER: &str = "FlourishingHumanityCorporation";
+const DEFAULT_GITHUB_AGENTS_REPO: &str = "opcode";
+const DEFAULT_GITHUB_AGENTS_PATH: &str = "cc_agents";
+
+/// Setting key (in `app_settings`) for a GitHub token sent with agent import
+/// requests; the `GITHUB_TOKEN` environment variable is used when it's unset
+pub const GITHUB_TOKEN_SETTING: &str = "github_token";
+
+/// GitHub token for agent import: the app setting, else `GITHUB_TOKEN`
+fn resolve_github_token(setting: Option<String>, env_token: Option<String>) -> Option<String> {
+    setting
+        .into_iter()
+        .chain(env_token)
+        .map(|token| token.trim().to_string())
+        .find(|token| !token.is_empty())
+}
+
+fn github_token(db: &State<'_, AgentDb>) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(resolve_github_token(
+        read_app_setting(&conn, GITHUB_TOKEN_SETTING),
+        env::var("GITHUB_TOKEN").ok(),
+    ))
+}
+
+/// Only GitHub's own hosts get the token, since download URLs come from the caller
+fn is_github_host(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .is_some_and(|host| {
+            matches!(
+                host.as_str(),
+                "github.com" | "api.github.com" | "raw.githubusercontent.com"
+            )
+        })
+}
+
+/// GET a GitHub URL with the headers every agent import request sends
+fn github_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client.get(url).header("User-Agent", "codeinterfacex-App");
+    match token {
+        Some(token) if is_github_host(url) => request.bearer_auth(token),
+        _ => request,
+    }
+}
+
+/// Error for a failed GitHub request, explaining rate limiting when that's the cause
+fn github_error_message(
+    context: &str,
+    status: reqwest::StatusCode,
+    rate_limit_remaining: Option<&str>,
+    body: &str,
+) -> String {
+    let mut message = format!("{} (HTTP {})", context, status);
+    if status == reqwest::StatusCode::FORBIDDEN {
+        if let Some(remaining) = rate_limit_remaining {
+            message.push_str(&format!(
+                "; GitHub rate limit remaining: {}. Set a GitHub token in settings or \
+                 GITHUB_TOKEN to raise the limit",
+                remaining
+            ));
+        }
+    }
+    if !body.trim().is_empty() {
+        message.push_str(&format!(": {}", body.trim()));
+    }
+    message
+}
+
+async fn github_response_error(context: &str, response: reqwest::Response) -> String {
+    let status = response.status();
+    let remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.unwrap_or_default();
+    github_error_message(context, status, remaining.as_deref(), &body)
+}
+
+/// Whether `segment` is a single, literal GitHub name or path component
+fn is_safe_github_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// GitHub contents API URL for an agent library, falling back to the defaults for
+/// missing parts. Inputs are validated so they can't rewrite the URL.
+fn github_agents_url(
+    owner: Option<&str>,
+    repo: Option<&str>,
+    path: Option<&str>,
+) -> Result<String, String> {
+    let owner = owner.map(str::trim).unwrap_or(DEFAULT_GITHUB_AGENTS_OWNER);
+    let repo = repo.map(str::trim).unwrap_or(DEFAULT_GITHUB_AGENTS_REPO);
+    let path = path
+        .map(|path| path.trim().trim_matches('/'))
+        .unwrap_or(DEFAULT_GITHUB_AGENTS_PATH);
+
+    if !is_safe_github_segment(owner) {
+        return Err(format!("Invalid GitHub owner: {:?}", owner));
+    }
+    if !is_safe_github_segment(repo) {
+        return Err(format!("Invalid GitHub repository: {:?}", repo));
+    }
+    // An empty path lists the repository root
+    if !path.is_empty() && !path.split('/').all(is_safe_github_segment) {
+        return Err(format!("Invalid path in GitHub repository: {:?}", path));
+    }
+
+    Ok(format!(
+        "https://api.github.com/repos/{}/{}/contents/{}",
+        owner, repo, path
+    ))
+}
+
+/// Fetch list of agents from a GitHub repository, by default opcode's own library
+#[tauri::command]
+pub async fn fetch_github_agents(
+    db: State<'_, AgentDb>,
+    owner: Option<String>,
+    repo: Option<String>,
+    path: Option<String>,
+) -> Result<Vec<GitHubAgentFile>, String> {
+    let url = github_agents_url(owner.as_deref(), repo.as_deref(), path.as_deref())?;
+    let token = github_token(&db)?;
+    list_github_agent_files(&reqwest::Client::new(), &url, token.as_deref()).await
+}
+
+/// Agent files in the GitHub directory listed by contents API `url`
+async fn list_github_agent_files(
+    client: &reqwest::Client,
+    url: &str,
+    token: Option<&str>,
+) -> Result<Vec<GitHubAgentFile>, String> {
+    tracing::info!("Fetching agents from GitHub: {}", url);
+
+    let response = github_get(client, url, token)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch from GitHub: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(github_response_error("GitHub API error", response).await);
+    }
+
+    let api_files: Vec<GitHubApiResponse> = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+
+    // Filter only .codeinterfacex.json agent files
+    let agent_files: Vec<GitHubAgentFile> = api_files
+        .into_iter()
+        .filter(|f| f.name.ends_with(".codeinterfacex.json") && f.file_type == "file")
+        .filter_map(|f| {
+            f.download_url.map(|download_url| GitHubAgentFile {
+                name: f.name,
+                path: f.path,
+                download_url,
+                size: f.size,
+                sha: f.sha,
+            })
+        })
+        .collect();
+
+    tracing::info!("Found {} agents on GitHub", agent_files.len());
+    Ok(agent_files)
+}
+
+/// Manifest in an agent library that describes its agents for the gallery
+const AGENT_GALLERY_MANIFEST: &str = "gallery.json";
+
+/// Gallery manifest version this build understands
+const AGENT_GALLERY_VERSION: u32 = 1;
+
+/// An agent as shown in the gallery
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentGalleryEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub download_url: String,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentGalleryManifest {
+    version: u32,
+    agents: Vec<AgentGalleryEntry>,
+}
+
+fn parse_agent_gallery(json: &str) -> Result<Vec<AgentGalleryEntry>, String> {
+    let manifest: AgentGalleryManifest =
+        serde_json::from_str(json).map_err(|e| format!("Invalid agent gallery manifest: {}", e))?;
+    if manifest.version != AGENT_GALLERY_VERSION {
+        return Err(format!(
+            "Unsupported agent gallery version: {}",
+            manifest.version
+        ));
+    }
+    Ok(manifest.agents)
+}
+
+/// Bare gallery entry for a library without a manifest
+fn gallery_entry_from_file(file: GitHubAgentFile) -> AgentGalleryEntry {
+    let name = file
+        .name
+        .strip_suffix(".codeinterfacex.json")
+        .unwrap_or(&file.name)
+        .to_string();
+    AgentGalleryEntry {
+        name,
+        description: String::new(),
+        tags: Vec::new(),
+        download_url: file.download_url,
+        author: None,
+    }
+}
+
+/// Fetch the agent gallery of a GitHub agent library from its `gallery.json`
+/// manifest, falling back to the library's agent files when it has none
+#[tauri::command]
+pub async fn fetch_agent_gallery(
+    db: State<'_, AgentDb>,
+    owner: Option<String>,
+    repo: Option<String>,
+    path: Option<String>,
+) -> Result<Vec<AgentGalleryEntry>, String> {
+    let library_url = github_agents_url(owner.as_deref(), repo.as_deref(), path.as_deref())?;
+    let manifest_url = format!(
+        "{}/{}",
+        library_url.trim_end_matches('/'),
+        AGENT_GALLERY_MANIFEST
+    );
+    let token = github_token(&db)?;
+    tracing::info!("Fetching agent gallery from: {}", manifest_url);
+
+    let client = reqwest::Client::new();
+    let response = github_get(&client, &manifest_url, token.as_deref())
+        .header("Accept", "application/vnd.github.raw+json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch agent gallery: {}", e))?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        tracing::info!("No agent gallery manifest, listing the library instead");
+        let files = list_github_agent_files(&client, &library_url, token.as_deref()).await?;
+        return Ok(files.into_iter().map(gallery_entry_from_file).collect());
+    }
+    if !response.status().is_success() {
+        return Err(github_response_error("Failed to fetch agent gallery", response).await);
+    }
+
+    let manifest = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read agent gallery: {}", e))?;
+    parse_agent_gallery(&manifest)
+}
+
+/// Fetch and preview a specific agent from GitHub
+#[tauri::command]
+pub async fn fetch_github_agent_content(
+    db: State<'_, AgentDb>,
+    download_url: String,
+) -> Result<AgentExport, String> {
+    let token = github_token(&db)?;
+    download_github_agent(&download_url, token.as_deref()).await
+}
+
+async fn download_github_agent(
+    download_url: &str,
+    token: Option<&str>,
+) -> Result<AgentExport, String> {
+    tracing::info!("Fetching agent content from: {}", download_url);
+
+    let client = reqwest::Client::new();
+    let response = github_get(&client, download_url, token)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download agent: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(github_response_error("Failed to download agent", response).await);
+    }
+
+    let json_text = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    // Parse and validate the agent data
+    let export_data: AgentExport = serde_json::from_str(&json_text)
+        .map_err(|e| format!("Invalid agent JSON format: {}", e))?;
+
+    // Validate version
+    if export_data.version != 1 {
+        return Err(format!(
+            "Unsupported agent version: {}",
+            export_data.version
+        ));
+    }
+
+    Ok(export_data)
+}
+
+/// Import an agent directly from GitHub
+#[tauri::command]
+pub async fn import_agent_from_github(
+    db: State<'_, AgentDb>,
+    download_url: String,
+) -> Result<Agent, String> {
+    tracing::info!("Importing agent from GitHub: {}", download_url);
+
+    // First, fetch the agent content
+    let token = github_token(&db)?;
+    let export_data = download_github_agent(&download_url, token.as_deref()).await?;
+
+    // Convert to JSON string and use existing import logic
+    let json_data = serde_json::to_string(&export_data)
+        .map_err(|e| format!("Failed to serialize agent data: {}", e))?;
+
+    // Import using existing function
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    import_agent_json(&conn, &json_data, false)
+}
+
+/// Load agent session history from JSONL file
+/// Similar to provider-session history loading, but searches across all project directories
+#[tauri::command]
+pub async fn load_agent_session_history(
+    session_id: String,
+) -> Result<Vec<serde_json::Value>, String> {
+    tracing::info!("Loading agent session history for session: {}", session_id);
+
+    let claude_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+
+    let projects_dir = claude_dir.join("projects");
+
+    if !projects_dir.exists() {
+        tracing::error!("Projects directory not found at: {:?}", projects_dir);
+        return Err("Projects directory not found".to_string());
+    }
+
+    // Search for the session file in all project directories
+    let mut session_file_path = None;
+    tracing::info!(
+        "Searching for session file {} in all project directories",
+        session_id
+    );
+
+    match crate::commands::claude::project_directories(&projects_dir) {
+        Ok(project_dirs) => {
+            for path in project_dirs {
+                let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
+                tracing::debug!("Checking project directory: {}", dir_name);
+
+                let potential_session_file = path.join(format!("{}.jsonl", session_id));
+                if potential_session_file.exists() {
+                    tracing::info!("Found session file at: {:?}", potential_session_file);
+                    session_file_path = Some(potential_session_file);
+                    break;
+                } else {
+                    tracing::debug!("Session file not found in: {}", dir_name);
+                }
+            }
+        }
+        Err(e) => tracing::error!("{}", e),
+    }
+
+    if let Some(session_path) = session_file_path {
+        let file = std::fs::File::open(&session_path)
+            .map_err(|e| format!("Failed to open session file: {}", e))?;
+
+        let reader = BufReader::new(file);
+        let mut messages = Vec::new();
+
+        for line in reader.lines() {
+            if let Ok(line) = line {
+                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
+                    messages.push(json);
+                }
+            }
+        }
+
+        Ok(messages)
+    } else {
+        Err(format!("Session file not found: {}", session_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_provider_args_claude_contains_expected_flags() {
+        let args = build_provider_args(
+            "claude",
+            "test task",
+            "sonnet",
+            Some("system prompt here"),
+            None,
+            &[],
+        );
+        assert_eq!(args[0], "-p");
+        assert_eq!(args[1], "test task");
+        assert!(args.contains(&"--system-prompt".to_string()));
+        assert!(args.contains(&"--model".to_string()));
+        assert!(args.contains(&"--output-format".to_string()));
+        assert!(args.contains(&"stream-json".to_string()));
+    }
+
+    #[test]
+    fn validate_provider_id_accepts_known_and_rejects_typos() {
+        assert!(validate_provider_id("claude").is_ok());
+        assert!(validate_provider_id("opencode").is_ok());
+
+        let error = validate_provider_id("cluade").unwrap_err();
+        assert!(error.contains("Unknown provider 'cluade'"));
+        assert!(error.contains("aider, claude, codex, gemini, goose, opencode"));
+    }
+
+    #[test]
+    fn every_provider_with_args_is_listed_with_install_instructions() {
+        let supported = crate::providers::runtime::list_supported_providers();
+        let ids = supported
+            .iter()
+            .map(|provider| provider.provider_id.as_str())
+            .collect::<Vec<_>>();
+
+        for provider_id in ["claude", "codex", "aider", "gemini", "goose", "opencode"] {
+            // Unknown providers fall through to just the task
+            let args = build_provider_args(provider_id, "task", "default", None, None, &[]);
+            assert_ne!(args, vec!["task".to_string()], "{} has no args", provider_id);
+            assert!(ids.contains(&provider_id), "{} is not listed", provider_id);
+        }
+
+        for provider in &supported {
+            assert!(!provider.display_name.is_empty());
+            assert!(!provider.install_command.is_empty());
+            assert!(provider.install_url.starts_with("https://"));
+        }
+    }
+
+    #[test]
+    fn build_provider_args_codex_contains_exec_json() {
+        let args = build_provider_args("codex", "refactor code", "gpt-5.3-codex", None, None, &[]);
+        assert_eq!(
+            args,
+            vec![
+                "exec".to_string(),
+                "--json".to_string(),
+                "refactor code".to_string(),
+                "--model".to_string(),
+                "gpt-5.3-codex".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn build_provider_args_codex_includes_reasoning_effort() {
+        let args = build_provider_args(
+            "codex",
+            "refactor code",
+            "gpt-5.3-codex",
+            None,
+            Some("xhigh"),
+            &[],
+        );
+        assert!(args.contains(&"-c".to_string()));
+        assert!(args.contains(&"model_reasoning_effort=\"xhigh\"".to_string()));
+    }
+
+    #[test]
+    fn build_provider_args_codex_ignores_invalid_reasoning_effort() {
+        let args = build_provider_args(
+            "codex",
+            "refactor code",
+            "gpt-5.3-codex",
+            None,
+            Some("extra_high"),
+            &[],
+        );
+        assert!(!args.contains(&"-c".to_string()));
+        assert!(!args
+            .iter()
+            .any(|arg| arg.contains("model_reasoning_effort")));
+    }
+
+    #[test]
+    fn extra_args_are_appended_after_provider_args() {
+        let extra = parse_extra_args(Some(r#"["--max-turns", "20"]"#)).unwrap();
+        let args = build_provider_args("claude", "task", "sonnet", None, None, &extra);
+        assert_eq!(&args[args.len() - 2..], ["--max-turns", "20"]);
+
+        assert_eq!(parse_extra_args(None), Ok(Vec::new()));
+        assert_eq!(parse_extra_args(Some("  ")), Ok(Vec::new()));
+        assert!(parse_extra_args(Some("--max-turns 20")).is_err());
+        assert!(parse_extra_args(Some(r#"["--max-turns", 20]"#)).is_err());
+    }
+
+    #[test]
+    fn claude_output_format_switches_between_stream_json_and_text() {
+        let stream = build_provider_args("claude", "task", "sonnet", Some("prompt"), None, &[]);
+        let mut text = stream.clone();
+        apply_output_format("claude", &mut text, OutputFormat::Text);
+
+        let format_of = |args: &[String]| {
+            let index = args.iter().position(|arg| arg == "--output-format").unwrap();
+            args[index + 1].clone()
+        };
+        assert_eq!(format_of(&stream), "stream-json");
+        assert!(stream.contains(&"--verbose".to_string()));
+        assert_eq!(format_of(&text), "text");
+        assert!(!text.contains(&"--verbose".to_string()));
+
+        let mut unchanged = stream.clone();
+        apply_output_format("claude", &mut unchanged, OutputFormat::StreamJson);
+        assert_eq!(unchanged, stream);
+    }
+
+    #[test]
+    fn output_format_defaults_to_stream_json_and_rejects_unknown_values() {
+        assert_eq!(parse_output_format(None), Ok(OutputFormat::StreamJson));
+        assert_eq!(parse_output_format(Some("text")), Ok(OutputFormat::Text));
+        assert!(parse_output_format(Some("xml")).is_err());
+
+        let mut codex = build_provider_args("codex", "task", "default", None, None, &[]);
+        apply_output_format("codex", &mut codex, OutputFormat::Text);
+        assert!(!codex.contains(&"--json".to_string()));
+    }
+
+    #[test]
+    fn build_provider_args_aider_uses_architect_for_high_effort() {
+        for effort in ["high", "XHIGH"] {
+            let args =
+                build_provider_args("aider", "refactor code", "sonnet", None, Some(effort), &[]);
+            assert!(args.contains(&"--architect".to_string()));
+        }
+
+        for effort in [None, Some("low"), Some("medium"), Some("extra_high")] {
+            let args = build_provider_args("aider", "refactor code", "sonnet", None, effort, &[]);
+            assert!(!args.contains(&"--architect".to_string()));
+        }
+    }
+
+    #[test]
+    fn queued_runs_are_listed_oldest_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('Queue', 'bot', '')",
+            [],
+        )
+        .unwrap();
+        let agent_id = conn.last_insert_rowid();
+        let first = insert_run_with_status(&conn, agent_id, QUEUED_RUN_STATUS);
+        insert_run_with_status(&conn, agent_id, "running");
+        let second = insert_run_with_status(&conn, agent_id, QUEUED_RUN_STATUS);
+
+        let queued: Vec<i64> = query_queued_runs(&conn)
+            .unwrap()
+            .into_iter()
+            .filter_map(|run| run.id)
+            .collect();
+        assert_eq!(queued, vec![first, second]);
+    }
+
+    #[test]
+    fn cancelling_a_queued_run_never_spawns_it() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('Queue', 'bot', '')",
+            [],
+        )
+        .unwrap();
+        let agent_id = conn.last_insert_rowid();
+        let queued = insert_run_with_status(&conn, agent_id, QUEUED_RUN_STATUS);
+        let running = insert_run_with_status(&conn, agent_id, "running");
+
+        cancel_queued_run_in(&conn, queued).unwrap();
+        assert!(query_queued_runs(&conn).unwrap().is_empty());
+
+        let (status, pid, started_at): (String, Option<i64>, Option<String>) = conn
+            .query_row(
+                "SELECT status, pid, process_started_at FROM agent_runs WHERE id = ?1",
+                params![queued],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(status, "cancelled");
+        assert!(pid.is_none());
+        assert!(started_at.is_none());
+
+        // Only queued runs can be cancelled this way
+        assert!(cancel_queued_run_in(&conn, queued).is_err());
+        assert!(cancel_queued_run_in(&conn, running).is_err());
+    }
+
+    #[test]
+    fn run_note_round_trips_without_control_characters() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let run_id = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+        assert_eq!(get_run_note_in(&conn, run_id).unwrap(), None);
+
+        let stored =
+            set_run_note_in(&conn, run_id, "  Flaky \u{1b}[31mAPI\n\tretry later\u{0} ").unwrap();
+        assert_eq!(stored.as_deref(), Some("Flaky [31mAPI\n\tretry later"));
+        assert_eq!(get_run_note_in(&conn, run_id).unwrap(), stored);
+        assert_eq!(query_queued_runs(&conn).unwrap()[0].note, stored);
+
+        // A blank note clears it
+        assert_eq!(set_run_note_in(&conn, run_id, " \n ").unwrap(), None);
+        assert_eq!(get_run_note_in(&conn, run_id).unwrap(), None);
+        assert!(get_run_note_in(&conn, run_id + 1).is_err());
+    }
+
+    #[test]
+    fn run_note_size_is_capped() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let run_id = insert_run_with_status(&conn, 1, "completed");
+
+        let at_limit = "é".repeat(MAX_RUN_NOTE_CHARS);
+        assert!(set_run_note_in(&conn, run_id, &at_limit).is_ok());
+
+        let over_limit = "é".repeat(MAX_RUN_NOTE_CHARS + 1);
+        assert!(set_run_note_in(&conn, run_id, &over_limit).is_err());
+        assert_eq!(get_run_note_in(&conn, run_id).unwrap(), Some(at_limit));
+    }
+
+    #[test]
+    fn rate_limit_retries_count_against_the_original_run() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('Retry', 'bot', '')",
+            [],
+        )
+        .unwrap();
+        let agent_id = conn.last_insert_rowid();
+        let original = insert_run_with_status(&conn, agent_id, "failed");
+        assert_eq!(rate_limit_retry_state(&conn, original).unwrap(), (original, 0));
+
+        let retry = insert_run_with_status(&conn, agent_id, "failed");
+        conn.execute(
+            "UPDATE agent_runs SET retry_of_run_id = ?1 WHERE id = ?2",
+            params![original, retry],
+        )
+        .unwrap();
+
+        // A retry of a retry still resolves to the original run
+        assert_eq!(rate_limit_retry_state(&conn, retry).unwrap(), (original, 1));
+        assert_eq!(rate_limit_retry_state(&conn, original).unwrap(), (original, 1));
+    }
+
+    #[test]
+    fn cancelled_runs_are_not_retried() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let failed = insert_run_with_status(&conn, 1, "failed");
+        let cancelled = insert_run_with_status(&conn, 1, "cancelled");
+
+        assert!(!run_was_cancelled(&conn, failed));
+        assert!(run_was_cancelled(&conn, cancelled));
+        assert!(!run_was_cancelled(&conn, cancelled + 1));
+    }
+
+    #[tokio::test]
+    async fn aborting_rate_limit_retries_stops_their_tasks() {
+        let state = RateLimitRetryState::default();
+        let retry = tokio::spawn(tokio::time::sleep(std::time::Duration::from_secs(60)));
+        state.0.lock().unwrap().insert(7, retry.abort_handle());
+
+        assert_eq!(state.abort_all(), vec![7]);
+        assert!(retry.await.unwrap_err().is_cancelled());
+        assert!(state.abort_all().is_empty());
+    }
+
+    #[test]
+    fn metrics_report_cache_tokens_separately_from_total() {
+        let jsonl = r#"{"type":"assistant","message":{"usage":{"input_tokens":10,"output_tokens":5,"cache_creation_input_tokens":200,"cache_read_input_tokens":3000}}}"#;
+        let metrics = AgentRunMetrics::from_jsonl(jsonl);
+
+        assert_eq!(metrics.total_tokens, Some(15));
+        assert_eq!(metrics.cache_creation_tokens, Some(200));
+        assert_eq!(metrics.cache_read_tokens, Some(3000));
+    }
+
+    #[test]
+    fn metrics_cost_comes_from_the_final_result_total() {
+        let jsonl = [
+            r#"{"type":"system","subtype":"init","session_id":"s1"}"#,
+            r#"{"type":"assistant","cost":0.01,"message":{"usage":{"input_tokens":10,"output_tokens":5}}}"#,
+            r#"{"type":"assistant","cost":0.02,"message":{"usage":{"input_tokens":20,"output_tokens":8}}}"#,
+            r#"{"type":"result","subtype":"success","is_error":false,"total_cost_usd":0.0731,"num_turns":2}"#,
+        ]
+        .join("\n");
+        assert_eq!(AgentRunMetrics::from_jsonl(&jsonl).cost_usd, Some(0.0731));
+
+        // Without a result message the per-line costs are summed
+        let partial = jsonl.lines().take(3).collect::<Vec<_>>().join("\n");
+        let cost = AgentRunMetrics::from_jsonl(&partial).cost_usd.unwrap();
+        assert!((cost - 0.03).abs() < 1e-9, "{}", cost);
+    }
+
+    #[test]
+    fn metrics_accumulator_reports_usage_lines_incrementally() {
+        let mut metrics = RunMetricsAccumulator::default();
+        assert!(!metrics.push_line(r#"{"type":"system","subtype":"init","session_id":"s1"}"#));
+        assert!(!metrics.push_line("not json"));
+        assert!(metrics.push_line(
+            r#"{"type":"assistant","message":{"usage":{"input_tokens":10,"output_tokens":5}}}"#
+        ));
+        assert_eq!(
+            metrics.live(),
+            LiveRunMetrics {
+                total_tokens: 15,
+                cost_usd: 0.0,
+                message_count: 2,
+            }
+        );
+
+        assert!(metrics.push_line(r#"{"type":"result","usage":{"output_tokens":5},"cost":0.25}"#));
+        assert_eq!(metrics.live().total_tokens, 20);
+        assert_eq!(metrics.live().cost_usd, 0.25);
+        assert_eq!(metrics.finish().message_count, Some(3));
+    }
+
+    #[test]
+    fn smoke_test_reports_the_final_result_of_a_successful_run() {
+        let stdout = concat!(
+            r#"{"type":"system","subtype":"init","session_id":"s1"}"#,
+            "\n",
+            r#"{"type":"result","subtype":"success","is_error":false,"result":"OK"}"#,
+            "\n",
+        );
+        let result = smoke_test_result(
+            SmokeTestOutcome::Exited {
+                success: true,
+                exit_code: Some(0),
+            },
+            stdout,
+            "",
+            1200,
+        );
+
+        assert_eq!(
+            result,
+            AgentSmokeTestResult {
+                succeeded: true,
+                output_excerpt: "OK".to_string(),
+                duration_ms: 1200,
+                error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn smoke_test_failures_carry_an_error() {
+        let failed = smoke_test_result(
+            SmokeTestOutcome::Exited {
+                success: false,
+                exit_code: Some(1),
+            },
+            "partial output",
+            "warning: retrying\nError: invalid API key\n",
+            300,
+        );
+        assert!(!failed.succeeded);
+        assert_eq!(failed.output_excerpt, "partial output");
+        assert_eq!(
+            failed.error.as_deref(),
+            Some("Agent exited with code 1: Error: invalid API key")
+        );
+
+        let silent = smoke_test_result(
+            SmokeTestOutcome::Exited {
+                success: true,
+                exit_code: Some(0),
+            },
+            "",
+            "",
+            10,
+        );
+        assert!(!silent.succeeded);
+
+        let timed_out = smoke_test_result(SmokeTestOutcome::TimedOut, "", "", 60_000);
+        assert!(!timed_out.succeeded);
+        assert!(timed_out.error.unwrap().contains("did not finish"));
+    }
+
+    #[test]
+    fn smoke_test_excerpt_keeps_the_tail_of_long_output() {
+        let long = format!("{}é{}", "a".repeat(600), "b".repeat(10));
+        let excerpt = smoke_test_excerpt(&long);
+        assert_eq!(excerpt.chars().count(), SMOKE_TEST_EXCERPT_CHARS);
+        assert!(excerpt.ends_with("ébbbbbbbbbb"));
+    }
+
+    fn timed_run(first_output_at: Option<&str>, process_ended_at: Option<&str>) -> AgentRun {
+        AgentRun {
+            id: Some(1),
+            agent_id: 1,
+            agent_name: "Timer".to_string(),
+            agent_icon: "bot".to_string(),
+            provider_id: "codex".to_string(),
+            task: "task".to_string(),
+            model: "o3".to_string(),
+            project_path: "/tmp/project".to_string(),
+            session_id: String::new(),
+            output: None,
+            status: "completed".to_string(),
+            pid: None,
+            process_started_at: Some("2025-03-10T12:00:00.000+00:00".to_string()),
+            created_at: "2025-03-10 12:00:00".to_string(),
+            completed_at: None,
+            exit_code: Some(0),
+            first_output_at: first_output_at.map(str::to_string),
+            process_ended_at: process_ended_at.map(str::to_string),
+            retry_of_run_id: None,
+            note: None,
+            comparison_id: None,
+        }
+    }
+
+    #[test]
+    fn run_timing_separates_first_output_from_wall_time() {
+        let run = timed_run(
+            Some("2025-03-10T12:00:02.500+00:00"),
+            Some("2025-03-10T12:00:10.000+00:00"),
+        );
+
+        let metrics = AgentRunMetrics::from_jsonl("").with_run_timing(&run);
+
+        assert_eq!(metrics.time_to_first_output_ms, Some(2_500));
+        assert_eq!(metrics.total_wall_ms, Some(10_000));
+    }
+
+    #[test]
+    fn run_timing_is_empty_without_recorded_timestamps() {
+        let run = timed_run(None, Some("2025-03-10T12:00:04.000+00:00"));
+
+        let metrics = AgentRunMetrics::from_jsonl("").with_run_timing(&run);
+
+        assert_eq!(metrics.time_to_first_output_ms, None);
+        assert_eq!(metrics.total_wall_ms, Some(4_000));
+
+        let unstarted = AgentRun {
+            process_started_at: None,
+            ..timed_run(Some("2025-03-10T12:00:01.000+00:00"), None)
+        };
+        let metrics = AgentRunMetrics::from_jsonl("").with_run_timing(&unstarted);
+        assert_eq!(metrics.time_to_first_output_ms, None);
+        assert_eq!(metrics.total_wall_ms, None);
+    }
+
+    #[tokio::test]
+    async fn kill_each_run_keeps_going_after_a_failure() {
+        let mut attempted = Vec::new();
+        let (cancelled, errors) = kill_each_run([1, 2, 3, 4], |run_id| {
+            attempted.push(run_id);
+            async move {
+                match run_id {
+                    2 => Err("no such process".to_string()),
+                    3 => Ok(false),
+                    _ => Ok(true),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(attempted, vec![1, 2, 3, 4]);
+        assert_eq!(cancelled, vec![1, 4]);
+        assert_eq!(
+            errors,
+            vec!["Failed to kill agent run 2: no such process".to_string()]
+        );
+    }
+
+    #[test]
+    fn first_output_poll_limit_follows_configured_timeout() {
+        assert_eq!(
+            first_output_poll_limit(DEFAULT_FIRST_OUTPUT_TIMEOUT_SECS),
+            300
+        );
+        assert_eq!(first_output_poll_limit(600), 6000);
+        assert_eq!(first_output_poll_limit(1), 1000 / FIRST_OUTPUT_POLL_INTERVAL_MS);
+        // 0 disables the first-output timeout
+        assert_eq!(first_output_poll_limit(0), 0);
+        assert_eq!(first_output_poll_limit(-5), 0);
+    }
+
+    #[test]
+    fn agents_default_to_the_standard_first_output_timeout() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('Slow', 'bot', '')",
+            [],
+        )
+        .unwrap();
+
+        let agents = query_agents(&conn, false, false).unwrap();
+        assert_eq!(
+            agents[0].first_output_timeout_secs,
+            DEFAULT_FIRST_OUTPUT_TIMEOUT_SECS
+        );
+    }
+
+    #[test]
+    fn reasoning_effort_precedence_is_explicit_then_project_then_provider() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let resolve = |explicit: Option<&str>| {
+            resolve_reasoning_effort(&conn, "codex", "/work/app", explicit.map(str::to_string))
+        };
+
+        assert_eq!(resolve(None), None);
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, 'high')",
+            params![format!("{}codex", PROVIDER_REASONING_EFFORT_PREFIX)],
+        )
+        .unwrap();
+        assert_eq!(resolve(None).as_deref(), Some("high"));
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, 'low')",
+            params![format!("{}/work/app", PROJECT_REASONING_EFFORT_PREFIX)],
+        )
+        .unwrap();
+        assert_eq!(resolve(None).as_deref(), Some("low"));
+
+        assert_eq!(resolve(Some("xhigh")).as_deref(), Some("xhigh"));
+        assert_eq!(resolve(Some("  ")).as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn provider_reasoning_effort_default_is_scoped_to_its_provider() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, 'medium')",
+            params![format!("{}claude", PROVIDER_REASONING_EFFORT_PREFIX)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolve_reasoning_effort(&conn, "claude", "/work/app", None).as_deref(),
+            Some("medium")
+        );
+        assert_eq!(resolve_reasoning_effort(&conn, "codex", "/work/app", None), None);
+    }
+
+    #[test]
+    fn build_provider_args_goose_uses_non_interactive_stream_mode() {
+        let args = build_provider_args("goose", "summarize repo", "gpt-5", None, None, &[]);
+        assert_eq!(args[0], "run");
+        assert_eq!(args[1], "--text");
+        assert!(args.contains(&"--no-session".to_string()));
+        assert!(args.contains(&"--output-format".to_string()));
+        assert!(args.contains(&"stream-json".to_string()));
+        assert!(args.contains(&"--model".to_string()));
+    }
+
+    #[test]
+    fn build_provider_args_opencode_uses_run_command() {
+        let args = build_provider_args("opencode", "fix failing tests", "gpt-5", None, None, &[]);
+        assert_eq!(args[0], "run");
+        assert_eq!(args[1], "fix failing tests");
+        assert_eq!(&args[2..4], ["--format", "json"]);
+        assert!(args.contains(&"--model".to_string()));
+        assert!(args.contains(&"gpt-5".to_string()));
+
+        let mut text = args.clone();
+        apply_output_format("opencode", &mut text, OutputFormat::Text);
+        assert!(!text.contains(&"--format".to_string()));
+        assert!(!text.contains(&"json".to_string()));
+    }
+
+    #[test]
+    fn transform_provider_output_wraps_plain_text_for_generic_provider() {
+        let wrapped = transform_provider_output("gemini", "hello world").unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&wrapped).unwrap();
+        assert_eq!(parsed["type"], "assistant");
+        assert_eq!(parsed["message"]["content"][0]["text"], "hello world");
+    }
+
+    #[test]
+    fn transform_provider_output_passes_claude_json_line_through() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"ok"}]}}"#;
+        let transformed = transform_provider_output("claude", line).unwrap();
+        assert_eq!(line, transformed);
+    }
+
+    #[test]
+    fn transform_provider_output_tags_thinking_but_not_text() {
+        let codex_reasoning =
+            r#"{"type":"item.completed","item":{"type":"reasoning","text":"Plan the edit"}}"#;
+        let codex_message =
+            r#"{"type":"item.completed","item":{"type":"agent_message","text":"Done."}}"#;
+        let claude_thinking = r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Plan the edit"}]}}"#;
+
+        let reasoning: JsonValue =
+            serde_json::from_str(&transform_provider_output("codex", codex_reasoning).unwrap())
+                .unwrap();
+        assert_eq!(reasoning["message"]["content"][0]["type"], "thinking");
+
+        let message: JsonValue =
+            serde_json::from_str(&transform_provider_output("codex", codex_message).unwrap())
+                .unwrap();
+        assert_eq!(message["message"]["content"][0]["type"], "text");
+
+        let claude: JsonValue =
+            serde_json::from_str(&transform_provider_output("claude", claude_thinking).unwrap())
+                .unwrap();
+        assert_eq!(claude["message"]["content"][0]["type"], "thinking");
+
+        // Unknown providers are left untouched
+        let generic = transform_provider_output("gemini", "thinking about it").unwrap();
+        let generic: JsonValue = serde_json::from_str(&generic).unwrap();
+        assert_eq!(generic["message"]["content"][0]["type"], "text");
+        assert_eq!(generic["message"]["content"][0]["text"], "thinking about it");
+    }
+
+    #[test]
+    fn tail_lines_returns_last_lines_or_everything() {
+        let output = "line1\nline2\nline3\nline4\nline5\n";
+        assert_eq!(tail_lines(output, 2), "line4\nline5");
+        assert_eq!(tail_lines(output, 10), "line1\nline2\nline3\nline4\nline5");
+        assert_eq!(tail_lines(output, 0), "");
+    }
+
+    #[test]
+    fn read_file_tail_reads_backwards_across_chunks() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let all_lines: Vec<String> = (0..2000)
+            .map(|i| format!("{{\"type\":\"assistant\",\"index\":{}}}", i))
+            .collect();
+        writeln!(file, "{}", all_lines.join("\n")).unwrap();
+
+        let tail = read_file_tail(file.path(), 3).unwrap();
+        assert_eq!(tail, all_lines[1997..].join("\n"));
+
+        let everything = read_file_tail(file.path(), 5000).unwrap();
+        assert_eq!(everything, all_lines.join("\n"));
+    }
+
+    #[test]
+    fn build_resume_forked_args_resumes_the_forked_session() {
+        let args = build_resume_forked_args("forked-session-id", "keep going", "opus").unwrap();
+        assert_eq!(
+            &args[..4],
+            &[
+                "--resume".to_string(),
+                "forked-session-id".to_string(),
+                "-p".to_string(),
+                "keep going".to_string(),
+            ]
+        );
+        assert!(args.contains(&"--model".to_string()));
+        assert!(args.contains(&"opus".to_string()));
+        assert!(args.contains(&"stream-json".to_string()));
+    }
+
+    #[test]
+    fn build_resume_forked_args_omits_default_model_and_requires_session_id() {
+        let args = build_resume_forked_args("forked-session-id", "keep going", "default").unwrap();
+        assert!(!args.contains(&"--model".to_string()));
+
+        assert!(build_resume_forked_args("  ", "keep going", "opus").is_err());
+    }
+
+    #[test]
+    fn compact_agent_export_is_single_line_and_round_trips() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, hooks)
+             VALUES ('Reviewer', 'bot', 'Review the diff.\nBe terse.', 'Review', 'codex', 'o3', '{\"PreToolUse\":[]}')",
+            [],
+        )
+        .unwrap();
+        let id = conn.last_insert_rowid();
+
+        let compact = serde_json::to_string(&build_agent_export(&conn, id).unwrap()).unwrap();
+        assert!(!compact.contains('\n'));
+
+        let parsed: AgentExport = serde_json::from_str(&compact).unwrap();
+        assert_eq!(parsed.version, 1);
+        assert_eq!(parsed.agent.name, "Reviewer");
+        assert_eq!(parsed.agent.icon, "bot");
+        assert_eq!(parsed.agent.system_prompt, "Review the diff.\nBe terse.");
+        assert_eq!(parsed.agent.default_task.as_deref(), Some("Review"));
+        assert_eq!(parsed.agent.provider_id, "codex");
+        assert_eq!(parsed.agent.model, "o3");
+        assert_eq!(parsed.agent.hooks.as_deref(), Some("{\"PreToolUse\":[]}"));
+    }
+
+    fn agent_export_json(hooks: Option<&str>) -> String {
+        serde_json::json!({
+            "version": 1,
+            "exported_at": "2025-01-01T00:00:00Z",
+            "agent": {
+                "name": "Hooked",
+                "icon": "bot",
+                "system_prompt": "prompt",
+                "default_task": null,
+                "provider_id": "claude",
+                "model": "sonnet",
+                "hooks": hooks
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn bulk_export_round_trips_through_import() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 2);
+        trash_agent(&conn, 2).unwrap();
+        conn.execute(
+            "INSERT INTO agents (id, name, icon, system_prompt, provider_id, model) VALUES (3, 'Coder', 'code', 'Write code', 'codex', 'o3')",
+            [],
+        )
+        .unwrap();
+
+        let export = build_all_agents_export(&conn).unwrap();
+        let names: Vec<&str> = export["agents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|agent| agent["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Agent 1", "Coder"]);
+
+        let imported = import_agents_json(&mut conn, &export.to_string(), false).unwrap();
+        let ImportedAgents::Bulk(agents) = imported else {
+            panic!("expected a bulk import");
+        };
+        assert_eq!(agents.len(), 2);
+        assert_eq!(agents[0].name, "Agent 1 (Imported)");
+        assert_eq!(agents[1].name, "Coder (Imported)");
+        assert_eq!(agents[1].provider_id, "codex");
+        assert_eq!(agents[1].model, "o3");
+    }
+
+    #[test]
+    fn import_reuses_the_name_of_a_trashed_agent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        trash_agent(&conn, 1).unwrap();
+        let export = serde_json::json!({
+            "version": 1,
+            "exported_at": "2025-01-01T00:00:00Z",
+            "agent": {"name": "Agent 1", "icon": "bot", "system_prompt": "p", "model": "sonnet"}
+        });
+
+        let imported = import_agents_json(&mut conn, &export.to_string(), false).unwrap();
+        let ImportedAgents::Single(agent) = imported else {
+            panic!("expected a single import");
+        };
+        assert_eq!(agent.name, "Agent 1");
+    }
+
+    #[test]
+    fn bulk_import_is_all_or_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let export = serde_json::json!({
+            "version": 1,
+            "exported_at": "2025-01-01T00:00:00Z",
+            "agents": [
+                {"name": "Good", "icon": "bot", "system_prompt": "p", "model": "sonnet"},
+                {"name": "Bad", "icon": "bot", "system_prompt": "p", "model": "sonnet", "hooks": "{not json"}
+            ]
+        });
+
+        assert!(import_agents_json(&mut conn, &export.to_string(), false).is_err());
+        assert_eq!(count_rows(&conn, "agents"), 0);
+
+        let single = import_agents_json(&mut conn, &agent_export_json(None), false).unwrap();
+        assert!(matches!(single, ImportedAgents::Single(agent) if agent.name == "Hooked"));
+    }
+
+    #[test]
+    fn import_agent_keeps_valid_hooks() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let hooks = r#"{"PreToolUse":[{"matcher":"Bash","hooks":[{"type":"command","command":"echo ok"}]}]}"#;
+
+        let agent = import_agent_json(&conn, &agent_export_json(Some(hooks)), false).unwrap();
+        assert_eq!(agent.hooks.as_deref(), Some(hooks));
+    }
+
+    #[test]
+    fn import_agent_rejects_malformed_hooks_unless_lenient() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let hooks = r#"{"PreToolUse":[{"matcher":"(","hooks":[]}],"Stop":[{"type":"command","command":"say done"}]}"#;
+
+        let err = import_agent_json(&conn, &agent_export_json(Some(hooks)), false).unwrap_err();
+        assert!(err.contains("Invalid regex pattern"));
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM agents", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let agent = import_agent_json(&conn, &agent_export_json(Some(hooks)), true).unwrap();
+        let kept: JsonValue = serde_json::from_str(agent.hooks.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            kept,
+            serde_json::json!({ "Stop": [{ "type": "command", "command": "say done" }] })
+        );
 
-    if installations.is_empty() {
-        return Err("No Claude Code installations found on the system".to_string());
+        let agent = import_agent_json(&conn, &agent_export_json(Some("{not json")), true).unwrap();
+        assert!(agent.hooks.is_none());
+        assert!(import_agent_json(&conn, &agent_export_json(Some("{not json")), false).is_err());
     }
 
-    Ok(installations)
-}
+    #[test]
+    fn query_agents_includes_latest_run_when_requested() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (id, name, icon, system_prompt) VALUES (1, 'Runner', 'bot', 'prompt'), (2, 'Idle', 'bot', 'prompt')",
+            [],
+        )
+        .unwrap();
+        for (status, created_at) in [
+            ("failed", "2025-01-01 10:00:00"),
+            ("completed", "2025-01-02 10:00:00"),
+        ] {
+            conn.execute(
+                "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, created_at)
+                 VALUES (1, 'Runner', 'bot', 'task', 'sonnet', '/tmp', '', ?1, ?2)",
+                params![status, created_at],
+            )
+            .unwrap();
+        }
 
-/// Helper function to create a tokio Command with proper environment variables
-/// This ensures commands like Claude can find Node.js and other dependencies
-fn create_command_with_env(program: &str) -> Command {
-    // Convert std::process::Command to tokio::process::Command
-    let _std_cmd = crate::claude_binary::create_command_with_env(program);
+        let agents = query_agents(&conn, true, false).unwrap();
+        let runner = agents.iter().find(|a| a.id == Some(1)).unwrap();
+        assert_eq!(runner.last_run_status.as_deref(), Some("completed"));
+        assert_eq!(runner.last_run_at.as_deref(), Some("2025-01-02 10:00:00"));
+        let idle = agents.iter().find(|a| a.id == Some(2)).unwrap();
+        assert!(idle.last_run_status.is_none());
+        assert!(idle.last_run_at.is_none());
 
-    // Create a new tokio Command from the program path
-    let mut tokio_cmd = Command::new(program);
+        let cheap = query_agents(&conn, false, false).unwrap();
+        assert!(cheap.iter().all(|a| a.last_run_status.is_none()));
+    }
 
-    // Inherit full environment so all providers receive auth/config vars.
-    for (key, value) in std::env::vars() {
-        tokio_cmd.env(&key, &value);
+    #[test]
+    fn record_last_used_model_tracks_run_overrides() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+
+        let agent = query_agents(&conn, false, false).unwrap().remove(0);
+        assert_eq!(agent.model, "sonnet");
+        assert!(agent.last_used_model.is_none());
+
+        // A run started with an override model
+        record_last_used_model(&conn, 1, "opus").unwrap();
+        let agent = query_agents(&conn, false, false).unwrap().remove(0);
+        assert_eq!(agent.model, "sonnet");
+        assert_eq!(agent.last_used_model.as_deref(), Some("opus"));
+
+        record_last_used_model(&conn, 1, "  ").unwrap();
+        let agent = query_agents(&conn, false, false).unwrap().remove(0);
+        assert_eq!(agent.last_used_model.as_deref(), Some("opus"));
     }
 
-    // Add NVM support if the program is in an NVM directory
-    if program.contains("/.nvm/versions/node/") {
-        if let Some(node_bin_dir) = std::path::Path::new(program).parent() {
-            let current_path = std::env::var("PATH").unwrap_or_default();
-            let node_bin_str = node_bin_dir.to_string_lossy();
-            if !current_path.contains(&node_bin_str.as_ref()) {
-                let new_path = format!("{}:{}", node_bin_str, current_path);
-                tokio_cmd.env("PATH", new_path);
-            }
+    fn pending_test_run() -> PendingRun<'static> {
+        PendingRun {
+            agent_id: 1,
+            agent_name: "Agent 1",
+            agent_icon: "bot",
+            provider_id: "gemini",
+            task: "task",
+            model: "gemini-2.5-pro",
+            project_path: "/tmp",
+            session_id: "gemini-run-1",
+            retry_of_run_id: None,
+            comparison_id: None,
         }
     }
 
-    // Ensure PATH contains common Homebrew locations
-    if let Ok(existing_path) = std::env::var("PATH") {
-        let mut paths: Vec<&str> = existing_path.split(':').collect();
-        for p in ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin", "/bin"].iter() {
-            if !paths.contains(p) {
-                paths.push(p);
-            }
-        }
-        let joined = paths.join(":");
-        tokio_cmd.env("PATH", joined);
-    } else {
-        tokio_cmd.env("PATH", "/opt/homebrew/bin:/usr/local/bin:/usr/bin:/bin");
+    #[tokio::test]
+    async fn failing_preflight_skips_run_insert() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let db = AgentDb(Mutex::new(conn));
+
+        let result = record_run_after_preflight(
+            &db,
+            async { Err("Gemini API key was rejected".to_string()) },
+            &pending_test_run(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(count_rows(&db.0.lock().unwrap(), "agent_runs"), 0);
+
+        let run_id = record_run_after_preflight(&db, async { Ok(()) }, &pending_test_run())
+            .await
+            .unwrap();
+        let conn = db.0.lock().unwrap();
+        assert_eq!(count_rows(&conn, "agent_runs"), 1);
+        let status: String = conn
+            .query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(status, "pending");
     }
 
-    tokio_cmd
-}
+    #[test]
+    fn cloned_agent_copies_configuration_under_a_new_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (id, name, icon, system_prompt, default_task, provider_id, model, enable_network, hooks, extra_args)
+             VALUES (1, 'Reviewer', 'shield', 'Review code', 'Review the diff', 'codex', 'gpt-5', 1, '{}', '[\"--full-auto\"]')",
+            [],
+        )
+        .unwrap();
+        record_last_used_model(&conn, 1, "o3").unwrap();
+
+        let clone_id = clone_agent_row(&conn, 1).unwrap();
+        let mut agents = query_agents(&conn, false, false).unwrap();
+        agents.sort_by_key(|agent| agent.id);
+        let (original, clone) = (&agents[0], &agents[1]);
+
+        assert_eq!(clone.id, Some(clone_id));
+        assert_eq!(clone.name, "Reviewer (Copy)");
+        assert_eq!(clone.icon, original.icon);
+        assert_eq!(clone.system_prompt, original.system_prompt);
+        assert_eq!(clone.default_task, original.default_task);
+        assert_eq!(clone.provider_id, "codex");
+        assert_eq!(clone.model, "gpt-5");
+        assert!(clone.enable_network);
+        assert_eq!(clone.hooks.as_deref(), Some("{}"));
+        assert_eq!(clone.extra_args, original.extra_args);
+        assert_eq!(clone.last_used_model, None);
+
+        assert!(clone_agent_row(&conn, 99).is_err());
+    }
 
-/// Import an agent from JSON data
-#[tauri::command]
-pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<Agent, String> {
-    // Parse the JSON data
-    let export_data: AgentExport =
-        serde_json::from_str(&json_data).map_err(|e| format!("Invalid JSON format: {}", e))?;
+    fn insert_test_agents(conn: &Connection, count: i64) {
+        for id in 1..=count {
+            conn.execute(
+                "INSERT INTO agents (id, name, icon, system_prompt) VALUES (?1, ?2, 'bot', 'prompt')",
+                params![id, format!("Agent {}", id)],
+            )
+            .unwrap();
+        }
+    }
 
-    // Validate version
-    if export_data.version != 1 {
-        return Err(format!(
-            "Unsupported export version: {}. This version of the app only supports version 1.",
-            export_data.version
-        ));
+    fn insert_run_with_status(conn: &Connection, agent_id: i64, status: &str) -> i64 {
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status)
+             VALUES (?1, 'Agent', 'bot', 'task', 'sonnet', '/tmp', '', ?2)",
+            params![agent_id, status],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
     }
 
-    let agent_data = export_data.agent;
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    fn count_rows(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
 
-    // Check if an agent with the same name already exists
-    let existing_count: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM agents WHERE name = ?1",
-            params![agent_data.name],
-            |row| row.get(0),
-        )
-        .map_err(|e| e.to_string())?;
+    #[test]
+    fn trashed_agents_are_hidden_until_restored() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 2);
+
+        trash_agent(&conn, 1).unwrap();
+        let active = query_agents(&conn, true, false).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, Some(2));
+        let trashed = query_agents(&conn, false, true).unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id, Some(1));
+        assert!(trashed[0].deleted_at.is_some());
+
+        restore_trashed_agent(&conn, 1).unwrap();
+        assert_eq!(query_agents(&conn, false, false).unwrap().len(), 2);
+        assert!(query_agents(&conn, false, true).unwrap().is_empty());
+        assert!(restore_trashed_agent(&conn, 1).is_err());
+    }
 
-    // If agent with same name exists, append a suffix
-    let final_name = if existing_count > 0 {
-        format!("{} (Imported)", agent_data.name)
-    } else {
-        agent_data.name
-    };
+    #[test]
+    fn purge_only_removes_trashed_agents() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 2);
+        insert_run_with_status(&conn, 1, "completed");
+
+        assert!(purge_trashed_agent(&conn, 1).is_err());
+        assert_eq!(count_rows(&conn, "agents"), 2);
+
+        trash_agent(&conn, 1).unwrap();
+        purge_trashed_agent(&conn, 1).unwrap();
+        assert_eq!(count_rows(&conn, "agents"), 1);
+        assert_eq!(count_rows(&conn, "agent_runs"), 0);
+    }
 
-    // Create the agent
-    conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, 1, 0, ?7)",
-        params![
-            final_name,
-            agent_data.icon,
-            agent_data.system_prompt,
-            agent_data.default_task,
-            agent_data.provider_id,
-            agent_data.model,
-            agent_data.hooks
-        ],
-    )
-    .map_err(|e| format!("Failed to create agent: {}", e))?;
+    #[test]
+    fn delete_agents_removes_agents_and_cascades_runs() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 3);
+        insert_run_with_status(&conn, 1, "completed");
+        insert_run_with_status(&conn, 2, "failed");
+
+        let removed = delete_agents_in_transaction(&mut conn, &[1, 2, 99], true).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(count_rows(&conn, "agents"), 1);
+        assert_eq!(count_rows(&conn, "agent_runs"), 0);
+    }
 
-    let id = conn.last_insert_rowid();
+    #[test]
+    fn delete_agents_moves_agents_to_the_trash_by_default() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 3);
+        insert_run_with_status(&conn, 1, "completed");
+
+        let removed = delete_agents_in_transaction(&mut conn, &[1, 2, 99], false).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(count_rows(&conn, "agents"), 3);
+        assert_eq!(count_rows(&conn, "agent_runs"), 1);
+        assert_eq!(query_agents(&conn, false, true).unwrap().len(), 2);
+        restore_trashed_agent(&conn, 1).unwrap();
+    }
 
-    // Fetch the created agent
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, provider_id, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    provider_id: row
-                        .get::<_, String>(5)
-                        .unwrap_or_else(|_| "claude".to_string()),
-                    model: row.get(6)?,
-                    enable_file_read: row.get(7)?,
-                    enable_file_write: row.get(8)?,
-                    enable_network: row.get(9)?,
-                    hooks: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            },
-        )
-        .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
+    #[test]
+    fn delete_agents_refuses_agent_with_running_run() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 2);
+        insert_run_with_status(&conn, 2, "running");
+
+        let err = delete_agents_in_transaction(&mut conn, &[1, 2], false).unwrap_err();
+        assert!(err.contains("Agent 2"));
+        // Nothing is deleted when any agent is refused
+        assert_eq!(count_rows(&conn, "agents"), 2);
+        assert_eq!(count_rows(&conn, "agent_runs"), 1);
+    }
 
-    Ok(agent)
-}
+    fn insert_test_run(conn: &Connection, model: &str) -> i64 {
+        conn.execute(
+            "INSERT OR IGNORE INTO agents (id, name, icon, system_prompt) VALUES (1, 'Test Agent', 'bot', 'prompt')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, provider_id, task, model, project_path, session_id)
+             VALUES (1, 'Test Agent', 'bot', 'claude', 'task', ?1, '/tmp/project', '')",
+            params![model],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
 
-/// Import agent from file
-#[tauri::command]
-pub async fn import_agent_from_file(
-    db: State<'_, AgentDb>,
-    file_path: String,
-) -> Result<Agent, String> {
-    // Read the file
-    let mut json_data =
-        std::fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    #[test]
+    fn resolve_concrete_model_reads_init_and_result_lines() {
+        let init: JsonValue = serde_json::from_str(
+            r#"{"type":"system","subtype":"init","session_id":"abc","model":"claude-sonnet-4-5"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_concrete_model("default", &init),
+            Some("claude-sonnet-4-5".to_string())
+        );
+        assert_eq!(resolve_concrete_model("opus", &init), None);
 
-    // Normalize potential BOM and whitespace issues
-    if json_data.starts_with('\u{feff}') {
-        json_data = json_data.trim_start_matches('\u{feff}').to_string();
+        let result: JsonValue = serde_json::from_str(
+            r#"{"type":"result","modelUsage":{"claude-opus-4-1":{"inputTokens":10}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_concrete_model("", &result),
+            Some("claude-opus-4-1".to_string())
+        );
     }
-    // Also trim leading/trailing whitespace to avoid parse surprises
-    json_data = json_data.trim().to_string();
 
-    // Import the agent
-    import_agent(db, json_data).await
-}
+    #[test]
+    fn persist_resolved_model_updates_default_model_only() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let default_run = insert_test_run(&conn, "default");
+        let explicit_run = insert_test_run(&conn, "opus");
+
+        let init: JsonValue = serde_json::from_str(
+            r#"{"type":"system","subtype":"init","model":"claude-sonnet-4-5"}"#,
+        )
+        .unwrap();
+        let resolved = resolve_concrete_model("default", &init).unwrap();
 
-// GitHub Agent Import functionality
+        assert_eq!(persist_resolved_model(&conn, default_run, &resolved).unwrap(), 1);
+        assert_eq!(persist_resolved_model(&conn, explicit_run, &resolved).unwrap(), 0);
 
-/// Represents a GitHub agent file from the API
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct GitHubAgentFile {
-    pub name: String,
-    pub path: String,
-    pub download_url: String,
-    pub size: i64,
-    pub sha: String,
-}
+        let model_for = |id: i64| -> String {
+            conn.query_row(
+                "SELECT model FROM agent_runs WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(model_for(default_run), "claude-sonnet-4-5");
+        assert_eq!(model_for(explicit_run), "opus");
+    }
 
-/// Represents the GitHub API response for directory contents
-#[derive(Debug, Deserialize)]
-struct GitHubApiResponse {
-    name: String,
-    path: String,
-    sha: String,
-    size: i64,
-    download_url: Option<String>,
-    #[serde(rename = "type")]
-    file_type: String,
-}
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failing_process_records_its_exit_code() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let run_id = insert_test_run(&conn, "sonnet");
+        conn.execute(
+            "UPDATE agent_runs SET status = 'running' WHERE id = ?1",
+            params![run_id],
+        )
+        .unwrap();
+
+        let status = Command::new("sh")
+            .args(["-c", "exit 3"])
+            .status()
+            .await
+            .unwrap();
+        finish_agent_run(&conn, run_id, "session", "", status.success(), status.code()).unwrap();
+
+        let (run_status, exit_code): (String, Option<i32>) = conn
+            .query_row(
+                "SELECT status, exit_code FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(run_status, "failed");
+        assert_eq!(exit_code, Some(3));
+    }
 
-/// Fetch list of agents from GitHub repository
-#[tauri::command]
-pub async fn fetch_github_agents() -> Result<Vec<GitHubAgentFile>, String> {
-    tracing::info!("Fetching agents from GitHub repository...");
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn signal_terminated_process_leaves_exit_code_null() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let run_id = insert_test_run(&conn, "sonnet");
+        conn.execute(
+            "UPDATE agent_runs SET status = 'running' WHERE id = ?1",
+            params![run_id],
+        )
+        .unwrap();
+
+        let status = Command::new("sh")
+            .args(["-c", "kill -TERM $$"])
+            .status()
+            .await
+            .unwrap();
+        assert_eq!(status.code(), None);
+        finish_agent_run(&conn, run_id, "session", "", status.success(), status.code()).unwrap();
+
+        let (run_status, exit_code): (String, Option<i32>) = conn
+            .query_row(
+                "SELECT status, exit_code FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(run_status, "failed");
+        assert_eq!(exit_code, None);
+    }
 
-    let client = reqwest::Client::new();
-    let url = "https://api.github.com/repos/FlourishingHumanityCorporation/opcode/contents/cc_agents";
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn raw_output_tap_tags_stdout_and_stderr_lines() {
+        let received = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let tap = RawOutputTap(Some(std::sync::Arc::new(move |raw| {
+            sink.lock().unwrap().push(raw)
+        })));
+
+        let mut child = Command::new("sh")
+            .args([
+                "-c",
+                r#"echo '{"type":"raw"}'; echo 'warning: slow' >&2; echo done"#,
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdout = CappedLines::new(TokioBufReader::new(child.stdout.take().unwrap()), 1024);
+        let mut stderr = CappedLines::new(TokioBufReader::new(child.stderr.take().unwrap()), 1024);
+        let (stdout_tap, stderr_tap) = (tap.clone(), tap);
+        tokio::join!(
+            async move {
+                while let Ok(Some(line)) = stdout.next_line().await {
+                    stdout_tap.send(OutputStream::Stdout, &line);
+                }
+            },
+            async move {
+                while let Ok(Some(line)) = stderr.next_line().await {
+                    stderr_tap.send(OutputStream::Stderr, &line);
+                }
+            },
+        );
+        child.wait().await.unwrap();
+
+        // The streams interleave nondeterministically; order within each is kept
+        let mut received = received.lock().unwrap().clone();
+        received.sort_by_key(|raw| raw.stream == OutputStream::Stderr);
+        let raw = |stream, line: &str| RawOutputLine {
+            stream,
+            line: line.to_string(),
+        };
+        assert_eq!(
+            received,
+            vec![
+                raw(OutputStream::Stdout, r#"{"type":"raw"}"#),
+                raw(OutputStream::Stdout, "done"),
+                raw(OutputStream::Stderr, "warning: slow"),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&received[2]).unwrap(),
+            serde_json::json!({ "stream": "stderr", "line": "warning: slow" })
+        );
+    }
 
-    let response = client
-        .get(url)
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "codeinterfacex-App")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch from GitHub: {}", e))?;
+    #[test]
+    fn env_overrides_win_over_inherited_environment() {
+        std::env::set_var("OPCODE_TEST_ENV_OVERRIDE", "inherited");
+        std::env::set_var("OPCODE_TEST_ENV_UNSET", "inherited");
+        let overrides = parse_env_overrides(Some(
+            r#"{"OPCODE_TEST_ENV_OVERRIDE": "override", "OPCODE_TEST_ENV_UNSET": ""}"#,
+        ))
+        .unwrap();
+
+        let cmd = create_command_with_env("true", "true", &overrides);
+        let envs: HashMap<_, _> = cmd.as_std().get_envs().collect();
+        assert_eq!(
+            envs.get(std::ffi::OsStr::new("OPCODE_TEST_ENV_OVERRIDE")),
+            Some(&Some(std::ffi::OsStr::new("override")))
+        );
+        assert_eq!(envs.get(std::ffi::OsStr::new("OPCODE_TEST_ENV_UNSET")), Some(&None));
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("GitHub API error ({}): {}", status, error_text));
+        assert!(parse_env_overrides(None).unwrap().is_empty());
+        assert!(parse_env_overrides(Some(r#"{"A": 1}"#)).is_err());
+        assert!(parse_env_overrides(Some(r#"{"A=B": "x"}"#)).is_err());
     }
 
-    let api_files: Vec<GitHubApiResponse> = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+    #[test]
+    fn sandboxed_command_keeps_provider_node_bin_on_path() {
+        let binary = "/home/dev/.nvm/versions/node/v20.11.0/bin/claude";
+        let (program, args) = sandbox::wrap_command(
+            sandbox::SandboxTool::Bubblewrap,
+            SandboxPolicy::for_agent(false, false),
+            binary,
+            vec!["-p".to_string()],
+            &[],
+        );
 
-    // Filter only .codeinterfacex.json agent files
-    let agent_files: Vec<GitHubAgentFile> = api_files
-        .into_iter()
-        .filter(|f| f.name.ends_with(".codeinterfacex.json") && f.file_type == "file")
-        .filter_map(|f| {
-            f.download_url.map(|download_url| GitHubAgentFile {
-                name: f.name,
-                path: f.path,
-                download_url,
-                size: f.size,
-                sha: f.sha,
-            })
-        })
-        .collect();
+        let cmd =
+            create_agent_system_command(&program, binary, args, "/tmp", &EnvOverrides::new());
+        assert_eq!(cmd.as_std().get_program(), "bwrap");
+        let path = cmd
+            .as_std()
+            .get_envs()
+            .find(|(key, _)| *key == "PATH")
+            .and_then(|(_, value)| value)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert_eq!(
+            path.split(':').next(),
+            Some("/home/dev/.nvm/versions/node/v20.11.0/bin")
+        );
+    }
 
-    tracing::info!("Found {} agents on GitHub", agent_files.len());
-    Ok(agent_files)
-}
+    #[test]
+    fn run_stderr_is_stored_apart_from_output() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let run_id = insert_test_run(&conn, "sonnet");
+        assert_eq!(get_run_stderr_in(&conn, run_id).unwrap(), None);
+
+        finish_agent_run(&conn, run_id, "session", "stdout line\n", false, Some(1)).unwrap();
+        record_run_stderr(&conn, run_id, "Error: boom\n").unwrap();
+        assert_eq!(
+            get_run_stderr_in(&conn, run_id).unwrap().as_deref(),
+            Some("Error: boom\n")
+        );
 
-/// Fetch and preview a specific agent from GitHub
-#[tauri::command]
-pub async fn fetch_github_agent_content(download_url: String) -> Result<AgentExport, String> {
-    tracing::info!("Fetching agent content from: {}", download_url);
+        record_run_stderr(&conn, run_id, "").unwrap();
+        assert_eq!(get_run_stderr_in(&conn, run_id).unwrap(), None);
+        assert!(get_run_stderr_in(&conn, run_id + 1).is_err());
+    }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
-        .header("Accept", "application/json")
-        .header("User-Agent", "codeinterfacex-App")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download agent: {}", e))?;
+    #[test]
+    fn run_stderr_keeps_the_newest_lines_under_the_cap() {
+        let mut stderr = String::new();
+        for line in ["first", "second", "third"] {
+            append_run_stderr(&mut stderr, line, 14);
+        }
+        assert_eq!(stderr, "second\nthird\n");
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to download agent: HTTP {}",
-            response.status()
-        ));
+        append_run_stderr(&mut stderr, "a line longer than the cap", 14);
+        assert_eq!(stderr, "a line longer than the cap\n");
     }
 
-    let json_text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+    #[test]
+    fn retrying_requires_a_live_agent() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_run(&conn, "sonnet");
+        assert!(ensure_run_agent_available(&conn, 1, "Test Agent").is_ok());
+
+        trash_agent(&conn, 1).unwrap();
+        let trashed = ensure_run_agent_available(&conn, 1, "Test Agent").unwrap_err();
+        assert!(trashed.contains("trash"), "{}", trashed);
+
+        let missing = ensure_run_agent_available(&conn, 42, "Gone Agent").unwrap_err();
+        assert!(missing.contains("'Gone Agent' has been deleted"), "{}", missing);
+    }
 
-    // Parse and validate the agent data
-    let export_data: AgentExport = serde_json::from_str(&json_text)
-        .map_err(|e| format!("Invalid agent JSON format: {}", e))?;
+    #[test]
+    fn claude_binary_path_rejections_carry_reason_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let reason = |path: &std::path::Path| {
+            validate_claude_binary_path(&path.to_string_lossy()).reason_code
+        };
 
-    // Validate version
-    if export_data.version != 1 {
-        return Err(format!(
-            "Unsupported agent version: {}",
-            export_data.version
-        ));
+        let bundle = validate_claude_binary_path("/Applications/Claude.app");
+        assert_eq!(bundle.reason_code, Some(BinaryPathRejection::AppBundle));
+        assert!(bundle.message.contains("which claude"));
+        assert_eq!(
+            reason(&dir.path().join("missing")),
+            Some(BinaryPathRejection::NotFound)
+        );
+        assert_eq!(reason(dir.path()), Some(BinaryPathRejection::NotAFile));
+
+        let binary = dir.path().join("claude");
+        std::fs::write(&binary, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert_eq!(reason(&binary), Some(BinaryPathRejection::NotExecutable));
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let accepted = validate_claude_binary_path(&binary.to_string_lossy());
+        assert!(accepted.ok);
+        assert_eq!(accepted.reason_code, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pinned_claude_binary_is_revalidated_before_use() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude-2.0");
+        std::fs::write(&binary, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let binary = binary.to_string_lossy().to_string();
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt, binary_path) VALUES ('Pinned', 'bot', '', ?1)",
+            params![binary],
+        )
+        .unwrap();
+        let mut agent = query_agents(&conn, false, false).unwrap().remove(0);
+        assert_eq!(pinned_claude_binary(&agent, "claude"), Ok(Some(binary.clone())));
+        assert_eq!(pinned_claude_binary(&agent, "codex"), Ok(None));
+
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let err = pinned_claude_binary(&agent, "claude").unwrap_err();
+        assert!(err.contains("not executable"), "{}", err);
+        assert!(validate_agent_binary_path(Some("/Applications/Claude.app")).is_err());
+
+        agent.binary_path = None;
+        assert_eq!(pinned_claude_binary(&agent, "claude"), Ok(None));
     }
 
-    Ok(export_data)
-}
+    #[test]
+    fn session_scan_finds_the_jsonl_in_any_project() {
+        let projects = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(projects.path().join("-home-me-alpha")).unwrap();
+        std::fs::create_dir_all(projects.path().join("-home-me-beta")).unwrap();
+        let session_file = projects.path().join("-home-me-beta").join("sess-1.jsonl");
+        std::fs::write(&session_file, "{}\n").unwrap();
 
-/// Import an agent directly from GitHub
-#[tauri::command]
-pub async fn import_agent_from_github(
-    db: State<'_, AgentDb>,
-    download_url: String,
-) -> Result<Agent, String> {
-    tracing::info!("Importing agent from GitHub: {}", download_url);
+        assert_eq!(
+            scan_projects_for_session(projects.path(), "sess-1"),
+            Some(session_file)
+        );
+        assert_eq!(scan_projects_for_session(projects.path(), "sess-2"), None);
+        assert_eq!(
+            scan_projects_for_session(&projects.path().join("missing"), "sess-1"),
+            None
+        );
+    }
 
-    // First, fetch the agent content
-    let export_data = fetch_github_agent_content(download_url).await?;
+    #[test]
+    fn command_preview_quotes_only_what_needs_it() {
+        let args = vec![
+            "-p".to_string(),
+            "fix the bug".to_string(),
+            "--model".to_string(),
+            "sonnet".to_string(),
+            "it's".to_string(),
+            String::new(),
+        ];
+        let launch = SandboxedCommand {
+            program: "/usr/local/bin/claude".to_string(),
+            args,
+            env: Vec::new(),
+            warning: None,
+        };
+        assert_eq!(
+            format_command_preview(&launch, "/tmp/my project"),
+            "cd '/tmp/my project' && /usr/local/bin/claude -p 'fix the bug' --model sonnet \
+             'it'\\''s' ''"
+        );
 
-    // Convert to JSON string and use existing import logic
-    let json_data = serde_json::to_string(&export_data)
-        .map_err(|e| format!("Failed to serialize agent data: {}", e))?;
+        let sandboxed = SandboxedCommand {
+            program: "/usr/bin/bwrap".to_string(),
+            args: vec!["--".to_string(), "claude".to_string()],
+            env: vec![("npm_config_cache".to_string(), "/tmp/npm cache".to_string())],
+            warning: Some("Network access can't be blocked".to_string()),
+        };
+        assert_eq!(
+            format_command_preview(&sandboxed, "/work"),
+            "cd /work && npm_config_cache='/tmp/npm cache' /usr/bin/bwrap -- claude\n\
+             # Network access can't be blocked"
+        );
+    }
 
-    // Import using existing function
-    import_agent(db, json_data).await
-}
+    #[test]
+    fn concurrency_limit_defaults_when_unset_or_invalid() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert_eq!(max_concurrent_agent_runs(&conn), DEFAULT_MAX_CONCURRENT_RUNS);
+
+        for (value, expected) in [
+            ("2", 2),
+            ("0", DEFAULT_MAX_CONCURRENT_RUNS),
+            ("x", DEFAULT_MAX_CONCURRENT_RUNS),
+        ] {
+            conn.execute(
+                "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![MAX_CONCURRENT_RUNS_SETTING, value],
+            )
+            .unwrap();
+            assert_eq!(max_concurrent_agent_runs(&conn), expected);
+        }
+    }
 
-/// Load agent session history from JSONL file
-/// Similar to provider-session history loading, but searches across all project directories
-#[tauri::command]
-pub async fn load_agent_session_history(
-    session_id: String,
-) -> Result<Vec<serde_json::Value>, String> {
-    tracing::info!("Loading agent session history for session: {}", session_id);
+    #[tokio::test]
+    async fn comparison_runs_share_one_tag() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let db = AgentDb(Mutex::new(conn));
 
-    let claude_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+        assert_eq!(comparison_agent_ids(vec![2, 1, 2]).unwrap(), vec![2, 1]);
+        assert!(comparison_agent_ids(Vec::new()).is_err());
 
-    let projects_dir = claude_dir.join("projects");
+        let compared = PendingRun {
+            comparison_id: Some("cmp-1"),
+            ..pending_test_run()
+        };
+        let first = record_run_after_preflight(&db, async { Ok(()) }, &compared)
+            .await
+            .unwrap();
+        let second = record_run_after_preflight(&db, async { Ok(()) }, &compared)
+            .await
+            .unwrap();
+        let other = record_run_after_preflight(&db, async { Ok(()) }, &pending_test_run())
+            .await
+            .unwrap();
+
+        let runs = query_agent_runs(&db.0.lock().unwrap(), &AgentRunFilter::default()).unwrap();
+        let tag = |run_id: i64| {
+            runs.iter()
+                .find(|run| run.id == Some(run_id))
+                .and_then(|run| run.comparison_id.clone())
+        };
+        assert_eq!(tag(first).as_deref(), Some("cmp-1"));
+        assert_eq!(tag(second).as_deref(), Some("cmp-1"));
+        assert_eq!(tag(other), None);
+    }
 
-    if !projects_dir.exists() {
-        tracing::error!("Projects directory not found at: {:?}", projects_dir);
-        return Err("Projects directory not found".to_string());
+    #[test]
+    fn successful_runs_are_checkpointed_when_enabled() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert!(!resolve_checkpoint_on_complete(&conn, None));
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, 'true')",
+            params![CHECKPOINT_AFTER_RUNS_SETTING],
+        )
+        .unwrap();
+        assert!(resolve_checkpoint_on_complete(&conn, None));
+        // The per-run option wins over the setting
+        assert!(!resolve_checkpoint_on_complete(&conn, Some(false)));
+
+        assert_eq!(
+            completion_checkpoint_request(7, true, true, "session-1", "/work/app"),
+            Some(RunCheckpointRequest {
+                session_id: "session-1".to_string(),
+                project_id: "-work-app".to_string(),
+                project_path: "/work/app".to_string(),
+                description: "After run 7".to_string(),
+            })
+        );
+        assert_eq!(completion_checkpoint_request(7, false, true, "session-1", "/work/app"), None);
+        assert_eq!(completion_checkpoint_request(7, true, false, "session-1", "/work/app"), None);
+        assert_eq!(completion_checkpoint_request(7, true, true, "", "/work/app"), None);
     }
 
-    // Search for the session file in all project directories
-    let mut session_file_path = None;
-    tracing::info!(
-        "Searching for session file {} in all project directories",
-        session_id
-    );
+    #[test]
+    fn process_usage_is_parsed_per_platform() {
+        let ps = "    1     0  0.0  1024\n 4242     1 12.5 20480\nbogus\n";
+        assert_eq!(
+            parse_ps_table(ps),
+            vec![
+                ProcessSample {
+                    pid: 1,
+                    parent_pid: 0,
+                    cpu_percent: 0.0,
+                    memory_mb: 1.0,
+                },
+                ProcessSample {
+                    pid: 4242,
+                    parent_pid: 1,
+                    cpu_percent: 12.5,
+                    memory_mb: 20.0,
+                },
+            ]
+        );
 
-    if let Ok(entries) = std::fs::read_dir(&projects_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_dir() {
-                let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
-                tracing::debug!("Checking project directory: {}", dir_name);
+        let wmic = "\r\nNode,CreatingProcessID,IDProcess,PercentProcessorTime,WorkingSet\r\nHOST,1,4242,37,52428800\r\n";
+        assert_eq!(
+            parse_wmic_table(wmic),
+            vec![ProcessSample {
+                pid: 4242,
+                parent_pid: 1,
+                cpu_percent: 37.0,
+                memory_mb: 50.0,
+            }]
+        );
+        assert!(parse_wmic_table("No Instance(s) Available.\r\n").is_empty());
+    }
 
-                let potential_session_file = path.join(format!("{}.jsonl", session_id));
-                if potential_session_file.exists() {
-                    tracing::info!("Found session file at: {:?}", potential_session_file);
-                    session_file_path = Some(potential_session_file);
-                    break;
-                } else {
-                    tracing::debug!("Session file not found in: {}", dir_name);
-                }
-            }
-        }
-    } else {
-        tracing::error!("Failed to read projects directory");
+    #[test]
+    fn process_usage_sums_the_whole_tree() {
+        let sample = |pid, parent_pid, cpu_percent, memory_mb| ProcessSample {
+            pid,
+            parent_pid,
+            cpu_percent,
+            memory_mb,
+        };
+        // bwrap (10) -> claude (11) -> tool (12); 20 is unrelated
+        let samples = vec![
+            sample(10, 1, 0.5, 2.0),
+            sample(11, 10, 30.0, 200.0),
+            sample(12, 11, 10.0, 50.0),
+            sample(20, 1, 99.0, 999.0),
+        ];
+
+        assert_eq!(process_tree_usage(&samples, 10), Some((40.5, 252.0)));
+        assert_eq!(process_tree_usage(&samples, 12), Some((10.0, 50.0)));
+        // An exited process has no usage
+        assert_eq!(process_tree_usage(&samples, 30), None);
     }
 
-    if let Some(session_path) = session_file_path {
-        let file = std::fs::File::open(&session_path)
-            .map_err(|e| format!("Failed to open session file: {}", e))?;
+    #[test]
+    fn github_token_is_only_sent_to_github() {
+        assert_eq!(
+            resolve_github_token(Some(" ghp_setting ".to_string()), Some("ghp_env".to_string())),
+            Some("ghp_setting".to_string())
+        );
+        assert_eq!(
+            resolve_github_token(None, Some("ghp_env".to_string())),
+            Some("ghp_env".to_string())
+        );
+        assert_eq!(resolve_github_token(None, Some("  ".to_string())), None);
 
-        let reader = BufReader::new(file);
-        let mut messages = Vec::new();
+        assert!(is_github_host("https://api.github.com/repos/acme/agents/contents/x"));
+        assert!(is_github_host("https://raw.githubusercontent.com/acme/agents/main/a.json"));
+        assert!(!is_github_host("http://raw.githubusercontent.com/acme/a.json"));
+        assert!(!is_github_host("https://raw.githubusercontent.com.evil.test/a.json"));
+        assert!(!is_github_host("https://example.com/github.com/a.json"));
+    }
 
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
-                    messages.push(json);
+    #[test]
+    fn github_rate_limit_is_explained_on_403() {
+        let limited = github_error_message(
+            "GitHub API error",
+            reqwest::StatusCode::FORBIDDEN,
+            Some("0"),
+            "API rate limit exceeded",
+        );
+        assert!(limited.starts_with("GitHub API error (HTTP 403 Forbidden)"));
+        assert!(limited.contains("rate limit remaining: 0"));
+        assert!(limited.ends_with(": API rate limit exceeded"));
+
+        let missing = github_error_message(
+            "Failed to download agent",
+            reqwest::StatusCode::NOT_FOUND,
+            Some("59"),
+            "",
+        );
+        assert_eq!(missing, "Failed to download agent (HTTP 404 Not Found)");
+    }
+
+    #[test]
+    fn agent_gallery_manifest_parses_into_entries() {
+        let manifest = r#"{
+            "version": 1,
+            "agents": [
+                {
+                    "name": "Git Commit Bot",
+                    "description": "Writes commit messages",
+                    "tags": ["git", "automation"],
+                    "download_url": "https://example.com/git-commit-bot.codeinterfacex.json",
+                    "author": "opcode"
+                },
+                {
+                    "name": "Minimal",
+                    "download_url": "https://example.com/minimal.codeinterfacex.json"
                 }
+            ]
+        }"#;
+
+        let entries = parse_agent_gallery(manifest).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].tags, vec!["git", "automation"]);
+        assert_eq!(entries[0].author.as_deref(), Some("opcode"));
+        assert_eq!(
+            entries[1],
+            AgentGalleryEntry {
+                name: "Minimal".to_string(),
+                description: String::new(),
+                tags: Vec::new(),
+                download_url: "https://example.com/minimal.codeinterfacex.json".to_string(),
+                author: None,
             }
-        }
+        );
 
-        Ok(messages)
-    } else {
-        Err(format!("Session file not found: {}", session_id))
+        let err = parse_agent_gallery(r#"{"version": 2, "agents": []}"#).unwrap_err();
+        assert!(err.contains("Unsupported agent gallery version"));
+
+        let fallback = gallery_entry_from_file(GitHubAgentFile {
+            name: "unit-tests-bot.codeinterfacex.json".to_string(),
+            path: "cc_agents/unit-tests-bot.codeinterfacex.json".to_string(),
+            download_url: "https://example.com/unit-tests-bot.codeinterfacex.json".to_string(),
+            size: 1,
+            sha: "abc".to_string(),
+        });
+        assert_eq!(fallback.name, "unit-tests-bot");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn github_agents_url_rejects_traversal() {
+        assert_eq!(
+            github_agents_url(None, None, None).unwrap(),
+            "https://api.github.com/repos/FlourishingHumanityCorporation/opcode/contents/cc_agents"
+        );
+        assert_eq!(
+            github_agents_url(Some("acme"), Some("agents.lib"), Some("/team/claude/")).unwrap(),
+            "https://api.github.com/repos/acme/agents.lib/contents/team/claude"
+        );
+        assert_eq!(
+            github_agents_url(Some("acme"), Some("agents"), Some("")).unwrap(),
+            "https://api.github.com/repos/acme/agents/contents/"
+        );
+
+        assert!(github_agents_url(Some(".."), None, None).is_err());
+        assert!(github_agents_url(Some("acme/other"), None, None).is_err());
+        assert!(github_agents_url(None, Some(""), None).is_err());
+        assert!(github_agents_url(None, Some("repo?ref=x"), None).is_err());
+        assert!(github_agents_url(None, None, Some("agents/../../secrets")).is_err());
+        assert!(github_agents_url(None, None, Some("agents//nested")).is_err());
+        assert!(github_agents_url(None, None, Some("agents%2F..")).is_err());
+    }
 
     #[test]
-    fn build_provider_args_claude_contains_expected_flags() {
-        let args = build_provider_args(
-            "claude",
-            "test task",
-            "sonnet",
-            Some("system prompt here"),
-            None,
+    fn completion_webhook_reports_the_recorded_status() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let run_id = insert_run_with_status(&conn, 1, "cancelled");
+        assert_eq!(
+            completion_webhook_request(&conn, run_id, "Reviewer", 1200, "/work/app", true),
+            None
+        );
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '   ')",
+            params![COMPLETION_WEBHOOK_SETTING],
+        )
+        .unwrap();
+        assert_eq!(
+            completion_webhook_request(&conn, run_id, "Reviewer", 1200, "/work/app", true),
+            None
+        );
+
+        conn.execute(
+            "UPDATE app_settings SET value = ' https://hooks.example/run ' WHERE key = ?1",
+            params![COMPLETION_WEBHOOK_SETTING],
+        )
+        .unwrap();
+        let (url, payload) =
+            completion_webhook_request(&conn, run_id, "Reviewer", 1200, "/work/app", true)
+                .unwrap();
+        assert_eq!(url, "https://hooks.example/run");
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "run_id": run_id,
+                "agent_name": "Reviewer",
+                "status": "cancelled",
+                "duration_ms": 1200,
+                "project_path": "/work/app",
+            })
         );
-        assert_eq!(args[0], "-p");
-        assert_eq!(args[1], "test task");
-        assert!(args.contains(&"--system-prompt".to_string()));
-        assert!(args.contains(&"--model".to_string()));
-        assert!(args.contains(&"--output-format".to_string()));
-        assert!(args.contains(&"stream-json".to_string()));
     }
 
     #[test]
-    fn build_provider_args_codex_contains_exec_json() {
-        let args = build_provider_args("codex", "refactor code", "gpt-5.3-codex", None, None);
+    fn queued_runs_are_claimed_oldest_first_and_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('Queue', 'bot', '')",
+            [],
+        )
+        .unwrap();
+        let agent_id = conn.last_insert_rowid();
+        let first = insert_run_with_status(&conn, agent_id, QUEUED_RUN_STATUS);
+        let second = insert_run_with_status(&conn, agent_id, QUEUED_RUN_STATUS);
+
+        let claimed = claim_next_queued_run(&mut conn).unwrap().unwrap();
+        assert_eq!(claimed.id, Some(first));
+        assert_eq!(claimed.status, "pending");
         assert_eq!(
-            args,
-            vec![
-                "exec".to_string(),
-                "--json".to_string(),
-                "refactor code".to_string(),
-                "--model".to_string(),
-                "gpt-5.3-codex".to_string()
-            ]
+            claim_next_queued_run(&mut conn).unwrap().unwrap().id,
+            Some(second)
         );
+        assert!(claim_next_queued_run(&mut conn).unwrap().is_none());
     }
 
     #[test]
-    fn build_provider_args_codex_includes_reasoning_effort() {
-        let args = build_provider_args(
-            "codex",
-            "refactor code",
-            "gpt-5.3-codex",
-            None,
-            Some("xhigh"),
+    fn cancelling_the_queue_cancels_only_queued_runs() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let first = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+        let second = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+        let running = insert_run_with_status(&conn, 1, "running");
+
+        assert_eq!(
+            cancel_all_queued_runs_in(&conn).unwrap(),
+            vec![first, second]
         );
-        assert!(args.contains(&"-c".to_string()));
-        assert!(args.contains(&"model_reasoning_effort=\"xhigh\"".to_string()));
+        let status = |run_id: i64| -> String {
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(status(first), "cancelled");
+        assert_eq!(status(second), "cancelled");
+        assert_eq!(status(running), "running");
+        assert!(cancel_all_queued_runs_in(&conn).unwrap().is_empty());
     }
 
     #[test]
-    fn build_provider_args_codex_ignores_invalid_reasoning_effort() {
-        let args = build_provider_args(
-            "codex",
-            "refactor code",
-            "gpt-5.3-codex",
-            None,
-            Some("extra_high"),
+    fn panic_stop_cancels_queued_and_starting_runs() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let queued = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+        let pending = insert_run_with_status(&conn, 1, "pending");
+        let running = insert_run_with_status(&conn, 1, "running");
+        save_launch_options(&conn, queued, &RunLaunchOptions::default()).unwrap();
+
+        assert_eq!(
+            cancel_unstarted_runs_in(&conn).unwrap(),
+            vec![queued, pending]
         );
-        assert!(!args.contains(&"-c".to_string()));
-        assert!(!args
-            .iter()
-            .any(|arg| arg.contains("model_reasoning_effort")));
+        let status = |run_id: i64| -> String {
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(status(queued), "cancelled");
+        assert_eq!(status(pending), "cancelled");
+        assert_eq!(status(running), "running");
+        assert!(load_launch_options(&conn, queued).is_none());
     }
 
     #[test]
-    fn build_provider_args_goose_uses_non_interactive_stream_mode() {
-        let args = build_provider_args("goose", "summarize repo", "gpt-5", None, None);
-        assert_eq!(args[0], "run");
-        assert_eq!(args[1], "--text");
-        assert!(args.contains(&"--no-session".to_string()));
-        assert!(args.contains(&"--output-format".to_string()));
-        assert!(args.contains(&"stream-json".to_string()));
-        assert!(args.contains(&"--model".to_string()));
+    fn startup_fails_runs_left_pending_or_running() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let pending = insert_run_with_status(&conn, 1, "pending");
+        let running = insert_run_with_status(&conn, 1, "running");
+        let queued = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+
+        assert_eq!(fail_interrupted_runs(&conn).unwrap(), 2);
+        let status = |run_id: i64| -> String {
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(status(pending), "failed");
+        assert_eq!(status(running), "failed");
+        assert_eq!(status(queued), QUEUED_RUN_STATUS);
     }
 
     #[test]
-    fn build_provider_args_opencode_uses_run_command() {
-        let args = build_provider_args("opencode", "fix failing tests", "gpt-5", None, None);
-        assert_eq!(args[0], "run");
-        assert_eq!(args[1], "fix failing tests");
-        assert!(args.contains(&"--model".to_string()));
-        assert!(args.contains(&"gpt-5".to_string()));
+    fn launch_options_are_stored_with_the_run() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let run_id = insert_run_with_status(&conn, 1, "failed");
+        assert!(load_launch_options(&conn, run_id).is_none());
+
+        let options = RunLaunchOptions {
+            reasoning_effort: Some("high".to_string()),
+            output_format: OutputFormat::Text,
+            env_overrides: EnvOverrides::from([("RUST_LOG".to_string(), "debug".to_string())]),
+            checkpoint_on_complete: Some(true),
+            raw_output: true,
+        };
+        save_launch_options(&conn, run_id, &options).unwrap();
+
+        let loaded = load_launch_options(&conn, run_id).unwrap();
+        assert_eq!(loaded.reasoning_effort.as_deref(), Some("high"));
+        assert_eq!(loaded.output_format, OutputFormat::Text);
+        assert_eq!(loaded.env_overrides, options.env_overrides);
+        assert_eq!(loaded.checkpoint_on_complete, Some(true));
+        assert!(loaded.raw_output);
+
+        // Taking the options at the end of the run leaves nothing behind
+        let taken = take_launch_options(&conn, run_id).unwrap();
+        assert_eq!(taken.env_overrides, options.env_overrides);
+        assert!(load_launch_options(&conn, run_id).is_none());
     }
 
     #[test]
-    fn transform_provider_output_wraps_plain_text_for_generic_provider() {
-        let wrapped = transform_provider_output("gemini", "hello world").unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&wrapped).unwrap();
-        assert_eq!(parsed["type"], "assistant");
-        assert_eq!(parsed["message"]["content"][0]["text"], "hello world");
+    fn cancelling_a_queued_run_clears_its_launch_options() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        let run_id = insert_run_with_status(&conn, 1, QUEUED_RUN_STATUS);
+        save_launch_options(&conn, run_id, &RunLaunchOptions::default()).unwrap();
+
+        cancel_queued_run_in(&conn, run_id).unwrap();
+        assert!(load_launch_options(&conn, run_id).is_none());
     }
 
     #[test]
-    fn transform_provider_output_passes_claude_json_line_through() {
-        let line = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"ok"}]}}"#;
-        let transformed = transform_provider_output("claude", line).unwrap();
-        assert_eq!(line, transformed);
+    fn run_slots_are_counted_and_claimed_in_one_transaction() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 1);
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '2')",
+            params![MAX_CONCURRENT_RUNS_SETTING],
+        )
+        .unwrap();
+        insert_run_with_status(&conn, 1, "running");
+        // A run that is starting holds a slot before it has a process
+        let starting = insert_run_with_status(&conn, 1, "pending");
+        assert_eq!(queue_run_if_at_limit(&mut conn, starting).unwrap(), None);
+
+        let over_limit = insert_run_with_status(&conn, 1, "pending");
+        assert_eq!(
+            queue_run_if_at_limit(&mut conn, over_limit).unwrap(),
+            Some((2, 2))
+        );
+        let status: String = conn
+            .query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![over_limit],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(status, QUEUED_RUN_STATUS);
+
+        // The queued run waits until a slot frees up
+        assert!(claim_next_queued_run(&mut conn).unwrap().is_none());
+        conn.execute(
+            "UPDATE agent_runs SET status = 'completed' WHERE id = ?1",
+            params![starting],
+        )
+        .unwrap();
+        let claimed = claim_next_queued_run(&mut conn).unwrap().unwrap();
+        assert_eq!(claimed.id, Some(over_limit));
+        assert!(claim_next_queued_run(&mut conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn agent_runs_filter_by_status_and_creation_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let old_failed = insert_test_run(&conn, "sonnet");
+        let new_failed = insert_test_run(&conn, "sonnet");
+        let new_completed = insert_test_run(&conn, "sonnet");
+        for (run_id, status, created_at) in [
+            (old_failed, "failed", "2026-01-01 09:00:00"),
+            (new_failed, "failed", "2026-03-10 12:00:00"),
+            (new_completed, "completed", "2026-03-11 12:00:00"),
+        ] {
+            conn.execute(
+                "UPDATE agent_runs SET status = ?1, created_at = ?2 WHERE id = ?3",
+                params![status, created_at, run_id],
+            )
+            .unwrap();
+        }
+        let ids = |filter: AgentRunFilter| -> Vec<Option<i64>> {
+            query_agent_runs(&conn, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|run| run.id)
+                .collect()
+        };
+
+        assert_eq!(
+            ids(AgentRunFilter::default()),
+            vec![Some(new_completed), Some(new_failed), Some(old_failed)]
+        );
+        assert_eq!(
+            ids(AgentRunFilter {
+                status: Some("failed".to_string()),
+                since: Some("2026-03-10T13:00:00+02:00".to_string()),
+                ..Default::default()
+            }),
+            vec![Some(new_failed)]
+        );
+        assert_eq!(
+            ids(AgentRunFilter {
+                until: Some("2026-03-10T12:00:00Z".to_string()),
+                ..Default::default()
+            }),
+            vec![Some(new_failed), Some(old_failed)]
+        );
+        assert!(query_agent_runs(
+            &conn,
+            &AgentRunFilter {
+                since: Some("last week".to_string()),
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn agents_without_network_permission_run_network_isolated() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_agents(&conn, 2);
+        conn.execute("UPDATE agents SET enable_network = 1 WHERE id = 2", [])
+            .unwrap();
+
+        let policy = |id| {
+            let agent = query_agent_by_id(&conn, id).unwrap();
+            SandboxPolicy::for_agent(agent.enable_file_write, agent.enable_network)
+        };
+        assert!(policy(1).no_network);
+        assert!(!policy(2).no_network);
     }
 }
@@ -3,11 +3,17 @@ use base64::Engine as _;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{BufRead, BufReader};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 
+use crate::commands::agents::AgentDb;
+
 /// Represents a project in the ~/.claude/projects directory
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -40,6 +46,10 @@ pub struct Session {
     pub first_message: Option<String>,
     /// Timestamp of the first user message (if available)
     pub message_timestamp: Option<String>,
+    /// Total cost from the usage index (only populated when metrics are requested)
+    pub total_cost: Option<f64>,
+    /// Total tokens from the usage index (only populated when metrics are requested)
+    pub total_tokens: Option<u64>,
 }
 
 /// Represents a message entry in the JSONL file
@@ -107,10 +117,15 @@ pub struct FileEntry {
     pub path: String,
     /// Whether this is a directory
     pub is_directory: bool,
-    /// File size in bytes (0 for directories)
+    /// File size in bytes (recursive total for directories when `compute_dir_sizes` is set)
     pub size: u64,
     /// File extension (if applicable)
     pub extension: Option<String>,
+    /// Matching content lines (only populated by content-aware searches)
+    pub snippet: Option<String>,
+    /// Entries left out of a directory's recursive `size` because they were
+    /// unreadable, too deep or past the time budget (only set with `compute_dir_sizes`)
+    pub skipped_entries: Option<usize>,
 }
 
 /// Finds the full path to the claude binary
@@ -222,6 +237,211 @@ pub async fn get_home_directory() -> Result<String, String> {
         .ok_or_else(|| "Could not determine home directory".to_string())
 }
 
+/// Maximum number of rows kept in the recent_projects table
+const RECENT_PROJECTS_CAP: i64 = 50;
+
+/// A project opened in the app, as recorded by `record_project_open`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentProject {
+    /// The project ID (encoded directory name)
+    pub id: String,
+    /// RFC 3339 timestamp of the most recent open
+    pub opened_at: String,
+}
+
+fn record_recent_project(conn: &rusqlite::Connection, project_id: &str) -> Result<(), String> {
+    // REPLACE re-inserts the row, so the rowid also reflects open order
+    conn.execute(
+        "INSERT OR REPLACE INTO recent_projects (id, opened_at) VALUES (?1, ?2)",
+        rusqlite::params![
+            project_id,
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+        ],
+    )
+    .map_err(|e| format!("Failed to record project open: {}", e))?;
+
+    conn.execute(
+        "DELETE FROM recent_projects WHERE id NOT IN (
+            SELECT id FROM recent_projects ORDER BY opened_at DESC, rowid DESC LIMIT ?1
+        )",
+        rusqlite::params![RECENT_PROJECTS_CAP],
+    )
+    .map_err(|e| format!("Failed to trim recent projects: {}", e))?;
+
+    Ok(())
+}
+
+fn query_recent_projects(
+    conn: &rusqlite::Connection,
+    limit: i64,
+) -> Result<Vec<RecentProject>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, opened_at FROM recent_projects
+             ORDER BY opened_at DESC, rowid DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let projects = stmt
+        .query_map(rusqlite::params![limit], |row| {
+            Ok(RecentProject {
+                id: row.get(0)?,
+                opened_at: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(projects)
+}
+
+/// Records that a project was opened in the app
+#[tauri::command]
+pub async fn record_project_open(
+    db: State<'_, AgentDb>,
+    project_id: String,
+) -> Result<(), String> {
+    let project_id = project_id.trim();
+    if project_id.is_empty() {
+        return Err("Project ID cannot be empty".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_recent_project(&conn, project_id)
+}
+
+/// Lists projects opened in the app, most recent first
+#[tauri::command]
+pub async fn list_recent_projects(
+    db: State<'_, AgentDb>,
+    limit: Option<i64>,
+) -> Result<Vec<RecentProject>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, RECENT_PROJECTS_CAP);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_recent_projects(&conn, limit)
+}
+
+/// Project directories under `projects_dir`. Stray files are skipped, and symlinks are
+/// only followed when they resolve to a directory inside `~/.claude` (the parent of
+/// `projects_dir`), so a link can't pull in arbitrary parts of the filesystem.
+pub(crate) fn project_directories(projects_dir: &std::path::Path) -> Result<Vec<PathBuf>, String> {
+    let claude_root = projects_dir.parent().and_then(|dir| dir.canonicalize().ok());
+    let entries = fs::read_dir(projects_dir)
+        .map_err(|e| format!("Failed to read projects directory: {}", e))?;
+
+    let mut directories = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            directories.push(path);
+            continue;
+        }
+        if !file_type.is_symlink() {
+            tracing::debug!("Skipping non-directory entry in projects: {:?}", path);
+            continue;
+        }
+
+        // Broken links and symlink loops both fail to resolve
+        let target = match path.canonicalize() {
+            Ok(target) => target,
+            Err(e) => {
+                tracing::warn!("Skipping unresolvable symlink in projects {:?}: {}", path, e);
+                continue;
+            }
+        };
+        if !claude_root.as_ref().is_some_and(|root| target.starts_with(root)) {
+            tracing::warn!(
+                "Skipping symlink in projects pointing outside ~/.claude: {:?} -> {:?}",
+                path,
+                target
+            );
+            continue;
+        }
+        if target.is_dir() {
+            directories.push(path);
+        }
+    }
+
+    Ok(directories)
+}
+
+/// Reads a project directory into a `Project`
+fn load_project(path: &PathBuf) -> Result<Project, String> {
+    let dir_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| "Invalid directory name".to_string())?;
+
+    // Get directory creation time
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read directory metadata: {}", e))?;
+
+    let created_at = metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    // Get the actual project path from JSONL files
+    let project_path = match get_project_path_from_sessions(path) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to get project path from sessions for {}: {}, falling back to decode",
+                dir_name,
+                e
+            );
+            decode_project_path(dir_name)
+        }
+    };
+
+    // List all JSONL files (sessions) in this project directory
+    let mut sessions = Vec::new();
+    let mut most_recent_session: Option<u64> = None;
+
+    if let Ok(session_entries) = fs::read_dir(path) {
+        for session_entry in session_entries.flatten() {
+            let session_path = session_entry.path();
+            if session_path.is_file()
+                && session_path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+            {
+                if let Some(session_id) = session_path.file_stem().and_then(|s| s.to_str()) {
+                    sessions.push(session_id.to_string());
+
+                    // Track the most recent session timestamp
+                    if let Ok(metadata) = fs::metadata(&session_path) {
+                        let modified = metadata
+                            .modified()
+                            .unwrap_or(SystemTime::UNIX_EPOCH)
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs();
+
+                        most_recent_session = Some(match most_recent_session {
+                            Some(current) => current.max(modified),
+                            None => modified,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(Project {
+        id: dir_name.to_string(),
+        path: project_path,
+        sessions,
+        created_at,
+        most_recent_session,
+    })
+}
+
 /// Lists all projects in the ~/.claude/projects directory
 #[tauri::command]
 pub async fn list_projects() -> Result<Vec<Project>, String> {
@@ -238,81 +458,8 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
     let mut projects = Vec::new();
 
     // Read all directories in the projects folder
-    let entries = fs::read_dir(&projects_dir)
-        .map_err(|e| format!("Failed to read projects directory: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            let dir_name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .ok_or_else(|| "Invalid directory name".to_string())?;
-
-            // Get directory creation time
-            let metadata = fs::metadata(&path)
-                .map_err(|e| format!("Failed to read directory metadata: {}", e))?;
-
-            let created_at = metadata
-                .created()
-                .or_else(|_| metadata.modified())
-                .unwrap_or(SystemTime::UNIX_EPOCH)
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-
-            // Get the actual project path from JSONL files
-            let project_path = match get_project_path_from_sessions(&path) {
-                Ok(path) => path,
-                Err(e) => {
-                    tracing::warn!("Failed to get project path from sessions for {}: {}, falling back to decode", dir_name, e);
-                    decode_project_path(dir_name)
-                }
-            };
-
-            // List all JSONL files (sessions) in this project directory
-            let mut sessions = Vec::new();
-            let mut most_recent_session: Option<u64> = None;
-
-            if let Ok(session_entries) = fs::read_dir(&path) {
-                for session_entry in session_entries.flatten() {
-                    let session_path = session_entry.path();
-                    if session_path.is_file()
-                        && session_path.extension().and_then(|s| s.to_str()) == Some("jsonl")
-                    {
-                        if let Some(session_id) = session_path.file_stem().and_then(|s| s.to_str())
-                        {
-                            sessions.push(session_id.to_string());
-
-                            // Track the most recent session timestamp
-                            if let Ok(metadata) = fs::metadata(&session_path) {
-                                let modified = metadata
-                                    .modified()
-                                    .unwrap_or(SystemTime::UNIX_EPOCH)
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap_or_default()
-                                    .as_secs();
-
-                                most_recent_session = Some(match most_recent_session {
-                                    Some(current) => current.max(modified),
-                                    None => modified,
-                                });
-                            }
-                        }
-                    }
-                }
-            }
-
-            projects.push(Project {
-                id: dir_name.to_string(),
-                path: project_path,
-                sessions,
-                created_at,
-                most_recent_session,
-            });
-        }
+    for path in project_directories(&projects_dir)? {
+        projects.push(load_project(&path)?);
     }
 
     // Sort projects by most recent session activity, then by creation time
@@ -377,9 +524,204 @@ pub async fn create_project(path: String) -> Result<Project, String> {
     })
 }
 
+/// Moves `src` to `dst`, merging into directories that already exist there. Files
+/// already present at the destination are kept; their source copies are left in
+/// place and returned, so nothing is clobbered.
+fn merge_move(src: &std::path::Path, dst: &std::path::Path) -> Result<Vec<PathBuf>, String> {
+    if !dst.exists() {
+        fs::rename(src, dst).map_err(|e| {
+            format!(
+                "Failed to move {} to {}: {}",
+                src.display(),
+                dst.display(),
+                e
+            )
+        })?;
+        return Ok(Vec::new());
+    }
+    if !(src.is_dir() && dst.is_dir()) {
+        return Ok(vec![src.to_path_buf()]);
+    }
+
+    let mut conflicts = Vec::new();
+    let entries =
+        fs::read_dir(src).map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+    for entry in entries.flatten() {
+        conflicts.extend(merge_move(&entry.path(), &dst.join(entry.file_name()))?);
+    }
+    if conflicts.is_empty() {
+        let _ = fs::remove_dir(src);
+    }
+    Ok(conflicts)
+}
+
+/// Points a session transcript at `cwd` by rewriting the first entry that records
+/// one, which is where `list_projects` reads a project's path from
+fn rewrite_session_cwd(session_file: &std::path::Path, cwd: &str) -> Result<(), String> {
+    let content = fs::read_to_string(session_file)
+        .map_err(|e| format!("Failed to read {}: {}", session_file.display(), e))?;
+
+    let mut rewritten = String::with_capacity(content.len());
+    let mut updated = false;
+    for line in content.split_inclusive('\n') {
+        if !updated {
+            let (entry, newline) = match line.strip_suffix('\n') {
+                Some(entry) => (entry, "\n"),
+                None => (line, ""),
+            };
+            if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(entry) {
+                if json
+                    .get("cwd")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|v| !v.is_empty())
+                {
+                    json["cwd"] = serde_json::Value::String(cwd.to_string());
+                    rewritten.push_str(&json.to_string());
+                    rewritten.push_str(newline);
+                    updated = true;
+                    continue;
+                }
+            }
+        }
+        rewritten.push_str(line);
+    }
+    if !updated {
+        return Ok(());
+    }
+
+    let temp_file = session_file.with_extension("jsonl.tmp");
+    fs::write(&temp_file, rewritten)
+        .map_err(|e| format!("Failed to write {}: {}", temp_file.display(), e))?;
+    fs::rename(&temp_file, session_file)
+        .map_err(|e| format!("Failed to replace {}: {}", session_file.display(), e))
+}
+
+/// Moves a project's sessions to the directory encoding `new_path` and points
+/// them at it. An existing target directory is merged into rather than replaced.
+fn remap_project_dir(
+    projects_dir: &std::path::Path,
+    old_project_id: &str,
+    new_path: &str,
+) -> Result<Project, String> {
+    if !is_plain_path_component(old_project_id) {
+        return Err(format!("Invalid project id: {}", old_project_id));
+    }
+    let new_path = new_path.trim();
+    if new_path.is_empty() {
+        return Err("New project path cannot be empty".to_string());
+    }
+    if !std::path::Path::new(new_path).is_absolute() {
+        return Err(format!("New project path must be absolute: {}", new_path));
+    }
+    let new_project_id = new_path.replace('/', "-");
+    if !is_plain_path_component(&new_project_id) {
+        return Err(format!("Invalid project path: {}", new_path));
+    }
+
+    let old_dir = projects_dir.join(old_project_id);
+    if !old_dir.is_dir() {
+        return Err(format!("Project not found: {}", old_project_id));
+    }
+    let new_dir = projects_dir.join(&new_project_id);
+    fs::create_dir_all(&new_dir)
+        .map_err(|e| format!("Failed to create project directory: {}", e))?;
+
+    let entries =
+        fs::read_dir(&old_dir).map_err(|e| format!("Failed to read project directory: {}", e))?;
+    let mut sessions = Vec::new();
+    let mut conflicts = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let is_session = std::path::Path::new(&name)
+            .extension()
+            .and_then(|s| s.to_str())
+            == Some("jsonl");
+        if new_dir != old_dir {
+            let moved = merge_move(&entry.path(), &new_dir.join(&name))?;
+            if !moved.is_empty() {
+                tracing::warn!(
+                    "Kept {} in {:?}; the target project already has it",
+                    moved.len(),
+                    old_dir
+                );
+                conflicts.extend(moved);
+                continue;
+            }
+        }
+        if is_session {
+            sessions.push(new_dir.join(&name));
+        }
+    }
+
+    for session_file in &sessions {
+        rewrite_session_cwd(session_file, new_path)?;
+    }
+    if conflicts.is_empty() && new_dir != old_dir {
+        let _ = fs::remove_dir(&old_dir);
+    }
+
+    tracing::info!(
+        "Remapped project {} to {} ({} sessions moved)",
+        old_project_id,
+        new_project_id,
+        sessions.len()
+    );
+    load_project(&new_dir)
+}
+
+/// Remaps a project to a new location on disk after its directory was moved,
+/// keeping its session history
+#[tauri::command]
+pub async fn remap_project(old_project_id: String, new_path: String) -> Result<Project, String> {
+    tracing::info!("Remapping project {} to {}", old_project_id, new_path);
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    remap_project_dir(&claude_dir.join("projects"), &old_project_id, &new_path)
+}
+
 /// Gets sessions for a specific project
 #[tauri::command]
-pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, String> {
+pub async fn get_project_sessions(
+    app: AppHandle,
+    project_id: String,
+    with_metrics: Option<bool>,
+) -> Result<Vec<Session>, String> {
+    let mut sessions = load_project_sessions(project_id).await?;
+
+    if with_metrics.unwrap_or(false) {
+        match crate::usage_index::open_usage_index_connection(&app) {
+            Ok(conn) => {
+                if let Err(e) = apply_session_metrics(&conn, &mut sessions) {
+                    tracing::warn!("Failed to load session metrics: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Usage index unavailable for session metrics: {}", e),
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Fills in cost and token totals for sessions that have entries in the usage index
+fn apply_session_metrics(
+    conn: &rusqlite::Connection,
+    sessions: &mut [Session],
+) -> Result<(), String> {
+    let session_ids: Vec<String> = sessions.iter().map(|s| s.id.clone()).collect();
+    let totals = crate::usage_index::query::query_session_totals(conn, &session_ids)?;
+
+    for session in sessions.iter_mut() {
+        if let Some((cost, tokens)) = totals.get(&session.id) {
+            session.total_cost = Some(*cost);
+            session.total_tokens = Some(*tokens);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists sessions for a project without touching the usage index
+pub async fn load_project_sessions(project_id: String) -> Result<Vec<Session>, String> {
     tracing::info!("Getting sessions for project: {}", project_id);
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
@@ -448,6 +790,8 @@ pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, St
                     created_at,
                     first_message,
                     message_timestamp,
+                    total_cost: None,
+                    total_tokens: None,
                 });
             }
         }
@@ -464,6 +808,60 @@ pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, St
     Ok(sessions)
 }
 
+/// Session id of the most recently modified session JSONL in a project directory
+fn latest_session_id(project_dir: &std::path::Path) -> Result<Option<String>, String> {
+    let entries = fs::read_dir(project_dir)
+        .map_err(|e| format!("Failed to read project directory: {}", e))?;
+
+    let latest = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            let session_id = path.file_stem()?.to_str()?.to_string();
+            Some((modified, session_id))
+        })
+        .max();
+
+    Ok(latest.map(|(_, session_id)| session_id))
+}
+
+/// Resume the most recently modified session of a project with a new prompt
+#[tauri::command]
+pub async fn continue_latest_project_session(
+    app: AppHandle,
+    project_id: String,
+    prompt: String,
+    model: String,
+) -> Result<String, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let project_dir = claude_dir.join("projects").join(&project_id);
+    if !project_dir.exists() {
+        return Err(format!("Project directory not found: {}", project_id));
+    }
+
+    let session_id = latest_session_id(&project_dir)?
+        .ok_or_else(|| format!("Project {} has no sessions to continue", project_id))?;
+    let project_path = get_project_path_from_sessions(&project_dir)
+        .unwrap_or_else(|_| decode_project_path(&project_id));
+
+    tracing::info!(
+        "Continuing latest session {} of project {}",
+        session_id,
+        project_id
+    );
+    crate::commands::provider_session::resume_provider_session(
+        app,
+        project_path,
+        session_id.clone(),
+        prompt,
+        model,
+    )
+    .await?;
+    Ok(session_id)
+}
+
 /// Reads the Claude settings file
 #[tauri::command]
 pub async fn get_claude_settings() -> Result<ClaudeSettings, String> {
@@ -668,9 +1066,61 @@ pub async fn save_claude_settings(settings: serde_json::Value) -> Result<String,
     Ok("Settings saved successfully".to_string())
 }
 
-/// Recursively finds all CLAUDE.md files in a project directory
+pub const CLAUDE_MD_SCAN_PROGRESS_EVENT: &str = "claude-md-scan-progress";
+/// Emit a progress event every this many scanned directories
+const CLAUDE_MD_SCAN_PROGRESS_INTERVAL: usize = 25;
+
+/// Cancellation flags for in-flight CLAUDE.md scans, keyed by caller-provided token
+static CLAUDE_MD_SCAN_CANCELLATIONS: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Register a fresh cancellation flag under `token`. A token already held by an
+/// in-flight operation is rejected, since cancelling it would stop both.
+fn register_cancellation(
+    cancellations: &Mutex<HashMap<String, Arc<AtomicBool>>>,
+    token: &str,
+) -> Result<Arc<AtomicBool>, String> {
+    let mut cancellations = cancellations.lock().map_err(|e| e.to_string())?;
+    match cancellations.entry(token.to_string()) {
+        Entry::Occupied(_) => Err(format!("Cancel token is already in use: {}", token)),
+        Entry::Vacant(entry) => Ok(entry.insert(Arc::new(AtomicBool::new(false))).clone()),
+    }
+}
+
+/// Progress payload for `claude-md-scan-progress` events
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeMdScanProgress {
+    pub project_path: String,
+    pub cancel_token: Option<String>,
+    pub directories_scanned: usize,
+    pub files_found: usize,
+    /// Set on the final event, sent once the scan completes, fails or is cancelled
+    pub done: bool,
+}
+
+/// Optional hooks for a CLAUDE.md walk: a cancellation flag checked per directory
+/// and a callback invoked after each directory with (directories_scanned, files_found)
+#[derive(Default)]
+struct ClaudeMdScanContext<'a> {
+    cancelled: Option<&'a AtomicBool>,
+    on_progress: Option<&'a dyn Fn(usize, usize)>,
+    directories_scanned: usize,
+}
+
+/// Recursively finds all CLAUDE.md files in a project directory, walking on a
+/// blocking thread. With `emit_progress`, `claude-md-scan-progress` events are
+/// emitted while walking, ending with one marked `done`; with `cancel_token`,
+/// which must not be shared with another running scan, the scan can be aborted
+/// through `cancel_claude_md_scan`; with `respect_gitignore`, paths the project's
+/// `.gitignore` files exclude are skipped.
 #[tauri::command]
-pub async fn find_claude_md_files(project_path: String) -> Result<Vec<ClaudeMdFile>, String> {
+pub async fn find_claude_md_files(
+    app: AppHandle,
+    project_path: String,
+    emit_progress: Option<bool>,
+    cancel_token: Option<String>,
+    respect_gitignore: Option<bool>,
+) -> Result<Vec<ClaudeMdFile>, String> {
     tracing::info!("Finding CLAUDE.md files in project: {}", project_path);
 
     let path = PathBuf::from(&project_path);
@@ -678,8 +1128,62 @@ pub async fn find_claude_md_files(project_path: String) -> Result<Vec<ClaudeMdFi
         return Err(format!("Project path does not exist: {}", project_path));
     }
 
-    let mut claude_files = Vec::new();
-    find_claude_md_recursive(&path, &path, &mut claude_files)?;
+    let cancel_flag = cancel_token
+        .as_deref()
+        .map(|token| register_cancellation(&CLAUDE_MD_SCAN_CANCELLATIONS, token))
+        .transpose()?;
+
+    let scan_token = cancel_token.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let emit_progress = emit_progress.unwrap_or(false);
+        let send_progress = |directories_scanned: usize, files_found: usize, done: bool| {
+            let _ = app.emit(
+                CLAUDE_MD_SCAN_PROGRESS_EVENT,
+                ClaudeMdScanProgress {
+                    project_path: project_path.clone(),
+                    cancel_token: scan_token.clone(),
+                    directories_scanned,
+                    files_found,
+                    done,
+                },
+            );
+        };
+        let emit_progress_event = |directories_scanned: usize, files_found: usize| {
+            if directories_scanned % CLAUDE_MD_SCAN_PROGRESS_INTERVAL == 0 {
+                send_progress(directories_scanned, files_found, false);
+            }
+        };
+
+        let mut context = ClaudeMdScanContext {
+            cancelled: cancel_flag.as_deref(),
+            on_progress: if emit_progress {
+                Some(&emit_progress_event)
+            } else {
+                None
+            },
+            directories_scanned: 0,
+        };
+
+        let mut claude_files = Vec::new();
+        let walked = if respect_gitignore.unwrap_or(false) {
+            find_claude_md_respecting_gitignore(&path, &mut claude_files, &mut context)
+        } else {
+            find_claude_md_recursive(&path, &path, &mut claude_files, &mut context)
+        };
+        if emit_progress {
+            send_progress(context.directories_scanned, claude_files.len(), true);
+        }
+        walked.map(|()| claude_files)
+    })
+    .await
+    .map_err(|e| format!("CLAUDE.md scan failed: {}", e));
+
+    if let Some(token) = &cancel_token {
+        if let Ok(mut cancellations) = CLAUDE_MD_SCAN_CANCELLATIONS.lock() {
+            cancellations.remove(token);
+        }
+    }
+    let mut claude_files = result??;
 
     // Sort by relative path
     claude_files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
@@ -688,15 +1192,82 @@ pub async fn find_claude_md_files(project_path: String) -> Result<Vec<ClaudeMdFi
     Ok(claude_files)
 }
 
+/// Cancels an in-flight `find_claude_md_files` scan started with the given token
+#[tauri::command]
+pub async fn cancel_claude_md_scan(cancel_token: String) -> Result<bool, String> {
+    let cancellations = CLAUDE_MD_SCAN_CANCELLATIONS
+        .lock()
+        .map_err(|e| e.to_string())?;
+
+    match cancellations.get(&cancel_token) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Directories a CLAUDE.md walk never descends into
+const CLAUDE_MD_SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    ".git",
+    "dist",
+    "build",
+    ".next",
+    "__pycache__",
+];
+
+/// Builds the `ClaudeMdFile` entry for a CLAUDE.md found under `project_root`
+fn claude_md_file(path: &std::path::Path, project_root: &PathBuf) -> Result<ClaudeMdFile, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+
+    let relative_path = path
+        .strip_prefix(project_root)
+        .map_err(|e| format!("Failed to get relative path: {}", e))?
+        .to_string_lossy()
+        .to_string();
+
+    let modified = metadata
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    Ok(ClaudeMdFile {
+        relative_path,
+        absolute_path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        modified,
+    })
+}
+
 /// Helper function to recursively find CLAUDE.md files
 fn find_claude_md_recursive(
     current_path: &PathBuf,
     project_root: &PathBuf,
     claude_files: &mut Vec<ClaudeMdFile>,
+    context: &mut ClaudeMdScanContext<'_>,
 ) -> Result<(), String> {
+    if context
+        .cancelled
+        .map(|flag| flag.load(Ordering::SeqCst))
+        .unwrap_or(false)
+    {
+        return Err("CLAUDE.md scan was cancelled".to_string());
+    }
+
     let entries = fs::read_dir(current_path)
         .map_err(|e| format!("Failed to read directory {:?}: {}", current_path, e))?;
 
+    context.directories_scanned += 1;
+    if let Some(on_progress) = context.on_progress {
+        on_progress(context.directories_scanned, claude_files.len());
+    }
+
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
         let path = entry.path();
@@ -711,41 +1282,17 @@ fn find_claude_md_recursive(
         if path.is_dir() {
             // Skip common directories that shouldn't be searched
             if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-                if matches!(
-                    dir_name,
-                    "node_modules" | "target" | ".git" | "dist" | "build" | ".next" | "__pycache__"
-                ) {
+                if CLAUDE_MD_SKIPPED_DIRS.contains(&dir_name) {
                     continue;
                 }
             }
 
-            find_claude_md_recursive(&path, project_root, claude_files)?;
+            find_claude_md_recursive(&path, project_root, claude_files, context)?;
         } else if path.is_file() {
             // Check if it's a CLAUDE.md file (case insensitive)
             if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
                 if file_name.eq_ignore_ascii_case("CLAUDE.md") {
-                    let metadata = fs::metadata(&path)
-                        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
-
-                    let relative_path = path
-                        .strip_prefix(project_root)
-                        .map_err(|e| format!("Failed to get relative path: {}", e))?
-                        .to_string_lossy()
-                        .to_string();
-
-                    let modified = metadata
-                        .modified()
-                        .unwrap_or(SystemTime::UNIX_EPOCH)
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs();
-
-                    claude_files.push(ClaudeMdFile {
-                        relative_path,
-                        absolute_path: path.to_string_lossy().to_string(),
-                        size: metadata.len(),
-                        modified,
-                    });
+                    claude_files.push(claude_md_file(&path, project_root)?);
                 }
             }
         }
@@ -754,6 +1301,58 @@ fn find_claude_md_recursive(
     Ok(())
 }
 
+/// Finds CLAUDE.md files like `find_claude_md_recursive`, additionally skipping
+/// everything the project's `.gitignore` files exclude
+fn find_claude_md_respecting_gitignore(
+    project_root: &PathBuf,
+    claude_files: &mut Vec<ClaudeMdFile>,
+    context: &mut ClaudeMdScanContext<'_>,
+) -> Result<(), String> {
+    let walker = ignore::WalkBuilder::new(project_root)
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(false)
+        .parents(false)
+        .require_git(false)
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let name = entry.file_name().to_str().unwrap_or_default();
+            !(is_dir && entry.depth() > 0 && CLAUDE_MD_SKIPPED_DIRS.contains(&name))
+        })
+        .build();
+
+    for entry in walker {
+        if context
+            .cancelled
+            .map(|flag| flag.load(Ordering::SeqCst))
+            .unwrap_or(false)
+        {
+            return Err("CLAUDE.md scan was cancelled".to_string());
+        }
+
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            context.directories_scanned += 1;
+            if let Some(on_progress) = context.on_progress {
+                on_progress(context.directories_scanned, claude_files.len());
+            }
+        } else if file_type.is_file()
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.eq_ignore_ascii_case("CLAUDE.md"))
+        {
+            claude_files.push(claude_md_file(entry.path(), project_root)?);
+        }
+    }
+
+    Ok(())
+}
+
 /// Reads a specific CLAUDE.md file by its absolute path
 #[tauri::command]
 pub async fn read_claude_md_file(file_path: String) -> Result<String, String> {
@@ -907,23 +1506,474 @@ pub async fn load_provider_session_history(
     Ok(messages)
 }
 
-/// Lists files and directories in a given path
-#[tauri::command]
-pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileEntry>, String> {
-    tracing::info!("Listing directory contents: '{}'", directory_path);
-
-    // Check if path is empty
-    if directory_path.trim().is_empty() {
-        tracing::error!("Directory path is empty or whitespace");
-        return Err("Directory path cannot be empty".to_string());
+/// Whether a directory entry should be listed given the hidden-file settings.
+/// `.claude` is always shown; other dot-prefixed names need `show_hidden` or the allow list.
+fn is_listed_entry(name: &str, show_hidden: bool, allow_list: &[String]) -> bool {
+    if !name.starts_with('.') || show_hidden || name == ".claude" {
+        return true;
     }
 
-    let path = PathBuf::from(&directory_path);
-    tracing::debug!("Resolved path: {:?}", path);
+    allow_list.iter().any(|allowed| allowed == name)
+}
 
-    if !path.exists() {
-        tracing::error!("Path does not exist: {:?}", path);
-        return Err(format!("Path does not exist: {}", directory_path));
+/// Maximum nesting depth walked when computing directory sizes
+const DIR_SIZE_MAX_DEPTH: usize = 16;
+/// Time budget for computing all directory sizes of a single listing
+const DIR_SIZE_TIME_BUDGET: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Recursive size of a directory, with a count of the entries it leaves out
+#[derive(Debug, Default, Clone, Copy)]
+struct DirectorySize {
+    bytes: u64,
+    /// Unreadable entries and directories cut off by the depth cap or deadline
+    skipped: usize,
+}
+
+/// Recursively sums file sizes under `path`, stopping at the depth cap or deadline.
+/// Symlinks are not followed. Blocks on the filesystem, so call it off the async runtime.
+fn directory_size(
+    path: &std::path::Path,
+    depth: usize,
+    deadline: std::time::Instant,
+) -> DirectorySize {
+    let skipped_self = DirectorySize {
+        bytes: 0,
+        skipped: 1,
+    };
+    if depth > DIR_SIZE_MAX_DEPTH || std::time::Instant::now() >= deadline {
+        return skipped_self;
+    }
+
+    let Ok(entries) = fs::read_dir(path) else {
+        return skipped_self;
+    };
+
+    let mut total = DirectorySize::default();
+    for entry in entries {
+        let Ok(entry) = entry else {
+            total.skipped += 1;
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            total.skipped += 1;
+            continue;
+        };
+        if metadata.is_dir() {
+            let nested = directory_size(&entry.path(), depth + 1, deadline);
+            total.bytes += nested.bytes;
+            total.skipped += nested.skipped;
+        } else if metadata.is_file() {
+            total.bytes += metadata.len();
+        }
+    }
+
+    total
+}
+
+/// Time budget for walking the directories behind a project footprint
+const FOOTPRINT_TIME_BUDGET: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Disk space opcode uses for a project, in bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectFootprint {
+    /// Checkpoint timelines under `~/.claude/projects/<id>/.timelines`
+    pub checkpoint_bytes: u64,
+    /// Pasted attachments under `<project>/.codeinterfacex/attachments`
+    pub attachment_bytes: u64,
+    /// Everything else under `~/.claude/projects/<id>` (session JSONL and friends)
+    pub session_bytes: u64,
+    pub total_bytes: u64,
+}
+
+fn compute_project_footprint(
+    claude_dir: &std::path::Path,
+    project_path: &std::path::Path,
+    project_id: &str,
+) -> ProjectFootprint {
+    let deadline = std::time::Instant::now() + FOOTPRINT_TIME_BUDGET;
+    let project_dir = claude_dir.join("projects").join(project_id);
+
+    let checkpoint_bytes = directory_size(&project_dir.join(".timelines"), 0, deadline).bytes;
+    let attachment_bytes = directory_size(
+        &project_path.join(".codeinterfacex").join("attachments"),
+        0,
+        deadline,
+    )
+    .bytes;
+
+    let mut session_bytes = 0;
+    for entry in fs::read_dir(&project_dir).into_iter().flatten().flatten() {
+        if entry.file_name() == ".timelines" {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            session_bytes += directory_size(&entry.path(), 1, deadline).bytes;
+        } else if metadata.is_file() {
+            session_bytes += metadata.len();
+        }
+    }
+
+    ProjectFootprint {
+        checkpoint_bytes,
+        attachment_bytes,
+        session_bytes,
+        total_bytes: checkpoint_bytes + attachment_bytes + session_bytes,
+    }
+}
+
+/// Breaks down how much disk opcode uses for a project: checkpoints, attachments and sessions
+#[tauri::command]
+pub async fn get_project_footprint(
+    project_path: String,
+    project_id: String,
+) -> Result<ProjectFootprint, String> {
+    if project_id.is_empty() || project_id.contains(['/', '\\']) || project_id == ".." {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || {
+        compute_project_footprint(&claude_dir, std::path::Path::new(&project_path), &project_id)
+    })
+    .await
+    .map_err(|e| format!("Failed to compute project footprint: {}", e))
+}
+
+/// Whether `id` names a single entry of a directory, so joining it can't escape
+fn is_plain_path_component(id: &str) -> bool {
+    !id.is_empty() && id != "." && id != ".." && !id.contains(['/', '\\'])
+}
+
+/// Fail unless `path` resolves (following symlinks) to somewhere inside `root`
+fn ensure_within(root: &std::path::Path, path: &std::path::Path) -> Result<(), String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+    let resolved = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!(
+            "Refusing to delete {}: it resolves outside {}",
+            path.display(),
+            root.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Delete a session's transcript and todo list, and optionally its checkpoints.
+/// Returns the removed paths.
+fn delete_session_files(
+    claude_dir: &std::path::Path,
+    project_id: &str,
+    session_id: &str,
+    delete_checkpoints: bool,
+) -> Result<Vec<PathBuf>, String> {
+    if !is_plain_path_component(project_id) {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    if !is_plain_path_component(session_id) {
+        return Err(format!("Invalid session id: {}", session_id));
+    }
+
+    let projects_dir = claude_dir.join("projects");
+    let session_file = projects_dir
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    if !session_file.exists() {
+        return Err(format!("Session not found: {}", session_id));
+    }
+    ensure_within(&projects_dir, &session_file)?;
+
+    let mut removed = Vec::new();
+    fs::remove_file(&session_file).map_err(|e| format!("Failed to delete session file: {}", e))?;
+    removed.push(session_file);
+
+    let todos_dir = claude_dir.join("todos");
+    let todo_file = todos_dir.join(format!("{}.json", session_id));
+    if todo_file.exists() {
+        ensure_within(&todos_dir, &todo_file)?;
+        fs::remove_file(&todo_file).map_err(|e| format!("Failed to delete todo file: {}", e))?;
+        removed.push(todo_file);
+    }
+
+    if delete_checkpoints {
+        let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir.to_path_buf());
+        let timeline_dir =
+            crate::checkpoint::CheckpointPaths::new(&storage.claude_dir, project_id, session_id)
+                .timeline_file
+                .parent()
+                .map(std::path::Path::to_path_buf);
+        if let Some(timeline_dir) = timeline_dir.filter(|dir| dir.exists()) {
+            ensure_within(&projects_dir, &timeline_dir)?;
+        }
+        let removed_checkpoints = storage
+            .delete_session(project_id, session_id)
+            .map_err(|e| format!("Failed to delete session checkpoints: {}", e))?;
+        removed.extend(removed_checkpoints);
+    }
+
+    Ok(removed)
+}
+
+/// Deletes a session's transcript and todo list, plus its checkpoints when
+/// `delete_checkpoints` is set. Returns the paths that were removed.
+#[tauri::command]
+pub async fn delete_session(
+    checkpoint_state: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    project_id: String,
+    session_id: String,
+    delete_checkpoints: bool,
+) -> Result<Vec<String>, String> {
+    tracing::info!(
+        "Deleting session {} of project {} (checkpoints: {})",
+        session_id,
+        project_id,
+        delete_checkpoints
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let removed = delete_session_files(&claude_dir, &project_id, &session_id, delete_checkpoints)?;
+    if delete_checkpoints {
+        checkpoint_state.remove_manager(&session_id).await;
+    }
+
+    Ok(removed
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Most sessions `search_sessions` returns
+const SESSION_SEARCH_MAX_RESULTS: usize = 50;
+
+/// Characters of context kept on each side of the match in a search snippet
+const SESSION_SEARCH_SNIPPET_CONTEXT: usize = 60;
+
+/// A session whose messages mention a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchResult {
+    pub session_id: String,
+    pub project_id: String,
+    /// Case-insensitive matches across the session's messages
+    pub match_count: usize,
+    /// The first matching message, trimmed around the match
+    pub first_match_snippet: String,
+}
+
+/// Text of a JSONL entry's message, whether its content is a string or a list of blocks
+fn session_entry_text(entry: &serde_json::Value) -> String {
+    match entry.pointer("/message/content") {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|block| {
+                block
+                    .get("text")
+                    .or_else(|| block.get("content"))
+                    .and_then(|text| text.as_str())
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Byte offsets in `text` where non-overlapping, case-insensitive matches of
+/// `needle` (already lowercase) start
+fn find_matches_ignore_case(text: &str, needle: &str) -> Vec<usize> {
+    let needle_chars = needle.chars().count();
+    let mut matches = Vec::new();
+    let mut skip = 0;
+    for (offset, _) in text.char_indices() {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        let mut rest = text[offset..].chars().flat_map(char::to_lowercase);
+        if needle.chars().all(|c| rest.next() == Some(c)) {
+            matches.push(offset);
+            skip = needle_chars.saturating_sub(1);
+        }
+    }
+    matches
+}
+
+/// `text` around the match at `offset`, on one line
+fn match_snippet(text: &str, offset: usize) -> String {
+    let before: Vec<char> = text[..offset].chars().collect();
+    let start = before.len().saturating_sub(SESSION_SEARCH_SNIPPET_CONTEXT);
+    let mut snippet: String = before[start..].iter().collect();
+    snippet.extend(
+        text[offset..]
+            .chars()
+            .take(SESSION_SEARCH_SNIPPET_CONTEXT * 2),
+    );
+
+    let snippet = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if text[offset..].chars().count() > SESSION_SEARCH_SNIPPET_CONTEXT * 2 {
+        "…"
+    } else {
+        ""
+    };
+    format!("{}{}{}", prefix, snippet, suffix)
+}
+
+/// Matches of `needle` (lowercase) in one session file, with the first match's snippet
+fn search_session_file(path: &std::path::Path, needle: &str) -> Option<(usize, String)> {
+    let file = fs::File::open(path).ok()?;
+    let mut match_count = 0;
+    let mut first_snippet = None;
+
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        let text = session_entry_text(&entry);
+        let matches = find_matches_ignore_case(&text, needle);
+        if let (None, Some(&offset)) = (&first_snippet, matches.first()) {
+            first_snippet = Some(match_snippet(&text, offset));
+        }
+        match_count += matches.len();
+    }
+
+    first_snippet.map(|snippet| (match_count, snippet))
+}
+
+/// Search the sessions under `projects_dir`, or only `project_id`'s, for `query`.
+/// The sessions with the most matches come first.
+fn search_project_sessions(
+    projects_dir: &std::path::Path,
+    query: &str,
+    project_id: Option<&str>,
+) -> Result<Vec<SessionSearchResult>, String> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let project_dirs = match project_id {
+        Some(id) => {
+            if id.is_empty() || id.contains(['/', '\\']) || id == ".." {
+                return Err(format!("Invalid project id: {}", id));
+            }
+            vec![projects_dir.join(id)]
+        }
+        None => project_directories(projects_dir)?,
+    };
+
+    let mut results = Vec::new();
+    for project_dir in project_dirs {
+        let Ok(entries) = fs::read_dir(&project_dir) else {
+            continue;
+        };
+        let project_id = project_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if let Some((match_count, first_match_snippet)) = search_session_file(&path, &needle) {
+                results.push(SessionSearchResult {
+                    session_id: session_id.to_string(),
+                    project_id: project_id.clone(),
+                    match_count,
+                    first_match_snippet,
+                });
+            }
+        }
+    }
+
+    results.sort_by(|a, b| {
+        b.match_count
+            .cmp(&a.match_count)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    results.truncate(SESSION_SEARCH_MAX_RESULTS);
+    Ok(results)
+}
+
+/// Finds sessions whose messages mention `query` (case-insensitive), optionally
+/// only within one project
+#[tauri::command]
+pub async fn search_sessions(
+    query: String,
+    project_id: Option<String>,
+) -> Result<Vec<SessionSearchResult>, String> {
+    tracing::info!(
+        "Searching sessions for {:?} (project: {:?})",
+        query,
+        project_id
+    );
+
+    let projects_dir = get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects");
+    if !projects_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        search_project_sessions(&projects_dir, &query, project_id.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to search sessions: {}", e))?
+}
+
+/// Lists files and directories in a given path. The listing runs on a blocking
+/// thread, since `compute_dir_sizes` can walk large trees.
+#[tauri::command]
+pub async fn list_directory_contents(
+    directory_path: String,
+    show_hidden: Option<bool>,
+    allow_list: Option<Vec<String>>,
+    compute_dir_sizes: Option<bool>,
+) -> Result<Vec<FileEntry>, String> {
+    tracing::info!("Listing directory contents: '{}'", directory_path);
+    let show_hidden = show_hidden.unwrap_or(false);
+    let allow_list = allow_list.unwrap_or_default();
+    let compute_dir_sizes = compute_dir_sizes.unwrap_or(false);
+
+    tokio::task::spawn_blocking(move || {
+        list_directory_entries(&directory_path, show_hidden, &allow_list, compute_dir_sizes)
+    })
+    .await
+    .map_err(|e| format!("Failed to list directory: {}", e))?
+}
+
+fn list_directory_entries(
+    directory_path: &str,
+    show_hidden: bool,
+    allow_list: &[String],
+    compute_dir_sizes: bool,
+) -> Result<Vec<FileEntry>, String> {
+    let size_deadline = std::time::Instant::now() + DIR_SIZE_TIME_BUDGET;
+
+    // Check if path is empty
+    if directory_path.trim().is_empty() {
+        tracing::error!("Directory path is empty or whitespace");
+        return Err("Directory path cannot be empty".to_string());
+    }
+
+    let path = PathBuf::from(directory_path);
+    tracing::debug!("Resolved path: {:?}", path);
+
+    if !path.exists() {
+        tracing::error!("Path does not exist: {:?}", path);
+        return Err(format!("Path does not exist: {}", directory_path));
     }
 
     if !path.is_dir() {
@@ -943,9 +1993,9 @@ pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileE
             .metadata()
             .map_err(|e| format!("Failed to read metadata: {}", e))?;
 
-        // Skip hidden files/directories unless they are .claude directories
+        // Skip hidden files/directories unless requested or allow-listed
         if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') && name != ".claude" {
+            if !is_listed_entry(name, show_hidden, allow_list) {
                 continue;
             }
         }
@@ -965,12 +2015,21 @@ pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileE
             None
         };
 
+        let (size, skipped_entries) = if compute_dir_sizes && metadata.is_dir() {
+            let dir_size = directory_size(&entry_path, 0, size_deadline);
+            (dir_size.bytes, Some(dir_size.skipped))
+        } else {
+            (metadata.len(), None)
+        };
+
         entries.push(FileEntry {
             name,
             path: entry_path.to_string_lossy().to_string(),
             is_directory: metadata.is_dir(),
-            size: metadata.len(),
+            size,
             extension,
+            snippet: None,
+            skipped_entries,
         });
     }
 
@@ -986,8 +2045,13 @@ pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileE
 
 /// Search for files and directories matching a pattern
 #[tauri::command]
-pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEntry>, String> {
+pub async fn search_files(
+    base_path: String,
+    query: String,
+    include_snippet: Option<bool>,
+) -> Result<Vec<FileEntry>, String> {
     tracing::info!("Searching files in '{}' for: '{}'", base_path, query);
+    let include_snippet = include_snippet.unwrap_or(false);
 
     // Check if path is empty
     if base_path.trim().is_empty() {
@@ -1012,7 +2076,7 @@ pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEn
     let query_lower = query.to_lowercase();
     let mut results = Vec::new();
 
-    search_files_recursive(&path, &path, &query_lower, &mut results, 0)?;
+    search_files_recursive(&path, &path, &query_lower, include_snippet, &mut results, 0)?;
 
     // Sort by relevance: exact matches first, then by name
     results.sort_by(|a, b| {
@@ -1032,10 +2096,48 @@ pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEn
     Ok(results)
 }
 
+/// Files larger than this are never scanned for snippets
+const SNIPPET_MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Lines of context kept on each side of the matching line
+const SNIPPET_CONTEXT_LINES: usize = 2;
+/// Upper bound on the returned snippet length, in characters
+const SNIPPET_MAX_CHARS: usize = 500;
+
+/// Returns the first line containing `query` (lowercase) plus surrounding context.
+/// Binary and oversized files yield `None`.
+fn extract_match_snippet(path: &std::path::Path, query: &str) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > SNIPPET_MAX_FILE_SIZE {
+        return None;
+    }
+
+    let bytes = fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return None;
+    }
+
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = content.lines().collect();
+    let match_index = lines
+        .iter()
+        .position(|line| line.to_lowercase().contains(query))?;
+
+    let start = match_index.saturating_sub(SNIPPET_CONTEXT_LINES);
+    let end = (match_index + SNIPPET_CONTEXT_LINES + 1).min(lines.len());
+    let snippet = lines[start..end].join("\n");
+
+    if snippet.chars().count() > SNIPPET_MAX_CHARS {
+        Some(snippet.chars().take(SNIPPET_MAX_CHARS).collect())
+    } else {
+        Some(snippet)
+    }
+}
+
 fn search_files_recursive(
     current_path: &PathBuf,
     base_path: &PathBuf,
     query: &str,
+    include_snippet: bool,
     results: &mut Vec<FileEntry>,
     depth: usize,
 ) -> Result<(), String> {
@@ -1057,8 +2159,15 @@ fn search_files_recursive(
                 continue;
             }
 
-            // Check if name matches query
-            if name.to_lowercase().contains(query) {
+            let name_matches = name.to_lowercase().contains(query);
+            let snippet = if include_snippet && entry_path.is_file() {
+                extract_match_snippet(&entry_path, query)
+            } else {
+                None
+            };
+
+            // Check if name or (when requested) content matches query
+            if name_matches || snippet.is_some() {
                 let metadata = entry
                     .metadata()
                     .map_err(|e| format!("Failed to read metadata: {}", e))?;
@@ -1078,6 +2187,8 @@ fn search_files_recursive(
                     is_directory: metadata.is_dir(),
                     size: metadata.len(),
                     extension,
+                    snippet,
+                    skipped_entries: None,
                 });
             }
         }
@@ -1094,22 +2205,55 @@ fn search_files_recursive(
                 }
             }
 
-            search_files_recursive(&entry_path, base_path, query, results, depth + 1)?;
+            search_files_recursive(
+                &entry_path,
+                base_path,
+                query,
+                include_snippet,
+                results,
+                depth + 1,
+            )?;
         }
     }
 
     Ok(())
 }
 
-/// Creates a checkpoint for the current session state
+pub const CHECKPOINT_PROGRESS_EVENT: &str = "checkpoint-progress";
+/// Emit a progress event every this many snapshotted files
+const CHECKPOINT_PROGRESS_INTERVAL: usize = 50;
+
+/// Cancellation flags for in-flight checkpoints, keyed by caller-provided token
+static CHECKPOINT_CANCELLATIONS: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Progress payload for `checkpoint-progress` events
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointProgress {
+    pub session_id: String,
+    pub cancel_token: Option<String>,
+    pub files_snapshotted: usize,
+    pub total_files: usize,
+    /// Set on the final event, sent once the checkpoint is created, fails or is cancelled
+    pub done: bool,
+}
+
+/// Creates a checkpoint for the current session state.
+/// With `emit_progress`, `checkpoint-progress` events are emitted while files are
+/// snapshotted, ending with one marked `done`; with `cancel_token`, which must not
+/// be shared with another running checkpoint, it can be aborted through
+/// `cancel_checkpoint`.
 #[tauri::command]
 pub async fn create_checkpoint(
+    app_handle: AppHandle,
     app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
     session_id: String,
     project_id: String,
     project_path: String,
     message_index: Option<usize>,
     description: Option<String>,
+    emit_progress: Option<bool>,
+    cancel_token: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
     tracing::info!(
         "Creating checkpoint for session: {} in project: {}",
@@ -1155,10 +2299,79 @@ pub async fn create_checkpoint(
         }
     }
 
-    manager
-        .create_checkpoint(description, None)
-        .await
-        .map_err(|e| format!("Failed to create checkpoint: {}", e))
+    let cancel_flag = cancel_token
+        .as_deref()
+        .map(|token| register_cancellation(&CHECKPOINT_CANCELLATIONS, token))
+        .transpose()?;
+
+    let emit_progress = emit_progress.unwrap_or(false);
+    let send_progress = |files_snapshotted: usize, total_files: usize, done: bool| {
+        let _ = app_handle.emit(
+            CHECKPOINT_PROGRESS_EVENT,
+            CheckpointProgress {
+                session_id: session_id.clone(),
+                cancel_token: cancel_token.clone(),
+                files_snapshotted,
+                total_files,
+                done,
+            },
+        );
+    };
+    // Last (files_snapshotted, total_files) reported, repeated in the final event
+    let last_progress = Mutex::new((0, 0));
+    let emit_progress_event = |files_snapshotted: usize, total_files: usize| {
+        if let Ok(mut last) = last_progress.lock() {
+            *last = (files_snapshotted, total_files);
+        }
+        if files_snapshotted % CHECKPOINT_PROGRESS_INTERVAL == 0 || files_snapshotted == total_files
+        {
+            send_progress(files_snapshotted, total_files, false);
+        }
+    };
+    let control = crate::checkpoint::CheckpointControl {
+        cancelled: cancel_flag.as_deref(),
+        on_progress: if emit_progress {
+            Some(&emit_progress_event)
+        } else {
+            None
+        },
+    };
+
+    let result = manager
+        .create_checkpoint_with(description, None, control)
+        .await;
+    if emit_progress {
+        let (files_snapshotted, total_files) =
+            last_progress.lock().map(|last| *last).unwrap_or_default();
+        send_progress(files_snapshotted, total_files, true);
+    }
+
+    if let Some(token) = &cancel_token {
+        if let Ok(mut cancellations) = CHECKPOINT_CANCELLATIONS.lock() {
+            cancellations.remove(token);
+        }
+    }
+    result.map_err(|e| {
+        if e.is::<crate::checkpoint::CheckpointCancelled>() {
+            e.to_string()
+        } else {
+            format!("Failed to create checkpoint: {}", e)
+        }
+    })
+}
+
+/// Cancels an in-flight `create_checkpoint` started with the given token
+#[tauri::command]
+pub async fn cancel_checkpoint(cancel_token: String) -> Result<bool, String> {
+    let cancellations = CHECKPOINT_CANCELLATIONS.lock().map_err(|e| e.to_string())?;
+
+    match cancellations.get(&cancel_token) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
 }
 
 /// Restores a session to a specific checkpoint
@@ -1232,6 +2445,103 @@ pub async fn list_checkpoints(
     Ok(manager.list_checkpoints().await)
 }
 
+/// Lists the checkpoints of every session in a project, oldest first
+#[tauri::command]
+pub async fn list_project_checkpoints(
+    project_id: String,
+) -> Result<Vec<crate::checkpoint::Checkpoint>, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    tracing::info!("Listing checkpoints for project: {}", project_id);
+    if !is_plain_path_component(&project_id) {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    CheckpointStorage::new(claude_dir)
+        .list_project_checkpoints(&project_id)
+        .map_err(|e| format!("Failed to list project checkpoints: {}", e))
+}
+
+/// Exports a checkpoint to `file_path` as a bundle that can be imported on another machine
+#[tauri::command]
+pub async fn export_checkpoint_bundle(
+    session_id: String,
+    project_id: String,
+    checkpoint_id: String,
+    file_path: String,
+) -> Result<(), String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    tracing::info!(
+        "Exporting checkpoint {} of session {} to {}",
+        checkpoint_id,
+        session_id,
+        file_path
+    );
+    if !is_plain_path_component(&project_id) {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    if !is_plain_path_component(&session_id) {
+        return Err(format!("Invalid session id: {}", session_id));
+    }
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let bundle = CheckpointStorage::new(claude_dir)
+        .export_bundle(&project_id, &session_id, &checkpoint_id)
+        .map_err(|e| format!("Failed to export checkpoint: {}", e))?;
+    fs::write(&file_path, bundle).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+/// Imports the checkpoint bundle at `file_path` as a new checkpoint of `target_session_id`
+#[tauri::command]
+pub async fn import_checkpoint_bundle(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    file_path: String,
+    target_session_id: String,
+    project_id: String,
+) -> Result<crate::checkpoint::Checkpoint, String> {
+    use crate::checkpoint::storage::{CheckpointStorage, MAX_CHECKPOINT_BUNDLE_BYTES};
+
+    tracing::info!(
+        "Importing checkpoint bundle {} into session {}",
+        file_path,
+        target_session_id
+    );
+    if !is_plain_path_component(&project_id) {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    if !is_plain_path_component(&target_session_id) {
+        return Err(format!("Invalid session id: {}", target_session_id));
+    }
+
+    let size = fs::metadata(&file_path)
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+    if size > MAX_CHECKPOINT_BUNDLE_BYTES {
+        return Err(format!(
+            "Checkpoint bundle is too large ({} bytes, the limit is {})",
+            size, MAX_CHECKPOINT_BUNDLE_BYTES
+        ));
+    }
+    let bundle = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let checkpoint = CheckpointStorage::new(claude_dir)
+        .import_bundle(&bundle, &project_id, &target_session_id)
+        .map_err(|e| format!("Failed to import checkpoint: {}", e))?;
+
+    // An open session would otherwise keep, and later save over, its old timeline
+    if let Some(manager) = app.get_manager(&target_session_id).await {
+        manager
+            .reload_timeline()
+            .await
+            .map_err(|e| format!("Failed to reload timeline: {}", e))?;
+    }
+
+    Ok(checkpoint)
+}
+
 /// Forks a new timeline branch from a checkpoint
 #[tauri::command]
 pub async fn fork_from_checkpoint(
@@ -1313,6 +2623,8 @@ pub async fn update_checkpoint_settings(
     project_path: String,
     auto_checkpoint_enabled: bool,
     checkpoint_strategy: String,
+    exclude_globs: Option<Vec<String>>,
+    interval_seconds: Option<u64>,
 ) -> Result<(), String> {
     use crate::checkpoint::CheckpointStrategy;
 
@@ -1323,6 +2635,7 @@ pub async fn update_checkpoint_settings(
         "per_prompt" => CheckpointStrategy::PerPrompt,
         "per_tool_use" => CheckpointStrategy::PerToolUse,
         "smart" => CheckpointStrategy::Smart,
+        "time_interval" => CheckpointStrategy::TimeInterval,
         _ => {
             return Err(format!(
                 "Invalid checkpoint strategy: {}",
@@ -1337,7 +2650,12 @@ pub async fn update_checkpoint_settings(
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
     manager
-        .update_settings(auto_checkpoint_enabled, strategy)
+        .update_settings(
+            auto_checkpoint_enabled,
+            strategy,
+            interval_seconds,
+            exclude_globs,
+        )
         .await
         .map_err(|e| format!("Failed to update settings: {}", e))
 }
@@ -1391,16 +2709,11 @@ pub async fn get_checkpoint_diff(
     for (path, from_file) in &from_map {
         if let Some(to_file) = to_map.get(path) {
             if from_file.hash != to_file.hash {
-                // File was modified
-                let additions = to_file.content.lines().count();
-                let deletions = from_file.content.lines().count();
-
-                modified_files.push(crate::checkpoint::FileDiff {
-                    path: path.clone(),
-                    additions,
-                    deletions,
-                    diff_content: None, // TODO: Generate actual diff
-                });
+                modified_files.push(crate::checkpoint::FileDiff::between(
+                    path.clone(),
+                    &from_file.content,
+                    &to_file.content,
+                ));
             }
         } else {
             // File was deleted
@@ -1520,6 +2833,8 @@ pub async fn get_checkpoint_settings(
     Ok(serde_json::json!({
         "auto_checkpoint_enabled": timeline.auto_checkpoint_enabled,
         "checkpoint_strategy": timeline.checkpoint_strategy,
+        "interval_seconds": timeline.checkpoint_interval_secs,
+        "exclude_globs": timeline.exclude_globs,
         "total_checkpoints": timeline.total_checkpoints,
         "current_checkpoint_id": timeline.current_checkpoint_id,
     }))
@@ -1726,6 +3041,109 @@ pub async fn update_hooks_config(
     Ok("Hooks configuration updated successfully".to_string())
 }
 
+/// Hook events whose entries are matcher groups (`{ matcher, hooks: [...] }`)
+const MATCHER_HOOK_EVENTS: &[&str] = &["PreToolUse", "PostToolUse"];
+/// Hook events whose entries are hook commands directly
+const DIRECT_HOOK_EVENTS: &[&str] = &["Notification", "Stop", "SubagentStop"];
+
+fn hook_command_errors(event: &str, location: &str, hook: &serde_json::Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Some(hook) = hook.as_object() else {
+        return vec![format!("{}{}: hook must be an object", event, location)];
+    };
+
+    if let Some(hook_type) = hook.get("type") {
+        if hook_type.as_str() != Some("command") {
+            errors.push(format!("{}{}: unsupported hook type", event, location));
+        }
+    }
+
+    match hook.get("command").and_then(|c| c.as_str()) {
+        Some(command) if !command.trim().is_empty() => {}
+        _ => errors.push(format!("{}{}: Empty command", event, location)),
+    }
+
+    if let Some(timeout) = hook.get("timeout") {
+        if !timeout.is_u64() {
+            errors.push(format!("{}{}: timeout must be a positive integer", event, location));
+        }
+    }
+
+    errors
+}
+
+fn hook_section_errors(event: &str, section: &serde_json::Value) -> Vec<String> {
+    let Some(entries) = section.as_array() else {
+        return vec![format!("{}: expected an array", event)];
+    };
+
+    let mut errors = Vec::new();
+    if MATCHER_HOOK_EVENTS.contains(&event) {
+        for (i, entry) in entries.iter().enumerate() {
+            let Some(matcher) = entry.as_object() else {
+                errors.push(format!("{}[{}]: matcher must be an object", event, i));
+                continue;
+            };
+
+            match matcher.get("matcher") {
+                None => {}
+                Some(serde_json::Value::String(pattern)) => {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        errors.push(format!("{}[{}]: Invalid regex pattern: {}", event, i, e));
+                    }
+                }
+                Some(_) => errors.push(format!("{}[{}]: matcher must be a string", event, i)),
+            }
+
+            match matcher.get("hooks").and_then(|h| h.as_array()) {
+                Some(hooks) => {
+                    for (j, hook) in hooks.iter().enumerate() {
+                        let location = format!("[{}].hooks[{}]", i, j);
+                        errors.extend(hook_command_errors(event, &location, hook));
+                    }
+                }
+                None => errors.push(format!("{}[{}]: hooks must be an array", event, i)),
+            }
+        }
+    } else if DIRECT_HOOK_EVENTS.contains(&event) {
+        for (i, hook) in entries.iter().enumerate() {
+            errors.extend(hook_command_errors(event, &format!("[{}]", i), hook));
+        }
+    }
+
+    errors
+}
+
+/// Structurally validates a hooks configuration, returning every problem found.
+/// Unknown events are left unchecked so newer hook types pass through.
+pub(crate) fn validate_hooks_config(hooks: &serde_json::Value) -> Vec<String> {
+    let Some(sections) = hooks.as_object() else {
+        return vec!["Hooks configuration must be a JSON object".to_string()];
+    };
+
+    sections
+        .iter()
+        .flat_map(|(event, section)| hook_section_errors(event, section))
+        .collect()
+}
+
+/// Removes every event section that fails validation, returning the removed event names
+pub(crate) fn strip_invalid_hook_sections(
+    hooks: &mut serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let invalid: Vec<String> = hooks
+        .iter()
+        .filter(|(event, section)| !hook_section_errors(event, section).is_empty())
+        .map(|(event, _)| event.clone())
+        .collect();
+
+    for event in &invalid {
+        hooks.remove(event);
+    }
+
+    invalid
+}
+
 /// Validates a hook command by dry-running it
 #[tauri::command]
 pub async fn validate_hook_command(command: String) -> Result<serde_json::Value, String> {
@@ -1785,6 +3203,359 @@ mod tests {
         Ok(())
     }
 
+    fn test_session(id: &str) -> Session {
+        Session {
+            id: id.to_string(),
+            project_id: "-Users-test-project".to_string(),
+            project_path: "/Users/test/project".to_string(),
+            todo_data: None,
+            created_at: 0,
+            first_message: None,
+            message_timestamp: None,
+            total_cost: None,
+            total_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_session_metrics_enriches_indexed_sessions() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::usage_index::schema::ensure_schema(&conn).unwrap();
+
+        for (uid, cost, input, output) in [("e1", 0.25, 100, 50), ("e2", 0.5, 200, 25)] {
+            conn.execute(
+                "INSERT INTO usage_events (event_uid, source_path, source_line, timestamp, event_date, model,
+                 input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost,
+                 session_id, project_path, project_name)
+                 VALUES (?1, 'a.jsonl', 1, '2025-01-01T00:00:00Z', '2025-01-01', 'opus',
+                 ?2, ?3, 10, 5, ?4, 'session-a', '/Users/test/project', 'project')",
+                rusqlite::params![uid, input, output, cost],
+            )
+            .unwrap();
+        }
+
+        let mut sessions = vec![test_session("session-a"), test_session("session-b")];
+        apply_session_metrics(&conn, &mut sessions).unwrap();
+
+        assert_eq!(sessions[0].total_cost, Some(0.75));
+        assert_eq!(sessions[0].total_tokens, Some(405));
+        assert_eq!(sessions[1].total_cost, None);
+        assert_eq!(sessions[1].total_tokens, None);
+    }
+
+    #[test]
+    fn test_latest_session_id_picks_most_recently_modified_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        for (name, age_secs) in [
+            ("old.jsonl", 300),
+            ("newest.jsonl", 10),
+            ("middle.jsonl", 60),
+            ("notes.txt", 0),
+        ] {
+            let path = temp_dir.path().join(name);
+            let file = fs::File::create(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age_secs))
+                .unwrap();
+        }
+
+        assert_eq!(
+            latest_session_id(temp_dir.path()).unwrap().as_deref(),
+            Some("newest")
+        );
+    }
+
+    #[test]
+    fn test_latest_session_id_is_none_without_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "not a session").unwrap();
+        assert_eq!(latest_session_id(temp_dir.path()).unwrap(), None);
+    }
+
+    fn create_hidden_listing_fixture() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".github")).unwrap();
+        fs::create_dir(temp_dir.path().join(".claude")).unwrap();
+        fs::write(temp_dir.path().join(".env"), "KEY=value").unwrap();
+        fs::write(temp_dir.path().join("README.md"), "# readme").unwrap();
+        temp_dir
+    }
+
+    fn listed_names(entries: &[FileEntry]) -> Vec<String> {
+        let mut names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    fn recent_projects_db() -> rusqlite::Connection {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::commands::agents::init_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_record_project_open_lists_most_recent_first() {
+        let conn = recent_projects_db();
+        record_recent_project(&conn, "project-a").unwrap();
+        record_recent_project(&conn, "project-b").unwrap();
+
+        let ids: Vec<String> = query_recent_projects(&conn, 10)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, vec!["project-b", "project-a"]);
+    }
+
+    #[test]
+    fn test_record_project_open_dedupes_repeat_opens() {
+        let conn = recent_projects_db();
+        record_recent_project(&conn, "project-a").unwrap();
+        record_recent_project(&conn, "project-b").unwrap();
+        record_recent_project(&conn, "project-a").unwrap();
+
+        let ids: Vec<String> = query_recent_projects(&conn, 10)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, vec!["project-a", "project-b"]);
+    }
+
+    #[test]
+    fn test_recent_projects_respects_limit_and_cap() {
+        let conn = recent_projects_db();
+        for i in 0..(RECENT_PROJECTS_CAP + 5) {
+            record_recent_project(&conn, &format!("project-{}", i)).unwrap();
+        }
+
+        let limited = query_recent_projects(&conn, 3).unwrap();
+        assert_eq!(limited.len(), 3);
+        assert_eq!(limited[0].id, format!("project-{}", RECENT_PROJECTS_CAP + 4));
+
+        let stored: i64 = conn
+            .query_row("SELECT COUNT(*) FROM recent_projects", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, RECENT_PROJECTS_CAP);
+    }
+
+    #[test]
+    fn test_validate_hooks_config_reports_structural_errors() {
+        let valid = serde_json::json!({
+            "PreToolUse": [{ "matcher": "Bash|Edit", "hooks": [{ "type": "command", "command": "echo ok" }] }],
+            "Stop": [{ "type": "command", "command": "say done", "timeout": 30 }]
+        });
+        assert!(validate_hooks_config(&valid).is_empty());
+
+        let invalid = serde_json::json!({
+            "PreToolUse": [{ "matcher": "(", "hooks": [{ "type": "command", "command": " " }] }],
+            "Stop": "echo done"
+        });
+        let errors = validate_hooks_config(&invalid);
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| e.contains("Invalid regex pattern")));
+        assert!(errors.iter().any(|e| e.contains("Empty command")));
+        assert!(errors.iter().any(|e| e == "Stop: expected an array"));
+
+        assert!(!validate_hooks_config(&serde_json::json!([])).is_empty());
+    }
+
+    #[test]
+    fn cancellation_tokens_are_exclusive_while_registered() {
+        let cancellations = Mutex::new(HashMap::new());
+
+        let flag = register_cancellation(&cancellations, "scan").unwrap();
+        assert!(register_cancellation(&cancellations, "scan").is_err());
+        assert!(!flag.load(Ordering::SeqCst));
+
+        cancellations.lock().unwrap().remove("scan");
+        assert!(register_cancellation(&cancellations, "scan").is_ok());
+    }
+
+    #[test]
+    fn test_find_claude_md_cancellation_stops_walk_early() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..20 {
+            let dir = temp_dir.path().join(format!("pkg-{}", i));
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("CLAUDE.md"), "# notes").unwrap();
+        }
+        let root = temp_dir.path().to_path_buf();
+
+        let cancelled = AtomicBool::new(false);
+        let cancel_after_three = |directories_scanned: usize, _files_found: usize| {
+            if directories_scanned >= 3 {
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        };
+        let mut context = ClaudeMdScanContext {
+            cancelled: Some(&cancelled),
+            on_progress: Some(&cancel_after_three),
+            directories_scanned: 0,
+        };
+
+        let mut files = Vec::new();
+        let result = find_claude_md_recursive(&root, &root, &mut files, &mut context);
+
+        assert!(result.is_err());
+        assert_eq!(context.directories_scanned, 3);
+        assert!(files.len() < 20);
+    }
+
+    #[test]
+    fn test_find_claude_md_without_hooks_walks_everything() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..5 {
+            let dir = temp_dir.path().join(format!("pkg-{}", i));
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("CLAUDE.md"), "# notes").unwrap();
+        }
+        let root = temp_dir.path().to_path_buf();
+
+        let mut context = ClaudeMdScanContext::default();
+        let mut files = Vec::new();
+        find_claude_md_recursive(&root, &root, &mut files, &mut context).unwrap();
+
+        assert_eq!(files.len(), 5);
+        assert_eq!(context.directories_scanned, 6);
+    }
+
+    #[test]
+    fn test_find_claude_md_respecting_gitignore_skips_ignored_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        for dir in ["app", "generated/nested", "vendor"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("CLAUDE.md"), "# notes").unwrap();
+        }
+        fs::write(root.join("CLAUDE.md"), "# root").unwrap();
+        fs::write(root.join(".gitignore"), "generated/\n").unwrap();
+
+        let mut context = ClaudeMdScanContext::default();
+        let mut files = Vec::new();
+        find_claude_md_respecting_gitignore(&root, &mut files, &mut context).unwrap();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let relative_paths: Vec<_> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(
+            relative_paths,
+            vec!["CLAUDE.md", "app/CLAUDE.md", "vendor/CLAUDE.md"]
+        );
+
+        let mut context = ClaudeMdScanContext::default();
+        let mut files = Vec::new();
+        find_claude_md_recursive(&root, &root, &mut files, &mut context).unwrap();
+        assert_eq!(files.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_list_directory_contents_hides_dotfiles_by_default() {
+        let temp_dir = create_hidden_listing_fixture();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let entries = list_directory_contents(path, None, None, None).await.unwrap();
+        assert_eq!(listed_names(&entries), vec![".claude", "README.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_directory_contents_show_hidden_reveals_dotfiles() {
+        let temp_dir = create_hidden_listing_fixture();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let entries = list_directory_contents(path, Some(true), None, None).await.unwrap();
+        assert_eq!(
+            listed_names(&entries),
+            vec![".claude", ".env", ".github", "README.md"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_directory_contents_allow_list_shows_specific_dotfile() {
+        let temp_dir = create_hidden_listing_fixture();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let entries = list_directory_contents(path, None, Some(vec![".github".to_string()]), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            listed_names(&entries),
+            vec![".claude", ".github", "README.md"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_directory_contents_computes_nested_dir_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("data").join("inner");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(temp_dir.path().join("data").join("a.txt"), vec![b'a'; 10]).unwrap();
+        fs::write(nested.join("b.txt"), vec![b'b'; 20]).unwrap();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let entries = list_directory_contents(path, None, None, Some(true))
+            .await
+            .unwrap();
+        let data = entries.iter().find(|e| e.name == "data").unwrap();
+        assert!(data.is_directory);
+        assert_eq!(data.size, 30);
+        assert_eq!(data.skipped_entries, Some(0));
+    }
+
+    #[test]
+    fn test_directory_size_reports_entries_past_the_depth_cap() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut deep = temp_dir.path().to_path_buf();
+        for depth in 0..=DIR_SIZE_MAX_DEPTH + 1 {
+            deep = deep.join(format!("d{}", depth));
+        }
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("deep.txt"), vec![b'd'; 40]).unwrap();
+        fs::write(temp_dir.path().join("top.txt"), vec![b't'; 5]).unwrap();
+
+        let deadline = std::time::Instant::now() + DIR_SIZE_TIME_BUDGET;
+        let size = directory_size(temp_dir.path(), 0, deadline);
+        assert_eq!((size.bytes, size.skipped), (5, 1));
+
+        let expired = directory_size(temp_dir.path(), 0, std::time::Instant::now());
+        assert_eq!((expired.bytes, expired.skipped), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_search_files_snippet_for_content_match() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "line one\nline two\nfn needle_handler() {}\nline four\nline five\nline six\n",
+        )
+        .unwrap();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let results = search_files(path, "needle".to_string(), Some(true))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "lib.rs");
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("line one\nline two\nfn needle_handler() {}\nline four\nline five")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_files_name_only_match_has_no_snippet() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("needle.txt"), "nothing relevant here").unwrap();
+        fs::write(temp_dir.path().join("binary.bin"), b"needle\0\0\0").unwrap();
+        let path = temp_dir.path().to_string_lossy().to_string();
+
+        let results = search_files(path, "needle".to_string(), Some(true))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "needle.txt");
+        assert!(results[0].snippet.is_none());
+    }
+
     #[test]
     fn test_get_project_path_from_sessions_normal_case() {
         let temp_dir = TempDir::new().unwrap();
@@ -1937,4 +3708,288 @@ mod tests {
         let error = decode_clipboard_image_data_url("data:image/tiff;base64,aGVsbG8=").unwrap_err();
         assert!(error.contains("Unsupported"));
     }
+
+    #[test]
+    fn project_footprint_breaks_down_component_sizes() {
+        let claude_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let project_dir = claude_dir.path().join("projects").join("-tmp-project");
+
+        let timeline_dir = project_dir.join(".timelines").join("session-1").join("files");
+        fs::create_dir_all(&timeline_dir).unwrap();
+        fs::write(timeline_dir.join("abc"), vec![0u8; 100]).unwrap();
+
+        let attachments = project.path().join(".codeinterfacex").join("attachments");
+        fs::create_dir_all(&attachments).unwrap();
+        fs::write(attachments.join("clipboard.png"), vec![0u8; 40]).unwrap();
+
+        fs::write(project_dir.join("session-1.jsonl"), vec![0u8; 25]).unwrap();
+        fs::create_dir_all(project_dir.join("session-1")).unwrap();
+        fs::write(project_dir.join("session-1").join("tool.txt"), vec![0u8; 5]).unwrap();
+
+        let footprint =
+            compute_project_footprint(claude_dir.path(), project.path(), "-tmp-project");
+        assert_eq!(
+            footprint,
+            ProjectFootprint {
+                checkpoint_bytes: 100,
+                attachment_bytes: 40,
+                session_bytes: 30,
+                total_bytes: 170,
+            }
+        );
+
+        let missing = compute_project_footprint(claude_dir.path(), project.path(), "missing");
+        assert_eq!(missing.session_bytes, 0);
+        assert_eq!(missing.checkpoint_bytes, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn project_directories_skip_files_and_escaping_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let claude_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let projects_dir = claude_dir.path().join("projects");
+        fs::create_dir_all(projects_dir.join("-real-project")).unwrap();
+        fs::create_dir_all(claude_dir.path().join("archived")).unwrap();
+        fs::write(projects_dir.join(".DS_Store"), "").unwrap();
+
+        symlink(claude_dir.path().join("archived"), projects_dir.join("-linked")).unwrap();
+        symlink(outside.path(), projects_dir.join("-escaping")).unwrap();
+        symlink(projects_dir.join("missing"), projects_dir.join("-broken")).unwrap();
+        symlink(projects_dir.join("-loop"), projects_dir.join("-loop")).unwrap();
+
+        let mut found: Vec<String> = project_directories(&projects_dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["-linked", "-real-project"]);
+    }
+
+    #[test]
+    fn test_search_sessions_counts_matches_case_insensitively() {
+        let temp_dir = TempDir::new().unwrap();
+        let projects_dir = temp_dir.path().join("projects");
+        let alpha = projects_dir.join("-Users-test-alpha");
+        let beta = projects_dir.join("-Users-test-beta");
+        fs::create_dir_all(&alpha).unwrap();
+        fs::create_dir_all(&beta).unwrap();
+
+        create_test_session_file(
+            &alpha,
+            "session-1.jsonl",
+            concat!(
+                r#"{"type":"user","message":{"role":"user","content":"How do I configure the Webhook retries?"}}"#,
+                "\n",
+                r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Webhook retries use WEBHOOK_RETRY. webhooks!"}]}}"#,
+                "\n",
+                "not json\n",
+            ),
+        )
+        .unwrap();
+        create_test_session_file(
+            &alpha,
+            "session-2.jsonl",
+            r#"{"type":"user","message":{"role":"user","content":"Unrelated question"}}"#,
+        )
+        .unwrap();
+        create_test_session_file(
+            &beta,
+            "session-3.jsonl",
+            r#"{"type":"user","message":{"role":"user","content":"A webhook here"}}"#,
+        )
+        .unwrap();
+
+        let results = search_project_sessions(&projects_dir, "WEBHOOK", None).unwrap();
+        let summary: Vec<_> = results
+            .iter()
+            .map(|r| (r.session_id.as_str(), r.project_id.as_str(), r.match_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("session-1", "-Users-test-alpha", 4),
+                ("session-3", "-Users-test-beta", 1),
+            ]
+        );
+        assert_eq!(
+            results[0].first_match_snippet,
+            "How do I configure the Webhook retries?"
+        );
+
+        let scoped =
+            search_project_sessions(&projects_dir, "webhook", Some("-Users-test-beta")).unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].session_id, "session-3");
+        assert!(search_project_sessions(&projects_dir, "webhook", Some("..")).is_err());
+    }
+
+    #[test]
+    fn test_match_snippet_trims_long_messages() {
+        let text = format!("{} needle {}", "a".repeat(200), "b".repeat(200));
+        let offset = find_matches_ignore_case(&text, "needle")[0];
+
+        let snippet = match_snippet(&text, offset);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+        assert!(snippet.chars().count() <= SESSION_SEARCH_SNIPPET_CONTEXT * 3 + 2);
+    }
+
+    #[test]
+    fn test_delete_session_removes_transcript_todos_and_checkpoints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let claude_dir = temp_dir.path();
+        let project_dir = claude_dir.join("projects").join("-Users-test-alpha");
+        let timeline_dir = project_dir.join(".timelines").join("session-1");
+        fs::create_dir_all(&timeline_dir).unwrap();
+        fs::create_dir_all(claude_dir.join("todos")).unwrap();
+        fs::write(project_dir.join("session-1.jsonl"), "{}\n").unwrap();
+        fs::write(project_dir.join("session-2.jsonl"), "{}\n").unwrap();
+        fs::write(claude_dir.join("todos").join("session-1.json"), "[]").unwrap();
+        fs::write(timeline_dir.join("timeline.json"), "{}").unwrap();
+
+        let removed =
+            delete_session_files(claude_dir, "-Users-test-alpha", "session-1", true).unwrap();
+
+        assert_eq!(
+            removed,
+            vec![
+                project_dir.join("session-1.jsonl"),
+                claude_dir.join("todos").join("session-1.json"),
+                timeline_dir.clone(),
+            ]
+        );
+        assert!(removed.iter().all(|path| !path.exists()));
+        assert!(project_dir.join("session-2.jsonl").exists());
+        assert!(delete_session_files(claude_dir, "-Users-test-alpha", "session-1", false).is_err());
+    }
+
+    #[test]
+    fn test_delete_session_rejects_paths_outside_projects() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let claude_dir = temp_dir.path();
+        let project_dir = claude_dir.join("projects").join("-Users-test-alpha");
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(claude_dir.join("outside.jsonl"), "{}\n").unwrap();
+
+        assert!(delete_session_files(claude_dir, "..", "outside", false).is_err());
+        assert!(
+            delete_session_files(claude_dir, "-Users-test-alpha", "../../outside", false).is_err()
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(
+                claude_dir.join("outside.jsonl"),
+                project_dir.join("linked.jsonl"),
+            )
+            .unwrap();
+            assert!(
+                delete_session_files(claude_dir, "-Users-test-alpha", "linked", false).is_err()
+            );
+            assert!(claude_dir.join("outside.jsonl").exists());
+        }
+    }
+
+    #[test]
+    fn test_remap_project_merges_sessions_into_the_new_location() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let projects_dir = temp_dir.path().join("projects");
+        let old_dir = projects_dir.join("-Users-test-old");
+        let new_dir = projects_dir.join("-Users-test-new");
+        fs::create_dir_all(old_dir.join(".timelines").join("session-1")).unwrap();
+        fs::create_dir_all(new_dir.join(".timelines").join("session-3")).unwrap();
+        fs::write(
+            old_dir.join("session-1.jsonl"),
+            concat!(
+                r#"{"type":"summary","cwd":null}"#,
+                "\n",
+                r#"{"type":"user","cwd":"/Users/test/old","message":{"role":"user","content":"hi"}}"#,
+                "\n",
+                r#"{"type":"assistant","cwd":"/Users/test/old"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        fs::write(
+            old_dir.join("session-2.jsonl"),
+            r#"{"type":"user","cwd":"/Users/test/old"}"#,
+        )
+        .unwrap();
+        fs::write(
+            new_dir.join("session-3.jsonl"),
+            r#"{"type":"user","cwd":"/Users/test/new"}"#,
+        )
+        .unwrap();
+
+        let project =
+            remap_project_dir(&projects_dir, "-Users-test-old", "/Users/test/new").unwrap();
+
+        assert_eq!(project.id, "-Users-test-new");
+        assert_eq!(project.path, "/Users/test/new");
+        let mut sessions = project.sessions.clone();
+        sessions.sort();
+        assert_eq!(sessions, vec!["session-1", "session-2", "session-3"]);
+        assert!(!old_dir.exists());
+        assert!(new_dir.join(".timelines").join("session-1").is_dir());
+        assert!(new_dir.join(".timelines").join("session-3").is_dir());
+
+        let lines: Vec<serde_json::Value> = fs::read_to_string(new_dir.join("session-1.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["cwd"], serde_json::Value::Null);
+        assert_eq!(lines[1]["cwd"], "/Users/test/new");
+        assert_eq!(lines[1]["message"]["content"], "hi");
+        assert_eq!(lines[2]["cwd"], "/Users/test/old");
+    }
+
+    #[test]
+    fn test_remap_project_keeps_conflicting_sessions_in_place() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let projects_dir = temp_dir.path().join("projects");
+        let old_dir = projects_dir.join("-Users-test-old");
+        let new_dir = projects_dir.join("-Users-test-new");
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::write(old_dir.join("shared.jsonl"), r#"{"cwd":"/Users/test/old"}"#).unwrap();
+        fs::write(new_dir.join("shared.jsonl"), r#"{"cwd":"/Users/test/new"}"#).unwrap();
+
+        remap_project_dir(&projects_dir, "-Users-test-old", "/Users/test/new").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(old_dir.join("shared.jsonl")).unwrap(),
+            r#"{"cwd":"/Users/test/old"}"#
+        );
+        assert_eq!(
+            fs::read_to_string(new_dir.join("shared.jsonl")).unwrap(),
+            r#"{"cwd":"/Users/test/new"}"#
+        );
+        assert!(remap_project_dir(&projects_dir, "..", "/Users/test/new").is_err());
+        assert!(remap_project_dir(&projects_dir, "-Users-test-missing", "/tmp/x").is_err());
+    }
+
+    #[test]
+    fn remap_project_dir_rejects_relative_targets() {
+        let temp = tempfile::tempdir().unwrap();
+        let projects_dir = temp.path().join("projects");
+        let old_dir = projects_dir.join("-Users-test-old");
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::write(old_dir.join("s1.jsonl"), r#"{"cwd":"/Users/test/old"}"#).unwrap();
+
+        for new_path in ["..", ".", "Users/test/new", "../../outside"] {
+            assert!(
+                remap_project_dir(&projects_dir, "-Users-test-old", new_path).is_err(),
+                "{} should be rejected",
+                new_path
+            );
+        }
+        assert!(old_dir.join("s1.jsonl").exists());
+        assert_eq!(fs::read_dir(&projects_dir).unwrap().count(), 1);
+    }
 }
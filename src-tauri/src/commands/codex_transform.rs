@@ -82,6 +82,23 @@ pub fn transform_codex_line(line: &str) -> Option<String> {
             Some(wrap_as_text(text))
         }
 
+        // Reasoning text — tagged as thinking so the UI can collapse it
+        "response.reasoning_summary_text.delta" | "response.reasoning_text.delta" => {
+            let delta = event.get("delta").and_then(|d| d.as_str()).unwrap_or("");
+            if delta.is_empty() {
+                return None;
+            }
+            Some(wrap_as_thinking(delta))
+        }
+
+        "response.reasoning_summary_text.done" | "response.reasoning_text.done" => {
+            let text = event.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            if text.is_empty() {
+                return None;
+            }
+            Some(wrap_as_thinking(text))
+        }
+
         // Completed output item — may contain message content
         "response.output_item.done" => {
             if let Some(item) = event.get("item") {
@@ -154,6 +171,28 @@ pub fn transform_codex_line(line: &str) -> Option<String> {
     }
 }
 
+/// Extract the session identifier Codex reports at the start of a run.
+///
+/// Current Codex CLIs emit `{"type":"thread.started","thread_id":"..."}`; older
+/// releases wrapped a `session_configured` message carrying `session_id`. The
+/// returned id is what `codex exec resume <id>` expects.
+pub fn extract_codex_session_id(line: &str) -> Option<String> {
+    let event: Value = serde_json::from_str(line.trim()).ok()?;
+
+    let id = match event.get("type").and_then(|t| t.as_str()) {
+        Some("thread.started") => event.get("thread_id"),
+        _ => event
+            .get("msg")
+            .filter(|msg| msg.get("type").and_then(|t| t.as_str()) == Some("session_configured"))
+            .and_then(|msg| msg.get("session_id")),
+    }?;
+
+    id.as_str()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
 /// Transform a Codex SDK `item.completed` event.
 fn transform_item_completed(event: &Value) -> Option<String> {
     let item = event.get("item")?;
@@ -173,7 +212,7 @@ fn transform_item_completed(event: &Value) -> Option<String> {
             if text.is_empty() {
                 return None;
             }
-            Some(wrap_as_text(&format!("[thinking] {}", text)))
+            Some(wrap_as_thinking(&text))
         }
 
         "command_execution" | "function_call" => {
@@ -299,6 +338,17 @@ fn wrap_as_text(text: &str) -> String {
     .to_string()
 }
 
+/// Wrap reasoning text as a Claude `thinking` content block.
+fn wrap_as_thinking(text: &str) -> String {
+    json!({
+        "type": "assistant",
+        "message": {
+            "content": [{"type": "thinking", "thinking": text}]
+        }
+    })
+    .to_string()
+}
+
 // ─── Tests ──────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -330,10 +380,30 @@ mod tests {
             r#"{"type":"item.completed","item":{"type":"reasoning","text":"I should check the file"}}"#;
         let result = transform_codex_line(line).unwrap();
         let parsed: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["message"]["content"][0]["type"], "thinking");
         assert_eq!(
-            parsed["message"]["content"][0]["text"],
-            "[thinking] I should check the file"
+            parsed["message"]["content"][0]["thinking"],
+            "I should check the file"
         );
+        assert!(parsed["message"]["content"][0]["text"].is_null());
+    }
+
+    #[test]
+    fn test_reasoning_summary_delta_is_thinking_block() {
+        let line = r#"{"type":"response.reasoning_summary_text.delta","delta":"Considering options"}"#;
+        let result = transform_codex_line(line).unwrap();
+        let parsed: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["message"]["content"][0]["type"], "thinking");
+        assert_eq!(parsed["message"]["content"][0]["thinking"], "Considering options");
+    }
+
+    #[test]
+    fn test_output_text_delta_is_not_thinking() {
+        let line = r#"{"type":"response.output_text.delta","delta":"Final answer"}"#;
+        let result = transform_codex_line(line).unwrap();
+        let parsed: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["message"]["content"][0]["type"], "text");
+        assert_eq!(parsed["message"]["content"][0]["text"], "Final answer");
     }
 
     #[test]
@@ -487,6 +557,18 @@ mod tests {
     fn test_real_codex_output_thread_started() {
         let line = r#"{"type":"thread.started","thread_id":"019c363b-c0c9-7362-b5da-5c0de26258d1"}"#;
         assert!(transform_codex_line(line).is_none(), "thread.started should be skipped");
+        assert_eq!(
+            extract_codex_session_id(line).as_deref(),
+            Some("019c363b-c0c9-7362-b5da-5c0de26258d1")
+        );
+    }
+
+    #[test]
+    fn test_extract_session_id_from_legacy_session_configured() {
+        let line = r#"{"id":"0","msg":{"type":"session_configured","session_id":"abc-123","model":"o3"}}"#;
+        assert_eq!(extract_codex_session_id(line).as_deref(), Some("abc-123"));
+        assert_eq!(extract_codex_session_id(r#"{"type":"turn.started"}"#), None);
+        assert_eq!(extract_codex_session_id("not json"), None);
     }
 
     #[test]
@@ -501,9 +583,10 @@ mod tests {
         let result = transform_codex_line(line).unwrap();
         let parsed: Value = serde_json::from_str(&result).unwrap();
         assert_eq!(parsed["type"], "assistant");
+        assert_eq!(parsed["message"]["content"][0]["type"], "thinking");
         assert_eq!(
-            parsed["message"]["content"][0]["text"],
-            "[thinking] **Providing simple greeting**"
+            parsed["message"]["content"][0]["thinking"],
+            "**Providing simple greeting**"
         );
     }
 
@@ -546,10 +629,7 @@ mod tests {
         // reasoning → assistant message
         let r2: Value = serde_json::from_str(results[2].as_ref().unwrap()).unwrap();
         assert_eq!(r2["type"], "assistant");
-        assert!(r2["message"]["content"][0]["text"]
-            .as_str()
-            .unwrap()
-            .starts_with("[thinking]"));
+        assert_eq!(r2["message"]["content"][0]["type"], "thinking");
 
         // agent_message → assistant message
         let r3: Value = serde_json::from_str(results[3].as_ref().unwrap()).unwrap();
@@ -0,0 +1,338 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+pub const DIRECTORY_CHANGED_EVENT_PREFIX: &str = "directory-changed";
+const DEBOUNCE_MS: u64 = 300;
+const IGNORED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    ".git",
+    "dist",
+    "build",
+    ".next",
+    "__pycache__",
+];
+
+/// Directory watchers started from the file browser, keyed by watched path.
+/// Independent of the hot-refresh watcher.
+#[derive(Default)]
+pub struct DirectoryWatchState {
+    watchers: Mutex<HashMap<String, DirectoryWatcher>>,
+}
+
+struct DirectoryWatcher {
+    watcher: Option<RecommendedWatcher>,
+    worker_thread: Option<JoinHandle<()>>,
+    running: Arc<AtomicBool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryChange {
+    pub path: String,
+    /// One of `created`, `modified` or `removed`
+    pub kind: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryChangedPayload {
+    pub path: String,
+    pub changes: Vec<DirectoryChange>,
+    pub timestamp_ms: u128,
+}
+
+impl DirectoryWatcher {
+    fn start<F>(root: PathBuf, emit: F) -> Result<Self, String>
+    where
+        F: Fn(DirectoryChangedPayload) + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let (event_tx, event_rx) = mpsc::channel::<notify::Result<Event>>();
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = event_tx.send(event);
+        })
+        .map_err(|error| format!("Failed to create directory watcher: {}", error))?;
+
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|error| format!("Failed to watch path {}: {}", root.display(), error))?;
+
+        let worker_running = running.clone();
+        let worker_thread = thread::spawn(move || {
+            run_watcher_worker(root, event_rx, worker_running, emit);
+        });
+
+        Ok(Self {
+            watcher: Some(watcher),
+            worker_thread: Some(worker_thread),
+            running,
+        })
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        let _ = self.watcher.take();
+
+        if let Some(thread) = self.worker_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for DirectoryWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Event names only allow alphanumerics, `-`, `/`, `:` and `_`, so other
+/// characters in the watched path are replaced with `_`.
+pub fn directory_event_name(path: &str) -> String {
+    let sanitized: String = path
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | ':' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}:{}", DIRECTORY_CHANGED_EVENT_PREFIX, sanitized)
+}
+
+fn is_ignored_path(root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components().any(|component| match component {
+        Component::Normal(name) => name
+            .to_str()
+            .map(|name| IGNORED_DIRS.contains(&name))
+            .unwrap_or(false),
+        _ => false,
+    })
+}
+
+fn change_kind(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) => Some("created"),
+        EventKind::Modify(_) => Some("modified"),
+        EventKind::Remove(_) => Some("removed"),
+        _ => None,
+    }
+}
+
+fn event_changes(root: &Path, event: &Event) -> Vec<DirectoryChange> {
+    let Some(kind) = change_kind(&event.kind) else {
+        return Vec::new();
+    };
+
+    event
+        .paths
+        .iter()
+        .filter(|path| !is_ignored_path(root, path))
+        .map(|path| DirectoryChange {
+            path: path.to_string_lossy().to_string(),
+            kind: kind.to_string(),
+        })
+        .collect()
+}
+
+fn now_timestamp_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn run_watcher_worker<F>(
+    root: PathBuf,
+    event_rx: mpsc::Receiver<notify::Result<Event>>,
+    running: Arc<AtomicBool>,
+    emit: F,
+) where
+    F: Fn(DirectoryChangedPayload),
+{
+    let debounce_window = Duration::from_millis(DEBOUNCE_MS);
+    let root_display = root.to_string_lossy().to_string();
+    // Latest change kind per path, in first-seen order
+    let mut pending: Vec<DirectoryChange> = Vec::new();
+    let mut last_change: Option<Instant> = None;
+
+    let flush = |pending: &mut Vec<DirectoryChange>| {
+        if pending.is_empty() {
+            return;
+        }
+        emit(DirectoryChangedPayload {
+            path: root_display.clone(),
+            changes: std::mem::take(pending),
+            timestamp_ms: now_timestamp_ms(),
+        });
+    };
+
+    while running.load(Ordering::Relaxed) {
+        match event_rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(Ok(event)) => {
+                let changes = event_changes(&root, &event);
+                if !changes.is_empty() {
+                    for change in changes {
+                        match pending.iter_mut().find(|p| p.path == change.path) {
+                            // A write right after creation is still reported as a creation
+                            Some(existing)
+                                if existing.kind == "created" && change.kind == "modified" => {}
+                            Some(existing) => existing.kind = change.kind,
+                            None => pending.push(change),
+                        }
+                    }
+                    last_change = Some(Instant::now());
+                }
+            }
+            Ok(Err(error)) => {
+                tracing::warn!("Directory watcher error: {}", error);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if let Some(changed_at) = last_change {
+            if changed_at.elapsed() >= debounce_window {
+                flush(&mut pending);
+                last_change = None;
+            }
+        }
+    }
+
+    flush(&mut pending);
+}
+
+/// Start emitting `directory-changed:{path}` events for changes under `path`
+#[tauri::command]
+pub fn watch_directory(
+    app: AppHandle,
+    state: State<'_, DirectoryWatchState>,
+    path: String,
+) -> Result<(), String> {
+    let key = path.trim().to_string();
+    let root = PathBuf::from(&key);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", key));
+    }
+
+    let mut watchers = state
+        .watchers
+        .lock()
+        .map_err(|_| "Failed to lock directory watcher state.".to_string())?;
+
+    if watchers.contains_key(&key) {
+        return Ok(());
+    }
+
+    let event_name = directory_event_name(&key);
+    let watcher = DirectoryWatcher::start(root, move |payload| {
+        if let Err(error) = app.emit(&event_name, payload) {
+            tracing::warn!("Failed to emit directory change event: {}", error);
+        }
+    })?;
+    watchers.insert(key, watcher);
+    Ok(())
+}
+
+/// Stop watching a directory previously passed to `watch_directory`
+#[tauri::command]
+pub fn unwatch_directory(state: State<'_, DirectoryWatchState>, path: String) -> Result<(), String> {
+    let mut watchers = state
+        .watchers
+        .lock()
+        .map_err(|_| "Failed to lock directory watcher state.".to_string())?;
+
+    if let Some(mut watcher) = watchers.remove(path.trim()) {
+        watcher.stop();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn wait_for_change(
+        rx: &mpsc::Receiver<DirectoryChangedPayload>,
+        path: &Path,
+        kind: &str,
+    ) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let expected = path.to_string_lossy().to_string();
+        while Instant::now() < deadline {
+            if let Ok(payload) = rx.recv_timeout(Duration::from_millis(200)) {
+                if payload
+                    .changes
+                    .iter()
+                    .any(|change| change.path == expected && change.kind == kind)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn directory_event_name_sanitizes_path() {
+        assert_eq!(
+            directory_event_name("/Users/me/my project/app.v2"),
+            "directory-changed:/Users/me/my_project/app_v2"
+        );
+    }
+
+    #[test]
+    fn event_changes_skip_build_dirs() {
+        let root = PathBuf::from("/repo");
+        let event = Event {
+            kind: EventKind::Create(notify::event::CreateKind::File),
+            paths: vec![
+                PathBuf::from("/repo/src/main.rs"),
+                PathBuf::from("/repo/target/debug/app"),
+                PathBuf::from("/repo/web/node_modules/pkg/index.js"),
+            ],
+            attrs: notify::event::EventAttributes::new(),
+        };
+
+        assert_eq!(
+            event_changes(&root, &event),
+            vec![DirectoryChange {
+                path: "/repo/src/main.rs".to_string(),
+                kind: "created".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn watcher_emits_created_and_removed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = DirectoryWatcher::start(root.clone(), move |payload| {
+            let _ = tx.send(payload);
+        })
+        .unwrap();
+
+        let file = root.join("notes.txt");
+        std::fs::write(&file, "hello").unwrap();
+        assert!(wait_for_change(&rx, &file, "created"));
+
+        std::fs::remove_file(&file).unwrap();
+        assert!(wait_for_change(&rx, &file, "removed"));
+
+        watcher.stop();
+    }
+}
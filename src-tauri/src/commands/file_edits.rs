@@ -0,0 +1,296 @@
+//! Normalize file edits reported by different providers into one event shape.
+//!
+//! Providers describe edits in their own way: Claude and opencode emit `tool_use`
+//! blocks (`Write`, `Edit`, `MultiEdit`), aider prints `Applied edit to <path>`
+//! lines that reach the stream as plain text, and Codex reports `file_change` items
+//! that its transform drops. Each stdout line is inspected after transformation
+//! (and, for Codex, before) so the frontend receives a single `file-edit:{run_id}`
+//! event per changed file, regardless of which provider made the change.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::checkpoint::diff::unified_diff;
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileEditOperation {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// Payload of the `file-edit:{run_id}` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileEdit {
+    pub path: String,
+    pub operation: FileEditOperation,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Collect the file edits described by one provider stdout line.
+///
+/// `raw_line` is what the provider printed and `emitted` is the (possibly
+/// multi-line) Claude-compatible output produced by its transform. Relative paths
+/// are resolved against `project_path`.
+pub fn extract_file_edits(
+    provider_id: &str,
+    project_path: &Path,
+    raw_line: &str,
+    emitted: &str,
+) -> Vec<FileEdit> {
+    let mut edits = Vec::new();
+
+    if provider_id == "codex" {
+        edits.extend(codex_file_changes(raw_line));
+    }
+
+    for line in emitted.lines() {
+        let Ok(json) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if json.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+            continue;
+        }
+        let Some(content) = json.pointer("/message/content").and_then(|c| c.as_array()) else {
+            continue;
+        };
+
+        for block in content {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("tool_use") => edits.extend(tool_use_edit(block, project_path)),
+                Some("text") if provider_id == "aider" => {
+                    let text = block.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                    edits.extend(text.lines().filter_map(aider_edit));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    edits
+}
+
+/// Number of lines in an edit's before or after text
+fn count_lines(text: &str) -> usize {
+    text.lines().count()
+}
+
+/// Lines added and removed by replacing `old` with `new` in `path`
+fn replacement_counts(path: &str, input: &Value) -> (usize, usize) {
+    let diff = unified_diff(
+        Path::new(path),
+        string_field(input, &["old_string", "oldString"]).unwrap_or(""),
+        string_field(input, &["new_string", "newString"]).unwrap_or(""),
+    );
+    (diff.additions, diff.deletions)
+}
+
+fn string_field<'a>(input: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| input.get(*key).and_then(|v| v.as_str()))
+}
+
+/// Map a Claude or opencode file tool call onto an edit
+fn tool_use_edit(block: &Value, project_path: &Path) -> Option<FileEdit> {
+    let name = block.get("name").and_then(|n| n.as_str())?.to_lowercase();
+    let input = block.get("input")?;
+    let path = string_field(input, &["file_path", "filePath"])?.to_string();
+
+    match name.as_str() {
+        "write" => {
+            let content = string_field(input, &["content"]).unwrap_or("");
+            let operation = if project_path.join(&path).exists() {
+                FileEditOperation::Modify
+            } else {
+                FileEditOperation::Create
+            };
+            Some(FileEdit {
+                path,
+                operation,
+                additions: count_lines(content),
+                deletions: 0,
+            })
+        }
+        "edit" => {
+            let (additions, deletions) = replacement_counts(&path, input);
+            Some(FileEdit {
+                path,
+                operation: FileEditOperation::Modify,
+                additions,
+                deletions,
+            })
+        }
+        "multiedit" => {
+            let (additions, deletions) = input
+                .get("edits")
+                .and_then(|e| e.as_array())
+                .map(|edits| {
+                    edits.iter().fold((0, 0), |(added, removed), edit| {
+                        let (additions, deletions) = replacement_counts(&path, edit);
+                        (added + additions, removed + deletions)
+                    })
+                })
+                .unwrap_or((0, 0));
+            Some(FileEdit {
+                path,
+                operation: FileEditOperation::Modify,
+                additions,
+                deletions,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parse aider's `Applied edit to <path>` confirmation. Aider prints the diff
+/// separately, so line counts aren't available here.
+fn aider_edit(line: &str) -> Option<FileEdit> {
+    let path = line.trim().strip_prefix("Applied edit to ")?.trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(FileEdit {
+        path: path.to_string(),
+        operation: FileEditOperation::Modify,
+        additions: 0,
+        deletions: 0,
+    })
+}
+
+/// Read the changes from a Codex `item.completed` event for a `file_change` item
+fn codex_file_changes(raw_line: &str) -> Vec<FileEdit> {
+    let Ok(event) = serde_json::from_str::<Value>(raw_line.trim()) else {
+        return Vec::new();
+    };
+    if event.get("type").and_then(|t| t.as_str()) != Some("item.completed") {
+        return Vec::new();
+    }
+    let Some(item) = event.get("item") else {
+        return Vec::new();
+    };
+    if item.get("type").and_then(|t| t.as_str()) != Some("file_change") {
+        return Vec::new();
+    }
+
+    item.get("changes")
+        .and_then(|c| c.as_array())
+        .map(|changes| {
+            changes
+                .iter()
+                .filter_map(|change| {
+                    let path = change.get("path").and_then(|p| p.as_str())?;
+                    let operation = match change.get("kind").and_then(|k| k.as_str()) {
+                        Some("add") => FileEditOperation::Create,
+                        Some("delete") => FileEditOperation::Delete,
+                        _ => FileEditOperation::Modify,
+                    };
+                    Some(FileEdit {
+                        path: path.to_string(),
+                        operation,
+                        additions: 0,
+                        deletions: 0,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_edit_tool_use_becomes_a_modify_event() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/main.rs","old_string":"a\nb","new_string":"a\nb\nc"}}]}}"#;
+
+        let edits = extract_file_edits("claude", Path::new("/project"), line, line);
+
+        assert_eq!(
+            edits,
+            vec![FileEdit {
+                path: "src/main.rs".to_string(),
+                operation: FileEditOperation::Modify,
+                additions: 1,
+                deletions: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn write_is_a_modify_only_when_the_file_already_exists() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("existing.rs"), "old\n").unwrap();
+        let write = |path: &str| {
+            format!(
+                r#"{{"type":"assistant","message":{{"content":[{{"type":"tool_use","id":"t1","name":"Write","input":{{"file_path":"{}","content":"a\nb"}}}}]}}}}"#,
+                path
+            )
+        };
+
+        let operations: Vec<_> = ["existing.rs", "new.rs"]
+            .into_iter()
+            .flat_map(|path| {
+                let line = write(path);
+                extract_file_edits("claude", project.path(), &line, &line)
+            })
+            .map(|edit| (edit.path, edit.operation, edit.additions))
+            .collect();
+
+        assert_eq!(
+            operations,
+            vec![
+                ("existing.rs".to_string(), FileEditOperation::Modify, 2),
+                ("new.rs".to_string(), FileEditOperation::Create, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn aider_applied_edit_line_becomes_a_modify_event() {
+        let raw = "Applied edit to src/lib.rs";
+        let emitted = crate::commands::agents::transform_provider_output("aider", raw).unwrap();
+
+        let project = Path::new("/project");
+        let edits = extract_file_edits("aider", project, raw, &emitted);
+
+        assert_eq!(
+            edits,
+            vec![FileEdit {
+                path: "src/lib.rs".to_string(),
+                operation: FileEditOperation::Modify,
+                additions: 0,
+                deletions: 0,
+            }]
+        );
+        assert!(
+            extract_file_edits("aider", project, "Tokens: 1k sent", "Tokens: 1k sent").is_empty()
+        );
+        // Other providers' text that happens to read like aider's isn't an edit
+        let wrapped = crate::commands::agents::transform_provider_output("gemini", raw).unwrap();
+        assert!(extract_file_edits("gemini", project, raw, &wrapped).is_empty());
+    }
+
+    #[test]
+    fn codex_file_change_items_map_kinds_to_operations() {
+        let raw = r#"{"type":"item.completed","item":{"type":"file_change","changes":[{"path":"new.rs","kind":"add"},{"path":"old.rs","kind":"delete"},{"path":"lib.rs","kind":"update"}]}}"#;
+
+        let operations: Vec<_> = extract_file_edits("codex", Path::new("/project"), raw, "")
+            .into_iter()
+            .map(|edit| (edit.path, edit.operation))
+            .collect();
+
+        assert_eq!(
+            operations,
+            vec![
+                ("new.rs".to_string(), FileEditOperation::Create),
+                ("old.rs".to_string(), FileEditOperation::Delete),
+                ("lib.rs".to_string(), FileEditOperation::Modify),
+            ]
+        );
+    }
+}
@@ -3,7 +3,7 @@ use dirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tauri::AppHandle;
 
@@ -723,3 +723,292 @@ pub async fn mcp_save_project_config(
 
     Ok("Project MCP configuration saved".to_string())
 }
+
+/// Problems found in one configured MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPServerValidation {
+    pub name: String,
+    /// Transport type: "stdio", "sse" or "http"
+    pub transport: String,
+    /// Empty when the server looks correctly configured
+    pub problems: Vec<String>,
+}
+
+/// MCP servers configured for `scope`, as raw JSON objects. User and local servers
+/// live in `~/.claude.json`; project servers in the project's `.mcp.json`.
+fn read_scope_servers(
+    scope: &str,
+    project_path: Option<&str>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let read_json = |path: PathBuf| -> Result<serde_json::Value, String> {
+        if !path.exists() {
+            return Ok(serde_json::Value::Null);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    };
+    let claude_json = || {
+        dirs::home_dir()
+            .map(|home| home.join(".claude.json"))
+            .ok_or_else(|| "Could not find home directory".to_string())
+    };
+    let project_path =
+        || project_path.ok_or_else(|| format!("A project path is required for {} scope", scope));
+
+    let config = match scope {
+        "user" => read_json(claude_json()?)?,
+        "local" => read_json(claude_json()?)?
+            .pointer_mut(&format!(
+                "/projects/{}",
+                project_path()?.replace('~', "~0").replace('/', "~1")
+            ))
+            .map(serde_json::Value::take)
+            .unwrap_or_default(),
+        "project" => read_json(PathBuf::from(project_path()?).join(".mcp.json"))?,
+        other => return Err(format!("Unknown MCP scope: {}", other)),
+    };
+
+    Ok(config
+        .get("mcpServers")
+        .and_then(|servers| servers.as_object())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Names of `${VAR}` references in `value` that aren't set and have no
+/// `${VAR:-default}` fallback
+fn unset_env_references(value: &str, env_var: &dyn Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut unset = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let reference = &rest[start + 2..start + 2 + len];
+        if !reference.contains(":-") && env_var(reference).is_none() {
+            unset.push(reference.to_string());
+        }
+        rest = &rest[start + 2 + len + 1..];
+    }
+    unset
+}
+
+/// Check that a stdio server's command can be run: an explicit path must be an
+/// executable file, a bare name must be found on PATH. Relative paths are
+/// resolved against the project, where the server is started.
+fn check_command(command: &str, project_path: Option<&Path>) -> Result<(), String> {
+    if !command.contains('/') && !command.contains('\\') {
+        return which::which(command)
+            .map(|_| ())
+            .map_err(|_| format!("Command not found on PATH: {}", command));
+    }
+
+    let path = match project_path {
+        Some(project_path) => project_path.join(command),
+        None => PathBuf::from(command),
+    };
+    let metadata =
+        fs::metadata(&path).map_err(|_| format!("Command does not exist: {}", command))?;
+    if !metadata.is_file() {
+        return Err(format!("Command is not a file: {}", command));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(format!("Command is not executable: {}", command));
+        }
+    }
+    Ok(())
+}
+
+/// Validate one server's configuration without starting or connecting to it
+fn validate_server_config(
+    name: &str,
+    config: &serde_json::Value,
+    project_path: Option<&Path>,
+    env_var: &dyn Fn(&str) -> Option<String>,
+) -> MCPServerValidation {
+    let field = |key: &str| config.get(key).and_then(|v| v.as_str());
+    let transport = match field("type") {
+        Some(transport) => transport.to_string(),
+        None if field("url").is_some() => "sse".to_string(),
+        None => "stdio".to_string(),
+    };
+    let mut problems = Vec::new();
+    let mut referenced = Vec::new();
+
+    match transport.as_str() {
+        "stdio" => match field("command").map(str::trim).filter(|c| !c.is_empty()) {
+            Some(command) => {
+                referenced.push(command.to_string());
+                // A command built from variables can only be checked once expanded
+                if !command.contains("${") {
+                    if let Err(problem) = check_command(command, project_path) {
+                        problems.push(problem);
+                    }
+                }
+                if let Some(args) = config.get("args").and_then(|a| a.as_array()) {
+                    referenced.extend(args.iter().filter_map(|a| a.as_str()).map(str::to_string));
+                }
+            }
+            None => problems.push("Missing command".to_string()),
+        },
+        "sse" | "http" => match field("url") {
+            Some(url) => {
+                referenced.push(url.to_string());
+                let parsed = reqwest::Url::parse(url);
+                let valid = parsed
+                    .as_ref()
+                    .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+                if !valid && !url.contains("${") {
+                    problems.push(format!("Invalid URL: {}", url));
+                }
+            }
+            None => problems.push("Missing url".to_string()),
+        },
+        other => problems.push(format!("Unknown transport type: {}", other)),
+    }
+
+    if let Some(env) = config.get("env").and_then(|e| e.as_object()) {
+        for (key, value) in env {
+            match value.as_str() {
+                Some(value) if value.trim().is_empty() => {
+                    problems.push(format!("Environment variable {} is empty", key))
+                }
+                Some(value) => referenced.push(value.to_string()),
+                None => problems.push(format!("Environment variable {} is not a string", key)),
+            }
+        }
+    }
+
+    let mut unset: Vec<String> = referenced
+        .iter()
+        .flat_map(|value| unset_env_references(value, env_var))
+        .collect();
+    unset.sort();
+    unset.dedup();
+    problems.extend(
+        unset
+            .into_iter()
+            .map(|var| format!("Environment variable {} is not set", var)),
+    );
+
+    MCPServerValidation {
+        name: name.to_string(),
+        transport,
+        problems,
+    }
+}
+
+/// Validates every MCP server configured for a scope without spawning or connecting
+/// to any of them. `project_path` is needed for the "local" and "project" scopes.
+#[tauri::command]
+pub async fn mcp_validate_all(
+    scope: String,
+    project_path: Option<String>,
+) -> Result<Vec<MCPServerValidation>, String> {
+    tracing::info!("Validating MCP servers in scope: {}", scope);
+
+    let servers = read_scope_servers(&scope, project_path.as_deref())?;
+    let env_var = |name: &str| std::env::var(name).ok();
+    let project_path = project_path.as_deref().map(Path::new);
+    let mut results: Vec<MCPServerValidation> = servers
+        .iter()
+        .map(|(name, config)| validate_server_config(name, config, project_path, &env_var))
+        .collect();
+    results.sort_by(|a, b| a.name.cmp(&b.name));
+
+    tracing::info!(
+        "Validated {} MCP servers, {} with problems",
+        results.len(),
+        results.iter().filter(|r| !r.problems.is_empty()).count()
+    );
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn env_var(name: &str) -> Option<String> {
+        (name == "API_TOKEN").then(|| "secret".to_string())
+    }
+
+    #[test]
+    fn valid_servers_have_no_problems() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let server = dir.path().join("mcp-server");
+        fs::write(&server, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&server, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let stdio = json!({
+            "command": server.to_string_lossy(),
+            "args": ["--token", "${API_TOKEN}"],
+            "env": {"LOG_LEVEL": "debug", "HOME_DIR": "${HOME_DIR:-/tmp}"}
+        });
+        let sse = json!({"type": "sse", "url": "https://example.com/mcp"});
+
+        let stdio_result = validate_server_config("local-tool", &stdio, None, &env_var);
+        assert_eq!(stdio_result.transport, "stdio");
+        assert!(
+            stdio_result.problems.is_empty(),
+            "{:?}",
+            stdio_result.problems
+        );
+        assert!(validate_server_config("remote", &sse, None, &env_var)
+            .problems
+            .is_empty());
+    }
+
+    #[test]
+    fn missing_command_binary_is_reported() {
+        let config = json!({
+            "command": "definitely-not-an-installed-mcp-server",
+            "env": {"API_KEY": "", "URL": "${MISSING_URL}"}
+        });
+
+        let result = validate_server_config("broken", &config, None, &env_var);
+        assert_eq!(
+            result.problems,
+            vec![
+                "Command not found on PATH: definitely-not-an-installed-mcp-server",
+                "Environment variable API_KEY is empty",
+                "Environment variable MISSING_URL is not set",
+            ]
+        );
+
+        let bad_url = json!({"type": "http", "url": "not a url"});
+        assert_eq!(
+            validate_server_config("bad", &bad_url, None, &env_var).problems,
+            vec!["Invalid URL: not a url"]
+        );
+    }
+
+    #[test]
+    fn relative_commands_resolve_against_the_project() {
+        let project = tempfile::TempDir::new().unwrap();
+        fs::create_dir(project.path().join("bin")).unwrap();
+        let server = project.path().join("bin").join("mcp-server");
+        fs::write(&server, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&server, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let config = json!({"command": "./bin/mcp-server"});
+        assert!(
+            validate_server_config("local", &config, Some(project.path()), &env_var)
+                .problems
+                .is_empty()
+        );
+    }
+}
@@ -4,9 +4,13 @@ pub mod claude;
 pub mod provider_session;
 pub mod codex_transform;
 pub mod diagnostics;
+pub mod directory_watch;
+pub mod file_edits;
 pub mod hot_refresh;
 pub mod logging;
 pub mod mcp;
+pub mod opencode_transform;
+pub mod panic_stop;
 pub mod proxy;
 pub mod slash_commands;
 pub mod storage;
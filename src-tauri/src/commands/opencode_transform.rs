@@ -0,0 +1,231 @@
+//! Transform opencode `run --format json` output into Claude-compatible stream-json.
+//!
+//! opencode emits one JSON event per line, each carrying a message `part`:
+//! `text`, `reasoning`, `tool_use`, `step_start`, `step_finish` and `error`.
+//! Text and tool calls map onto the `{ type, message: { content: [...] } }` envelope
+//! the frontend renders; a finished tool call becomes two lines, the assistant
+//! `tool_use` followed by the user `tool_result`, so tool widgets can pair them up.
+//! opencode finishes a step per model call, so each step's usage rides on an empty
+//! assistant message and the single `result` line comes from [`run_result_line`].
+
+use serde_json::{json, Value};
+
+/// Transform a single opencode JSONL line into Claude-compatible stream-json.
+///
+/// Returns `None` for bookkeeping events (e.g. `step_start`). The result may hold
+/// several newline-separated JSON lines. Lines that aren't JSON are wrapped as text,
+/// which keeps opencode's plain output readable too.
+pub fn transform_opencode_line(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let event: Value = match serde_json::from_str(trimmed) {
+        Ok(v) => v,
+        Err(_) => return Some(wrap_content(json!({"type": "text", "text": trimmed}))),
+    };
+
+    let part = event.get("part").unwrap_or(&Value::Null);
+    match event.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+        "text" => {
+            let text = part.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            if text.is_empty() {
+                return None;
+            }
+            Some(wrap_content(json!({"type": "text", "text": text})))
+        }
+
+        "reasoning" => {
+            let text = part.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            if text.is_empty() {
+                return None;
+            }
+            Some(wrap_content(json!({"type": "thinking", "thinking": text})))
+        }
+
+        "tool_use" => transform_tool_use(part),
+
+        "step_finish" => {
+            let tokens =
+                |pointer: &str| part.pointer(pointer).and_then(|v| v.as_u64()).unwrap_or(0);
+            Some(
+                json!({
+                    "type": "assistant",
+                    "message": {
+                        "content": [],
+                        "usage": {
+                            "input_tokens": tokens("/tokens/input"),
+                            "output_tokens": tokens("/tokens/output"),
+                            "cache_read_input_tokens": tokens("/tokens/cache/read"),
+                            "cache_creation_input_tokens": tokens("/tokens/cache/write"),
+                        },
+                    },
+                    "cost": part.get("cost").and_then(|c| c.as_f64()).unwrap_or(0.0),
+                })
+                .to_string(),
+            )
+        }
+
+        "error" => {
+            let error = event.get("error").unwrap_or(&Value::Null);
+            let message = error
+                .pointer("/data/message")
+                .or_else(|| error.get("message"))
+                .and_then(|m| m.as_str())
+                .or_else(|| error.get("name").and_then(|n| n.as_str()))
+                .unwrap_or("opencode reported an error");
+            Some(wrap_content(
+                json!({"type": "text", "text": format!("Error: {}", message)}),
+            ))
+        }
+
+        "step_start" => None,
+
+        other => {
+            tracing::debug!("Skipping opencode event: {}", other);
+            None
+        }
+    }
+}
+
+/// The `result` line closing an opencode run, emitted once its output has ended.
+pub fn run_result_line(total_cost_usd: f64) -> String {
+    json!({
+        "type": "result",
+        "total_cost_usd": total_cost_usd,
+    })
+    .to_string()
+}
+
+/// A tool call plus, once it has finished, its result.
+fn transform_tool_use(part: &Value) -> Option<String> {
+    let call_id = part.get("callID").and_then(|v| v.as_str())?;
+    let tool = part.get("tool").and_then(|v| v.as_str()).unwrap_or("tool");
+    let state = part.get("state").unwrap_or(&Value::Null);
+    let input = state.get("input").cloned().unwrap_or_else(|| json!({}));
+
+    let tool_use = wrap_content(json!({
+        "type": "tool_use",
+        "id": call_id,
+        "name": tool,
+        "input": input,
+    }));
+
+    let (result, is_error) = match state.get("status").and_then(|s| s.as_str()) {
+        Some("completed") => (state.get("output"), false),
+        Some("error") => (state.get("error"), true),
+        _ => return Some(tool_use),
+    };
+    let tool_result = json!({
+        "type": "user",
+        "message": {
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": call_id,
+                "content": result.and_then(|r| r.as_str()).unwrap_or(""),
+                "is_error": is_error,
+            }]
+        }
+    });
+
+    Some(format!("{}\n{}", tool_use, tool_result))
+}
+
+/// Wrap one content block in a Claude assistant message.
+fn wrap_content(block: Value) -> String {
+    json!({
+        "type": "assistant",
+        "message": {
+            "content": [block]
+        }
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_lines(output: &str) -> Vec<Value> {
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_text_part_becomes_assistant_text() {
+        let line = r#"{"type":"text","timestamp":1760000000000,"sessionID":"ses_6a1f","part":{"id":"prt_01","sessionID":"ses_6a1f","messageID":"msg_01","type":"text","text":"The tests pass now.","time":{"start":1760000000000,"end":1760000000100}}}"#;
+        let out = parse_lines(&transform_opencode_line(line).unwrap());
+        assert_eq!(
+            out,
+            vec![json!({
+                "type": "assistant",
+                "message": {"content": [{"type": "text", "text": "The tests pass now."}]}
+            })]
+        );
+    }
+
+    #[test]
+    fn test_completed_tool_use_emits_call_and_result() {
+        let line = r#"{"type":"tool_use","timestamp":1760000000200,"sessionID":"ses_6a1f","part":{"id":"prt_02","sessionID":"ses_6a1f","messageID":"msg_01","type":"tool","callID":"call_9","tool":"bash","state":{"status":"completed","input":{"command":"cargo test","description":"Run tests"},"output":"test result: ok","title":"cargo test","metadata":{},"time":{"start":1760000000150,"end":1760000000200}}}}"#;
+        let out = parse_lines(&transform_opencode_line(line).unwrap());
+        assert_eq!(
+            out,
+            vec![
+                json!({
+                    "type": "assistant",
+                    "message": {"content": [{
+                        "type": "tool_use",
+                        "id": "call_9",
+                        "name": "bash",
+                        "input": {"command": "cargo test", "description": "Run tests"}
+                    }]}
+                }),
+                json!({
+                    "type": "user",
+                    "message": {"content": [{
+                        "type": "tool_result",
+                        "tool_use_id": "call_9",
+                        "content": "test result: ok",
+                        "is_error": false
+                    }]}
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_tool_use_marks_result_as_error() {
+        let line = r#"{"type":"tool_use","part":{"type":"tool","callID":"call_3","tool":"read","state":{"status":"error","input":{"filePath":"/missing"},"error":"File not found"}}}"#;
+        let out = parse_lines(&transform_opencode_line(line).unwrap());
+        assert_eq!(out[1]["message"]["content"][0]["content"], "File not found");
+        assert_eq!(out[1]["message"]["content"][0]["is_error"], true);
+    }
+
+    #[test]
+    fn test_step_finish_reports_usage_and_cost() {
+        let line = r#"{"type":"step_finish","part":{"type":"step-finish","reason":"stop","cost":0.0123,"tokens":{"input":1200,"output":80,"reasoning":0,"cache":{"read":300,"write":0}}}}"#;
+        let out = parse_lines(&transform_opencode_line(line).unwrap());
+        assert_eq!(out[0]["type"], "assistant");
+        assert_eq!(out[0]["message"]["content"], json!([]));
+        assert_eq!(out[0]["message"]["usage"]["input_tokens"], 1200);
+        assert_eq!(out[0]["message"]["usage"]["output_tokens"], 80);
+        assert_eq!(out[0]["message"]["usage"]["cache_read_input_tokens"], 300);
+        assert_eq!(out[0]["cost"], 0.0123);
+
+        let result: Value = serde_json::from_str(&run_result_line(0.0246)).unwrap();
+        assert_eq!(result["type"], "result");
+        assert_eq!(result["total_cost_usd"], 0.0246);
+    }
+
+    #[test]
+    fn test_step_start_skipped_and_plain_text_wrapped() {
+        let line = r#"{"type":"step_start","part":{"type":"step-start"}}"#;
+        assert!(transform_opencode_line(line).is_none());
+
+        let out = parse_lines(&transform_opencode_line("plain output").unwrap());
+        assert_eq!(out[0]["message"]["content"][0]["text"], "plain output");
+    }
+}
@@ -0,0 +1,294 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::{
+    abort_rate_limit_retries, cancel_unstarted_runs_in, kill_agent_session, kill_each_run, AgentDb,
+};
+use super::provider_session::{cancel_provider_session, ProviderSessionProcessState};
+use crate::mobile_sync::MobileSyncServiceState;
+use crate::process::ProcessRegistryState;
+use crate::usage_index::UsageIndexState;
+
+/// What `panic_stop` managed to halt
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicStopSummary {
+    /// Runs cancelled before they got a process: queued and still-starting runs
+    pub cancelled_unstarted_runs: Vec<i64>,
+    /// Rate-limited runs whose scheduled retry was aborted
+    pub aborted_retries: Vec<i64>,
+    pub killed_agent_runs: Vec<i64>,
+    pub killed_provider_sessions: usize,
+    pub usage_sync_cancelled: bool,
+    pub mobile_sync_stopped: bool,
+    pub errors: Vec<String>,
+}
+
+/// Subsystems halted by `panic_stop`, split out so the orchestration can be
+/// exercised without a running app.
+trait PanicStopTargets {
+    fn abort_retries(&self) -> Vec<i64>;
+    fn cancel_unstarted_runs(&self) -> Result<Vec<i64>, String>;
+    async fn kill_agent_runs(&self) -> Result<Vec<i64>, String>;
+    async fn kill_provider_sessions(&self) -> Result<usize, String>;
+    fn cancel_usage_sync(&self) -> bool;
+    fn stop_mobile_sync(&self) -> bool;
+}
+
+/// Stop every subsystem, recording failures instead of bailing out so one
+/// stuck subsystem does not keep the others running.
+async fn run_panic_stop<T: PanicStopTargets>(targets: &T) -> PanicStopSummary {
+    // Runs that haven't started go first, so slots freed by the kills can't start them
+    let mut summary = PanicStopSummary {
+        aborted_retries: targets.abort_retries(),
+        ..PanicStopSummary::default()
+    };
+    match targets.cancel_unstarted_runs() {
+        Ok(run_ids) => summary.cancelled_unstarted_runs = run_ids,
+        Err(e) => summary
+            .errors
+            .push(format!("Failed to cancel queued runs: {}", e)),
+    }
+
+    match targets.kill_agent_runs().await {
+        Ok(run_ids) => summary.killed_agent_runs = run_ids,
+        Err(e) => summary.errors.push(format!("Failed to kill agent runs: {}", e)),
+    }
+
+    match targets.kill_provider_sessions().await {
+        Ok(count) => summary.killed_provider_sessions = count,
+        Err(e) => summary
+            .errors
+            .push(format!("Failed to kill provider sessions: {}", e)),
+    }
+
+    summary.usage_sync_cancelled = targets.cancel_usage_sync();
+    summary.mobile_sync_stopped = targets.stop_mobile_sync();
+    summary
+}
+
+struct AppPanicStopTargets<'a> {
+    app: &'a AppHandle,
+    db: State<'a, AgentDb>,
+    registry: State<'a, ProcessRegistryState>,
+}
+
+impl PanicStopTargets for AppPanicStopTargets<'_> {
+    fn abort_retries(&self) -> Vec<i64> {
+        abort_rate_limit_retries(self.app)
+    }
+
+    fn cancel_unstarted_runs(&self) -> Result<Vec<i64>, String> {
+        let conn = self.db.0.lock().map_err(|e| e.to_string())?;
+        cancel_unstarted_runs_in(&conn)
+    }
+
+    async fn kill_agent_runs(&self) -> Result<Vec<i64>, String> {
+        // Runs can be tracked by the registry, the database, or both
+        let mut run_ids: BTreeSet<i64> = self
+            .registry
+            .0
+            .get_running_agent_processes()?
+            .into_iter()
+            .map(|process| process.run_id)
+            .collect();
+        {
+            let conn = self.db.0.lock().map_err(|e| e.to_string())?;
+            let mut stmt = conn
+                .prepare("SELECT id FROM agent_runs WHERE status = 'running'")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get::<_, i64>(0))
+                .map_err(|e| e.to_string())?;
+            for id in rows {
+                run_ids.insert(id.map_err(|e| e.to_string())?);
+            }
+        }
+
+        let (killed, errors) = kill_each_run(run_ids, |run_id| {
+            kill_agent_session(
+                self.app.clone(),
+                self.db.clone(),
+                self.registry.clone(),
+                run_id,
+            )
+        })
+        .await;
+        for error in &errors {
+            tracing::warn!("{}", error);
+        }
+        Ok(killed)
+    }
+
+    async fn kill_provider_sessions(&self) -> Result<usize, String> {
+        let sessions = self.registry.0.get_running_provider_sessions()?;
+        let mut count = 0;
+        for session in sessions {
+            let session_id = match session.process_type {
+                crate::process::ProcessType::ProviderSession { session_id } => session_id,
+                _ => continue,
+            };
+            match cancel_provider_session(self.app.clone(), Some(session_id.clone())).await {
+                Ok(()) => count += 1,
+                Err(e) => tracing::warn!("Failed to cancel provider session {}: {}", session_id, e),
+            }
+        }
+
+        // A session that has not reported its ID yet only lives in the process state
+        let has_untracked_process = self
+            .app
+            .state::<ProviderSessionProcessState>()
+            .current_process
+            .lock()
+            .await
+            .is_some();
+        if has_untracked_process {
+            cancel_provider_session(self.app.clone(), None).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn cancel_usage_sync(&self) -> bool {
+        let usage_state = self.app.state::<UsageIndexState>();
+        if usage_state.snapshot().state != "indexing" {
+            return false;
+        }
+        usage_state.request_cancel();
+        true
+    }
+
+    fn stop_mobile_sync(&self) -> bool {
+        let mobile_state = self.app.state::<MobileSyncServiceState>();
+        mobile_state.cache.set_enabled(false);
+        mobile_state.request_server_shutdown()
+    }
+}
+
+/// Emergency stop: cancel queued runs and pending retries, kill all running agent
+/// runs and provider sessions, cancel any usage index sync and shut down the
+/// mobile sync server.
+#[tauri::command]
+pub async fn panic_stop(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<PanicStopSummary, String> {
+    tracing::warn!("Panic stop requested");
+    let targets = AppPanicStopTargets {
+        app: &app,
+        db,
+        registry,
+    };
+    let summary = run_panic_stop(&targets).await;
+    tracing::info!("Panic stop finished: {:?}", summary);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingTargets {
+        calls: Mutex<Vec<&'static str>>,
+        fail_agent_runs: bool,
+    }
+
+    impl RecordingTargets {
+        fn record(&self, call: &'static str) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl PanicStopTargets for RecordingTargets {
+        fn abort_retries(&self) -> Vec<i64> {
+            self.record("retries");
+            vec![5]
+        }
+
+        fn cancel_unstarted_runs(&self) -> Result<Vec<i64>, String> {
+            self.record("unstarted_runs");
+            Ok(vec![9])
+        }
+
+        async fn kill_agent_runs(&self) -> Result<Vec<i64>, String> {
+            self.record("agent_runs");
+            if self.fail_agent_runs {
+                return Err("database is locked".to_string());
+            }
+            Ok(vec![3, 7])
+        }
+
+        async fn kill_provider_sessions(&self) -> Result<usize, String> {
+            self.record("provider_sessions");
+            Ok(2)
+        }
+
+        fn cancel_usage_sync(&self) -> bool {
+            self.record("usage_sync");
+            true
+        }
+
+        fn stop_mobile_sync(&self) -> bool {
+            self.record("mobile_sync");
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn panic_stop_invokes_every_subsystem() {
+        let targets = RecordingTargets::default();
+
+        let summary = run_panic_stop(&targets).await;
+
+        assert_eq!(
+            targets.calls(),
+            vec![
+                "retries",
+                "unstarted_runs",
+                "agent_runs",
+                "provider_sessions",
+                "usage_sync",
+                "mobile_sync"
+            ]
+        );
+        assert_eq!(
+            summary,
+            PanicStopSummary {
+                cancelled_unstarted_runs: vec![9],
+                aborted_retries: vec![5],
+                killed_agent_runs: vec![3, 7],
+                killed_provider_sessions: 2,
+                usage_sync_cancelled: true,
+                mobile_sync_stopped: true,
+                errors: Vec::new(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn panic_stop_continues_after_a_subsystem_fails() {
+        let targets = RecordingTargets {
+            fail_agent_runs: true,
+            ..Default::default()
+        };
+
+        let summary = run_panic_stop(&targets).await;
+
+        assert_eq!(targets.calls().len(), 6);
+        assert!(summary.killed_agent_runs.is_empty());
+        assert_eq!(summary.killed_provider_sessions, 2);
+        assert!(summary.mobile_sync_stopped);
+        assert_eq!(
+            summary.errors,
+            vec!["Failed to kill agent runs: database is locked".to_string()]
+        );
+    }
+}
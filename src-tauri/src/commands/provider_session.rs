@@ -122,7 +122,7 @@ fn create_provider_session_command_with_env(program: &str) -> Command {
 }
 
 /// Creates a system command with the given arguments for provider sessions.
-fn create_provider_session_system_command(
+pub(crate) fn create_provider_session_system_command(
     provider_binary_path: &str,
     args: Vec<String>,
     project_path: &str,
@@ -419,15 +419,16 @@ pub async fn get_provider_session_output(
 }
 
 /// Helper function to spawn provider-session process and handle streaming.
-async fn spawn_provider_session_process(
+pub(crate) async fn spawn_provider_session_process(
     app: AppHandle,
     mut cmd: Command,
     prompt: String,
     model: String,
     project_path: String,
 ) -> Result<(), String> {
+    use crate::process::output_lines::{load_max_line_bytes, CappedLines};
     use std::sync::Mutex;
-    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::io::BufReader;
 
     // Spawn the process
     let mut child = cmd
@@ -445,6 +446,11 @@ async fn spawn_provider_session_process(
     // Create readers first (before moving child)
     let stdout_reader = BufReader::new(stdout);
     let stderr_reader = BufReader::new(stderr);
+    let max_line_bytes = {
+        let db = app.state::<crate::commands::agents::AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_max_line_bytes(&conn)
+    };
 
     // We'll extract the session ID from init message
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
@@ -472,7 +478,7 @@ async fn spawn_provider_session_process(
     let prompt_clone = prompt.clone();
     let model_clone = model.clone();
     let stdout_task = tokio::spawn(async move {
-        let mut lines = stdout_reader.lines();
+        let mut lines = CappedLines::new(stdout_reader, max_line_bytes);
         while let Ok(Some(line)) = lines.next_line().await {
             tracing::info!("Provider session stdout: {}", &line[..line.len().min(200)]);
 
@@ -527,7 +533,7 @@ async fn spawn_provider_session_process(
     let app_handle_stderr = app.clone();
     let session_id_holder_clone2 = session_id_holder.clone();
     let stderr_task = tokio::spawn(async move {
-        let mut lines = stderr_reader.lines();
+        let mut lines = CappedLines::new(stderr_reader, max_line_bytes);
         while let Ok(Some(line)) = lines.next_line().await {
             tracing::error!("Provider session stderr: {}", line);
             // Emit error lines with session isolation if we have session ID
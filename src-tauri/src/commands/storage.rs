@@ -636,6 +636,144 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Tables and columns created by `init_schema`, used to detect schema drift
+const EXPECTED_AGENTS_SCHEMA: &[(&str, &[&str])] = &[
+    (
+        "agents",
+        &[
+            "id",
+            "name",
+            "icon",
+            "system_prompt",
+            "default_task",
+            "provider_id",
+            "model",
+            "enable_file_read",
+            "enable_file_write",
+            "enable_network",
+            "hooks",
+            "created_at",
+            "updated_at",
+            "last_used_model",
+            "deleted_at",
+            "first_output_timeout_secs",
+            "extra_args",
+            "binary_path",
+        ],
+    ),
+    (
+        "agent_runs",
+        &[
+            "id",
+            "agent_id",
+            "agent_name",
+            "agent_icon",
+            "provider_id",
+            "task",
+            "model",
+            "project_path",
+            "session_id",
+            "output",
+            "status",
+            "pid",
+            "process_started_at",
+            "created_at",
+            "completed_at",
+            "exit_code",
+            "first_output_at",
+            "process_ended_at",
+            "retry_of_run_id",
+            "notes",
+            "stderr",
+            "comparison_id",
+            "launch_options",
+        ],
+    ),
+    ("app_settings", &["key", "value", "created_at", "updated_at"]),
+    (
+        "mobile_devices",
+        &[
+            "id",
+            "device_name",
+            "token_hash",
+            "revoked",
+            "created_at",
+            "updated_at",
+            "last_seen_at",
+        ],
+    ),
+    (
+        "mobile_pairing_codes",
+        &["code", "expires_at", "claimed", "created_at"],
+    ),
+    (
+        "mobile_sync_settings",
+        &["key", "value", "created_at", "updated_at"],
+    ),
+    ("recent_projects", &["id", "opened_at"]),
+];
+
+/// A table or column the agents database is expected to have but doesn't
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SchemaDiscrepancy {
+    pub table: String,
+    /// None when the whole table is missing
+    pub column: Option<String>,
+}
+
+fn find_schema_discrepancies(conn: &Connection) -> Result<Vec<SchemaDiscrepancy>, String> {
+    let mut discrepancies = Vec::new();
+
+    for (table, columns) in EXPECTED_AGENTS_SCHEMA {
+        if !is_valid_table_name(conn, table)? {
+            discrepancies.push(SchemaDiscrepancy {
+                table: table.to_string(),
+                column: None,
+            });
+            continue;
+        }
+
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .map_err(|e| e.to_string())?;
+        let existing = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .map_err(|e| e.to_string())?
+            .collect::<SqliteResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+
+        for column in columns.iter() {
+            if !existing.iter().any(|name| name == column) {
+                discrepancies.push(SchemaDiscrepancy {
+                    table: table.to_string(),
+                    column: Some(column.to_string()),
+                });
+            }
+        }
+    }
+
+    Ok(discrepancies)
+}
+
+/// List tables and columns missing from the agents database
+#[tauri::command]
+pub async fn verify_agents_schema(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<SchemaDiscrepancy>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    find_schema_discrepancies(&conn)
+}
+
+/// Re-run the schema migrations and return whatever they could not restore
+#[tauri::command]
+pub async fn repair_agents_schema(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<SchemaDiscrepancy>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    init_schema(&conn).map_err(|e| format!("Failed to repair schema: {}", e))?;
+    find_schema_discrepancies(&conn)
+}
+
 /// Helper function to validate table name exists
 fn is_valid_table_name(conn: &Connection, table_name: &str) -> Result<bool, String> {
     let count: i64 = conn
@@ -669,4 +807,42 @@ fn json_to_sql_value(value: &JsonValue) -> Result<Box<dyn rusqlite::ToSql>, Stri
 }
 
 /// Initialize the agents database (re-exported from agents module)
-use super::agents::init_database;
+use super::agents::{init_database, init_schema};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_schema_has_no_discrepancies() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert!(find_schema_discrepancies(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn dropped_column_is_detected_and_repaired() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute("ALTER TABLE agent_runs DROP COLUMN exit_code", [])
+            .unwrap();
+        conn.execute("DROP TABLE recent_projects", []).unwrap();
+
+        assert_eq!(
+            find_schema_discrepancies(&conn).unwrap(),
+            vec![
+                SchemaDiscrepancy {
+                    table: "agent_runs".to_string(),
+                    column: Some("exit_code".to_string()),
+                },
+                SchemaDiscrepancy {
+                    table: "recent_projects".to_string(),
+                    column: None,
+                },
+            ]
+        );
+
+        init_schema(&conn).unwrap();
+        assert!(find_schema_discrepancies(&conn).unwrap().is_empty());
+    }
+}
@@ -1,13 +1,21 @@
 use chrono::{DateTime, Local, NaiveDate};
 use std::any::Any;
 use std::panic::{catch_unwind, AssertUnwindSafe};
-use tauri::{command, AppHandle, State};
+use tauri::{command, AppHandle, Manager, State};
 
-use crate::usage_index::query::{query_session_stats, query_usage_details, query_usage_stats};
-use crate::usage_index::sync::run_usage_index_sync;
+use crate::commands::agents::AgentDb;
+use crate::usage_index::anonymize::{
+    anonymize_session_usage, anonymize_usage_entries, anonymize_usage_stats,
+    load_or_create_anonymize_salt,
+};
+use crate::usage_index::query::{
+    query_day_totals, query_session_model_mix, query_session_stats, query_usage_details,
+    query_usage_stats,
+};
+use crate::usage_index::sync::{run_usage_index_rebuild, run_usage_index_sync};
 use crate::usage_index::{
-    append_usage_debug_log, open_usage_index_connection, UsageEntry, UsageIndexState, UsageIndexStatus,
-    UsageStats,
+    append_usage_debug_log, open_usage_index_connection, DayUsageTotals, SessionModelShare,
+    UsageEntry, UsageIndexState, UsageIndexStatus, UsageStats,
 };
 
 fn panic_payload_to_string(payload: Box<dyn Any + Send>) -> String {
@@ -56,6 +64,13 @@ fn parse_date_input(input: &str, label: &str) -> Result<String, String> {
     Err(format!("Invalid {}: {}", label, input))
 }
 
+/// This install's salt for project pseudonyms
+fn anonymize_salt(app: &AppHandle) -> Result<String, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_or_create_anonymize_salt(&conn)
+}
+
 fn parse_compact_date(input: &str) -> Option<String> {
     NaiveDate::parse_from_str(input, "%Y%m%d")
         .ok()
@@ -112,8 +127,51 @@ pub fn cancel_usage_index_sync(
     Ok(state.snapshot())
 }
 
+/// Wipe the usage index and re-read every JSONL file, returning the final status
+#[command]
+pub async fn rebuild_usage_index(
+    app: AppHandle,
+    state: State<'_, UsageIndexState>,
+) -> Result<UsageIndexStatus, String> {
+    if !state.try_start() {
+        return Err("A usage index sync is already running".to_string());
+    }
+
+    state.mark_started(0);
+
+    let state_for_task = state.inner().clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let result = run_usage_index_rebuild(&app, &state_for_task);
+        match &result {
+            Ok(outcome) => {
+                if outcome.cancelled {
+                    state_for_task.mark_cancelled(outcome);
+                } else {
+                    state_for_task.mark_completed(outcome);
+                }
+            }
+            Err(error) => {
+                append_usage_debug_log(&format!("usage_index_rebuild error: {}", error));
+                state_for_task.mark_error(error);
+            }
+        }
+
+        state_for_task.finish();
+        result
+    })
+    .await
+    .map_err(|e| format!("Usage index rebuild task failed: {}", e))?;
+
+    result?;
+    Ok(state.snapshot())
+}
+
 #[command]
-pub fn get_usage_stats(days: Option<u32>, app: AppHandle) -> Result<UsageStats, String> {
+pub fn get_usage_stats(
+    days: Option<u32>,
+    anonymize: Option<bool>,
+    app: AppHandle,
+) -> Result<UsageStats, String> {
     panic_safe("get_usage_stats", || {
         let start_date = days.map(|value| {
             (Local::now().naive_local().date() - chrono::Duration::days(value as i64))
@@ -122,7 +180,20 @@ pub fn get_usage_stats(days: Option<u32>, app: AppHandle) -> Result<UsageStats,
         });
 
         let conn = open_usage_index_connection(&app)?;
-        query_usage_stats(&conn, start_date.as_deref(), None)
+        let mut stats = query_usage_stats(&conn, start_date.as_deref(), None)?;
+        if anonymize.unwrap_or(false) {
+            anonymize_usage_stats(&anonymize_salt(&app)?, &mut stats);
+        }
+        Ok(stats)
+    })
+}
+
+/// Cost and tokens for the current local day, for lightweight status displays
+#[command]
+pub fn get_today_usage(app: AppHandle) -> Result<DayUsageTotals, String> {
+    panic_safe("get_today_usage", || {
+        let conn = open_usage_index_connection(&app)?;
+        query_day_totals(&conn, &Local, Local::now().date_naive())
     })
 }
 
@@ -130,6 +201,7 @@ pub fn get_usage_stats(days: Option<u32>, app: AppHandle) -> Result<UsageStats,
 pub fn get_usage_by_date_range(
     start_date: String,
     end_date: String,
+    anonymize: Option<bool>,
     app: AppHandle,
 ) -> Result<UsageStats, String> {
     panic_safe("get_usage_by_date_range", || {
@@ -137,7 +209,11 @@ pub fn get_usage_by_date_range(
         let end = parse_date_input(&end_date, "end date")?;
 
         let conn = open_usage_index_connection(&app)?;
-        query_usage_stats(&conn, Some(start.as_str()), Some(end.as_str()))
+        let mut stats = query_usage_stats(&conn, Some(start.as_str()), Some(end.as_str()))?;
+        if anonymize.unwrap_or(false) {
+            anonymize_usage_stats(&anonymize_salt(&app)?, &mut stats);
+        }
+        Ok(stats)
     })
 }
 
@@ -147,17 +223,22 @@ pub fn get_usage_details(
     date: Option<String>,
     limit: Option<u32>,
     offset: Option<u32>,
+    anonymize: Option<bool>,
     app: AppHandle,
 ) -> Result<Vec<UsageEntry>, String> {
     panic_safe("get_usage_details", || {
         let conn = open_usage_index_connection(&app)?;
-        query_usage_details(
+        let mut entries = query_usage_details(
             &conn,
             project_path.as_deref(),
             date.as_deref(),
             limit,
             offset,
-        )
+        )?;
+        if anonymize.unwrap_or(false) {
+            anonymize_usage_entries(&anonymize_salt(&app)?, &mut entries);
+        }
+        Ok(entries)
     })
 }
 
@@ -168,6 +249,7 @@ pub fn get_session_stats(
     order: Option<String>,
     limit: Option<u32>,
     offset: Option<u32>,
+    anonymize: Option<bool>,
     app: AppHandle,
 ) -> Result<Vec<crate::usage_index::ProjectUsage>, String> {
     panic_safe("get_session_stats", || {
@@ -175,13 +257,28 @@ pub fn get_session_stats(
         let until_date = until.as_deref().and_then(parse_compact_date);
 
         let conn = open_usage_index_connection(&app)?;
-        query_session_stats(
+        let mut sessions = query_session_stats(
             &conn,
             since_date.as_deref(),
             until_date.as_deref(),
             order.as_deref(),
             limit,
             offset,
-        )
+        )?;
+        if anonymize.unwrap_or(false) {
+            anonymize_session_usage(&anonymize_salt(&app)?, &mut sessions);
+        }
+        Ok(sessions)
+    })
+}
+
+#[command]
+pub fn get_session_model_mix(
+    session_id: String,
+    app: AppHandle,
+) -> Result<Vec<SessionModelShare>, String> {
+    panic_safe("get_session_model_mix", || {
+        let conn = open_usage_index_connection(&app)?;
+        query_session_model_mix(&conn, &session_id)
     })
 }
@@ -0,0 +1,139 @@
+//! Structured error type for Tauri commands and the web server.
+//!
+//! Most commands still return `Result<_, String>`; migrated ones return
+//! `OpcodeError`, which serializes to the same plain message so the frontend
+//! sees no difference, while `code()` lets the web server pick a status.
+//! `From` conversions in both directions let the two styles mix through `?`.
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpcodeError {
+    /// The requested agent, run or record doesn't exist
+    NotFound(String),
+    /// The provider CLI is missing, misconfigured or not authenticated
+    ProviderUnavailable(String),
+    Io(String),
+    Db(String),
+    Cancelled,
+    /// Bad input from the caller
+    Validation(String),
+    /// Errors from code not migrated off `String` yet
+    Internal(String),
+}
+
+impl OpcodeError {
+    /// Stable machine-readable code, sent to web clients alongside the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::ProviderUnavailable(_) => "provider_unavailable",
+            Self::Io(_) => "io",
+            Self::Db(_) => "db",
+            Self::Cancelled => "cancelled",
+            Self::Validation(_) => "validation",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for OpcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(message)
+            | Self::ProviderUnavailable(message)
+            | Self::Validation(message)
+            | Self::Internal(message) => f.write_str(message),
+            Self::Io(message) => write!(f, "I/O error: {}", message),
+            Self::Db(message) => write!(f, "Database error: {}", message),
+            Self::Cancelled => f.write_str("Operation cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for OpcodeError {}
+
+/// Commands reject with the message alone, as they did with `String` errors
+impl Serialize for OpcodeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl From<std::io::Error> for OpcodeError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(error.to_string()),
+            _ => Self::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OpcodeError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error {
+            rusqlite::Error::QueryReturnedNoRows => Self::NotFound("Record not found".to_string()),
+            other => Self::Db(other.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for OpcodeError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Validation(error.to_string())
+    }
+}
+
+/// A poisoned lock; in practice the database mutex
+impl<T> From<std::sync::PoisonError<T>> for OpcodeError {
+    fn from(error: std::sync::PoisonError<T>) -> Self {
+        Self::Db(error.to_string())
+    }
+}
+
+impl From<String> for OpcodeError {
+    fn from(message: String) -> Self {
+        Self::Internal(message)
+    }
+}
+
+impl From<&str> for OpcodeError {
+    fn from(message: &str) -> Self {
+        Self::Internal(message.to_string())
+    }
+}
+
+impl From<OpcodeError> for String {
+    fn from(error: OpcodeError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_pick_the_matching_variant() {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(OpcodeError::from(missing).code(), "not_found");
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(OpcodeError::from(denied).code(), "io");
+        assert_eq!(
+            OpcodeError::from(rusqlite::Error::QueryReturnedNoRows).code(),
+            "not_found"
+        );
+        let bad_json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(OpcodeError::from(bad_json).code(), "validation");
+        assert_eq!(OpcodeError::from("legacy".to_string()).code(), "internal");
+    }
+
+    #[test]
+    fn serializes_to_the_plain_message() {
+        let error = OpcodeError::NotFound("Agent 7 not found".to_string());
+        assert_eq!(serde_json::to_value(&error).unwrap(), "Agent 7 not found");
+        assert_eq!(String::from(error), "Agent 7 not found");
+        assert_eq!(OpcodeError::Cancelled.to_string(), "Operation cancelled");
+    }
+}
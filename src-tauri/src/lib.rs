@@ -5,10 +5,12 @@ pub mod agent_binary;
 pub mod checkpoint;
 pub mod claude_binary;
 pub mod commands;
+pub mod error;
 pub mod mobile_sync;
 pub mod process;
 pub mod providers;
 pub mod usage_index;
+pub mod web_history;
 pub mod web_server;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -5,6 +5,7 @@ mod agent_binary;
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod error;
 mod logging;
 mod mobile_sync;
 mod process;
@@ -14,33 +15,43 @@ mod usage_index;
 
 use checkpoint::state::CheckpointState;
 use commands::agents::{
-    check_provider_runtime, cleanup_finished_processes, create_agent, delete_agent, execute_agent,
-    export_agent, export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
+    cancel_queued_run, check_provider_runtime, cleanup_finished_processes, create_agent, delete_agent,
+    clone_agent, delete_agents,
+    execute_agent, export_agent, export_all_agents, export_all_agents_to_file, export_agent_clipboard, export_agent_to_file, fetch_github_agent_content,
+    fetch_agent_gallery, fetch_github_agents, get_agent,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
-    get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
-    list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, load_agent_session_history, set_claude_binary_path,
-    stream_session_output, update_agent, AgentDb,
+    get_agent_run_output_tail, get_agent_run_stderr, get_live_session_output, get_run_output_tail, get_session_output, get_session_status,
+    import_agent, import_agent_from_file, import_agent_from_github, init_database,
+    kill_agent_session, kill_all_agent_sessions,
+    get_agent_run_jsonl_path, list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
+    list_deleted_agents, list_queued_runs, list_running_sessions, list_stuck_sessions,
+    get_running_session_resource_usage, load_agent_session_history, purge_agent,
+    get_run_note, restore_agent, retry_agent_run, run_agents_on_task, resume_forked_session, set_claude_binary_path, set_run_note, set_default_reasoning_effort,
+    smoke_test_agent, start_run_queue, stream_session_output,
+    update_agent, AgentDb, RateLimitRetryState, RunQueueState,
 };
 use commands::claude::{
     check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
-    clear_checkpoint_manager, create_checkpoint,
-    create_project, find_claude_md_files,
+    cancel_checkpoint, clear_checkpoint_manager, create_checkpoint,
+    cancel_claude_md_scan, continue_latest_project_session, create_project, find_claude_md_files,
     fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings,
     get_checkpoint_state_stats, get_claude_settings,
-    get_home_directory, get_hooks_config, get_project_sessions, get_recently_modified_files,
+    get_home_directory, get_hooks_config, get_project_footprint, get_project_sessions, get_recently_modified_files,
     get_session_timeline, get_system_prompt, list_checkpoints, list_detected_agents,
-    list_directory_contents, list_projects, load_provider_session_history,
+    list_project_checkpoints, export_checkpoint_bundle, import_checkpoint_bundle,
+    list_directory_contents, list_projects, list_recent_projects, record_project_open, load_provider_session_history,
     open_provider_session, read_claude_md_file, restore_checkpoint,
     save_claude_md_file, save_clipboard_image_attachment, save_claude_settings, save_system_prompt,
+    search_sessions, delete_session, remap_project,
     search_files, track_checkpoint_message, track_session_messages, update_checkpoint_settings,
     update_hooks_config, validate_hook_command,
 };
 use commands::agent_session::{
-    continue_agent_session, execute_agent_session, list_provider_capabilities,
+    continue_agent_session, execute_agent_session, export_provider_capabilities_schema,
+    list_provider_capabilities, list_supported_providers,
     resume_agent_session,
 };
+use commands::directory_watch::{unwatch_directory, watch_directory, DirectoryWatchState};
 use commands::hot_refresh::{
     hot_refresh_start, hot_refresh_stop, hot_refresh_update_paths, HotRefreshWatcherState,
 };
@@ -53,15 +64,16 @@ use commands::diagnostics::{open_external_terminal, run_session_startup_probe};
 use commands::mcp::{
     mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_get, mcp_get_server_status, mcp_list,
     mcp_read_project_config, mcp_remove, mcp_reset_project_choices, mcp_save_project_config,
-    mcp_serve, mcp_test_connection,
+    mcp_serve, mcp_test_connection, mcp_validate_all,
 };
+use commands::panic_stop::panic_stop;
 
 use commands::logging::log_frontend_event;
 use commands::proxy::{apply_proxy_settings, get_proxy_settings, save_proxy_settings};
 use commands::storage::{
-    storage_delete_row, storage_execute_sql, storage_insert_row, storage_list_tables,
-    storage_find_legacy_workspace_state, storage_read_table, storage_reset_database,
-    storage_update_row,
+    repair_agents_schema, storage_delete_row, storage_execute_sql, storage_insert_row,
+    storage_list_tables, storage_find_legacy_workspace_state, storage_read_table,
+    storage_reset_database, storage_update_row, verify_agents_schema,
 };
 use commands::title::generate_local_terminal_title;
 use commands::terminal::{
@@ -70,8 +82,9 @@ use commands::terminal::{
     EmbeddedTerminalState,
 };
 use commands::usage::{
-    cancel_usage_index_sync, get_session_stats, get_usage_by_date_range, get_usage_details,
-    get_usage_index_status, get_usage_stats, start_usage_index_sync,
+    cancel_usage_index_sync, get_session_model_mix, get_session_stats, get_today_usage,
+    get_usage_by_date_range, get_usage_details, get_usage_index_status, get_usage_stats,
+    rebuild_usage_index, start_usage_index_sync,
 };
 use process::ProcessRegistryState;
 use rusqlite::params;
@@ -84,15 +97,104 @@ use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
 
 const WINDOW_WIDTH_KEY: &str = "window_width";
 const WINDOW_HEIGHT_KEY: &str = "window_height";
+const WINDOW_MAXIMIZED_KEY: &str = "window_maximized";
+const WINDOW_FULLSCREEN_KEY: &str = "window_fullscreen";
+
+/// Main window state saved when the app closes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct PersistedWindowState {
+    /// Un-maximized size; not updated while maximized or fullscreen
+    size: Option<(f64, f64)>,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WindowRestoreAction {
+    SetSize(f64, f64),
+    Maximize,
+    Fullscreen,
+}
+
+/// Env var overriding how many times the dev server is probed
+#[cfg(debug_assertions)]
+const DEV_SERVER_MAX_ATTEMPTS_ENV: &str = "CODEINTERFACEX_DEV_SERVER_MAX_ATTEMPTS";
+/// Env var overriding the total time spent waiting for the dev server, in milliseconds
+#[cfg(debug_assertions)]
+const DEV_SERVER_TIMEOUT_MS_ENV: &str = "CODEINTERFACEX_DEV_SERVER_TIMEOUT_MS";
+#[cfg(any(debug_assertions, test))]
+const DEV_SERVER_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+#[cfg(any(debug_assertions, test))]
+const DEV_SERVER_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Delays to sleep between dev-server probes: exponential from `DEV_SERVER_BASE_DELAY`,
+/// capped at `DEV_SERVER_MAX_DELAY`, each jittered down to no less than half its nominal
+/// value, never decreasing, and trimmed so the total stays within `total_timeout`.
+#[cfg(any(debug_assertions, test))]
+fn dev_server_backoff_schedule(
+    max_attempts: u32,
+    total_timeout: std::time::Duration,
+    seed: u64,
+) -> Vec<std::time::Duration> {
+    let mut state = seed | 1;
+    let mut schedule = Vec::new();
+    let mut previous = std::time::Duration::ZERO;
+    let mut elapsed = std::time::Duration::ZERO;
+
+    for retry in 0..max_attempts.saturating_sub(1) {
+        if elapsed >= total_timeout {
+            break;
+        }
+        let nominal = DEV_SERVER_BASE_DELAY
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(DEV_SERVER_MAX_DELAY);
+
+        // xorshift64; good enough to spread out probes
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let half = nominal / 2;
+        let jitter = half.mul_f64((state % 1_000) as f64 / 999.0);
+
+        let delay = (half + jitter).max(previous).min(total_timeout - elapsed);
+        previous = delay;
+        elapsed += delay;
+        schedule.push(delay);
+    }
+
+    schedule
+}
 
 #[cfg(debug_assertions)]
 fn ensure_dev_server_reachable() -> Result<(), String> {
     let host = std::env::var("TAURI_DEV_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port = 1420_u16;
-    let max_attempts = 40_u32;
-    let retry_delay = std::time::Duration::from_millis(250);
-
-    for attempt in 1..=max_attempts {
+    let max_attempts = std::env::var(DEV_SERVER_MAX_ATTEMPTS_ENV)
+        .ok()
+        .and_then(|value| value.parse::<u32>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(40);
+    let total_timeout = std::env::var(DEV_SERVER_TIMEOUT_MS_ENV)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(std::time::Duration::from_secs(10));
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_nanos() as u64)
+        .unwrap_or_default();
+    let schedule = dev_server_backoff_schedule(max_attempts, total_timeout, seed);
+    tracing::info!(
+        "Waiting for dev server at http://{}:{}: up to {} attempts over {:?} (delays {:?})",
+        host,
+        port,
+        schedule.len() + 1,
+        total_timeout,
+        schedule
+    );
+
+    let attempts = schedule.len() as u32 + 1;
+    for attempt in 1..=attempts {
         match std::net::TcpStream::connect((host.as_str(), port)) {
             Ok(stream) => {
                 drop(stream);
@@ -101,18 +203,18 @@ fn ensure_dev_server_reachable() -> Result<(), String> {
                     host,
                     port,
                     attempt,
-                    max_attempts
+                    attempts
                 );
                 return Ok(());
             }
             Err(err) => {
-                if attempt == max_attempts {
+                if attempt == attempts {
                     return Err(format!(
                         "Dev server not reachable at http://{}:{} after {} attempts: {}",
-                        host, port, max_attempts, err
+                        host, port, attempts, err
                     ));
                 }
-                std::thread::sleep(retry_delay);
+                std::thread::sleep(schedule[attempt as usize - 1]);
             }
         }
     }
@@ -152,6 +254,59 @@ fn load_persisted_window_size(conn: &rusqlite::Connection) -> Option<(f64, f64)>
     Some((width, height))
 }
 
+fn load_persisted_window_state(conn: &rusqlite::Connection) -> PersistedWindowState {
+    let flag = |key: &str| {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        )
+        .map(|value| value == "true")
+        .unwrap_or(false)
+    };
+
+    PersistedWindowState {
+        size: load_persisted_window_size(conn),
+        maximized: flag(WINDOW_MAXIMIZED_KEY),
+        fullscreen: flag(WINDOW_FULLSCREEN_KEY),
+    }
+}
+
+/// Steps to bring the main window back to its saved state. The saved size is
+/// applied first so un-maximizing later returns to it; fullscreen wins over maximized.
+fn window_restore_actions(state: &PersistedWindowState) -> Vec<WindowRestoreAction> {
+    let mut actions = Vec::new();
+    if let Some((width, height)) = state.size {
+        actions.push(WindowRestoreAction::SetSize(width, height));
+    }
+    if state.fullscreen {
+        actions.push(WindowRestoreAction::Fullscreen);
+    } else if state.maximized {
+        actions.push(WindowRestoreAction::Maximize);
+    }
+    actions
+}
+
+fn persist_window_flags(app: &tauri::AppHandle, maximized: bool, fullscreen: bool) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        tracing::warn!("Failed to lock database while saving window state");
+        return;
+    };
+
+    for (key, value) in [
+        (WINDOW_MAXIMIZED_KEY, maximized),
+        (WINDOW_FULLSCREEN_KEY, fullscreen),
+    ] {
+        if let Err(err) = conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![key, value.to_string()],
+        ) {
+            tracing::warn!("Failed to persist {}: {}", key, err);
+        }
+    }
+}
+
 fn persist_window_size(app: &tauri::AppHandle, width: u32, height: u32) {
     if width == 0 || height == 0 {
         return;
@@ -181,7 +336,6 @@ fn persist_window_size(app: &tauri::AppHandle, width: u32, height: u32) {
 fn main() {
     // Initialize logger
     logging::init();
-    rebrand::archive_legacy_opcode_state();
 
     #[cfg(debug_assertions)]
     if let Err(err) = ensure_dev_server_reachable() {
@@ -198,8 +352,13 @@ fn main() {
             // Initialize agents database
             let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
 
+            // Bring over legacy opcode agents/settings before they're read below
+            if let Err(err) = rebrand::migrate_legacy_state_for(Some(&conn)) {
+                tracing::warn!("Legacy state migration skipped: {}", err);
+            }
+
             // Load and apply proxy settings from the database
-            let (proxy_settings, persisted_window_size) = {
+            let (proxy_settings, persisted_window_state) = {
                 // Directly query proxy settings from the database
                 let mut settings = commands::proxy::ProxySettings::default();
                 let keys = [
@@ -234,12 +393,15 @@ fn main() {
                 }
 
                 tracing::info!("Loaded proxy settings: enabled={}", settings.enabled);
-                (settings, load_persisted_window_size(&conn))
+                (settings, load_persisted_window_state(&conn))
             };
 
             // Apply the proxy settings
             apply_proxy_settings(&proxy_settings);
             app.manage(AgentDb(Mutex::new(conn)));
+            app.manage(RunQueueState::default());
+            app.manage(RateLimitRetryState::default());
+            start_run_queue(app.handle().clone());
 
             // Initialize checkpoint state
             let checkpoint_state = CheckpointState::new();
@@ -270,16 +432,23 @@ fn main() {
             app.manage(ProviderSessionProcessState::default());
             app.manage(UsageIndexState::default());
             app.manage(HotRefreshWatcherState::default());
+            app.manage(DirectoryWatchState::default());
             let mobile_sync_state = mobile_sync::MobileSyncServiceState::new("0.0.0.0", 8091);
             app.manage(mobile_sync_state.clone());
             mobile_sync::bootstrap_mobile_sync(app.handle().clone(), mobile_sync_state);
 
-            // Restore previous main window size if available.
-            if let Some((width, height)) = persisted_window_size {
-                if let Some(window) = app.get_webview_window("main") {
-                    if let Err(err) = window.set_size(Size::Logical(LogicalSize::new(width, height)))
-                    {
-                        tracing::warn!("Failed to restore persisted window size: {}", err);
+            // Restore previous main window size and maximized/fullscreen state.
+            if let Some(window) = app.get_webview_window("main") {
+                for action in window_restore_actions(&persisted_window_state) {
+                    let result = match action {
+                        WindowRestoreAction::SetSize(width, height) => {
+                            window.set_size(Size::Logical(LogicalSize::new(width, height)))
+                        }
+                        WindowRestoreAction::Maximize => window.maximize(),
+                        WindowRestoreAction::Fullscreen => window.set_fullscreen(true),
+                    };
+                    if let Err(err) = result {
+                        tracing::warn!("Failed to restore window state ({:?}): {}", action, err);
                     }
                 }
             }
@@ -292,6 +461,7 @@ fn main() {
                         if let Some(window) = app_handle.get_webview_window("main") {
                             let is_maximized = window.is_maximized().unwrap_or(false);
                             let is_fullscreen = window.is_fullscreen().unwrap_or(false);
+                            persist_window_flags(&app_handle, is_maximized, is_fullscreen);
                             if is_maximized || is_fullscreen {
                                 return;
                             }
@@ -348,6 +518,11 @@ fn main() {
             list_projects,
             create_project,
             get_project_sessions,
+            continue_latest_project_session,
+            get_project_footprint,
+            search_sessions,
+            delete_session,
+            remap_project,
             get_home_directory,
             get_claude_settings,
             open_provider_session,
@@ -356,6 +531,7 @@ fn main() {
             save_system_prompt,
             save_claude_settings,
             find_claude_md_files,
+            cancel_claude_md_scan,
             read_claude_md_file,
             save_claude_md_file,
             save_clipboard_image_attachment,
@@ -367,6 +543,8 @@ fn main() {
             list_running_provider_sessions,
             get_provider_session_output,
             list_directory_contents,
+            record_project_open,
+            list_recent_projects,
             search_files,
             get_recently_modified_files,
             get_hooks_config,
@@ -374,9 +552,14 @@ fn main() {
             validate_hook_command,
             // Checkpoint Management
             create_checkpoint,
+            cancel_checkpoint,
             restore_checkpoint,
             list_checkpoints,
+            list_project_checkpoints,
+            export_checkpoint_bundle,
+            import_checkpoint_bundle,
             fork_from_checkpoint,
+            resume_forked_session,
             get_session_timeline,
             update_checkpoint_settings,
             get_checkpoint_diff,
@@ -392,30 +575,57 @@ fn main() {
             create_agent,
             update_agent,
             delete_agent,
+            delete_agents,
+            list_deleted_agents,
+            restore_agent,
+            purge_agent,
             get_agent,
+            clone_agent,
             execute_agent,
+            smoke_test_agent,
+            set_default_reasoning_effort,
             check_provider_runtime,
             list_provider_capabilities,
+            list_supported_providers,
+            export_provider_capabilities_schema,
             list_agent_runs,
+            list_queued_runs,
+            cancel_queued_run,
+            set_run_note,
+            get_run_note,
             get_agent_run,
             list_agent_runs_with_metrics,
             get_agent_run_with_real_time_metrics,
             list_running_sessions,
+            list_stuck_sessions,
+            get_running_session_resource_usage,
             kill_agent_session,
+            kill_all_agent_sessions,
             get_session_status,
             cleanup_finished_processes,
             get_session_output,
+            get_agent_run_jsonl_path,
             get_live_session_output,
+            get_run_output_tail,
+            get_agent_run_output_tail,
+            get_agent_run_stderr,
+            retry_agent_run,
+            run_agents_on_task,
+            rebrand::migrate_legacy_state,
             stream_session_output,
             load_agent_session_history,
             get_claude_binary_path,
             set_claude_binary_path,
             list_claude_installations,
             export_agent,
+            export_agent_clipboard,
             export_agent_to_file,
+            export_all_agents,
+            export_all_agents_to_file,
             import_agent,
             import_agent_from_file,
             fetch_github_agents,
+            fetch_agent_gallery,
             fetch_github_agent_content,
             import_agent_from_github,
             // Usage & Analytics
@@ -426,6 +636,9 @@ fn main() {
             get_usage_index_status,
             start_usage_index_sync,
             cancel_usage_index_sync,
+            rebuild_usage_index,
+            get_today_usage,
+            get_session_model_mix,
             // MCP (Model Context Protocol)
             mcp_add,
             mcp_list,
@@ -435,6 +648,7 @@ fn main() {
             mcp_add_from_claude_desktop,
             mcp_serve,
             mcp_test_connection,
+            mcp_validate_all,
             mcp_reset_project_choices,
             mcp_get_server_status,
             mcp_read_project_config,
@@ -448,6 +662,8 @@ fn main() {
             storage_execute_sql,
             storage_find_legacy_workspace_state,
             storage_reset_database,
+            verify_agents_schema,
+            repair_agents_schema,
             // Slash Commands
             commands::slash_commands::slash_commands_list,
             commands::slash_commands::slash_command_get,
@@ -473,16 +689,115 @@ fn main() {
             mobile_sync::mobile_sync_get_status,
             mobile_sync::mobile_sync_set_enabled,
             mobile_sync::mobile_sync_set_public_host,
+            mobile_sync::mobile_sync_set_status_interval,
+            mobile_sync::mobile_sync_set_event_retention,
             mobile_sync::mobile_sync_publish_snapshot,
             mobile_sync::mobile_sync_publish_events,
             mobile_sync::mobile_sync_start_pairing,
             mobile_sync::mobile_sync_list_devices,
             mobile_sync::mobile_sync_revoke_device,
+            mobile_sync::mobile_sync_revoke_all_devices,
             hot_refresh_start,
             hot_refresh_stop,
             hot_refresh_update_paths,
+            watch_directory,
+            unwatch_directory,
+            panic_stop,
             log_frontend_event,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn maximized_window_is_restored_maximized() {
+        let maximized = PersistedWindowState {
+            size: Some((1200.0, 800.0)),
+            maximized: true,
+            fullscreen: false,
+        };
+        assert_eq!(
+            window_restore_actions(&maximized),
+            vec![
+                WindowRestoreAction::SetSize(1200.0, 800.0),
+                WindowRestoreAction::Maximize
+            ]
+        );
+
+        let fullscreen = PersistedWindowState {
+            size: None,
+            maximized: true,
+            fullscreen: true,
+        };
+        assert_eq!(
+            window_restore_actions(&fullscreen),
+            vec![WindowRestoreAction::Fullscreen]
+        );
+
+        assert!(window_restore_actions(&PersistedWindowState::default()).is_empty());
+    }
+
+    #[test]
+    fn window_state_round_trips_through_app_settings() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        for (key, value) in [
+            (WINDOW_WIDTH_KEY, "1280"),
+            (WINDOW_HEIGHT_KEY, "720"),
+            (WINDOW_MAXIMIZED_KEY, "true"),
+        ] {
+            conn.execute(
+                "INSERT INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            load_persisted_window_state(&conn),
+            PersistedWindowState {
+                size: Some((1280.0, 720.0)),
+                maximized: true,
+                fullscreen: false,
+            }
+        );
+    }
+
+    #[test]
+    fn dev_server_backoff_is_monotonic_capped_and_jittered() {
+        for seed in [0, 1, 42, u64::MAX] {
+            let schedule = dev_server_backoff_schedule(40, Duration::from_secs(600), seed);
+            assert_eq!(schedule.len(), 39);
+            for (retry, delay) in schedule.iter().enumerate() {
+                let nominal = DEV_SERVER_BASE_DELAY
+                    .saturating_mul(1u32.checked_shl(retry as u32).unwrap_or(u32::MAX))
+                    .min(DEV_SERVER_MAX_DELAY);
+                assert!(*delay >= nominal / 2 && *delay <= nominal, "{:?}", schedule);
+            }
+            assert!(schedule.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+
+        // Different seeds spread probes differently
+        assert_ne!(
+            dev_server_backoff_schedule(10, Duration::from_secs(60), 1),
+            dev_server_backoff_schedule(10, Duration::from_secs(60), 2)
+        );
+    }
+
+    #[test]
+    fn dev_server_backoff_respects_total_timeout() {
+        let timeout = Duration::from_millis(1_500);
+        let schedule = dev_server_backoff_schedule(40, timeout, 7);
+        assert_eq!(schedule.iter().sum::<Duration>(), timeout);
+        assert!(dev_server_backoff_schedule(1, timeout, 7).is_empty());
+    }
+}
@@ -1,12 +1,15 @@
 use axum::http::HeaderMap;
 use chrono::{DateTime, Utc};
+use rusqlite::Connection;
 use sha2::{Digest, Sha256};
 use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
 use crate::commands::agents::AgentDb;
 
-use super::protocol::{PROTOCOL_VERSION, VERSION_HEADER};
+use super::protocol::{
+    is_supported_version, MAX_SUPPORTED_VERSION, MIN_SUPPORTED_VERSION, VERSION_HEADER,
+};
 
 #[derive(Debug, Clone)]
 pub struct AuthenticatedDevice {
@@ -25,10 +28,10 @@ pub fn verify_protocol_version(headers: &HeaderMap) -> Result<(), String> {
         .parse::<u8>()
         .map_err(|_| format!("Invalid {} header", VERSION_HEADER))?;
 
-    if parsed != PROTOCOL_VERSION {
+    if !is_supported_version(parsed) {
         return Err(format!(
-            "Unsupported protocol version {} (expected {})",
-            parsed, PROTOCOL_VERSION
+            "Unsupported protocol version {} (supported {}-{})",
+            parsed, MIN_SUPPORTED_VERSION, MAX_SUPPORTED_VERSION
         ));
     }
 
@@ -66,6 +69,14 @@ pub fn authenticate_token(app: &AppHandle, token: &str) -> Result<AuthenticatedD
         .lock()
         .map_err(|error| format!("Failed to lock database: {}", error))?;
 
+    authenticate_token_with_conn(&conn, token)
+}
+
+/// Look up a raw device token by its hash, rejecting unknown and revoked devices
+pub fn authenticate_token_with_conn(
+    conn: &Connection,
+    token: &str,
+) -> Result<AuthenticatedDevice, String> {
     let token_hash = hash_token(token);
 
     let mut statement = conn
@@ -113,6 +124,19 @@ mod tests {
     use axum::http::HeaderValue;
 
     use super::*;
+    use crate::commands::agents::init_schema;
+
+    fn device_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO mobile_devices (id, device_name, token_hash, revoked)
+             VALUES ('device-1', 'Phone', ?1, 0), ('device-2', 'Old tablet', ?2, 1)",
+            [hash_token("valid-token"), hash_token("revoked-token")],
+        )
+        .unwrap();
+        conn
+    }
 
     #[test]
     fn extract_bearer_token_handles_valid_value() {
@@ -140,4 +164,38 @@ mod tests {
         assert_eq!(hash_a, hash_b);
         assert_ne!(hash_a, hash_c);
     }
+
+    #[test]
+    fn authenticate_token_accepts_active_device() {
+        let conn = device_conn();
+
+        let device = authenticate_token_with_conn(&conn, "valid-token").expect("auth should pass");
+        assert_eq!(device.device_id, "device-1");
+        assert_eq!(device.device_name, "Phone");
+
+        let last_seen: Option<String> = conn
+            .query_row(
+                "SELECT last_seen_at FROM mobile_devices WHERE id = 'device-1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(last_seen.is_some());
+    }
+
+    #[test]
+    fn authenticate_token_rejects_revoked_device() {
+        let conn = device_conn();
+
+        let error = authenticate_token_with_conn(&conn, "revoked-token").expect_err("auth should fail");
+        assert_eq!(error, "Device has been revoked");
+    }
+
+    #[test]
+    fn authenticate_token_rejects_unknown_token() {
+        let conn = device_conn();
+
+        let error = authenticate_token_with_conn(&conn, "unknown-token").expect_err("auth should fail");
+        assert_eq!(error, "Authentication failed");
+    }
 }
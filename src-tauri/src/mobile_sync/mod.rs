@@ -9,16 +9,20 @@ use std::sync::{
     Arc,
 };
 
+use rusqlite::Connection;
 use serde::Serialize;
 use tauri::{AppHandle, Manager, State};
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use uuid::Uuid;
 
 use crate::commands::agents::AgentDb;
 
 use self::{
     auth::{generate_pairing_code, generate_opaque_token, hash_token},
-    protocol::{PairingPayloadV1, PublishEventInput, SnapshotV1, PROTOCOL_VERSION},
+    protocol::{
+        PairingPayloadV1, PublishEventInput, SnapshotV1, MAX_SUPPORTED_VERSION,
+        MIN_SUPPORTED_VERSION, PROTOCOL_VERSION,
+    },
     state_cache::MobileSyncCache,
 };
 
@@ -29,6 +33,7 @@ pub struct MobileSyncServiceState {
     pub port: u16,
     pub public_host: Arc<RwLock<String>>,
     server_started: Arc<AtomicBool>,
+    shutdown: Arc<Notify>,
 }
 
 impl MobileSyncServiceState {
@@ -39,6 +44,7 @@ impl MobileSyncServiceState {
             port,
             public_host: Arc::new(RwLock::new("127.0.0.1".to_string())),
             server_started: Arc::new(AtomicBool::new(false)),
+            shutdown: Arc::new(Notify::new()),
         }
     }
 
@@ -51,6 +57,24 @@ impl MobileSyncServiceState {
     pub fn mark_server_stopped(&self) {
         self.server_started.store(false, Ordering::SeqCst);
     }
+
+    pub fn is_server_started(&self) -> bool {
+        self.server_started.load(Ordering::SeqCst)
+    }
+
+    /// Ask a running server to shut down gracefully. Returns false when no
+    /// server was running.
+    pub fn request_server_shutdown(&self) -> bool {
+        if !self.is_server_started() {
+            return false;
+        }
+        self.shutdown.notify_waiters();
+        true
+    }
+
+    pub(crate) async fn wait_for_shutdown(&self) {
+        self.shutdown.notified().await;
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -66,6 +90,8 @@ pub struct MobileSyncStatus {
     pub tailscale_ip: Option<String>,
     pub connected_clients: usize,
     pub sequence: u64,
+    pub min_supported_version: u8,
+    pub max_supported_version: u8,
 }
 
 #[derive(Debug, Serialize)]
@@ -96,12 +122,45 @@ pub fn bootstrap_mobile_sync(app: AppHandle, state: MobileSyncServiceState) {
         *host_guard = public_host;
     }
 
+    match snapshot_store_path(&app).and_then(|path| state.cache.attach_snapshot_store(path)) {
+        Ok(true) => tracing::info!(
+            "restored mobile sync snapshot at sequence {}",
+            state.cache.current_sequence()
+        ),
+        Ok(false) => {}
+        Err(error) => tracing::warn!("failed to restore mobile sync snapshot: {}", error),
+    }
+
     state.cache.set_enabled(enabled);
+    tauri::async_runtime::spawn(prune_event_history(app.clone(), state.cache.clone()));
     if enabled {
         ensure_server_running(app, state);
     }
 }
 
+/// Periodically drop buffered events older than the retention window
+async fn prune_event_history(app: AppHandle, cache: MobileSyncCache) {
+    let mut ticker = tokio::time::interval(EVENT_PRUNE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let cutoff = chrono::Utc::now() - read_event_retention(&app);
+        let pruned = cache.prune_events_before(cutoff);
+        if pruned > 0 {
+            tracing::debug!("pruned {} mobile sync events past retention", pruned);
+        }
+    }
+}
+
+fn snapshot_store_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    std::fs::create_dir_all(&app_dir)
+        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    Ok(app_dir.join("mobile_sync_snapshot.json"))
+}
+
 pub fn ensure_server_running(app: AppHandle, state: MobileSyncServiceState) {
     if !state.mark_server_started() {
         return;
@@ -110,8 +169,8 @@ pub fn ensure_server_running(app: AppHandle, state: MobileSyncServiceState) {
     tauri::async_runtime::spawn(async move {
         if let Err(error) = server::run_mobile_sync_server(app.clone(), state.clone()).await {
             tracing::error!("mobile sync server failed: {}", error);
-            state.mark_server_stopped();
         }
+        state.mark_server_stopped();
     });
 }
 
@@ -186,6 +245,8 @@ async fn build_status(state: &MobileSyncServiceState) -> MobileSyncStatus {
         tailscale_ip: tailscale_ip(),
         connected_clients: state.cache.connected_clients(),
         sequence: state.cache.current_sequence(),
+        min_supported_version: MIN_SUPPORTED_VERSION,
+        max_supported_version: MAX_SUPPORTED_VERSION,
     }
 }
 
@@ -230,6 +291,65 @@ pub async fn mobile_sync_set_public_host(
     Ok(build_status(&state).await)
 }
 
+/// Seconds between `sync.status` messages on each mobile WebSocket
+pub const STATUS_INTERVAL_SETTING: &str = "status_interval_secs";
+pub const DEFAULT_STATUS_INTERVAL_SECS: u64 = 15;
+
+pub fn read_status_interval(app: &AppHandle) -> std::time::Duration {
+    let seconds = read_mobile_sync_setting(app, STATUS_INTERVAL_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_STATUS_INTERVAL_SECS);
+    std::time::Duration::from_secs(seconds)
+}
+
+/// Change the status push interval; applies to connections opened afterwards
+#[tauri::command]
+pub async fn mobile_sync_set_status_interval(
+    app: AppHandle,
+    state: State<'_, MobileSyncServiceState>,
+    seconds: u64,
+) -> Result<MobileSyncStatus, String> {
+    if seconds == 0 {
+        return Err("Status interval must be at least one second".to_string());
+    }
+
+    write_mobile_sync_setting(&app, STATUS_INTERVAL_SETTING, &seconds.to_string())?;
+    Ok(build_status(&state).await)
+}
+
+/// Minutes buffered events are kept for replay to reconnecting clients
+pub const EVENT_RETENTION_SETTING: &str = "event_retention_minutes";
+pub const DEFAULT_EVENT_RETENTION_MINUTES: i64 = 60;
+const EVENT_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub fn read_event_retention(app: &AppHandle) -> chrono::Duration {
+    let minutes = read_mobile_sync_setting(app, EVENT_RETENTION_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_EVENT_RETENTION_MINUTES);
+    chrono::Duration::minutes(minutes)
+}
+
+/// Change how long events are kept; the next prune applies it
+#[tauri::command]
+pub async fn mobile_sync_set_event_retention(
+    app: AppHandle,
+    state: State<'_, MobileSyncServiceState>,
+    minutes: i64,
+) -> Result<MobileSyncStatus, String> {
+    if minutes <= 0 {
+        return Err("Event retention must be at least one minute".to_string());
+    }
+
+    write_mobile_sync_setting(&app, EVENT_RETENTION_SETTING, &minutes.to_string())?;
+    Ok(build_status(&state).await)
+}
+
 #[tauri::command]
 pub async fn mobile_sync_publish_snapshot(
     state: State<'_, MobileSyncServiceState>,
@@ -243,10 +363,11 @@ pub async fn mobile_sync_publish_events(
     state: State<'_, MobileSyncServiceState>,
     events: Vec<PublishEventInput>,
 ) -> Result<Vec<protocol::EventEnvelopeV1>, String> {
-    let envelopes = events
-        .iter()
-        .map(|event| state.cache.publish_event(&event.event_type, event.payload.clone()))
-        .collect::<Vec<_>>();
+    let envelopes = state.cache.publish_events(
+        events
+            .into_iter()
+            .map(|event| (event.event_type, event.payload)),
+    );
 
     Ok(envelopes)
 }
@@ -280,6 +401,8 @@ pub async fn mobile_sync_start_pairing(
         host,
         port: state.port,
         expires_at,
+        min_supported_version: MIN_SUPPORTED_VERSION,
+        max_supported_version: MAX_SUPPORTED_VERSION,
     })
 }
 
@@ -333,6 +456,66 @@ pub async fn mobile_sync_revoke_device(app: AppHandle, device_id: String) -> Res
     Ok(())
 }
 
+/// What `mobile_sync_revoke_all_devices` invalidated
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokeAllDevicesResult {
+    pub revoked_devices: usize,
+    pub deleted_pairing_codes: usize,
+    pub dropped_connections: usize,
+}
+
+fn revoke_all_devices_with_conn(
+    conn: &Connection,
+    cache: &MobileSyncCache,
+    delete_pairing_codes: bool,
+) -> Result<RevokeAllDevicesResult, String> {
+    let revoked_devices = conn
+        .execute(
+            "UPDATE mobile_devices SET revoked = 1, updated_at = CURRENT_TIMESTAMP
+             WHERE revoked = 0",
+            [],
+        )
+        .map_err(|error| format!("Failed to revoke devices: {}", error))?;
+
+    let deleted_pairing_codes = if delete_pairing_codes {
+        conn.execute("DELETE FROM mobile_pairing_codes WHERE claimed = 0", [])
+            .map_err(|error| format!("Failed to delete pairing codes: {}", error))?
+    } else {
+        0
+    };
+
+    Ok(RevokeAllDevicesResult {
+        revoked_devices,
+        deleted_pairing_codes,
+        dropped_connections: cache.disconnect_all_clients(),
+    })
+}
+
+/// Revoke every paired device and close their connections, e.g. after a
+/// suspected breach. Unclaimed pairing codes are deleted too when asked.
+#[tauri::command]
+pub async fn mobile_sync_revoke_all_devices(
+    app: AppHandle,
+    state: State<'_, MobileSyncServiceState>,
+    delete_pairing_codes: Option<bool>,
+) -> Result<RevokeAllDevicesResult, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db
+        .0
+        .lock()
+        .map_err(|error| format!("Failed to lock database: {}", error))?;
+
+    let result =
+        revoke_all_devices_with_conn(&conn, &state.cache, delete_pairing_codes.unwrap_or(false))?;
+    tracing::warn!(
+        "revoked {} mobile devices and dropped {} connections",
+        result.revoked_devices,
+        result.dropped_connections
+    );
+    Ok(result)
+}
+
 pub fn create_device_token(app: &AppHandle, device_name: &str) -> Result<(String, String), String> {
     let device_id = Uuid::new_v4().to_string();
     let raw_token = generate_opaque_token();
@@ -353,3 +536,74 @@ pub fn create_device_token(app: &AppHandle, device_name: &str) -> Result<(String
 
     Ok((device_id, raw_token))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::agents::init_schema;
+
+    #[test]
+    fn revoke_all_devices_revokes_everything_and_drops_connections() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO mobile_devices (id, device_name, token_hash, revoked)
+             VALUES ('phone', 'Phone', 'hash-1', 0), ('tablet', 'Tablet', 'hash-2', 0),
+                    ('old', 'Old phone', 'hash-3', 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO mobile_pairing_codes (code, expires_at, claimed)
+             VALUES ('111111', '2099-01-01T00:00:00Z', 0), ('222222', '2099-01-01T00:00:00Z', 1)",
+            [],
+        )
+        .unwrap();
+
+        let cache = MobileSyncCache::new();
+        let mut first_connection = cache.subscribe_disconnects();
+        let mut second_connection = cache.subscribe_disconnects();
+
+        let result = revoke_all_devices_with_conn(&conn, &cache, true).unwrap();
+        assert_eq!(
+            result,
+            RevokeAllDevicesResult {
+                revoked_devices: 2,
+                deleted_pairing_codes: 1,
+                dropped_connections: 2,
+            }
+        );
+
+        let active: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM mobile_devices WHERE revoked = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(active, 0);
+        let codes: Vec<String> = conn
+            .prepare("SELECT code FROM mobile_pairing_codes")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(codes, vec!["222222"]);
+
+        assert!(first_connection.try_recv().is_ok());
+        assert!(second_connection.try_recv().is_ok());
+
+        // With no connections open and pairing codes kept, only the count changes
+        drop((first_connection, second_connection));
+        let again = revoke_all_devices_with_conn(&conn, &cache, false).unwrap();
+        assert_eq!(
+            again,
+            RevokeAllDevicesResult {
+                revoked_devices: 0,
+                deleted_pairing_codes: 0,
+                dropped_connections: 0,
+            }
+        );
+    }
+}
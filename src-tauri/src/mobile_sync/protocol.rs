@@ -3,6 +3,24 @@ use serde_json::Value;
 
 pub const PROTOCOL_VERSION: u8 = 1;
 pub const VERSION_HEADER: &str = "x-codeinterfacex-sync-version";
+/// Oldest client protocol version the desktop still speaks
+pub const MIN_SUPPORTED_VERSION: u8 = 1;
+/// Newest client protocol version the desktop understands
+pub const MAX_SUPPORTED_VERSION: u8 = PROTOCOL_VERSION;
+/// Close reason sent to websocket clients outside the supported range
+pub const UNSUPPORTED_VERSION_REASON: &str = "unsupported_version";
+
+pub fn is_supported_version(version: u8) -> bool {
+    (MIN_SUPPORTED_VERSION..=MAX_SUPPORTED_VERSION).contains(&version)
+}
+
+fn default_min_supported_version() -> u8 {
+    MIN_SUPPORTED_VERSION
+}
+
+fn default_max_supported_version() -> u8 {
+    MAX_SUPPORTED_VERSION
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -54,6 +72,10 @@ pub struct PairingPayloadV1 {
     pub host: String,
     pub port: u16,
     pub expires_at: String,
+    #[serde(default = "default_min_supported_version")]
+    pub min_supported_version: u8,
+    #[serde(default = "default_max_supported_version")]
+    pub max_supported_version: u8,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -84,6 +106,8 @@ pub struct DeviceRevokeRequest {
 pub struct WsQuery {
     pub since: Option<u64>,
     pub token: Option<String>,
+    /// Protocol version declared by clients that can't set the version header
+    pub version: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,4 +140,21 @@ mod tests {
         assert_eq!(serialized["eventType"], "workspace.updated");
         assert_eq!(serialized["payload"]["workspaceId"], "workspace-123");
     }
+
+    #[test]
+    fn pairing_payload_without_version_range_uses_defaults() {
+        let payload: PairingPayloadV1 = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "pairCode": "ABC123",
+            "host": "127.0.0.1",
+            "port": 8091,
+            "expiresAt": "2025-01-01T00:00:00Z",
+        }))
+        .expect("payload must deserialize");
+
+        assert_eq!(payload.min_supported_version, MIN_SUPPORTED_VERSION);
+        assert_eq!(payload.max_supported_version, MAX_SUPPORTED_VERSION);
+        assert!(is_supported_version(PROTOCOL_VERSION));
+        assert!(!is_supported_version(MAX_SUPPORTED_VERSION + 1));
+    }
 }
@@ -1,13 +1,15 @@
-use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Request;
 use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::{
     extract::{Query, State as AxumState},
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
 use chrono::Utc;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use serde_json::json;
 use tauri::{AppHandle, Manager};
 use tokio::net::TcpListener;
@@ -17,12 +19,15 @@ use crate::commands::agents::AgentDb;
 use super::actions::dispatch_action_to_desktop;
 use super::auth::{
     authenticate_token, extract_bearer_token, parse_expiration, verify_protocol_version,
+    AuthenticatedDevice,
 };
 use super::protocol::{
     ActionRequestV1, ActionResultV1, DeviceRevokeRequest, PairClaimRequest, PairClaimResponse,
-    PairingPayloadV1, WsQuery, PROTOCOL_VERSION,
+    PairingPayloadV1, WsQuery, MAX_SUPPORTED_VERSION, MIN_SUPPORTED_VERSION, PROTOCOL_VERSION,
+    UNSUPPORTED_VERSION_REASON, VERSION_HEADER,
 };
-use super::{create_device_token, MobileSyncServiceState};
+use super::state_cache::MobileSyncCache;
+use super::{create_device_token, read_status_interval, MobileSyncServiceState};
 
 #[derive(Clone)]
 struct MobileServerAppState {
@@ -48,21 +53,33 @@ pub async fn run_mobile_sync_server(
 ) -> Result<(), Box<dyn std::error::Error>> {
     let bind_host = service.bind_host.clone();
     let port = service.port;
+    let shutdown_service = service.clone();
     let state = MobileServerAppState { app, service };
 
+    // The websocket route authenticates itself since it also accepts query tokens
+    let device_routes = Router::new()
+        .route("/mobile/v1/snapshot", get(snapshot_handler))
+        .route("/mobile/v1/action", post(action_handler))
+        .route("/mobile/v1/device/revoke", post(device_revoke_handler))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_device_auth,
+        ));
+
     let router = Router::new()
         .route("/mobile/v1/health", get(health_handler))
-        .route("/mobile/v1/snapshot", get(snapshot_handler))
         .route("/mobile/v1/ws", get(websocket_handler))
-        .route("/mobile/v1/action", post(action_handler))
         .route("/mobile/v1/pair/start", post(pair_start_handler))
         .route("/mobile/v1/pair/claim", post(pair_claim_handler))
-        .route("/mobile/v1/device/revoke", post(device_revoke_handler))
+        .merge(device_routes)
         .with_state(state);
 
     let listener = TcpListener::bind(format!("{}:{}", bind_host, port)).await?;
     tracing::info!("mobile sync server listening on {}:{}", bind_host, port);
-    axum::serve(listener, router).await?;
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { shutdown_service.wait_for_shutdown().await })
+        .await?;
+    tracing::info!("mobile sync server stopped");
     Ok(())
 }
 
@@ -95,16 +112,16 @@ fn verify_version(headers: &HeaderMap) -> Result<(), (StatusCode, Json<serde_jso
 fn authenticate_request(
     app: &AppHandle,
     headers: &HeaderMap,
-) -> Result<super::auth::AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)> {
     authenticate_request_with(headers, |token| authenticate_token(app, token))
 }
 
 fn authenticate_request_with<F>(
     headers: &HeaderMap,
     mut authenticate_fn: F,
-) -> Result<super::auth::AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)>
+) -> Result<AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)>
 where
-    F: FnMut(&str) -> Result<super::auth::AuthenticatedDevice, String>,
+    F: FnMut(&str) -> Result<AuthenticatedDevice, String>,
 {
     verify_version(headers)?;
 
@@ -114,11 +131,30 @@ where
     authenticate_fn(&token).map_err(|error| api_error(StatusCode::UNAUTHORIZED, error))
 }
 
+/// Route layer for device-scoped endpoints: rejects unknown or revoked bearer
+/// tokens with 401 and attaches the `AuthenticatedDevice` to the request.
+async fn require_device_auth(
+    AxumState(state): AxumState<MobileServerAppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Err(error) = require_enabled(&state) {
+        return error.into_response();
+    }
+    match authenticate_request(&state.app, request.headers()) {
+        Ok(device) => {
+            request.extensions_mut().insert(device);
+            next.run(request).await
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
 fn authenticate_ws_request(
     app: &AppHandle,
     headers: &HeaderMap,
     query: &WsQuery,
-) -> Result<super::auth::AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)> {
     authenticate_ws_request_with(headers, query, |token| authenticate_token(app, token))
 }
 
@@ -156,18 +192,66 @@ fn authenticate_ws_request_with<F>(
     headers: &HeaderMap,
     query: &WsQuery,
     mut authenticate_fn: F,
-) -> Result<super::auth::AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)>
+) -> Result<AuthenticatedDevice, (StatusCode, Json<serde_json::Value>)>
 where
-    F: FnMut(&str) -> Result<super::auth::AuthenticatedDevice, String>,
+    F: FnMut(&str) -> Result<AuthenticatedDevice, String>,
 {
     let selection = select_ws_auth_token(headers, query)?;
     authenticate_fn(&selection.token).map_err(|error| api_error(StatusCode::UNAUTHORIZED, error))
 }
 
+/// Version a websocket client declared, via the version header or, for
+/// query-token clients, the `version` query parameter
+fn declared_ws_version(headers: &HeaderMap, query: &WsQuery) -> Option<u8> {
+    headers
+        .get(VERSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u8>().ok())
+        .or(query.version)
+}
+
+/// Declared version when it falls outside the supported range
+fn unsupported_ws_version(headers: &HeaderMap, query: &WsQuery) -> Option<u8> {
+    declared_ws_version(headers, query)
+        .filter(|version| !super::protocol::is_supported_version(*version))
+}
+
+fn unsupported_version_close_frame(version: u8) -> CloseFrame {
+    CloseFrame {
+        code: close_code::PROTOCOL,
+        reason: format!(
+            "{}: client {} (supported {}-{})",
+            UNSUPPORTED_VERSION_REASON, version, MIN_SUPPORTED_VERSION, MAX_SUPPORTED_VERSION
+        )
+        .into(),
+    }
+}
+
 fn requires_resnapshot(since: u64, current_sequence: u64) -> bool {
     since.saturating_add(1) < current_sequence
 }
 
+/// Periodic `sync.status` message so clients can spot a degraded server
+/// without polling the HTTP status endpoint
+fn build_status_envelope(cache: &MobileSyncCache) -> super::protocol::EventEnvelopeV1 {
+    super::protocol::EventEnvelopeV1 {
+        version: PROTOCOL_VERSION,
+        sequence: cache.current_sequence(),
+        event_type: "sync.status".to_string(),
+        generated_at: Utc::now().to_rfc3339(),
+        payload: json!({
+            "connectedClients": cache.connected_clients(),
+            "sequence": cache.current_sequence(),
+            "enabled": cache.is_enabled(),
+        }),
+    }
+}
+
+/// Interval whose first tick is one period away rather than immediate
+fn status_interval(period: std::time::Duration) -> tokio::time::Interval {
+    tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+}
+
 fn action_dispatch_error(error: String) -> (StatusCode, Json<serde_json::Value>) {
     api_error(StatusCode::INTERNAL_SERVER_ERROR, error)
 }
@@ -187,12 +271,8 @@ async fn health_handler(
 }
 
 async fn snapshot_handler(
-    headers: HeaderMap,
     AxumState(state): AxumState<MobileServerAppState>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    require_enabled(&state)?;
-    let _device = authenticate_request(&state.app, &headers)?;
-
     let snapshot = match state.service.cache.latest_snapshot().await {
         Some(snapshot) => snapshot,
         None => {
@@ -215,13 +295,10 @@ async fn snapshot_handler(
 }
 
 async fn action_handler(
-    headers: HeaderMap,
     AxumState(state): AxumState<MobileServerAppState>,
+    Extension(device): Extension<AuthenticatedDevice>,
     Json(request): Json<ActionRequestV1>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    require_enabled(&state)?;
-    let device = authenticate_request(&state.app, &headers)?;
-
     if request.version != PROTOCOL_VERSION {
         return Err(api_error(
             StatusCode::BAD_REQUEST,
@@ -291,6 +368,8 @@ async fn pair_start_handler(
         host,
         port: state.service.port,
         expires_at,
+        min_supported_version: MIN_SUPPORTED_VERSION,
+        max_supported_version: MAX_SUPPORTED_VERSION,
     };
 
     Ok(Json(json!({
@@ -363,13 +442,9 @@ async fn pair_claim_handler(
 }
 
 async fn device_revoke_handler(
-    headers: HeaderMap,
     AxumState(state): AxumState<MobileServerAppState>,
     Json(request): Json<DeviceRevokeRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    require_enabled(&state)?;
-    let _device = authenticate_request(&state.app, &headers)?;
-
     let db = state.app.state::<AgentDb>();
     let conn = db
         .0
@@ -398,6 +473,17 @@ async fn websocket_handler(
         return error.into_response();
     }
 
+    // Upgrade first so the client gets a close frame it can act on instead of
+    // a bare HTTP error
+    if let Some(version) = unsupported_ws_version(&headers, &query) {
+        tracing::warn!("rejecting mobile sync websocket with protocol version {}", version);
+        return ws.on_upgrade(move |mut socket| async move {
+            let _ = socket
+                .send(Message::Close(Some(unsupported_version_close_frame(version))))
+                .await;
+        });
+    }
+
     if let Err(error) = authenticate_ws_request(&state.app, &headers, &query) {
         return error.into_response();
     }
@@ -406,17 +492,52 @@ async fn websocket_handler(
 }
 
 async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u64) {
-    let service = state.service.clone();
-    service.cache.increment_clients();
+    let (sender, receiver) = socket.split();
+    let status_period = read_status_interval(&state.app);
+    serve_websocket(sender, receiver, &state.service.cache, since, status_period).await;
+}
+
+/// Stream events, heartbeats and status messages to one client until it closes
+/// the socket or its device is revoked. Takes the socket halves separately so
+/// tests can drive it over in-memory channels.
+async fn serve_websocket<Tx, Rx, E>(
+    mut sender: Tx,
+    mut receiver: Rx,
+    cache: &MobileSyncCache,
+    since: u64,
+    status_period: std::time::Duration,
+) where
+    Tx: Sink<Message> + Unpin,
+    Rx: Stream<Item = Result<Message, E>> + Unpin,
+{
+    cache.increment_clients();
 
-    let (mut sender, mut receiver) = socket.split();
-    let mut event_receiver = service.cache.subscribe();
+    let mut event_receiver = cache.subscribe();
+    let mut disconnect_receiver = cache.subscribe_disconnects();
     let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(10));
+    let mut status_ticker = status_interval(status_period);
 
-    if requires_resnapshot(since, service.cache.current_sequence()) {
+    // Events replayed from history below; the live stream skips them
+    let mut replayed_through = 0;
+    let missed_events = if since > 0 {
+        cache.events_since(since)
+    } else {
+        None
+    };
+
+    if let Some(missed_events) = missed_events {
+        for event in missed_events {
+            replayed_through = event.sequence;
+            let payload = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+            if sender.send(Message::Text(payload.into())).await.is_err() {
+                cache.decrement_clients();
+                return;
+            }
+        }
+    } else if requires_resnapshot(since, cache.current_sequence()) {
         let resync = super::protocol::EventEnvelopeV1 {
             version: PROTOCOL_VERSION,
-            sequence: service.cache.current_sequence(),
+            sequence: cache.current_sequence(),
             event_type: "sync.resnapshot_required".to_string(),
             generated_at: Utc::now().to_rfc3339(),
             payload: json!({
@@ -427,7 +548,7 @@ async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u
 
         let message = serde_json::to_string(&resync).unwrap_or_else(|_| "{}".to_string());
         if sender.send(Message::Text(message.into())).await.is_err() {
-            service.cache.decrement_clients();
+            cache.decrement_clients();
             return;
         }
     }
@@ -443,6 +564,7 @@ async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u
             }
             event_message = event_receiver.recv() => {
                 match event_message {
+                    Ok(event) if event.sequence <= replayed_through => {}
                     Ok(event) => {
                         let payload = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
                         if sender.send(Message::Text(payload.into())).await.is_err() {
@@ -452,7 +574,7 @@ async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u
                     Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
                         let resync = super::protocol::EventEnvelopeV1 {
                             version: PROTOCOL_VERSION,
-                            sequence: service.cache.current_sequence(),
+                            sequence: cache.current_sequence(),
                             event_type: "sync.resnapshot_required".to_string(),
                             generated_at: Utc::now().to_rfc3339(),
                             payload: json!({
@@ -467,10 +589,26 @@ async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u
                     Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
                 }
             }
+            _ = status_ticker.tick() => {
+                let status = build_status_envelope(cache);
+                let payload = serde_json::to_string(&status).unwrap_or_else(|_| "{}".to_string());
+                if sender.send(Message::Text(payload.into())).await.is_err() {
+                    break;
+                }
+            }
+            _ = disconnect_receiver.recv() => {
+                let _ = sender
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: "device_revoked".into(),
+                    })))
+                    .await;
+                break;
+            }
             _ = heartbeat_interval.tick() => {
                 let heartbeat = super::protocol::EventEnvelopeV1 {
                     version: PROTOCOL_VERSION,
-                    sequence: service.cache.current_sequence(),
+                    sequence: cache.current_sequence(),
                     event_type: "sync.heartbeat".to_string(),
                     generated_at: Utc::now().to_rfc3339(),
                     payload: json!({ "ok": true }),
@@ -484,7 +622,7 @@ async fn websocket_loop(socket: WebSocket, state: MobileServerAppState, since: u
         }
     }
 
-    service.cache.decrement_clients();
+    cache.decrement_clients();
 }
 
 #[cfg(test)]
@@ -492,12 +630,12 @@ mod tests {
     use axum::http::HeaderValue;
 
     use super::*;
-    use crate::mobile_sync::auth::AuthenticatedDevice;
 
     fn ws_query(token: Option<&str>) -> WsQuery {
         WsQuery {
             since: None,
             token: token.map(ToOwned::to_owned),
+            version: None,
         }
     }
 
@@ -604,6 +742,22 @@ mod tests {
         assert_eq!(error.0, StatusCode::UNAUTHORIZED);
     }
 
+    #[test]
+    fn authenticate_request_with_unknown_device_token_maps_unauthorized() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::commands::agents::init_schema(&conn).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer unknown-token"));
+        headers.insert("x-codeinterfacex-sync-version", HeaderValue::from_static("1"));
+
+        let error = authenticate_request_with(&headers, |token| {
+            crate::mobile_sync::auth::authenticate_token_with_conn(&conn, token)
+        })
+        .expect_err("auth should fail");
+
+        assert_eq!(error.0, StatusCode::UNAUTHORIZED);
+    }
+
     #[test]
     fn action_dispatch_error_maps_to_internal_server_error() {
         let (status, body) = action_dispatch_error("dispatch failure".to_string());
@@ -632,10 +786,116 @@ mod tests {
         assert_eq!(selection.source, WsAuthTokenSource::Query);
     }
 
+    #[test]
+    fn in_range_ws_client_version_is_accepted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-codeinterfacex-sync-version", HeaderValue::from_static("1"));
+        assert_eq!(unsupported_ws_version(&headers, &ws_query(Some("token"))), None);
+
+        let query = WsQuery {
+            version: Some(PROTOCOL_VERSION),
+            ..ws_query(Some("token"))
+        };
+        assert_eq!(unsupported_ws_version(&HeaderMap::new(), &query), None);
+    }
+
+    #[test]
+    fn out_of_range_ws_client_version_gets_unsupported_version_close() {
+        let query = WsQuery {
+            version: Some(MAX_SUPPORTED_VERSION + 1),
+            ..ws_query(Some("token"))
+        };
+
+        let version = unsupported_ws_version(&HeaderMap::new(), &query)
+            .expect("version should be rejected");
+        let frame = unsupported_version_close_frame(version);
+
+        assert_eq!(frame.code, close_code::PROTOCOL);
+        assert!(frame.reason.as_str().starts_with(UNSUPPORTED_VERSION_REASON));
+    }
+
     #[test]
     fn requires_resnapshot_detects_sequence_gap() {
         assert!(!requires_resnapshot(0, 1));
         assert!(!requires_resnapshot(10, 11));
         assert!(requires_resnapshot(10, 12));
     }
+
+    #[tokio::test]
+    async fn status_message_is_emitted_on_the_interval() {
+        let cache = MobileSyncCache::new();
+        cache.set_enabled(true);
+        cache.increment_clients();
+        cache.publish_event("tab.updated", json!({}));
+
+        let period = std::time::Duration::from_millis(50);
+        let mut ticker = status_interval(period);
+        let started = tokio::time::Instant::now();
+
+        for tick in 1..=2u32 {
+            ticker.tick().await;
+            assert!(started.elapsed() >= period * tick);
+
+            let status = build_status_envelope(&cache);
+            assert_eq!(status.event_type, "sync.status");
+            assert_eq!(status.payload["connectedClients"], 1);
+            assert_eq!(status.payload["sequence"], 1);
+            assert_eq!(status.payload["enabled"], true);
+        }
+    }
+
+    #[tokio::test]
+    async fn revoking_devices_closes_the_socket() {
+        use futures::channel::mpsc;
+
+        let cache = MobileSyncCache::new();
+        cache.set_enabled(true);
+        let (client_tx, server_rx) = mpsc::unbounded::<Result<Message, std::convert::Infallible>>();
+        let (server_tx, mut client_rx) = mpsc::unbounded::<Message>();
+
+        let server_cache = cache.clone();
+        let connection = tokio::spawn(async move {
+            let period = std::time::Duration::from_millis(20);
+            serve_websocket(server_tx, server_rx, &server_cache, 0, period).await;
+        });
+
+        let within = std::time::Duration::from_secs(5);
+        // The first status message shows the loop is up and counts this client
+        loop {
+            let message = tokio::time::timeout(within, client_rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            let Message::Text(text) = message else {
+                panic!("expected a text message, got {:?}", message);
+            };
+            let envelope: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if envelope["eventType"] == "sync.status" {
+                assert_eq!(envelope["payload"]["connectedClients"], 1);
+                break;
+            }
+        }
+
+        assert_eq!(cache.disconnect_all_clients(), 1);
+
+        let close = loop {
+            let message = tokio::time::timeout(within, client_rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            if let Message::Close(frame) = message {
+                break frame.unwrap();
+            }
+        };
+        assert_eq!(close.code, close_code::POLICY);
+        assert_eq!(close.reason.as_str(), "device_revoked");
+
+        tokio::time::timeout(within, connection)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(client_rx.next().await, None);
+        assert_eq!(cache.connected_clients(), 0);
+        drop(client_tx);
+    }
 }
@@ -1,14 +1,25 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex, MutexGuard, RwLock,
 };
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::broadcast;
 
 use super::protocol::{EventEnvelopeV1, SnapshotV1, PROTOCOL_VERSION};
 
+/// Most events kept for replay to reconnecting clients, whatever their age
+pub const MAX_EVENT_HISTORY: usize = 1000;
+
+/// Sequences reserved on disk at a time. After a restart, sequences resume past
+/// the reservation, so none handed out before it are ever reused.
+const SEQUENCE_RESERVATION: u64 = 1000;
+
 #[derive(Clone)]
 pub struct MobileSyncCache {
     sequence: Arc<AtomicU64>,
@@ -16,17 +27,58 @@ pub struct MobileSyncCache {
     connected_clients: Arc<AtomicUsize>,
     snapshot: Arc<RwLock<Option<SnapshotV1>>>,
     event_tx: broadcast::Sender<EventEnvelopeV1>,
+    /// Held while assigning a sequence and broadcasting, so subscribers see
+    /// envelopes in sequence order even with concurrent publishers
+    publish_lock: Arc<Mutex<()>>,
+    /// Where each published snapshot is written, once a store is attached
+    snapshot_path: Arc<RwLock<Option<PathBuf>>>,
+    /// Highest sequence persisted as reserved; crossing it persists a new reservation
+    reserved_through: Arc<AtomicU64>,
+    /// Recent events, oldest first, for clients reconnecting with `since`
+    history: Arc<Mutex<EventHistory>>,
+    /// Tells every open WebSocket to close, e.g. after all devices are revoked
+    disconnect_tx: broadcast::Sender<()>,
+}
+
+#[derive(Default)]
+struct EventHistory {
+    events: VecDeque<EventEnvelopeV1>,
+    /// Every event up to this sequence is gone from `events`; a client that has
+    /// seen less than this must resnapshot
+    trimmed_through: u64,
+}
+
+impl EventHistory {
+    fn pop_front(&mut self) -> Option<EventEnvelopeV1> {
+        let event = self.events.pop_front()?;
+        self.trimmed_through = self.trimmed_through.max(event.sequence);
+        Some(event)
+    }
+}
+
+/// On-disk copy of the latest snapshot, so phones reconnecting after a
+/// desktop restart get the last-known state instead of nothing
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedSnapshot {
+    sequence: u64,
+    snapshot: SnapshotV1,
 }
 
 impl MobileSyncCache {
     pub fn new() -> Self {
         let (event_tx, _) = broadcast::channel(512);
+        let (disconnect_tx, _) = broadcast::channel(1);
         Self {
             sequence: Arc::new(AtomicU64::new(0)),
             enabled: Arc::new(AtomicBool::new(false)),
             connected_clients: Arc::new(AtomicUsize::new(0)),
             snapshot: Arc::new(RwLock::new(None)),
             event_tx,
+            publish_lock: Arc::new(Mutex::new(())),
+            snapshot_path: Arc::new(RwLock::new(None)),
+            reserved_through: Arc::new(AtomicU64::new(0)),
+            history: Arc::new(Mutex::new(EventHistory::default())),
+            disconnect_tx,
         }
     }
 
@@ -42,8 +94,15 @@ impl MobileSyncCache {
         self.sequence.load(Ordering::Relaxed)
     }
 
+    /// Callers must hold `publish_lock`
     fn next_sequence(&self) -> u64 {
-        self.sequence.fetch_add(1, Ordering::Relaxed) + 1
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        if sequence > self.reserved_through.load(Ordering::Relaxed) {
+            if let Err(error) = self.reserve_sequences(sequence) {
+                tracing::warn!("failed to persist mobile sync sequence: {}", error);
+            }
+        }
+        sequence
     }
 
     pub fn connected_clients(&self) -> usize {
@@ -59,10 +118,20 @@ impl MobileSyncCache {
     }
 
     pub async fn latest_snapshot(&self) -> Option<SnapshotV1> {
-        self.snapshot.read().await.clone()
+        self.snapshot
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn lock_publish(&self) -> MutexGuard<'_, ()> {
+        self.publish_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 
     pub async fn publish_snapshot(&self, state: Value) -> SnapshotV1 {
+        let _guard = self.lock_publish();
         let snapshot = SnapshotV1 {
             version: PROTOCOL_VERSION,
             sequence: self.next_sequence(),
@@ -70,22 +139,120 @@ impl MobileSyncCache {
             state,
         };
 
-        {
-            let mut guard = self.snapshot.write().await;
-            *guard = Some(snapshot.clone());
-        }
+        *self
+            .snapshot
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(snapshot.clone());
 
-        let _ = self.publish_event(
+        self.send_event(
             "snapshot.updated",
             serde_json::json!({
                 "sequence": snapshot.sequence,
             }),
         );
 
+        // Persisted after the event so a restart resumes past its sequence too
+        if let Err(error) = self.persist_snapshot(&snapshot) {
+            tracing::warn!("failed to persist mobile sync snapshot: {}", error);
+        }
+
         snapshot
     }
 
     pub fn publish_event(&self, event_type: &str, payload: Value) -> EventEnvelopeV1 {
+        let _guard = self.lock_publish();
+        self.send_event(event_type, payload)
+    }
+
+    /// Publish a batch with consecutive sequences, not interleaved with other publishers
+    pub fn publish_events<I>(&self, events: I) -> Vec<EventEnvelopeV1>
+    where
+        I: IntoIterator<Item = (String, Value)>,
+    {
+        let _guard = self.lock_publish();
+        events
+            .into_iter()
+            .map(|(event_type, payload)| self.send_event(&event_type, payload))
+            .collect()
+    }
+
+    /// Restore the snapshot saved at `path`, if any, and persist every later
+    /// snapshot there. Returns whether a snapshot was restored.
+    pub fn attach_snapshot_store(&self, path: PathBuf) -> Result<bool, String> {
+        let restored = self.restore_snapshot(&path)?;
+        *self
+            .snapshot_path
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(path);
+        Ok(restored)
+    }
+
+    fn restore_snapshot(&self, path: &Path) -> Result<bool, String> {
+        if let Some(reserved) = read_reserved_sequence(&sequence_path(path))? {
+            let _guard = self.lock_publish();
+            self.sequence.fetch_max(reserved, Ordering::Relaxed);
+            self.lock_history().trimmed_through = self.current_sequence();
+        }
+
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(format!("Failed to read persisted snapshot: {}", error)),
+        };
+        let persisted: PersistedSnapshot = serde_json::from_str(&raw)
+            .map_err(|error| format!("Failed to parse persisted snapshot: {}", error))?;
+
+        let _guard = self.lock_publish();
+        self.sequence.fetch_max(persisted.sequence, Ordering::Relaxed);
+        // Events from before the restart weren't kept
+        self.lock_history().trimmed_through = self.current_sequence();
+        *self
+            .snapshot
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(persisted.snapshot);
+        Ok(true)
+    }
+
+    /// Callers must hold `publish_lock`
+    fn persist_snapshot(&self, snapshot: &SnapshotV1) -> Result<(), String> {
+        let path = self
+            .snapshot_path
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let Some(path) = path else {
+            return Ok(());
+        };
+
+        let persisted = PersistedSnapshot {
+            sequence: self.current_sequence(),
+            snapshot: snapshot.clone(),
+        };
+        let raw = serde_json::to_string(&persisted)
+            .map_err(|error| format!("Failed to serialize snapshot: {}", error))?;
+        write_replacing(&path, &raw)
+    }
+
+    /// Persist a reservation running `SEQUENCE_RESERVATION` past `sequence`.
+    /// Callers must hold `publish_lock`.
+    fn reserve_sequences(&self, sequence: u64) -> Result<(), String> {
+        let path = self
+            .snapshot_path
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let Some(path) = path else {
+            return Ok(());
+        };
+
+        let reserved = sequence + SEQUENCE_RESERVATION;
+        write_replacing(&sequence_path(&path), &reserved.to_string())?;
+        self.reserved_through.store(reserved, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Callers must hold `publish_lock`
+    fn send_event(&self, event_type: &str, payload: Value) -> EventEnvelopeV1 {
         let envelope = EventEnvelopeV1 {
             version: PROTOCOL_VERSION,
             sequence: self.next_sequence(),
@@ -94,13 +261,96 @@ impl MobileSyncCache {
             payload,
         };
 
+        {
+            let mut history = self.lock_history();
+            history.events.push_back(envelope.clone());
+            while history.events.len() > MAX_EVENT_HISTORY {
+                history.pop_front();
+            }
+        }
+
         let _ = self.event_tx.send(envelope.clone());
         envelope
     }
 
+    fn lock_history(&self) -> MutexGuard<'_, EventHistory> {
+        self.history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Buffered events after `since`, or None when some of them were already
+    /// trimmed and the client has to resnapshot instead
+    pub fn events_since(&self, since: u64) -> Option<Vec<EventEnvelopeV1>> {
+        let history = self.lock_history();
+        if since < history.trimmed_through {
+            return None;
+        }
+        Some(
+            history
+                .events
+                .iter()
+                .filter(|event| event.sequence > since)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Drop buffered events generated before `cutoff`, returning how many went
+    pub fn prune_events_before(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut history = self.lock_history();
+        let mut pruned = 0;
+        while history.events.front().is_some_and(|event| {
+            DateTime::parse_from_rfc3339(&event.generated_at)
+                .map(|generated_at| generated_at < cutoff)
+                .unwrap_or(true)
+        }) {
+            history.pop_front();
+            pruned += 1;
+        }
+        pruned
+    }
+
     pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelopeV1> {
         self.event_tx.subscribe()
     }
+
+    pub fn subscribe_disconnects(&self) -> broadcast::Receiver<()> {
+        self.disconnect_tx.subscribe()
+    }
+
+    /// Ask every open connection to close, returning how many were signalled
+    pub fn disconnect_all_clients(&self) -> usize {
+        self.disconnect_tx.send(()).unwrap_or(0)
+    }
+}
+
+/// Where the sequence reservation is kept, next to the snapshot at `snapshot_path`
+fn sequence_path(snapshot_path: &Path) -> PathBuf {
+    snapshot_path.with_extension("sequence")
+}
+
+fn read_reserved_sequence(path: &Path) -> Result<Option<u64>, String> {
+    match fs::read_to_string(path) {
+        Ok(raw) => raw
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|error| format!("Failed to parse persisted sequence: {}", error)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("Failed to read persisted sequence: {}", error)),
+    }
+}
+
+/// Write then rename so a crash mid-write never leaves a torn file
+fn write_replacing(path: &Path, raw: &str) -> Result<(), String> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, raw)
+        .map_err(|error| format!("Failed to write {}: {}", path.display(), error))?;
+    fs::rename(&tmp_path, path)
+        .map_err(|error| format!("Failed to replace {}: {}", path.display(), error))
 }
 
 impl Default for MobileSyncCache {
@@ -108,3 +358,134 @@ impl Default for MobileSyncCache {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_publishers_produce_ordered_gap_free_sequences() {
+        let cache = MobileSyncCache::new();
+        let mut rx = cache.subscribe();
+
+        let mut tasks = Vec::new();
+        for publisher in 0..8 {
+            let cache = cache.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut snapshot_sequences = Vec::new();
+                for round in 0..10 {
+                    cache.publish_event("tab.updated", json!({ "publisher": publisher }));
+                    let batch = cache.publish_events(
+                        (0..3).map(|i| ("tab.output".to_string(), json!({ "index": i }))),
+                    );
+                    assert!(batch.windows(2).all(|pair| pair[1].sequence == pair[0].sequence + 1));
+                    if round % 5 == 0 {
+                        snapshot_sequences.push(cache.publish_snapshot(json!({})).await.sequence);
+                    }
+                    tokio::task::yield_now().await;
+                }
+                snapshot_sequences
+            }));
+        }
+
+        let mut all_sequences = Vec::new();
+        for task in tasks {
+            all_sequences.extend(task.await.unwrap());
+        }
+
+        let mut received = Vec::new();
+        while let Ok(envelope) = rx.try_recv() {
+            received.push(envelope.sequence);
+        }
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+
+        all_sequences.extend(received);
+        all_sequences.sort_unstable();
+        let expected: Vec<u64> = (1..=cache.current_sequence()).collect();
+        assert_eq!(all_sequences, expected);
+        // 8 publishers x 10 rounds x (1 event + 3 batched) + 16 snapshots with their events
+        assert_eq!(cache.current_sequence(), 8 * 10 * 4 + 16 * 2);
+    }
+
+    #[test]
+    fn events_older_than_the_retention_window_are_pruned() {
+        let cache = MobileSyncCache::new();
+        let stale = cache.publish_event("tab.updated", json!({ "n": 1 }));
+        let also_stale = cache.publish_event("tab.updated", json!({ "n": 2 }));
+        let fresh = cache.publish_event("tab.updated", json!({ "n": 3 }));
+        {
+            // Backdate the first two past a 30 minute window
+            let mut history = cache.lock_history();
+            let old = (Utc::now() - chrono::Duration::minutes(45)).to_rfc3339();
+            history.events[0].generated_at = old.clone();
+            history.events[1].generated_at = old;
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::minutes(30);
+        assert_eq!(cache.prune_events_before(cutoff), 2);
+        assert_eq!(cache.prune_events_before(cutoff), 0);
+
+        let remaining = cache.events_since(also_stale.sequence).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].sequence, fresh.sequence);
+        // Clients that missed a pruned event can't be caught up from history
+        assert!(cache.events_since(stale.sequence).is_none());
+        assert!(cache.events_since(0).is_none());
+
+        // New events keep their sequence after the pruned ones
+        let next = cache.publish_event("tab.updated", json!({}));
+        assert_eq!(next.sequence, fresh.sequence + 1);
+        assert_eq!(cache.events_since(fresh.sequence).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn persisted_snapshot_is_restored_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mobile_sync_snapshot.json");
+
+        let before_restart = MobileSyncCache::new();
+        assert!(!before_restart.attach_snapshot_store(path.clone()).unwrap());
+        before_restart.publish_event("tab.updated", json!({}));
+        let published = before_restart
+            .publish_snapshot(json!({ "activeTabId": "tab-1" }))
+            .await;
+        let last_sequence = before_restart.current_sequence();
+        assert!(last_sequence > published.sequence);
+
+        let after_restart = MobileSyncCache::new();
+        assert!(after_restart.attach_snapshot_store(path).unwrap());
+
+        let restored = after_restart.latest_snapshot().await.expect("snapshot restored");
+        assert_eq!(restored.sequence, published.sequence);
+        assert_eq!(restored.state, json!({ "activeTabId": "tab-1" }));
+        assert!(after_restart.current_sequence() >= last_sequence);
+
+        // New publishes continue past the snapshot.updated event
+        let next = after_restart.publish_event("tab.updated", json!({}));
+        assert!(next.sequence > last_sequence);
+    }
+
+    #[test]
+    fn sequences_published_after_the_last_snapshot_are_not_reused_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mobile_sync_snapshot.json");
+
+        let before_restart = MobileSyncCache::new();
+        before_restart.attach_snapshot_store(path.clone()).unwrap();
+        let mut last = 0;
+        for _ in 0..(SEQUENCE_RESERVATION + 5) {
+            last = before_restart
+                .publish_event("tab.updated", json!({}))
+                .sequence;
+        }
+
+        // No snapshot was ever published, yet the restart resumes past `last`
+        let after_restart = MobileSyncCache::new();
+        assert!(!after_restart.attach_snapshot_store(path).unwrap());
+        assert!(after_restart.current_sequence() >= last);
+        assert!(after_restart.events_since(last - 1).is_none());
+        let next = after_restart.publish_event("tab.updated", json!({}));
+        assert!(next.sequence > last);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod output_lines;
+pub mod rate_limit;
 pub mod registry;
+pub mod sandbox;
 
 pub use registry::*;
@@ -0,0 +1,123 @@
+use crate::commands::agents::read_app_setting;
+use rusqlite::Connection;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Setting key (in `app_settings`) overriding the per-line cap
+pub const MAX_LINE_BYTES_SETTING: &str = "max_output_line_bytes";
+/// Default per-line cap for provider stdout/stderr
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+/// Smallest accepted cap, so a bad setting can't mangle ordinary output
+pub const MIN_MAX_LINE_BYTES: usize = 4 * 1024;
+/// Appended to lines that were cut at the cap
+pub const TRUNCATED_LINE_MARKER: &str = "…[truncated";
+
+/// Read the configured line cap, falling back to the default when unset or invalid
+pub fn load_max_line_bytes(conn: &Connection) -> usize {
+    read_app_setting(conn, MAX_LINE_BYTES_SETTING)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map(|value| value.max(MIN_MAX_LINE_BYTES))
+        .unwrap_or(DEFAULT_MAX_LINE_BYTES)
+}
+
+/// Line reader that never buffers more than `max_len` bytes of a single line.
+/// Bytes past the cap are discarded and replaced by a truncation marker, so
+/// one multi-megabyte line from a provider can't balloon memory.
+pub struct CappedLines<R> {
+    reader: R,
+    max_len: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> CappedLines<R> {
+    pub fn new(reader: R, max_len: usize) -> Self {
+        Self {
+            reader,
+            max_len,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Next line without its trailing newline, or `None` at end of stream
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buf.clear();
+        let mut omitted = 0usize;
+        let mut read_any = false;
+
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                break;
+            }
+            read_any = true;
+
+            let (chunk_len, consumed, found_newline) =
+                match available.iter().position(|byte| *byte == b'\n') {
+                    Some(index) => (index, index + 1, true),
+                    None => (available.len(), available.len(), false),
+                };
+            let keep = chunk_len.min(self.max_len.saturating_sub(self.buf.len()));
+            self.buf.extend_from_slice(&available[..keep]);
+            omitted += chunk_len - keep;
+            self.reader.consume(consumed);
+
+            if found_newline {
+                break;
+            }
+        }
+
+        if !read_any {
+            return Ok(None);
+        }
+        if omitted == 0 && self.buf.last() == Some(&b'\r') {
+            self.buf.pop();
+        }
+
+        // The cut may land inside a multi-byte character
+        let mut line = String::from_utf8_lossy(&self.buf).into_owned();
+        if omitted > 0 {
+            line.push_str(&format!("{} {} bytes]", TRUNCATED_LINE_MARKER, omitted));
+        }
+        Ok(Some(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[tokio::test]
+    async fn over_long_line_is_truncated_with_marker() {
+        let long_line = "x".repeat(10_000);
+        let input = format!("short\r\n{}\nafter\n", long_line);
+        let mut lines = CappedLines::new(input.as_bytes(), 4096);
+
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("short"));
+
+        let truncated = lines.next_line().await.unwrap().unwrap();
+        assert!(truncated.starts_with(&"x".repeat(4096)));
+        assert!(truncated.ends_with(&format!("{} 5904 bytes]", TRUNCATED_LINE_MARKER)));
+        assert!(truncated.len() < 4200);
+
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("after"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[test]
+    fn max_line_bytes_setting_is_clamped() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(load_max_line_bytes(&conn), DEFAULT_MAX_LINE_BYTES);
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '16')",
+            params![MAX_LINE_BYTES_SETTING],
+        )
+        .unwrap();
+        assert_eq!(load_max_line_bytes(&conn), MIN_MAX_LINE_BYTES);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::commands::agents::read_app_setting;
+use rusqlite::Connection;
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+
+/// Setting key (in `app_settings`) turning automatic rate-limit retries on
+pub const RETRY_ENABLED_SETTING: &str = "rate_limit_retry_enabled";
+/// Setting key (in `app_settings`) overriding how many retries a run may spawn
+pub const RETRY_MAX_ATTEMPTS_SETTING: &str = "rate_limit_retry_max_attempts";
+/// Retries per original run when the setting is unset or invalid
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Upper bound on the configured attempts, so a typo can't retry forever
+pub const MAX_MAX_ATTEMPTS: u32 = 10;
+/// First backoff step when the provider gives no `retry-after`
+pub const BASE_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// Longest computed backoff step
+pub const MAX_BACKOFF_DELAY: Duration = Duration::from_secs(5 * 60);
+/// Longest `retry-after` we honour; anything beyond is clamped
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "rate_limit_error",
+    "rate limit",
+    "rate-limit",
+    "ratelimit",
+    "too many requests",
+    "api error: 429",
+    "status code 429",
+    "status: 429",
+];
+
+const RETRY_AFTER_MARKERS: &[&str] = &["retry-after", "retry_after", "retry after"];
+
+/// A rate-limit error reported by the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitSignal {
+    /// Wait requested by the provider, when it sent one
+    pub retry_after: Option<Duration>,
+}
+
+/// Classify a plain-text line (stderr) as a rate-limit error
+pub fn detect_rate_limit(line: &str) -> Option<RateLimitSignal> {
+    let lower = line.to_ascii_lowercase();
+    if !RATE_LIMIT_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+    {
+        return None;
+    }
+
+    Some(RateLimitSignal {
+        retry_after: parse_retry_after(&lower),
+    })
+}
+
+/// Classify a stream-json event. Only error events are inspected, so assistant
+/// text that merely talks about rate limits is never mistaken for one.
+pub fn detect_rate_limit_in_event(event: &JsonValue) -> Option<RateLimitSignal> {
+    let is_error_event = match event.get("type").and_then(|t| t.as_str()) {
+        Some("error") => true,
+        Some("result") => event.get("is_error").and_then(|v| v.as_bool()) == Some(true),
+        _ => false,
+    };
+    if !is_error_event {
+        return None;
+    }
+
+    detect_rate_limit(&event.to_string())
+}
+
+/// Seconds following a `retry-after` marker, e.g. `retry-after: 30` or `retry after 30s`
+fn parse_retry_after(lower: &str) -> Option<Duration> {
+    RETRY_AFTER_MARKERS.iter().find_map(|marker| {
+        let start = lower.find(marker)? + marker.len();
+        let rest = lower[start..]
+            .trim_start_matches(|c: char| c == ':' || c == '"' || c == '=' || c.is_whitespace());
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse::<u64>().ok().map(Duration::from_secs)
+    })
+}
+
+/// How rate-limited runs are retried; only present when the feature is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitRetryPolicy {
+    pub max_attempts: u32,
+}
+
+impl RateLimitRetryPolicy {
+    /// Delay before the next retry, or `None` once `retries_so_far` reached the limit.
+    /// A provider `retry-after` wins over the exponential backoff.
+    pub fn next_delay(&self, retries_so_far: u32, signal: &RateLimitSignal) -> Option<Duration> {
+        if retries_so_far >= self.max_attempts {
+            return None;
+        }
+
+        if let Some(retry_after) = signal.retry_after {
+            return Some(retry_after.min(MAX_RETRY_AFTER));
+        }
+
+        let factor = 1u32.checked_shl(retries_so_far).unwrap_or(u32::MAX);
+        Some(
+            BASE_RETRY_DELAY
+                .checked_mul(factor)
+                .unwrap_or(MAX_BACKOFF_DELAY)
+                .min(MAX_BACKOFF_DELAY),
+        )
+    }
+}
+
+/// Read the retry policy, or `None` when automatic retries are disabled (the default)
+pub fn load_retry_policy(conn: &Connection) -> Option<RateLimitRetryPolicy> {
+    let enabled = read_app_setting(conn, RETRY_ENABLED_SETTING)
+        .map(|value| matches!(value.trim(), "true" | "1"))
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+
+    let max_attempts = read_app_setting(conn, RETRY_MAX_ATTEMPTS_SETTING)
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .map(|value| value.min(MAX_MAX_ATTEMPTS))
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+
+    Some(RateLimitRetryPolicy { max_attempts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[test]
+    fn detects_rate_limit_lines_and_retry_after() {
+        assert_eq!(
+            detect_rate_limit("Error: 429 Too Many Requests (retry-after: 42)"),
+            Some(RateLimitSignal {
+                retry_after: Some(Duration::from_secs(42))
+            })
+        );
+        assert_eq!(
+            detect_rate_limit("API Error: Rate limit reached, please retry after 7s"),
+            Some(RateLimitSignal {
+                retry_after: Some(Duration::from_secs(7))
+            })
+        );
+        assert_eq!(
+            detect_rate_limit("rate_limit_error: slow down"),
+            Some(RateLimitSignal { retry_after: None })
+        );
+        assert_eq!(detect_rate_limit("Error: invalid API key"), None);
+    }
+
+    #[test]
+    fn only_error_events_are_classified() {
+        let result = serde_json::json!({
+            "type": "result",
+            "is_error": true,
+            "result": "API Error: 429 {\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\"}}",
+        });
+        assert!(detect_rate_limit_in_event(&result).is_some());
+
+        let assistant = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "Add a rate limit to the API"}]},
+        });
+        assert_eq!(detect_rate_limit_in_event(&assistant), None);
+    }
+
+    #[test]
+    fn backoff_doubles_until_attempts_run_out() {
+        let policy = RateLimitRetryPolicy { max_attempts: 3 };
+        let signal = RateLimitSignal { retry_after: None };
+
+        assert_eq!(policy.next_delay(0, &signal), Some(BASE_RETRY_DELAY));
+        assert_eq!(policy.next_delay(1, &signal), Some(BASE_RETRY_DELAY * 2));
+        assert_eq!(policy.next_delay(2, &signal), Some(BASE_RETRY_DELAY * 4));
+        assert_eq!(policy.next_delay(3, &signal), None);
+
+        let long = RateLimitRetryPolicy { max_attempts: 10 };
+        assert_eq!(long.next_delay(9, &signal), Some(MAX_BACKOFF_DELAY));
+    }
+
+    #[test]
+    fn retry_after_overrides_backoff_but_is_clamped() {
+        let policy = RateLimitRetryPolicy { max_attempts: 3 };
+        let short = RateLimitSignal {
+            retry_after: Some(Duration::from_secs(3)),
+        };
+        let huge = RateLimitSignal {
+            retry_after: Some(Duration::from_secs(86_400)),
+        };
+
+        assert_eq!(policy.next_delay(2, &short), Some(Duration::from_secs(3)));
+        assert_eq!(policy.next_delay(0, &huge), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn retry_policy_is_opt_in() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(load_retry_policy(&conn), None);
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, 'true')",
+            params![RETRY_ENABLED_SETTING],
+        )
+        .unwrap();
+        assert_eq!(
+            load_retry_policy(&conn),
+            Some(RateLimitRetryPolicy {
+                max_attempts: DEFAULT_MAX_ATTEMPTS
+            })
+        );
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '99')",
+            params![RETRY_MAX_ATTEMPTS_SETTING],
+        )
+        .unwrap();
+        assert_eq!(
+            load_retry_policy(&conn),
+            Some(RateLimitRetryPolicy {
+                max_attempts: MAX_MAX_ATTEMPTS
+            })
+        );
+    }
+}
@@ -29,6 +29,17 @@ pub struct ProcessHandle {
     pub info: ProcessInfo,
     pub child: Arc<Mutex<Option<Child>>>,
     pub live_output: Arc<Mutex<String>>,
+    /// Last live-output append, or registration time before any output
+    pub last_output_at: Arc<Mutex<DateTime<Utc>>>,
+}
+
+/// A running agent run that has produced no output for a while
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StuckProcess {
+    #[serde(flatten)]
+    pub info: ProcessInfo,
+    pub last_output_at: DateTime<Utc>,
+    pub idle_secs: i64,
 }
 
 /// Registry for tracking active agent processes
@@ -108,10 +119,12 @@ impl ProcessRegistry {
         // For sidecar processes, we register without the child handle since it's managed differently
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
+        let started_at = process_info.started_at;
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No tokio::process::Child handle for sidecar
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: Arc::new(Mutex::new(started_at)),
         };
 
         processes.insert(run_id, process_handle);
@@ -142,10 +155,12 @@ impl ProcessRegistry {
         // Register without child - Claude sessions use ClaudeProcessState for process management
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
+        let started_at = process_info.started_at;
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No child handle for Claude sessions
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: Arc::new(Mutex::new(started_at)),
         };
 
         processes.insert(run_id, process_handle);
@@ -161,10 +176,12 @@ impl ProcessRegistry {
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
+        let started_at = process_info.started_at;
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
             live_output: Arc::new(Mutex::new(String::new())),
+            last_output_at: Arc::new(Mutex::new(started_at)),
         };
 
         processes.insert(run_id, process_handle);
@@ -473,10 +490,57 @@ impl ProcessRegistry {
             let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
             live_output.push_str(output);
             live_output.push('\n');
+            *handle.last_output_at.lock().map_err(|e| e.to_string())? = Utc::now();
         }
         Ok(())
     }
 
+    /// When a process last appended live output (its registration time if it never did)
+    #[cfg(test)]
+    pub fn last_output_at(&self, run_id: i64) -> Result<Option<DateTime<Utc>>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        match processes.get(&run_id) {
+            Some(handle) => Ok(Some(
+                *handle.last_output_at.lock().map_err(|e| e.to_string())?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Running agent runs whose last output is older than `threshold`
+    pub fn get_stuck_agent_processes(
+        &self,
+        threshold: chrono::Duration,
+    ) -> Result<Vec<StuckProcess>, String> {
+        self.stuck_agent_processes_at(Utc::now(), threshold)
+    }
+
+    fn stuck_agent_processes_at(
+        &self,
+        now: DateTime<Utc>,
+        threshold: chrono::Duration,
+    ) -> Result<Vec<StuckProcess>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let mut stuck = Vec::new();
+        for handle in processes.values() {
+            if !matches!(handle.info.process_type, ProcessType::AgentRun { .. }) {
+                continue;
+            }
+            let last_output_at = *handle.last_output_at.lock().map_err(|e| e.to_string())?;
+            let idle = now - last_output_at;
+            if idle > threshold {
+                stuck.push(StuckProcess {
+                    info: handle.info.clone(),
+                    last_output_at,
+                    idle_secs: idle.num_seconds(),
+                });
+            }
+        }
+        // Longest idle first
+        stuck.sort_by_key(|process| process.last_output_at);
+        Ok(stuck)
+    }
+
     /// Get live output for a process
     pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -488,6 +552,31 @@ impl ProcessRegistry {
         }
     }
 
+    /// Live output appended since byte offset `from_byte`, plus the current length to
+    /// poll from next. An offset past the end yields nothing and the current length.
+    pub fn get_live_output_since(
+        &self,
+        run_id: i64,
+        from_byte: usize,
+    ) -> Result<(String, usize), String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let Some(handle) = processes.get(&run_id) else {
+            return Ok((String::new(), 0));
+        };
+        let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+        let len = live_output.len();
+        if from_byte >= len {
+            return Ok((String::new(), len));
+        }
+
+        // Offsets we hand out are always char boundaries; step back for any other
+        let mut start = from_byte;
+        while !live_output.is_char_boundary(start) {
+            start -= 1;
+        }
+        Ok((live_output[start..].to_string(), len))
+    }
+
     /// Cleanup finished processes
     #[allow(dead_code)]
     pub async fn cleanup_finished_processes(&self) -> Result<Vec<i64>, String> {
@@ -533,3 +622,94 @@ impl Default for ProcessRegistryState {
         Self(Arc::new(ProcessRegistry::new()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register_run(registry: &ProcessRegistry, run_id: i64) {
+        registry
+            .register_sidecar_process(
+                run_id,
+                1,
+                "Agent".to_string(),
+                4242,
+                "/tmp".to_string(),
+                "task".to_string(),
+                "sonnet".to_string(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn appending_output_moves_last_output_time() {
+        let registry = ProcessRegistry::new();
+        register_run(&registry, 1);
+        let registered_at = registry.last_output_at(1).unwrap().unwrap();
+        assert_eq!(
+            registered_at,
+            registry.get_process(1).unwrap().unwrap().started_at
+        );
+
+        registry.append_live_output(1, "line").unwrap();
+        assert!(registry.last_output_at(1).unwrap().unwrap() >= registered_at);
+        assert_eq!(registry.last_output_at(2).unwrap(), None);
+    }
+
+    #[test]
+    fn live_output_since_returns_only_new_bytes() {
+        let registry = ProcessRegistry::new();
+        register_run(&registry, 1);
+        registry.append_live_output(1, "first").unwrap();
+
+        let (chunk, offset) = registry.get_live_output_since(1, 0).unwrap();
+        assert_eq!((chunk.as_str(), offset), ("first\n", 6));
+
+        registry.append_live_output(1, "second").unwrap();
+        let (chunk, offset) = registry.get_live_output_since(1, offset).unwrap();
+        assert_eq!((chunk.as_str(), offset), ("second\n", 13));
+
+        assert_eq!(
+            registry.get_live_output_since(1, 1_000).unwrap(),
+            (String::new(), 13)
+        );
+        assert_eq!(
+            registry.get_live_output_since(2, 0).unwrap(),
+            (String::new(), 0)
+        );
+    }
+
+    #[test]
+    fn only_runs_quiet_past_the_threshold_are_stuck() {
+        let registry = ProcessRegistry::new();
+        register_run(&registry, 1);
+        register_run(&registry, 2);
+        registry
+            .register_provider_session(
+                "session".to_string(),
+                4343,
+                "/tmp".to_string(),
+                "task".to_string(),
+                "sonnet".to_string(),
+            )
+            .unwrap();
+
+        let threshold = chrono::Duration::minutes(5);
+        let later = Utc::now() + chrono::Duration::minutes(10);
+        // Run 2 wrote output just before `later`, so only run 1 is quiet
+        *registry.processes.lock().unwrap()[&2]
+            .last_output_at
+            .lock()
+            .unwrap() = later - chrono::Duration::minutes(1);
+
+        let stuck = registry.stuck_agent_processes_at(later, threshold).unwrap();
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].info.run_id, 1);
+        assert!(stuck[0].idle_secs >= 600);
+
+        assert!(registry
+            .stuck_agent_processes_at(Utc::now(), threshold)
+            .unwrap()
+            .is_empty());
+    }
+}
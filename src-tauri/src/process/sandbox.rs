@@ -0,0 +1,492 @@
+use std::path::{Path, PathBuf};
+
+/// Home-relative paths providers keep their own state in. They stay writable under
+/// a read-only sandbox, since a provider can't run without saving sessions and caches.
+/// Only each provider's own directories are listed: shared ones like `.local/bin` or
+/// `.config/autostart` would let an agent plant code that runs outside the sandbox.
+const PROVIDER_STATE_PATHS: &[&str] = &[
+    ".claude",
+    ".claude.json",
+    ".config/claude",
+    ".cache/claude-cli-nodejs",
+    "Library/Caches/claude-cli-nodejs",
+    ".codex",
+    ".gemini",
+    ".aider",
+    ".config/opencode",
+    ".local/share/opencode",
+    ".local/state/opencode",
+    ".cache/opencode",
+];
+
+/// npm cache used inside a read-only sandbox, under the temp directory so the shared
+/// `~/.npm` (whose `_npx` packages run unsandboxed later) stays read-only
+const SANDBOX_NPM_CACHE_DIR: &str = "codeinterfacex-npm-cache";
+
+/// Restrictions an agent process runs under, derived from the agent's permissions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    /// Deny writes outside the provider's own state and temp directories
+    pub read_only: bool,
+    /// Deny network access. This includes the provider's own API, so only agents
+    /// backed by a local model can work without it.
+    pub no_network: bool,
+}
+
+impl SandboxPolicy {
+    pub fn for_agent(enable_file_write: bool, enable_network: bool) -> Self {
+        Self {
+            read_only: !enable_file_write,
+            no_network: !enable_network,
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        !self.read_only && !self.no_network
+    }
+
+    /// What the policy restricts, for warnings when it can't be enforced
+    fn restrictions(&self) -> &'static str {
+        match (self.read_only, self.no_network) {
+            (true, true) => "File writes and network access",
+            (true, false) => "File writes",
+            (false, true) => "Network access",
+            (false, false) => "Nothing",
+        }
+    }
+}
+
+/// Sandbox wrapper used to enforce a policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxTool {
+    /// bubblewrap, on Linux
+    Bubblewrap,
+    /// Seatbelt's `sandbox-exec`, on macOS
+    SandboxExec,
+}
+
+impl SandboxTool {
+    /// Tool for `os`, as named by `std::env::consts::OS`
+    pub fn for_os(os: &str) -> Option<Self> {
+        match os {
+            "linux" => Some(Self::Bubblewrap),
+            "macos" => Some(Self::SandboxExec),
+            _ => None,
+        }
+    }
+
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Bubblewrap => "bwrap",
+            Self::SandboxExec => "sandbox-exec",
+        }
+    }
+}
+
+/// A provider invocation, possibly wrapped in a sandbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxedCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Environment variables the sandboxed process needs on top of the usual ones
+    pub env: Vec<(String, String)>,
+    /// Why the policy couldn't be enforced, when it couldn't
+    pub warning: Option<String>,
+}
+
+/// Program and arguments running `binary args` under `tool` with `policy`.
+/// `writable` paths stay writable when the policy is read-only.
+pub fn wrap_command(
+    tool: SandboxTool,
+    policy: SandboxPolicy,
+    binary: &str,
+    args: Vec<String>,
+    writable: &[PathBuf],
+) -> (String, Vec<String>) {
+    let mut wrapped = Vec::new();
+    match tool {
+        SandboxTool::Bubblewrap => {
+            if policy.read_only {
+                wrapped.extend(["--ro-bind", "/", "/"].map(String::from));
+                for path in writable {
+                    let path = path.to_string_lossy().into_owned();
+                    wrapped.extend(["--bind".to_string(), path.clone(), path]);
+                }
+            } else {
+                wrapped.extend(["--bind", "/", "/"].map(String::from));
+            }
+            if policy.no_network {
+                wrapped.push("--unshare-net".to_string());
+            }
+            wrapped.extend(
+                [
+                    "--dev",
+                    "/dev",
+                    "--proc",
+                    "/proc",
+                    "--die-with-parent",
+                    "--",
+                ]
+                .map(String::from),
+            );
+        }
+        SandboxTool::SandboxExec => {
+            wrapped.push("-p".to_string());
+            wrapped.push(seatbelt_profile(policy, writable));
+        }
+    }
+    wrapped.push(binary.to_string());
+    wrapped.extend(args);
+
+    (tool.program().to_string(), wrapped)
+}
+
+/// Seatbelt profile for `policy`; later rules take precedence over earlier ones
+fn seatbelt_profile(policy: SandboxPolicy, writable: &[PathBuf]) -> String {
+    let mut profile = String::from("(version 1)\n(allow default)\n");
+    if policy.read_only {
+        profile.push_str("(deny file-write*)\n(allow file-write*\n    (subpath \"/dev\")\n");
+        for path in writable {
+            profile.push_str(&format!(
+                "    (subpath \"{}\")\n",
+                seatbelt_escape(&path.to_string_lossy())
+            ));
+        }
+        profile.push_str(")\n");
+    }
+    if policy.no_network {
+        // Unix sockets stay usable; they're local IPC rather than network access
+        profile.push_str("(deny network*)\n(allow network* (remote unix-socket))\n");
+    }
+    profile
+}
+
+fn seatbelt_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Provider state directories under `home` and the temp directory, as real paths
+/// since `sandbox-exec` matches paths after resolving symlinks
+pub fn writable_paths(home: Option<&Path>, temp_dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = home
+        .map(|home| {
+            PROVIDER_STATE_PATHS
+                .iter()
+                .map(|relative| home.join(relative))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.push(temp_dir.to_path_buf());
+    if temp_dir != Path::new("/tmp") {
+        paths.push(PathBuf::from("/tmp"));
+    }
+
+    let mut paths: Vec<PathBuf> = paths
+        .into_iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// `binary args` wrapped in this platform's sandbox for `policy`. When the policy
+/// can't be enforced the command is returned as is, with a warning saying why.
+pub fn sandboxed_command(
+    policy: SandboxPolicy,
+    binary: &str,
+    args: Vec<String>,
+) -> SandboxedCommand {
+    sandboxed_command_for(policy, binary, args, std::env::consts::OS, |program| {
+        which::which(program).ok()
+    })
+}
+
+/// `sandboxed_command` for `os`, locating the sandbox tool with `find_tool`
+fn sandboxed_command_for(
+    policy: SandboxPolicy,
+    binary: &str,
+    args: Vec<String>,
+    os: &str,
+    find_tool: impl Fn(&str) -> Option<PathBuf>,
+) -> SandboxedCommand {
+    let unwrapped = |args: Vec<String>, warning: Option<String>| SandboxedCommand {
+        program: binary.to_string(),
+        args,
+        env: Vec::new(),
+        warning,
+    };
+    if policy.is_unrestricted() {
+        return unwrapped(args, None);
+    }
+
+    let Some(tool) = SandboxTool::for_os(os) else {
+        return unwrapped(
+            args,
+            Some(format!(
+                "{} can't be blocked on {}; the agent runs without a sandbox",
+                policy.restrictions(),
+                os
+            )),
+        );
+    };
+    let Some(tool_path) = find_tool(tool.program()) else {
+        return unwrapped(
+            args,
+            Some(format!(
+                "{} is not installed, so {} can't be blocked; the agent runs without a sandbox",
+                tool.program(),
+                policy.restrictions().to_lowercase()
+            )),
+        );
+    };
+
+    let temp_dir = std::env::temp_dir();
+    let writable = writable_paths(dirs::home_dir().as_deref(), &temp_dir);
+    let (_, args) = wrap_command(tool, policy, binary, args, &writable);
+    let env = if policy.read_only {
+        let npm_cache = temp_dir.join(SANDBOX_NPM_CACHE_DIR);
+        vec![(
+            "npm_config_cache".to_string(),
+            npm_cache.to_string_lossy().into_owned(),
+        )]
+    } else {
+        Vec::new()
+    };
+    SandboxedCommand {
+        program: tool_path.to_string_lossy().into_owned(),
+        args,
+        env,
+        warning: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn bubblewrap_mounts_root_read_only_with_writable_state() {
+        let writable = vec![PathBuf::from("/home/me/.claude"), PathBuf::from("/tmp")];
+
+        let (program, wrapped) = wrap_command(
+            SandboxTool::Bubblewrap,
+            SandboxPolicy::for_agent(false, true),
+            "/usr/bin/claude",
+            args(&["-p", "task"]),
+            &writable,
+        );
+
+        assert_eq!(program, "bwrap");
+        assert_eq!(
+            wrapped,
+            args(&[
+                "--ro-bind",
+                "/",
+                "/",
+                "--bind",
+                "/home/me/.claude",
+                "/home/me/.claude",
+                "--bind",
+                "/tmp",
+                "/tmp",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--die-with-parent",
+                "--",
+                "/usr/bin/claude",
+                "-p",
+                "task",
+            ])
+        );
+    }
+
+    #[test]
+    fn sandbox_exec_denies_writes_outside_allowed_paths() {
+        let writable = vec![
+            PathBuf::from("/Users/me/.claude"),
+            PathBuf::from("/Users/me/we\"ird"),
+        ];
+
+        let (program, wrapped) = wrap_command(
+            SandboxTool::SandboxExec,
+            SandboxPolicy::for_agent(false, true),
+            "/opt/homebrew/bin/claude",
+            args(&["-p", "task"]),
+            &writable,
+        );
+
+        assert_eq!(program, "sandbox-exec");
+        assert_eq!(wrapped[0], "-p");
+        assert_eq!(
+            wrapped[1],
+            "(version 1)\n(allow default)\n(deny file-write*)\n(allow file-write*\n    \
+             (subpath \"/dev\")\n    (subpath \"/Users/me/.claude\")\n    \
+             (subpath \"/Users/me/we\\\"ird\")\n)\n"
+        );
+        assert_eq!(
+            &wrapped[2..],
+            &args(&["/opt/homebrew/bin/claude", "-p", "task"])[..]
+        );
+    }
+
+    #[test]
+    fn unsupported_platforms_and_writable_agents_run_unwrapped() {
+        assert_eq!(SandboxTool::for_os("linux"), Some(SandboxTool::Bubblewrap));
+        assert_eq!(SandboxTool::for_os("macos"), Some(SandboxTool::SandboxExec));
+        assert_eq!(SandboxTool::for_os("windows"), None);
+
+        let command = sandboxed_command(
+            SandboxPolicy::for_agent(true, true),
+            "claude",
+            args(&["-p"]),
+        );
+        assert_eq!(
+            command,
+            SandboxedCommand {
+                program: "claude".to_string(),
+                args: args(&["-p"]),
+                env: Vec::new(),
+                warning: None,
+            }
+        );
+    }
+
+    #[test]
+    fn network_isolation_unshares_the_network_namespace_or_denies_network() {
+        let policy = SandboxPolicy::for_agent(true, false);
+
+        let (_, wrapped) = wrap_command(
+            SandboxTool::Bubblewrap,
+            policy,
+            "claude",
+            args(&["-p"]),
+            &[],
+        );
+        assert_eq!(
+            wrapped,
+            args(&[
+                "--bind",
+                "/",
+                "/",
+                "--unshare-net",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--die-with-parent",
+                "--",
+                "claude",
+                "-p",
+            ])
+        );
+
+        let (_, wrapped) = wrap_command(
+            SandboxTool::SandboxExec,
+            policy,
+            "claude",
+            args(&["-p"]),
+            &[],
+        );
+        assert_eq!(
+            wrapped[1],
+            "(version 1)\n(allow default)\n\
+             (deny network*)\n(allow network* (remote unix-socket))\n"
+        );
+    }
+
+    #[test]
+    fn missing_sandbox_tool_warns_and_runs_unwrapped() {
+        let policy = SandboxPolicy::for_agent(true, false);
+
+        let command = sandboxed_command_for(policy, "claude", args(&["-p"]), "linux", |_| None);
+        assert_eq!(command.program, "claude");
+        assert_eq!(command.args, args(&["-p"]));
+        assert_eq!(
+            command.warning.as_deref(),
+            Some("bwrap is not installed, so network access can't be blocked; the agent runs without a sandbox")
+        );
+
+        let command = sandboxed_command_for(
+            SandboxPolicy::for_agent(false, false),
+            "claude",
+            args(&["-p"]),
+            "windows",
+            |_| None,
+        );
+        assert_eq!(
+            command.warning.as_deref(),
+            Some("File writes and network access can't be blocked on windows; the agent runs without a sandbox")
+        );
+
+        let command = sandboxed_command_for(policy, "claude", args(&["-p"]), "linux", |_| {
+            Some(PathBuf::from("/usr/bin/bwrap"))
+        });
+        assert_eq!(command.program, "/usr/bin/bwrap");
+        assert!(command.args.contains(&"--unshare-net".to_string()));
+        assert!(command.warning.is_none());
+    }
+
+    #[test]
+    fn read_only_sandbox_keeps_shared_home_directories_read_only() {
+        let home = tempfile::TempDir::new().unwrap();
+        let temp = tempfile::TempDir::new().unwrap();
+        for dir in [
+            ".claude",
+            ".config/opencode",
+            ".config/autostart",
+            ".local/bin",
+            ".npm",
+        ] {
+            std::fs::create_dir_all(home.path().join(dir)).unwrap();
+        }
+        let home_path = home.path().canonicalize().unwrap();
+
+        let writable = writable_paths(Some(home.path()), temp.path());
+        assert!(writable.contains(&home_path.join(".claude")));
+        assert!(writable.contains(&home_path.join(".config/opencode")));
+        // The temp directory is writable too, and here it also holds the home
+        let writable_in_home: Vec<&PathBuf> = writable
+            .iter()
+            .filter(|path| path.starts_with(&home_path))
+            .collect();
+        for shared in [
+            "",
+            ".local/bin",
+            ".local",
+            ".config/autostart",
+            ".config",
+            ".npm",
+        ] {
+            assert!(
+                !writable_in_home
+                    .iter()
+                    .any(|path| home_path.join(shared).starts_with(path)),
+                "~/{} is writable",
+                shared
+            );
+        }
+
+        let command = sandboxed_command_for(
+            SandboxPolicy::for_agent(false, true),
+            "claude",
+            args(&["-p"]),
+            "linux",
+            |_| Some(PathBuf::from("/usr/bin/bwrap")),
+        );
+        let npm_cache = std::env::temp_dir().join(SANDBOX_NPM_CACHE_DIR);
+        assert_eq!(
+            command.env,
+            vec![(
+                "npm_config_cache".to_string(),
+                npm_cache.to_string_lossy().into_owned()
+            )]
+        );
+    }
+}
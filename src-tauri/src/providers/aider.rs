@@ -22,6 +22,12 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: false,
             supports_reasoning_effort: false,
             model_strategy: "flag_optional",
+            models: &[],
+            auth_env_vars: &["OPENAI_API_KEY", "ANTHROPIC_API_KEY"],
+            auth_setup_hints: &[],
+            display_name: "Aider",
+            install_command: "python -m pip install aider-install && aider-install",
+            install_url: "https://aider.chat/docs/install.html",
         },
         build_args,
     }
@@ -52,6 +52,12 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: true,
             supports_reasoning_effort: false,
             model_strategy: "flag_optional",
+            models: &["default", "sonnet", "haiku", "opus"],
+            auth_env_vars: &["ANTHROPIC_API_KEY"],
+            auth_setup_hints: &[],
+            display_name: "Claude Code",
+            install_command: "npm install -g @anthropic-ai/claude-code",
+            install_url: "https://docs.anthropic.com/en/docs/claude-code",
         },
         build_args,
     }
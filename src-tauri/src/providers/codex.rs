@@ -40,6 +40,18 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: false,
             supports_reasoning_effort: true,
             model_strategy: "flag_optional",
+            models: &[
+                "gpt-5.3-codex",
+                "gpt-5.2-codex",
+                "gpt-5.1-codex-max",
+                "gpt-5.2",
+                "gpt-5.1-codex-mini",
+            ],
+            auth_env_vars: &["OPENAI_API_KEY"],
+            auth_setup_hints: &[],
+            display_name: "Codex CLI",
+            install_command: "npm install -g @openai/codex",
+            install_url: "https://github.com/openai/codex",
         },
         build_args,
     }
@@ -25,6 +25,16 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: false,
             supports_reasoning_effort: false,
             model_strategy: "flag_optional",
+            models: &["gemini-2.5-pro", "gemini-2.5-flash"],
+            auth_env_vars: &["GEMINI_API_KEY", "GOOGLE_API_KEY"],
+            auth_setup_hints: &[
+                "Set `GEMINI_API_KEY` (or `GOOGLE_API_KEY`) before running Gemini tasks.",
+                "Or configure Vertex auth with `GOOGLE_GENAI_USE_VERTEXAI=true`, `GOOGLE_CLOUD_PROJECT`, and `GOOGLE_CLOUD_LOCATION`.",
+                "Or run `gcloud auth application-default login` to create ADC credentials.",
+            ],
+            display_name: "Gemini CLI",
+            install_command: "npm install -g @google/gemini-cli",
+            install_url: "https://github.com/google-gemini/gemini-cli",
         },
         build_args,
     }
@@ -25,6 +25,12 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: false,
             supports_reasoning_effort: false,
             model_strategy: "flag_optional",
+            models: &[],
+            auth_env_vars: &[],
+            auth_setup_hints: &[],
+            display_name: "Goose",
+            install_command: "curl -fsSL https://github.com/block/goose/releases/download/stable/download_cli.sh | bash",
+            install_url: "https://block.github.io/goose/docs/getting-started/installation",
         },
         build_args,
     }
@@ -18,6 +18,12 @@ pub fn descriptor() -> ProviderRuntimeDescriptor {
             supports_resume: false,
             supports_reasoning_effort: false,
             model_strategy: "flag_optional",
+            models: &[],
+            auth_env_vars: &[],
+            auth_setup_hints: &[],
+            display_name: "OpenCode",
+            install_command: "npm install -g opencode-ai",
+            install_url: "https://opencode.ai/docs",
         },
         build_args,
     }
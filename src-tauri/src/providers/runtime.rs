@@ -50,6 +50,49 @@ pub struct ProviderCapabilityDef {
     pub supports_resume: bool,
     pub supports_reasoning_effort: bool,
     pub model_strategy: &'static str,
+    /// Known model ids; empty when the CLI's own configuration picks the model
+    pub models: &'static [&'static str],
+    /// Environment variables the CLI reads credentials from
+    pub auth_env_vars: &'static [&'static str],
+    /// Shown when the provider's credentials are missing
+    pub auth_setup_hints: &'static [&'static str],
+    pub display_name: &'static str,
+    pub install_command: &'static str,
+    pub install_url: &'static str,
+}
+
+/// Reasoning effort values accepted by providers that support them
+pub const REASONING_EFFORTS: &[&str] = &["none", "minimal", "low", "medium", "high", "xhigh"];
+
+/// Bumped whenever a field is removed or changes meaning in the exported document
+pub const PROVIDER_CAPABILITIES_SCHEMA_VERSION: u32 = 1;
+
+/// Capability plus the metadata external tooling needs to drive a provider
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderCapabilityDetails {
+    #[serde(flatten)]
+    pub capability: ProviderCapability,
+    pub supported_models: Vec<String>,
+    pub reasoning_efforts: Vec<String>,
+    pub auth_env_vars: Vec<String>,
+}
+
+/// What a new user needs to get a provider's CLI installed and authenticated
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportedProvider {
+    pub provider_id: String,
+    pub display_name: String,
+    pub install_command: String,
+    pub install_url: String,
+    pub auth_env_vars: Vec<String>,
+    pub auth_setup_hints: Vec<String>,
+}
+
+/// Stable JSON document describing every registered provider
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderCapabilitiesDocument {
+    pub schema_version: u32,
+    pub providers: Vec<ProviderCapabilityDetails>,
 }
 
 impl ProviderRuntimeDescriptor {
@@ -62,6 +105,32 @@ impl ProviderRuntimeDescriptor {
             model_strategy: self.capabilities.model_strategy.to_string(),
         }
     }
+
+    pub fn capability_details(&self) -> ProviderCapabilityDetails {
+        let to_strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        ProviderCapabilityDetails {
+            capability: self.capability(),
+            supported_models: to_strings(self.capabilities.models),
+            reasoning_efforts: if self.capabilities.supports_reasoning_effort {
+                to_strings(REASONING_EFFORTS)
+            } else {
+                Vec::new()
+            },
+            auth_env_vars: to_strings(self.capabilities.auth_env_vars),
+        }
+    }
+
+    pub fn supported_provider(&self) -> SupportedProvider {
+        let to_strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        SupportedProvider {
+            provider_id: self.provider_id.to_string(),
+            display_name: self.capabilities.display_name.to_string(),
+            install_command: self.capabilities.install_command.to_string(),
+            install_url: self.capabilities.install_url.to_string(),
+            auth_env_vars: to_strings(self.capabilities.auth_env_vars),
+            auth_setup_hints: to_strings(self.capabilities.auth_setup_hints),
+        }
+    }
 }
 
 pub fn append_optional_model_arg(args: &mut Vec<String>, model: &str) {
@@ -117,6 +186,10 @@ pub fn get_provider_runtime(provider_id: &str) -> Option<&'static ProviderRuntim
     provider_registry().get(provider_id)
 }
 
+pub fn is_known_provider(provider_id: &str) -> bool {
+    provider_registry().contains_key(provider_id)
+}
+
 pub fn list_provider_capabilities() -> Vec<ProviderCapability> {
     let mut capabilities = provider_registry()
         .values()
@@ -126,6 +199,31 @@ pub fn list_provider_capabilities() -> Vec<ProviderCapability> {
     capabilities
 }
 
+pub fn list_supported_providers() -> Vec<SupportedProvider> {
+    let mut providers = provider_registry()
+        .values()
+        .map(ProviderRuntimeDescriptor::supported_provider)
+        .collect::<Vec<_>>();
+    providers.sort_by(|left, right| left.provider_id.cmp(&right.provider_id));
+    providers
+}
+
+pub fn provider_capabilities_document() -> ProviderCapabilitiesDocument {
+    let mut providers = provider_registry()
+        .values()
+        .map(ProviderRuntimeDescriptor::capability_details)
+        .collect::<Vec<_>>();
+    providers.sort_by(|left, right| {
+        left.capability
+            .provider_id
+            .cmp(&right.capability.provider_id)
+    });
+    ProviderCapabilitiesDocument {
+        schema_version: PROVIDER_CAPABILITIES_SCHEMA_VERSION,
+        providers,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,6 +242,48 @@ mod tests {
         assert!(ids.contains(&"opencode".to_string()));
     }
 
+    #[test]
+    fn capabilities_document_lists_every_provider_with_its_fields() {
+        let document = serde_json::to_value(provider_capabilities_document()).unwrap();
+        assert_eq!(
+            document["schema_version"],
+            PROVIDER_CAPABILITIES_SCHEMA_VERSION
+        );
+
+        let providers = document["providers"].as_array().unwrap();
+        let ids = providers
+            .iter()
+            .map(|provider| provider["provider_id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec!["aider", "claude", "codex", "gemini", "goose", "opencode"]
+        );
+
+        for provider in providers {
+            for field in [
+                "supports_continue",
+                "supports_resume",
+                "supports_reasoning_effort",
+                "model_strategy",
+                "supported_models",
+                "reasoning_efforts",
+                "auth_env_vars",
+            ] {
+                assert!(
+                    provider.get(field).is_some(),
+                    "{} is missing {}",
+                    provider["provider_id"],
+                    field
+                );
+            }
+            assert_eq!(
+                provider["supports_reasoning_effort"].as_bool().unwrap(),
+                !provider["reasoning_efforts"].as_array().unwrap().is_empty()
+            );
+        }
+    }
+
     #[test]
     fn sanitize_reasoning_effort_filters_invalid_values() {
         assert_eq!(sanitize_reasoning_effort(Some("xhigh")), Some("xhigh"));
@@ -1,6 +1,31 @@
 use chrono::Utc;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// App identifier the legacy opcode build stored its data under
+const LEGACY_APP_IDENTIFIER: &str = "opcode.asp";
+const ARCHIVE_MARKER: &str = ".opcode-archive-v1.complete";
+const DATABASE_MARKER: &str = ".opcode-db-migration-v1.complete";
+
+/// Agent columns copied from the legacy database, with the SQL value used when
+/// the legacy schema predates the column. `name` is required.
+const LEGACY_AGENT_COLUMNS: &[(&str, &str)] = &[
+    ("name", "NULL"),
+    ("icon", "'bot'"),
+    ("system_prompt", "''"),
+    ("default_task", "NULL"),
+    ("model", "'sonnet'"),
+    ("enable_file_read", "1"),
+    ("enable_file_write", "1"),
+    ("enable_network", "0"),
+    ("hooks", "NULL"),
+];
 
 fn legacy_targets(home: &Path) -> Vec<PathBuf> {
     vec![
@@ -13,26 +38,43 @@ fn legacy_targets(home: &Path) -> Vec<PathBuf> {
     ]
 }
 
-pub fn archive_legacy_opcode_state() {
-    let Some(home) = dirs::home_dir() else {
-        tracing::warn!("Unable to resolve home directory for legacy archive check");
-        return;
-    };
+/// Legacy paths under `home` that still exist
+fn detect_legacy_targets(home: &Path) -> Vec<PathBuf> {
+    legacy_targets(home)
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
 
-    let legacy_root = home.join(".codeinterfacex").join("legacy");
-    let marker_path = legacy_root.join(".opcode-archive-v1.complete");
+/// The legacy app's agents database, when one is left in the platform data dir
+fn detect_legacy_database(data_dir: &Path) -> Option<PathBuf> {
+    let path = data_dir.join(LEGACY_APP_IDENTIFIER).join("agents.db");
+    path.is_file().then_some(path)
+}
 
-    if marker_path.exists() {
-        return;
-    }
+/// What `migrate_legacy_state` found and did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LegacyMigrationSummary {
+    /// Legacy paths found on disk, including the legacy database
+    pub detected: Vec<String>,
+    /// Paths moved into `archive_dir`
+    pub archived: Vec<String>,
+    pub archive_dir: Option<String>,
+    pub migrated_agents: usize,
+    pub migrated_settings: usize,
+    pub errors: Vec<String>,
+}
 
-    let existing_targets: Vec<PathBuf> = legacy_targets(&home)
-        .into_iter()
-        .filter(|path| path.exists())
-        .collect();
+/// Move `targets` into a timestamped directory under `legacy_root` and write the marker
+fn archive_targets(
+    targets: Vec<PathBuf>,
+    legacy_root: &Path,
+    summary: &mut LegacyMigrationSummary,
+) {
+    let marker_path = legacy_root.join(ARCHIVE_MARKER);
 
-    if existing_targets.is_empty() {
-        if let Err(err) = fs::create_dir_all(&legacy_root) {
+    if targets.is_empty() {
+        if let Err(err) = fs::create_dir_all(legacy_root) {
             tracing::warn!("Failed to create legacy archive directory: {}", err);
             return;
         }
@@ -47,12 +89,20 @@ pub fn archive_legacy_opcode_state() {
     let archive_dir = legacy_root.join(format!("opcode-{}", stamp));
     if let Err(err) = fs::create_dir_all(&archive_dir) {
         tracing::warn!("Failed to create legacy archive destination: {}", err);
+        summary.errors.push(format!(
+            "Failed to create legacy archive destination: {}",
+            err
+        ));
         return;
     }
+    summary.archive_dir = Some(archive_dir.display().to_string());
 
-    for source in existing_targets {
+    for source in targets {
         let Some(file_name) = source.file_name() else {
-            tracing::warn!("Skipping legacy path without file name: {}", source.display());
+            tracing::warn!(
+                "Skipping legacy path without file name: {}",
+                source.display()
+            );
             continue;
         };
 
@@ -64,6 +114,7 @@ pub fn archive_legacy_opcode_state() {
                     destination = %destination.display(),
                     "Archived legacy opcode state"
                 );
+                summary.archived.push(source.display().to_string());
             }
             Err(err) => {
                 tracing::warn!(
@@ -72,6 +123,9 @@ pub fn archive_legacy_opcode_state() {
                     "Failed to archive legacy opcode state: {}",
                     err
                 );
+                summary
+                    .errors
+                    .push(format!("Failed to archive {}: {}", source.display(), err));
             }
         }
     }
@@ -85,3 +139,313 @@ pub fn archive_legacy_opcode_state() {
         tracing::warn!("Failed to write legacy archive marker: {}", err);
     }
 }
+
+/// Copy agents and settings from the legacy database. Agents whose name already
+/// exists and settings already set in the current database are left alone.
+fn migrate_legacy_database(legacy_db: &Path, conn: &Connection) -> Result<(usize, usize), String> {
+    let legacy = Connection::open_with_flags(legacy_db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open legacy database: {}", e))?;
+
+    // Older legacy builds lack some columns; those get the current defaults
+    let legacy_columns: Vec<String> = legacy
+        .prepare("SELECT name FROM pragma_table_info('agents')")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| format!("Failed to read legacy agents: {}", e))?;
+    if !legacy_columns.iter().any(|column| column == "name") {
+        return Err("Legacy agents table has no name column".to_string());
+    }
+    let select = LEGACY_AGENT_COLUMNS
+        .iter()
+        .map(|(column, default)| {
+            if legacy_columns.iter().any(|legacy| legacy == column) {
+                column.to_string()
+            } else {
+                format!("{} AS {}", default, column)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut stmt = legacy
+        .prepare(&format!(
+            "SELECT {} FROM agents WHERE name IS NOT NULL",
+            select
+        ))
+        .map_err(|e| format!("Failed to read legacy agents: {}", e))?;
+    let agents = stmt
+        .query_map([], |row| {
+            (0..LEGACY_AGENT_COLUMNS.len())
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("Failed to read legacy agents: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read legacy agents: {}", e))?;
+
+    let columns = LEGACY_AGENT_COLUMNS
+        .iter()
+        .map(|(column, _)| *column)
+        .collect::<Vec<_>>();
+    let placeholders = (1..=columns.len())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>();
+    let insert = format!(
+        "INSERT INTO agents ({})
+         SELECT {}
+         WHERE NOT EXISTS (SELECT 1 FROM agents WHERE name = ?1)",
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+    let mut migrated_agents = 0;
+    for agent in agents {
+        migrated_agents += conn
+            .execute(&insert, params_from_iter(&agent))
+            .map_err(|e| format!("Failed to migrate agent {:?}: {}", agent[0], e))?;
+    }
+
+    // Older builds may predate app_settings; that's not an error
+    let settings: Vec<(String, String)> = legacy
+        .prepare("SELECT key, value FROM app_settings")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut migrated_settings = 0;
+    for (key, value) in settings {
+        migrated_settings += conn
+            .execute(
+                "INSERT OR IGNORE INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .map_err(|e| format!("Failed to migrate setting '{}': {}", key, e))?;
+    }
+
+    Ok((migrated_agents, migrated_settings))
+}
+
+/// Migrate the legacy database into `conn`, when given, and archive other legacy
+/// paths. Each step runs once; markers under `~/.codeinterfacex/legacy` record
+/// completion.
+fn migrate_legacy_state_in(
+    home: &Path,
+    data_dir: &Path,
+    conn: Option<&Connection>,
+) -> LegacyMigrationSummary {
+    let legacy_root = home.join(".codeinterfacex").join("legacy");
+    let mut summary = LegacyMigrationSummary::default();
+
+    let database_marker = legacy_root.join(DATABASE_MARKER);
+    if let Some(conn) = conn.filter(|_| !database_marker.exists()) {
+        if let Some(legacy_db) = detect_legacy_database(data_dir) {
+            summary.detected.push(legacy_db.display().to_string());
+            match migrate_legacy_database(&legacy_db, conn) {
+                Ok((agents, settings)) => {
+                    summary.migrated_agents = agents;
+                    summary.migrated_settings = settings;
+                    let _ = fs::create_dir_all(&legacy_root);
+                    let marker_contents = format!(
+                        "migrated_at={}\nsource={}\nagents={}\nsettings={}\n",
+                        Utc::now().to_rfc3339(),
+                        legacy_db.display(),
+                        agents,
+                        settings
+                    );
+                    if let Err(err) = fs::write(&database_marker, marker_contents) {
+                        tracing::warn!("Failed to write legacy database marker: {}", err);
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!("Failed to migrate legacy opcode database: {}", err);
+                    summary.errors.push(err);
+                }
+            }
+        }
+    }
+
+    if !legacy_root.join(ARCHIVE_MARKER).exists() {
+        let targets = detect_legacy_targets(home);
+        summary
+            .detected
+            .extend(targets.iter().map(|path| path.display().to_string()));
+        archive_targets(targets, &legacy_root, &mut summary);
+    }
+
+    summary
+}
+
+/// Migrate legacy opcode settings/agents into the current database and archive
+/// the remaining legacy state. Without a database, as in the web server, only
+/// the archive step runs.
+pub fn migrate_legacy_state_for(
+    conn: Option<&Connection>,
+) -> Result<LegacyMigrationSummary, String> {
+    let home = dirs::home_dir().ok_or("Unable to resolve home directory")?;
+    let data_dir = dirs::data_dir().ok_or("Unable to resolve data directory")?;
+    let summary = migrate_legacy_state_in(&home, &data_dir, conn);
+    if !summary.detected.is_empty() {
+        tracing::info!("Legacy opcode migration: {:?}", summary);
+    }
+    Ok(summary)
+}
+
+/// Detect legacy opcode state, migrate what can be migrated and archive the rest
+#[tauri::command]
+pub async fn migrate_legacy_state(
+    db: State<'_, AgentDb>,
+) -> Result<LegacyMigrationSummary, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    migrate_legacy_state_for(Some(&conn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                icon TEXT NOT NULL,
+                system_prompt TEXT NOT NULL,
+                default_task TEXT,
+                model TEXT NOT NULL DEFAULT 'sonnet',
+                enable_file_read BOOLEAN NOT NULL DEFAULT 1,
+                enable_file_write BOOLEAN NOT NULL DEFAULT 1,
+                enable_network BOOLEAN NOT NULL DEFAULT 0,
+                hooks TEXT
+            );
+            CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            INSERT INTO agents (name, icon, system_prompt) VALUES ('Reviewer', 'bot', 'current');
+            INSERT INTO app_settings (key, value) VALUES ('theme', 'dark');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn write_legacy_db(data_dir: &Path) {
+        let dir = data_dir.join(LEGACY_APP_IDENTIFIER);
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = Connection::open(dir.join("agents.db")).unwrap();
+        legacy
+            .execute_batch(
+                "CREATE TABLE agents (
+                    id INTEGER PRIMARY KEY, name TEXT, icon TEXT, system_prompt TEXT,
+                    default_task TEXT, model TEXT, hooks TEXT
+                );
+                CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT);
+                INSERT INTO agents (name, icon, system_prompt, model)
+                    VALUES ('Reviewer', 'bot', 'legacy', 'opus'), ('Tester', 'bug', 'legacy', 'opus');
+                INSERT INTO app_settings (key, value)
+                    VALUES ('theme', 'light'), ('claude_binary_path', '/usr/bin/claude');",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn detects_only_existing_legacy_paths() {
+        let home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        assert!(detect_legacy_targets(home.path()).is_empty());
+        assert_eq!(detect_legacy_database(data_dir.path()), None);
+
+        fs::create_dir_all(home.path().join(".opcode")).unwrap();
+        fs::write(home.path().join(".opcode-usage-debug.log"), "").unwrap();
+        write_legacy_db(data_dir.path());
+
+        assert_eq!(
+            detect_legacy_targets(home.path()),
+            vec![
+                home.path().join(".opcode"),
+                home.path().join(".opcode-usage-debug.log")
+            ]
+        );
+        assert!(detect_legacy_database(data_dir.path()).is_some());
+    }
+
+    #[test]
+    fn migrates_database_archives_the_rest_and_runs_once() {
+        let home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(home.path().join(".opcode")).unwrap();
+        write_legacy_db(data_dir.path());
+        let conn = current_db();
+
+        let summary = migrate_legacy_state_in(home.path(), data_dir.path(), Some(&conn));
+        assert_eq!(summary.detected.len(), 2);
+        assert_eq!(summary.migrated_agents, 1);
+        assert_eq!(summary.migrated_settings, 1);
+        assert_eq!(
+            summary.archived,
+            vec![home.path().join(".opcode").display().to_string()]
+        );
+        assert!(summary.errors.is_empty());
+        assert!(!home.path().join(".opcode").exists());
+
+        let theme: String = conn
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = 'theme'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(theme, "dark");
+
+        let again = migrate_legacy_state_in(home.path(), data_dir.path(), Some(&conn));
+        assert_eq!(again, LegacyMigrationSummary::default());
+    }
+
+    #[test]
+    fn legacy_agents_missing_newer_columns_get_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy_path = dir.path().join("agents.db");
+        Connection::open(&legacy_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE agents (
+                    id INTEGER PRIMARY KEY, name TEXT, icon TEXT, system_prompt TEXT,
+                    enable_network BOOLEAN
+                );
+                INSERT INTO agents (name, icon, system_prompt, enable_network)
+                    VALUES ('Fetcher', 'globe', 'legacy', 1);",
+            )
+            .unwrap();
+        let conn = current_db();
+
+        assert_eq!(
+            migrate_legacy_database(&legacy_path, &conn).unwrap(),
+            (1, 0)
+        );
+        let (model, hooks, enable_network): (String, Option<String>, bool) = conn
+            .query_row(
+                "SELECT model, hooks, enable_network FROM agents WHERE name = 'Fetcher'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(model, "sonnet");
+        assert_eq!(hooks, None);
+        assert!(enable_network);
+    }
+
+    #[test]
+    fn without_a_database_only_legacy_paths_are_archived() {
+        let home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(home.path().join(".opcode")).unwrap();
+        write_legacy_db(data_dir.path());
+
+        let summary = migrate_legacy_state_in(home.path(), data_dir.path(), None);
+        assert_eq!(summary.detected.len(), 1);
+        assert_eq!(summary.migrated_agents, 0);
+        assert!(!home.path().join(".opcode").exists());
+        // The database step is left for a run that has one
+        assert!(!home
+            .path()
+            .join(".codeinterfacex/legacy")
+            .join(DATABASE_MARKER)
+            .exists());
+    }
+}
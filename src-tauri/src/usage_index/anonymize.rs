@@ -0,0 +1,172 @@
+use crate::commands::agents::read_app_setting;
+use crate::usage_index::{ProjectUsage, UsageEntry, UsageStats};
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Setting key (in `app_settings`) holding this install's pseudonym salt
+pub const ANONYMIZE_SALT_SETTING: &str = "usage_anonymize_salt";
+
+/// The install's pseudonym salt, generated and saved on first use. Without a
+/// secret salt, a pseudonym could be reversed by hashing guessed project paths.
+pub fn load_or_create_anonymize_salt(conn: &Connection) -> Result<String, String> {
+    if let Some(salt) = read_app_setting(conn, ANONYMIZE_SALT_SETTING) {
+        return Ok(salt);
+    }
+
+    let salt = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    // Another caller may have stored a salt since the read; theirs wins
+    conn.execute(
+        "INSERT OR IGNORE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![ANONYMIZE_SALT_SETTING, salt],
+    )
+    .map_err(|e| format!("Failed to save anonymization salt: {}", e))?;
+    read_app_setting(conn, ANONYMIZE_SALT_SETTING)
+        .ok_or_else(|| "Failed to read back anonymization salt".to_string())
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+
+    let inner = Sha256::new()
+        .chain_update(inner_pad)
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(outer_pad)
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Stable stand-in for a project path: with the same salt, the same path always
+/// maps to the same pseudonym, so rows grouped by project stay grouped after
+/// anonymizing.
+pub fn project_pseudonym(salt: &str, project_path: &str) -> String {
+    let digest = hmac_sha256(salt.as_bytes(), project_path.as_bytes());
+    let hex: String = digest[..6].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("project-{}", hex)
+}
+
+pub fn anonymize_usage_stats(salt: &str, stats: &mut UsageStats) {
+    for project in &mut stats.by_project {
+        let pseudonym = project_pseudonym(salt, &project.project_path);
+        project.project_name = pseudonym.clone();
+        project.project_path = pseudonym;
+    }
+}
+
+pub fn anonymize_usage_entries(salt: &str, entries: &mut [UsageEntry]) {
+    for entry in entries {
+        entry.project_path = project_pseudonym(salt, &entry.project_path);
+    }
+}
+
+/// Session rows carry the session id in `project_name`, so only the path is replaced
+pub fn anonymize_session_usage(salt: &str, sessions: &mut [ProjectUsage]) {
+    for session in sessions {
+        session.project_path = project_pseudonym(salt, &session.project_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(path: &str, cost: f64) -> ProjectUsage {
+        ProjectUsage {
+            project_path: path.to_string(),
+            project_name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            total_cost: cost,
+            total_tokens: 10,
+            session_count: 1,
+            last_used: "2025-03-10T12:00:00Z".to_string(),
+        }
+    }
+
+    const SALT: &str = "test-salt";
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let digest = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // Keys longer than a block are hashed first
+        let digest = hmac_sha256(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+        );
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            hex,
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn pseudonyms_are_stable_per_path_and_salt() {
+        let first = project_pseudonym(SALT, "/Users/me/secret-client");
+        assert_eq!(first, project_pseudonym(SALT, "/Users/me/secret-client"));
+        assert_ne!(first, project_pseudonym(SALT, "/Users/me/other-client"));
+        assert_ne!(
+            first,
+            project_pseudonym("other-salt", "/Users/me/secret-client")
+        );
+        assert!(first.starts_with("project-"));
+        assert!(!first.contains("secret"));
+    }
+
+    #[test]
+    fn salt_is_created_once_and_reused() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+
+        let salt = load_or_create_anonymize_salt(&conn).unwrap();
+        assert_eq!(salt.len(), 64);
+        assert_eq!(load_or_create_anonymize_salt(&conn).unwrap(), salt);
+        assert_eq!(
+            read_app_setting(&conn, ANONYMIZE_SALT_SETTING).as_deref(),
+            Some(salt.as_str())
+        );
+    }
+
+    #[test]
+    fn anonymized_stats_keep_per_project_totals() {
+        let mut stats = UsageStats {
+            by_project: vec![
+                project("/Users/me/secret-client", 2.0),
+                project("/Users/me/other-client", 1.0),
+            ],
+            ..Default::default()
+        };
+
+        anonymize_usage_stats(SALT, &mut stats);
+
+        assert_eq!(
+            stats.by_project[0].project_path,
+            project_pseudonym(SALT, "/Users/me/secret-client")
+        );
+        assert_eq!(stats.by_project[0].project_name, stats.by_project[0].project_path);
+        assert_ne!(stats.by_project[0].project_path, stats.by_project[1].project_path);
+        assert_eq!(stats.by_project[0].total_cost, 2.0);
+        assert_eq!(stats.by_project[1].total_cost, 1.0);
+    }
+}
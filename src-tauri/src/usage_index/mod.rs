@@ -8,6 +8,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
 
+pub mod anonymize;
 pub mod query;
 pub mod schema;
 pub mod sync;
@@ -86,6 +87,24 @@ pub struct ProjectUsage {
     pub last_used: String,
 }
 
+/// One model's portion of a session's usage; shares are fractions in `0.0..=1.0`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionModelShare {
+    pub model: String,
+    pub total_cost: f64,
+    pub total_tokens: u64,
+    pub cost_share: f64,
+    pub token_share: f64,
+}
+
+/// Usage totals for a single local calendar day
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DayUsageTotals {
+    pub date: String,
+    pub total_cost: f64,
+    pub total_tokens: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageIndexStatus {
     pub state: String,
@@ -1,5 +1,10 @@
-use crate::usage_index::{DailyUsage, ModelUsage, ProjectUsage, UsageEntry, UsageStats};
-use rusqlite::{params_from_iter, types::ToSql, Connection};
+use crate::usage_index::{
+    DailyUsage, DayUsageTotals, ModelUsage, ProjectUsage, SessionModelShare, UsageEntry,
+    UsageStats,
+};
+use chrono::{NaiveDate, TimeZone, Utc};
+use rusqlite::{params, params_from_iter, types::ToSql, Connection};
+use std::collections::HashMap;
 
 const MAX_LIMIT: u32 = 500;
 
@@ -335,3 +340,226 @@ pub fn query_session_stats(
     rows.collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Failed to parse session usage rows: {}", e))
 }
+
+/// Total cost and token count per session id, limited to the given sessions.
+pub fn query_session_totals(
+    conn: &Connection,
+    session_ids: &[String],
+) -> Result<HashMap<String, (f64, u64)>, String> {
+    let mut totals = HashMap::new();
+    if session_ids.is_empty() {
+        return Ok(totals);
+    }
+
+    let placeholders = vec!["?"; session_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT session_id, \
+         COALESCE(SUM(cost), 0), \
+         COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0) \
+         FROM usage_events WHERE session_id IN ({}) \
+         GROUP BY session_id",
+        placeholders
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare session totals query: {}", e))?;
+
+    let rows = stmt
+        .query_map(params_from_iter(session_ids.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, i64>(2)?.max(0) as u64,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute session totals query: {}", e))?;
+
+    for row in rows {
+        let (session_id, cost, tokens) =
+            row.map_err(|e| format!("Failed to parse session totals row: {}", e))?;
+        totals.insert(session_id, (cost, tokens));
+    }
+
+    Ok(totals)
+}
+
+/// Per-model split of a session's tokens and cost, largest share first
+pub fn query_session_model_mix(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Vec<SessionModelShare>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT model, \
+             COALESCE(SUM(cost), 0), \
+             COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0) \
+             FROM usage_events WHERE session_id = ?1 \
+             GROUP BY model",
+        )
+        .map_err(|e| format!("Failed to prepare session model mix query: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![session_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, i64>(2)?.max(0) as u64,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute session model mix query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to parse session model mix rows: {}", e))?;
+
+    let session_cost: f64 = rows.iter().map(|(_, cost, _)| cost).sum();
+    let session_tokens: u64 = rows.iter().map(|(_, _, tokens)| tokens).sum();
+    let share = |part: f64, total: f64| if total > 0.0 { part / total } else { 0.0 };
+
+    let mut mix = rows
+        .into_iter()
+        .map(|(model, total_cost, total_tokens)| SessionModelShare {
+            cost_share: share(total_cost, session_cost),
+            token_share: share(total_tokens as f64, session_tokens as f64),
+            model,
+            total_cost,
+            total_tokens,
+        })
+        .collect::<Vec<_>>();
+    mix.sort_by(|a, b| {
+        b.total_tokens
+            .cmp(&a.total_tokens)
+            .then_with(|| a.model.cmp(&b.model))
+    });
+
+    Ok(mix)
+}
+
+/// UTC bounds `[start, end)` of `date` in `tz`, formatted to compare against
+/// the stored RFC 3339 UTC timestamps. `event_date` is derived from the
+/// timestamp's own offset, so it can't be used for the local day.
+pub fn day_bounds_utc<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> Option<(String, String)> {
+    let to_utc = |day: NaiveDate| {
+        tz.from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+            .earliest()
+            .map(|start| {
+                start
+                    .with_timezone(&Utc)
+                    .format("%Y-%m-%dT%H:%M:%S")
+                    .to_string()
+            })
+    };
+    Some((to_utc(date)?, to_utc(date.succ_opt()?)?))
+}
+
+/// Cost and token totals for `date` in `tz`, using the timestamp index
+pub fn query_day_totals<Tz: TimeZone>(
+    conn: &Connection,
+    tz: &Tz,
+    date: NaiveDate,
+) -> Result<DayUsageTotals, String> {
+    let (start, end) =
+        day_bounds_utc(tz, date).ok_or_else(|| format!("Invalid local day: {}", date))?;
+
+    let (total_cost, total_tokens) = conn
+        .query_row(
+            "SELECT \
+             COALESCE(SUM(cost), 0), \
+             COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0) \
+             FROM usage_events WHERE timestamp >= ?1 AND timestamp < ?2",
+            params![start, end],
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .map_err(|e| format!("Failed to execute day totals query: {}", e))?;
+
+    Ok(DayUsageTotals {
+        date: date.format("%Y-%m-%d").to_string(),
+        total_cost,
+        total_tokens: total_tokens.max(0) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage_index::schema::ensure_schema;
+    use chrono::FixedOffset;
+
+    fn insert_event(conn: &Connection, uid: &str, timestamp: &str, cost: f64, tokens: i64) {
+        conn.execute(
+            "INSERT INTO usage_events (event_uid, source_path, source_line, timestamp, event_date, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost, session_id, project_path, project_name)
+             VALUES (?1, '/log.jsonl', 1, ?2, substr(?2, 1, 10), 'claude-sonnet-4', ?3, 0, 0, 0, ?4, 's1', '/p', 'p')",
+            params![uid, timestamp, tokens, cost],
+        )
+        .unwrap();
+    }
+
+    fn insert_session_event(conn: &Connection, uid: &str, model: &str, tokens: i64, cost: f64) {
+        conn.execute(
+            "INSERT INTO usage_events (event_uid, source_path, source_line, timestamp, event_date, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost, session_id, project_path, project_name)
+             VALUES (?1, '/log.jsonl', 1, '2025-03-10T12:00:00Z', '2025-03-10', ?2, ?3, 0, 0, 0, ?4, 'mixed', '/p', 'p')",
+            params![uid, model, tokens, cost],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn session_model_mix_splits_tokens_and_cost_by_model() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&conn).unwrap();
+        insert_session_event(&conn, "a", "claude-opus-4", 200, 3.0);
+        insert_session_event(&conn, "b", "claude-opus-4", 100, 1.5);
+        insert_session_event(&conn, "c", "claude-sonnet-4", 700, 0.5);
+        insert_event(&conn, "other", "2025-03-10T12:00:00Z", 9.0, 5000);
+
+        let mix = query_session_model_mix(&conn, "mixed").unwrap();
+
+        assert_eq!(mix.len(), 2);
+        assert_eq!(mix[0].model, "claude-sonnet-4");
+        assert_eq!(mix[0].total_tokens, 700);
+        assert!((mix[0].token_share - 0.7).abs() < 1e-9);
+        assert!((mix[0].cost_share - 0.1).abs() < 1e-9);
+        assert_eq!(mix[1].model, "claude-opus-4");
+        assert_eq!(mix[1].total_tokens, 300);
+        assert!((mix[1].token_share - 0.3).abs() < 1e-9);
+        assert!((mix[1].cost_share - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn session_model_mix_is_empty_for_unknown_session() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&conn).unwrap();
+        assert!(query_session_model_mix(&conn, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn day_totals_only_count_the_local_day() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&conn).unwrap();
+        // UTC+02:00: local 2025-03-10 runs from 2025-03-09T22:00Z to 2025-03-10T22:00Z
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        insert_event(&conn, "before", "2025-03-09T21:59:59.999Z", 1.0, 10);
+        insert_event(&conn, "start", "2025-03-09T22:00:00.000Z", 0.5, 100);
+        insert_event(&conn, "late", "2025-03-10T21:59:59.500Z", 0.25, 200);
+        insert_event(&conn, "after", "2025-03-10T22:00:00.000Z", 2.0, 1000);
+
+        let totals = query_day_totals(&conn, &tz, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap())
+            .unwrap();
+
+        assert_eq!(totals.date, "2025-03-10");
+        assert_eq!(totals.total_tokens, 300);
+        assert!((totals.total_cost - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn day_totals_are_zero_without_events() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&conn).unwrap();
+        insert_event(&conn, "other_day", "2025-03-08T12:00:00Z", 1.0, 10);
+
+        let totals =
+            query_day_totals(&conn, &Utc, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()).unwrap();
+
+        assert_eq!(totals.total_tokens, 0);
+        assert_eq!(totals.total_cost, 0.0);
+    }
+}
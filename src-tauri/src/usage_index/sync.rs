@@ -601,19 +601,62 @@ fn process_file(
     Ok(())
 }
 
+/// Drop every indexed event and the per-file offsets so the next sync
+/// re-reads all JSONL files from the start.
+pub fn clear_usage_index(conn: &mut Connection) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start usage index reset: {}", e))?;
+    tx.execute("DELETE FROM usage_events", [])
+        .map_err(|e| format!("Failed to clear usage events: {}", e))?;
+    tx.execute("DELETE FROM source_files", [])
+        .map_err(|e| format!("Failed to clear source file cache: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit usage index reset: {}", e))?;
+
+    Ok(())
+}
+
+/// Clear the index and run a full sync
+pub fn run_usage_index_rebuild(app: &AppHandle, state: &UsageIndexState) -> Result<SyncOutcome, String> {
+    append_usage_debug_log("usage_index_rebuild start");
+    let mut conn = open_usage_index_connection(app)?;
+    let files = list_usage_jsonl_files()?;
+    rebuild_usage_index_in(&mut conn, state, &files)
+}
+
 pub fn run_usage_index_sync(app: &AppHandle, state: &UsageIndexState) -> Result<SyncOutcome, String> {
-    let started_at = Local::now();
     append_usage_debug_log("usage_index_sync start");
-
     let mut conn = open_usage_index_connection(app)?;
     let files = list_usage_jsonl_files()?;
+    sync_usage_index_in(&mut conn, state, &files)
+}
+
+/// Clear the index behind `conn` and re-read every file in `files`
+fn rebuild_usage_index_in(
+    conn: &mut Connection,
+    state: &UsageIndexState,
+    files: &[PathBuf],
+) -> Result<SyncOutcome, String> {
+    clear_usage_index(conn)?;
+    sync_usage_index_in(conn, state, files)
+}
+
+/// Bring the index behind `conn` up to date with `files`, dropping rows for
+/// files that no longer exist
+fn sync_usage_index_in(
+    conn: &mut Connection,
+    state: &UsageIndexState,
+    files: &[PathBuf],
+) -> Result<SyncOutcome, String> {
+    let started_at = Local::now();
 
     let mut tracked_paths = HashSet::new();
-    for path in &files {
+    for path in files {
         tracked_paths.insert(path.to_string_lossy().to_string());
     }
 
-    remove_deleted_files(&mut conn, &tracked_paths)?;
+    remove_deleted_files(conn, &tracked_paths)?;
 
     let mut outcome = SyncOutcome::default();
     outcome.files_total = files.len() as u64;
@@ -631,7 +674,7 @@ pub fn run_usage_index_sync(app: &AppHandle, state: &UsageIndexState) -> Result<
         }
 
         process_file(
-            &mut conn,
+            conn,
             state,
             path,
             (index + 1) as u64,
@@ -669,3 +712,59 @@ pub fn run_usage_index_sync(app: &AppHandle, state: &UsageIndexState) -> Result<
 
     Ok(outcome)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage_index::schema::ensure_schema;
+    use tempfile::TempDir;
+
+    fn event_uids(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT event_uid FROM usage_events ORDER BY event_uid")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn rebuild_clears_prior_entries_before_repopulating() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.jsonl");
+        std::fs::write(
+            &path,
+            r#"{"timestamp":"2025-01-02T10:00:00Z","sessionId":"s1","requestId":"r1","message":{"id":"m1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#
+                .to_string()
+                + "\n",
+        )
+        .unwrap();
+        let source_path = path.to_string_lossy().to_string();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO usage_events (event_uid, source_path, source_line, timestamp, event_date, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost, session_id, project_path, project_name)
+             VALUES ('stale', ?1, 1, '2025-01-01T00:00:00Z', '2025-01-01', 'old', 1, 1, 0, 0, 9.0, 's0', '/p', 'p')",
+            params![source_path],
+        )
+        .unwrap();
+        // A cached offset at end-of-file would make an incremental sync skip the file
+        let size = file_size_bytes(&path).unwrap();
+        let mtime = file_mtime_unix_ms(&path).unwrap();
+        conn.execute(
+            "INSERT INTO source_files (source_path, size_bytes, modified_unix_ms, last_offset, last_line, last_scanned_at)
+             VALUES (?1, ?2, ?3, ?2, 1, '2025-01-01T00:00:00Z')",
+            params![source_path, size, mtime],
+        )
+        .unwrap();
+
+        let state = UsageIndexState::default();
+        let outcome = rebuild_usage_index_in(&mut conn, &state, &[path]).unwrap();
+
+        assert!(!outcome.cancelled);
+        assert_eq!(event_uids(&conn), vec!["mr:m1:r1".to_string()]);
+        assert_eq!(outcome.entries_indexed, 1);
+    }
+}
@@ -0,0 +1,400 @@
+//! Persistent history of provider sessions run over the web server's WebSocket.
+//!
+//! Each request gets a history id; the request itself and every message streamed
+//! back to the client are stored in a small SQLite database in the web data
+//! directory, so a transcript survives the client disconnecting.
+//!
+//! Writes never block the caller: they are queued for a writer thread, which
+//! commits whatever has queued up in one transaction. Sessions older than
+//! [`HISTORY_RETENTION_DAYS`] are pruned as new ones start.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
+
+/// Database file inside the web data directory
+const WEB_HISTORY_DB: &str = "web_sessions.db";
+
+/// How long a session's transcript is kept
+pub const HISTORY_RETENTION_DAYS: i64 = 30;
+
+/// Most writes committed in one transaction
+const MAX_WRITE_BATCH: usize = 512;
+
+/// Web data directory: `CODEINTERFACEX_WEB_DATA_DIR`, or `~/.codeinterfacex/web`
+pub fn web_data_dir() -> PathBuf {
+    std::env::var("CODEINTERFACEX_WEB_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".codeinterfacex")
+                .join("web")
+        })
+}
+
+/// A recorded web session with everything that was streamed to the client
+#[derive(Debug, Clone, Serialize)]
+pub struct WebSessionTranscript {
+    pub id: String,
+    pub command_type: String,
+    pub project_path: String,
+    pub prompt: String,
+    pub model: Option<String>,
+    /// `running`, or the completion status sent to the client
+    pub status: String,
+    pub error: Option<String>,
+    pub provider_session_id: Option<String>,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+    /// Streamed messages, in the order they were sent
+    pub messages: Vec<serde_json::Value>,
+}
+
+/// A write queued for the writer thread
+enum HistoryWrite {
+    Start {
+        id: String,
+        command_type: String,
+        project_path: String,
+        prompt: String,
+        model: Option<String>,
+        started_at: String,
+    },
+    Message {
+        id: String,
+        message: String,
+    },
+    Finish {
+        id: String,
+        status: String,
+        error: Option<String>,
+        provider_session_id: Option<String>,
+        completed_at: String,
+    },
+    /// Answered once every write queued before it is committed
+    Flush(mpsc::Sender<()>),
+}
+
+pub struct WebSessionStore {
+    writer: Option<mpsc::Sender<HistoryWrite>>,
+    writer_thread: Option<JoinHandle<()>>,
+    reader: Mutex<Connection>,
+}
+
+impl WebSessionStore {
+    /// Open (creating if needed) the history database in `data_dir`
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create web data directory: {}", e))?;
+        let path = data_dir.join(WEB_HISTORY_DB);
+        let conn = Connection::open(&path)
+            .map_err(|e| format!("Failed to open web session history: {}", e))?;
+        // WAL lets transcripts be read while the writer thread commits
+        if let Err(error) = conn.pragma_update(None, "journal_mode", "WAL") {
+            println!(
+                "[TRACE] Failed to enable WAL for web session history: {}",
+                error
+            );
+        }
+        init_schema(&conn)
+            .map_err(|e| format!("Failed to initialize web session history: {}", e))?;
+        if let Err(error) = prune_expired(&conn) {
+            println!("[TRACE] Failed to prune web session history: {}", error);
+        }
+        let reader = Connection::open(&path)
+            .map_err(|e| format!("Failed to open web session history: {}", e))?;
+
+        let (writer, writes) = mpsc::channel();
+        let writer_thread = std::thread::Builder::new()
+            .name("web-history-writer".to_string())
+            .spawn(move || run_writer(conn, writes))
+            .map_err(|e| format!("Failed to start web session history writer: {}", e))?;
+        Ok(Self {
+            writer: Some(writer),
+            writer_thread: Some(writer_thread),
+            reader: Mutex::new(reader),
+        })
+    }
+
+    fn queue(&self, write: HistoryWrite) -> Result<(), String> {
+        self.writer
+            .as_ref()
+            .and_then(|writer| writer.send(write).ok())
+            .ok_or_else(|| "Web session history writer has stopped".to_string())
+    }
+
+    /// Wait until every write queued so far is committed
+    fn flush(&self) -> Result<(), String> {
+        let (done, flushed) = mpsc::channel();
+        self.queue(HistoryWrite::Flush(done))?;
+        flushed
+            .recv()
+            .map_err(|_| "Web session history writer has stopped".to_string())
+    }
+
+    /// Record the start of a session for a WebSocket request
+    pub fn start_session(
+        &self,
+        id: &str,
+        command_type: &str,
+        project_path: &str,
+        prompt: &str,
+        model: Option<&str>,
+    ) -> Result<(), String> {
+        self.queue(HistoryWrite::Start {
+            id: id.to_string(),
+            command_type: command_type.to_string(),
+            project_path: project_path.to_string(),
+            prompt: prompt.to_string(),
+            model: model.map(str::to_string),
+            started_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Append a message streamed to the client
+    pub fn append_message(&self, id: &str, message: &str) -> Result<(), String> {
+        self.queue(HistoryWrite::Message {
+            id: id.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    /// Record how a session ended
+    pub fn finish_session(
+        &self,
+        id: &str,
+        status: &str,
+        error: Option<&str>,
+        provider_session_id: Option<&str>,
+    ) -> Result<(), String> {
+        self.queue(HistoryWrite::Finish {
+            id: id.to_string(),
+            status: status.to_string(),
+            error: error.map(str::to_string),
+            provider_session_id: provider_session_id.map(str::to_string),
+            completed_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Transcript of the session with history id `id`, or of the latest session
+    /// that ran provider session `id`. Blocks until queued writes are committed.
+    pub fn transcript(&self, id: &str) -> Result<Option<WebSessionTranscript>, String> {
+        self.flush()?;
+        let conn = self.reader.lock().map_err(|e| e.to_string())?;
+        let session = conn
+            .query_row(
+                "SELECT id, command_type, project_path, prompt, model, status, error,
+                        provider_session_id, started_at, completed_at
+                 FROM web_sessions
+                 WHERE id = ?1 OR provider_session_id = ?1
+                 ORDER BY id = ?1 DESC, started_at DESC, rowid DESC
+                 LIMIT 1",
+                params![id],
+                |row| {
+                    Ok(WebSessionTranscript {
+                        id: row.get(0)?,
+                        command_type: row.get(1)?,
+                        project_path: row.get(2)?,
+                        prompt: row.get(3)?,
+                        model: row.get(4)?,
+                        status: row.get(5)?,
+                        error: row.get(6)?,
+                        provider_session_id: row.get(7)?,
+                        started_at: row.get(8)?,
+                        completed_at: row.get(9)?,
+                        messages: Vec::new(),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read web session: {}", e))?;
+        let Some(mut session) = session else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn
+            .prepare("SELECT message FROM web_session_messages WHERE session_id = ?1 ORDER BY seq")
+            .map_err(|e| e.to_string())?;
+        session.messages = stmt
+            .query_map(params![session.id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|message| message.ok())
+            .map(|message| {
+                serde_json::from_str(&message).unwrap_or(serde_json::Value::String(message))
+            })
+            .collect();
+        Ok(Some(session))
+    }
+}
+
+impl Drop for WebSessionStore {
+    /// Let the writer commit what is still queued before the store goes away
+    fn drop(&mut self) {
+        self.writer.take();
+        if let Some(writer_thread) = self.writer_thread.take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Commit queued writes in batches until the store is dropped
+fn run_writer(mut conn: Connection, writes: mpsc::Receiver<HistoryWrite>) {
+    while let Ok(first) = writes.recv() {
+        let batch: Vec<HistoryWrite> = std::iter::once(first)
+            .chain(writes.try_iter().take(MAX_WRITE_BATCH - 1))
+            .collect();
+        let mut flushes = Vec::new();
+        let committed = conn.transaction().and_then(|tx| {
+            for write in batch {
+                let result = match write {
+                    HistoryWrite::Flush(done) => {
+                        flushes.push(done);
+                        continue;
+                    }
+                    write => apply_write(&tx, write),
+                };
+                if let Err(error) = result {
+                    println!("[TRACE] Failed to record web session history: {}", error);
+                }
+            }
+            tx.commit()
+        });
+        if let Err(error) = committed {
+            println!("[TRACE] Failed to commit web session history: {}", error);
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn apply_write(conn: &Connection, write: HistoryWrite) -> rusqlite::Result<()> {
+    match write {
+        HistoryWrite::Start {
+            id,
+            command_type,
+            project_path,
+            prompt,
+            model,
+            started_at,
+        } => {
+            conn.execute(
+                "INSERT INTO web_sessions (id, command_type, project_path, prompt, model, started_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, command_type, project_path, prompt, model, started_at],
+            )?;
+            prune_expired(conn)
+        }
+        HistoryWrite::Message { id, message } => conn
+            .execute(
+                "INSERT INTO web_session_messages (session_id, message) VALUES (?1, ?2)",
+                params![id, message],
+            )
+            .map(|_| ()),
+        HistoryWrite::Finish {
+            id,
+            status,
+            error,
+            provider_session_id,
+            completed_at,
+        } => conn
+            .execute(
+                "UPDATE web_sessions
+                 SET status = ?1, error = ?2, provider_session_id = ?3, completed_at = ?4
+                 WHERE id = ?5",
+                params![status, error, provider_session_id, completed_at, id],
+            )
+            .map(|_| ()),
+        HistoryWrite::Flush(_) => Ok(()),
+    }
+}
+
+/// Delete sessions, and their messages, that started before the retention window
+fn prune_expired(conn: &Connection) -> rusqlite::Result<()> {
+    let cutoff = (chrono::Utc::now() - chrono::Duration::days(HISTORY_RETENTION_DAYS)).to_rfc3339();
+    conn.execute(
+        "DELETE FROM web_session_messages
+         WHERE session_id IN (SELECT id FROM web_sessions WHERE started_at < ?1)",
+        params![cutoff],
+    )?;
+    conn.execute(
+        "DELETE FROM web_sessions WHERE started_at < ?1",
+        params![cutoff],
+    )?;
+    Ok(())
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS web_sessions (
+            id TEXT PRIMARY KEY,
+            command_type TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            prompt TEXT NOT NULL,
+            model TEXT,
+            status TEXT NOT NULL DEFAULT 'running',
+            error TEXT,
+            provider_session_id TEXT,
+            started_at TEXT NOT NULL,
+            completed_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_web_sessions_provider_session
+            ON web_sessions(provider_session_id);
+        CREATE TABLE IF NOT EXISTS web_session_messages (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            message TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_web_session_messages_session
+            ON web_session_messages(session_id, seq);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_past_the_retention_window_are_pruned() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = WebSessionStore::open(data_dir.path()).unwrap();
+        store
+            .start_session("old", "execute", "/work", "old prompt", None)
+            .unwrap();
+        store.append_message("old", "{}").unwrap();
+        store.flush().unwrap();
+        let expired =
+            (chrono::Utc::now() - chrono::Duration::days(HISTORY_RETENTION_DAYS + 1)).to_rfc3339();
+        store
+            .reader
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE web_sessions SET started_at = ?1 WHERE id = 'old'",
+                params![expired],
+            )
+            .unwrap();
+
+        store
+            .start_session("new", "execute", "/work", "new prompt", None)
+            .unwrap();
+        store.append_message("new", "{}").unwrap();
+
+        assert!(store.transcript("old").unwrap().is_none());
+        assert_eq!(store.transcript("new").unwrap().unwrap().messages.len(), 1);
+        let orphaned: i64 = store
+            .reader
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM web_session_messages WHERE session_id = 'old'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(orphaned, 0);
+    }
+}
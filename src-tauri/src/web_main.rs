@@ -4,11 +4,14 @@ mod agent_binary;
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod error;
 mod logging;
+mod mobile_sync;
 mod process;
 mod providers;
 mod rebrand;
 mod usage_index;
+mod web_history;
 mod web_server;
 
 #[derive(Parser)]
@@ -22,12 +25,18 @@ struct Args {
     /// Host to bind to (0.0.0.0 for all interfaces)
     #[arg(short = 'H', long, default_value = "0.0.0.0")]
     host: String,
+
+    /// Kill provider processes that produce no output for this many seconds (off by default)
+    #[arg(long, default_value_t = web_server::DEFAULT_PROVIDER_IDLE_TIMEOUT_SECS)]
+    idle_timeout_secs: u64,
 }
 
 #[tokio::main]
 async fn main() {
     logging::init();
-    rebrand::archive_legacy_opcode_state();
+    if let Err(err) = rebrand::migrate_legacy_state_for(None) {
+        tracing::warn!("Legacy state migration skipped: {}", err);
+    }
 
     let args = Args::parse();
 
@@ -37,7 +46,9 @@ async fn main() {
         args.host, args.port
     );
 
-    if let Err(e) = web_server::start_web_mode(Some(args.port)).await {
+    if let Err(e) =
+        web_server::start_web_mode(Some(args.port), Some(args.idle_timeout_secs)).await
+    {
         eprintln!("❌ Failed to start web server: {}", e);
         std::process::exit(1);
     }
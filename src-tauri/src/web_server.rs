@@ -1,5 +1,5 @@
 use axum::extract::ws::{Message, WebSocket};
-use axum::http::Method;
+use axum::http::{Method, StatusCode};
 use axum::{
     extract::{Path, State as AxumState, WebSocketUpgrade},
     response::{Html, Json, Response},
@@ -19,17 +19,55 @@ use tower_http::services::ServeDir;
 use which;
 
 use crate::commands;
+use crate::error::OpcodeError;
+use crate::web_history::{web_data_dir, WebSessionStore, WebSessionTranscript};
 
-// Find Claude binary for web mode - use bundled binary first
-fn find_claude_binary_web() -> Result<String, String> {
+/// File name of the Claude binary bundled next to the app
+const BUNDLED_CLAUDE_BINARY: &str = "claude-code-x86_64-unknown-linux-gnu";
+
+/// Places the bundled binary may live relative to the running executable: beside it,
+/// in a sibling `binaries/` dir, or in `src-tauri/binaries` when run from `target/<profile>`
+fn bundled_binary_candidates(exe: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let Some(exe_dir) = exe.parent() else {
+        return Vec::new();
+    };
+
+    let mut candidates = vec![
+        exe_dir.join(BUNDLED_CLAUDE_BINARY),
+        exe_dir.join("binaries").join(BUNDLED_CLAUDE_BINARY),
+    ];
+    if let Some(crate_dir) = exe_dir.parent().and_then(|target| target.parent()) {
+        candidates.push(crate_dir.join("binaries").join(BUNDLED_CLAUDE_BINARY));
+    }
+    candidates
+}
+
+fn find_bundled_binary(exe: &std::path::Path) -> Option<std::path::PathBuf> {
+    bundled_binary_candidates(exe)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+/// Which Claude binary web mode will run, and where it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct WebClaudeBinaryStatus {
+    /// `"bundled"`, `"system"`, or `"none"` when nothing was found
+    pub source: String,
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
+
+fn resolve_claude_binary_web() -> WebClaudeBinaryStatus {
     // First try the bundled binary (same location as Tauri app uses)
-    let bundled_binary = "src-tauri/binaries/claude-code-x86_64-unknown-linux-gnu";
-    if std::path::Path::new(bundled_binary).exists() {
-        println!(
-            "[find_claude_binary_web] Using bundled binary: {}",
-            bundled_binary
-        );
-        return Ok(bundled_binary.to_string());
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| find_bundled_binary(&exe));
+    if let Some(bundled) = bundled {
+        return WebClaudeBinaryStatus {
+            source: "bundled".to_string(),
+            path: Some(bundled.to_string_lossy().to_string()),
+            error: None,
+        };
     }
 
     // Fall back to system installation paths
@@ -48,15 +86,34 @@ fn find_claude_binary_web() -> Result<String, String> {
 
     for candidate in candidates {
         if which::which(candidate).is_ok() {
+            return WebClaudeBinaryStatus {
+                source: "system".to_string(),
+                path: Some(candidate.to_string()),
+                error: None,
+            };
+        }
+    }
+
+    WebClaudeBinaryStatus {
+        source: "none".to_string(),
+        path: None,
+        error: Some("Claude binary not found in bundled location or system paths".to_string()),
+    }
+}
+
+// Find Claude binary for web mode - use bundled binary first
+fn find_claude_binary_web() -> Result<String, String> {
+    let status = resolve_claude_binary_web();
+    match status.path {
+        Some(path) => {
             println!(
-                "[find_claude_binary_web] Using system binary: {}",
-                candidate
+                "[find_claude_binary_web] Using {} binary: {}",
+                status.source, path
             );
-            return Ok(candidate.to_string());
+            Ok(path)
         }
+        None => Err(status.error.unwrap_or_default()),
     }
-
-    Err("Claude binary not found in bundled location or system paths".to_string())
 }
 
 #[derive(Clone)]
@@ -69,8 +126,18 @@ pub struct AppState {
         Arc<Mutex<std::collections::HashMap<String, tokio::sync::watch::Sender<bool>>>>,
     // Map provider runtime session IDs (session_id) back to WebSocket session IDs.
     pub session_aliases: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    // Kill provider processes that produce no output for this long (None disables).
+    pub provider_idle_timeout: Option<std::time::Duration>,
+    // Persistent transcripts of web sessions (None when the store couldn't be opened).
+    pub history: Option<Arc<WebSessionStore>>,
+    // Map WebSocket session IDs to the history ID of the request they're running.
+    pub active_history: Arc<Mutex<std::collections::HashMap<String, String>>>,
 }
 
+/// Default for `--idle-timeout-secs`: disabled, since a provider can think for
+/// a long time without printing anything
+pub const DEFAULT_PROVIDER_IDLE_TIMEOUT_SECS: u64 = 0;
+
 #[derive(Debug, Deserialize)]
 pub struct ProviderSessionExecutionRequest {
     pub project_path: String,
@@ -176,6 +243,75 @@ async fn remove_websocket_session_state(state: &AppState, websocket_session_id:
         let mut aliases = state.session_aliases.lock().await;
         aliases.retain(|_, mapped_session_id| mapped_session_id != websocket_session_id);
     }
+    {
+        let mut active_history = state.active_history.lock().await;
+        active_history.remove(websocket_session_id);
+    }
+}
+
+/// Start recording a request's transcript; messages later sent to the WebSocket
+/// session are appended to it until the request finishes
+async fn start_history(
+    state: &AppState,
+    websocket_session_id: &str,
+    history_id: &str,
+    request: &ProviderSessionExecutionRequest,
+) {
+    let Some(history) = &state.history else {
+        return;
+    };
+    if let Err(error) = history.start_session(
+        history_id,
+        &request.command_type,
+        &request.project_path,
+        &request.prompt,
+        request.model.as_deref(),
+    ) {
+        println!("[TRACE] Failed to record web session history: {}", error);
+        return;
+    }
+
+    let mut active_history = state.active_history.lock().await;
+    active_history.insert(websocket_session_id.to_string(), history_id.to_string());
+}
+
+async fn record_history_message(state: &AppState, websocket_session_id: &str, message: &str) {
+    let Some(history) = &state.history else {
+        return;
+    };
+    let history_id = {
+        let active_history = state.active_history.lock().await;
+        active_history.get(websocket_session_id).cloned()
+    };
+    if let Some(history_id) = history_id {
+        if let Err(error) = history.append_message(&history_id, message) {
+            println!("[TRACE] Failed to record web session output: {}", error);
+        }
+    }
+}
+
+async fn finish_history(
+    state: &AppState,
+    websocket_session_id: &str,
+    history_id: &str,
+    status: ProviderSessionCompletionStatus,
+    error: Option<&str>,
+    provider_session_id: Option<&str>,
+) {
+    {
+        let mut active_history = state.active_history.lock().await;
+        if active_history.get(websocket_session_id).map(String::as_str) == Some(history_id) {
+            active_history.remove(websocket_session_id);
+        }
+    }
+    let Some(history) = &state.history else {
+        return;
+    };
+    if let Err(error) =
+        history.finish_session(history_id, status.as_str(), error, provider_session_id)
+    {
+        println!("[TRACE] Failed to record web session completion: {}", error);
+    }
 }
 
 #[derive(Deserialize)]
@@ -189,6 +325,9 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// `OpcodeError::code` of the failure, when it came from one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
 }
 
 impl<T> ApiResponse<T> {
@@ -197,6 +336,7 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            code: None,
         }
     }
 
@@ -205,30 +345,71 @@ impl<T> ApiResponse<T> {
             success: false,
             data: None,
             error: Some(error),
+            code: None,
+        }
+    }
+
+    pub fn from_error(error: &OpcodeError) -> Self {
+        Self {
+            code: Some(error.code()),
+            ..Self::error(error.to_string())
         }
     }
 }
 
+/// HTTP status reported for each `OpcodeError` variant
+pub fn error_status(error: &OpcodeError) -> StatusCode {
+    match error {
+        OpcodeError::NotFound(_) => StatusCode::NOT_FOUND,
+        OpcodeError::ProviderUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        OpcodeError::Validation(_) => StatusCode::BAD_REQUEST,
+        OpcodeError::Cancelled => StatusCode::CONFLICT,
+        OpcodeError::Io(_) | OpcodeError::Db(_) | OpcodeError::Internal(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Error reply with the status and `code` matching `error`
+fn error_reply<T>(error: OpcodeError) -> (StatusCode, Json<ApiResponse<T>>) {
+    (error_status(&error), Json(ApiResponse::from_error(&error)))
+}
+
+/// Reply for provider-session commands that need the desktop app: a 503 with
+/// code `provider_unavailable`, whose body still has `success: false` for
+/// clients that only read the body
+fn desktop_only_reply() -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    error_reply(OpcodeError::ProviderUnavailable(
+        "Claude execution is not available in web mode. Please use the desktop app for running Claude commands.".to_string(),
+    ))
+}
+
 /// Serve the React frontend
 async fn serve_frontend() -> Html<&'static str> {
     Html(include_str!("../../dist/index.html"))
 }
 
 /// API endpoint to get projects (equivalent to Tauri command)
-async fn get_projects() -> Json<ApiResponse<Vec<commands::claude::Project>>> {
+async fn get_projects() -> (
+    StatusCode,
+    Json<ApiResponse<Vec<commands::claude::Project>>>,
+) {
     match commands::claude::list_projects().await {
-        Ok(projects) => Json(ApiResponse::success(projects)),
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+        Ok(projects) => (StatusCode::OK, Json(ApiResponse::success(projects))),
+        Err(e) => error_reply(OpcodeError::from(e)),
     }
 }
 
 /// API endpoint to get sessions for a project
 async fn get_sessions(
     Path(project_id): Path<String>,
-) -> Json<ApiResponse<Vec<commands::claude::Session>>> {
-    match commands::claude::get_project_sessions(project_id).await {
-        Ok(sessions) => Json(ApiResponse::success(sessions)),
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+) -> (
+    StatusCode,
+    Json<ApiResponse<Vec<commands::claude::Session>>>,
+) {
+    match commands::claude::load_project_sessions(project_id).await {
+        Ok(sessions) => (StatusCode::OK, Json(ApiResponse::success(sessions))),
+        Err(e) => error_reply(OpcodeError::from(e)),
     }
 }
 
@@ -311,17 +492,24 @@ async fn check_claude_version() -> Json<ApiResponse<serde_json::Value>> {
     Json(ApiResponse::success(version_status))
 }
 
+/// Report which Claude binary web mode will use
+async fn web_claude_binary_status() -> Json<ApiResponse<WebClaudeBinaryStatus>> {
+    Json(ApiResponse::success(resolve_claude_binary_web()))
+}
+
 /// List all available Claude installations on the system
-async fn list_claude_installations(
-) -> Json<ApiResponse<Vec<crate::claude_binary::ClaudeInstallation>>> {
+async fn list_claude_installations() -> (
+    StatusCode,
+    Json<ApiResponse<Vec<crate::claude_binary::ClaudeInstallation>>>,
+) {
     let installations = crate::claude_binary::discover_claude_installations();
 
     if installations.is_empty() {
-        Json(ApiResponse::error(
+        error_reply(OpcodeError::ProviderUnavailable(
             "No Claude Code installations found on the system".to_string(),
         ))
     } else {
-        Json(ApiResponse::success(installations))
+        (StatusCode::OK, Json(ApiResponse::success(installations)))
     }
 }
 
@@ -352,10 +540,10 @@ async fn mcp_list() -> Json<ApiResponse<Vec<serde_json::Value>>> {
 /// Load provider session history from JSONL file
 async fn load_provider_session_history(
     Path((session_id, project_id)): Path<(String, String)>,
-) -> Json<ApiResponse<Vec<serde_json::Value>>> {
+) -> (StatusCode, Json<ApiResponse<Vec<serde_json::Value>>>) {
     match commands::claude::load_provider_session_history(session_id, project_id).await {
-        Ok(history) => Json(ApiResponse::success(history)),
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+        Ok(history) => (StatusCode::OK, Json(ApiResponse::success(history))),
+        Err(e) => error_reply(OpcodeError::from(e)),
     }
 }
 
@@ -366,32 +554,35 @@ async fn list_running_provider_sessions() -> Json<ApiResponse<Vec<serde_json::Va
 }
 
 /// Execute provider session - mock for web mode.
-async fn execute_provider_session() -> Json<ApiResponse<serde_json::Value>> {
-    Json(ApiResponse::error("Claude execution is not available in web mode. Please use the desktop app for running Claude commands.".to_string()))
+async fn execute_provider_session() -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    desktop_only_reply()
 }
 
 /// Continue provider session - mock for web mode.
-async fn continue_provider_session() -> Json<ApiResponse<serde_json::Value>> {
-    Json(ApiResponse::error("Claude execution is not available in web mode. Please use the desktop app for running Claude commands.".to_string()))
+async fn continue_provider_session() -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    desktop_only_reply()
 }
 
 /// Resume provider session - mock for web mode.
-async fn resume_provider_session() -> Json<ApiResponse<serde_json::Value>> {
-    Json(ApiResponse::error("Claude execution is not available in web mode. Please use the desktop app for running Claude commands.".to_string()))
+async fn resume_provider_session() -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    desktop_only_reply()
 }
 
 /// Cancel provider session execution.
 async fn cancel_provider_session(
     Path(session_id): Path<String>,
     AxumState(state): AxumState<AppState>,
-) -> Json<ApiResponse<()>> {
+) -> (StatusCode, Json<ApiResponse<()>>) {
     println!("[TRACE] Cancel request for session: {}", session_id);
-
-    let Some(websocket_session_id) = resolve_websocket_session_id(&state, &session_id).await else {
-        return Json(ApiResponse::error(format!(
+    let not_running = || {
+        error_reply(OpcodeError::NotFound(format!(
             "Provider session is not running: {}",
             session_id
-        )));
+        )))
+    };
+
+    let Some(websocket_session_id) = resolve_websocket_session_id(&state, &session_id).await else {
+        return not_running();
     };
 
     let cancellation_sender = {
@@ -400,20 +591,14 @@ async fn cancel_provider_session(
     };
 
     let Some(cancellation_sender) = cancellation_sender else {
-        return Json(ApiResponse::error(format!(
-            "Provider session is not running: {}",
-            session_id
-        )));
+        return not_running();
     };
 
     if cancellation_sender.send(true).is_err() {
-        return Json(ApiResponse::error(format!(
-            "Provider session is not running: {}",
-            session_id
-        )));
+        return not_running();
     }
 
-    Json(ApiResponse::success(()))
+    (StatusCode::OK, Json(ApiResponse::success(())))
 }
 
 /// Get provider session output.
@@ -425,6 +610,31 @@ async fn get_provider_session_output(Path(session_id): Path<String>) -> Json<Api
     ))
 }
 
+/// Transcript of a past web-mode provider session, by history ID or provider session ID.
+async fn get_web_session_transcript(
+    Path(id): Path<String>,
+    AxumState(state): AxumState<AppState>,
+) -> (StatusCode, Json<ApiResponse<WebSessionTranscript>>) {
+    let Some(history) = state.history.clone() else {
+        return error_reply(OpcodeError::Internal(
+            "Web session history is not available".to_string(),
+        ));
+    };
+
+    let lookup_id = id.clone();
+    let transcript = tokio::task::spawn_blocking(move || history.transcript(&lookup_id))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match transcript {
+        Ok(Some(transcript)) => (StatusCode::OK, Json(ApiResponse::success(transcript))),
+        Ok(None) => error_reply(OpcodeError::NotFound(format!(
+            "Web session not found: {}",
+            id
+        ))),
+        Err(error) => error_reply(OpcodeError::Db(error)),
+    }
+}
+
 /// WebSocket handler for provider-session execution with streaming output.
 async fn provider_session_websocket(ws: WebSocketUpgrade, AxumState(state): AxumState<AppState>) -> Response {
     ws.on_upgrade(move |socket| provider_session_websocket_handler(socket, state))
@@ -501,6 +711,9 @@ async fn provider_session_websocket_handler(socket: WebSocket, state: AppState)
                             }
                         }
 
+                        let history_id = uuid::Uuid::new_v4().to_string();
+                        start_history(&state, &websocket_session_id, &history_id, &request).await;
+
                         // Execute provider session command based on request type.
                         let websocket_session_id_clone = websocket_session_id.clone();
                         let state_clone = state.clone();
@@ -518,50 +731,70 @@ async fn provider_session_websocket_handler(socket: WebSocket, state: AppState)
                         tokio::spawn(async move {
                             println!("[TRACE] Task started for command execution");
                             let request_session_id = request.session_id.clone();
-                            let result = match request.command_type.as_str() {
-                                "execute" => {
-                                    println!("[TRACE] Calling execute_provider_session_command");
-                                    execute_provider_session_command(
-                                        request.project_path,
-                                        request.prompt,
-                                        request.model.unwrap_or_default(),
-                                        websocket_session_id_clone.clone(),
-                                        state_clone.clone(),
-                                        cancel_rx.clone(),
-                                    )
-                                    .await
-                                }
-                                "continue" => {
-                                    println!("[TRACE] Calling continue_provider_session_command");
-                                    continue_provider_session_command(
-                                        request.project_path,
-                                        request.prompt,
-                                        request.model.unwrap_or_default(),
-                                        websocket_session_id_clone.clone(),
-                                        state_clone.clone(),
-                                        cancel_rx.clone(),
-                                    )
-                                    .await
-                                }
-                                "resume" => {
-                                    println!("[TRACE] Calling resume_provider_session_command");
-                                    resume_provider_session_command(
-                                        request.project_path,
-                                        request.session_id.unwrap_or_default(),
-                                        request.prompt,
-                                        request.model.unwrap_or_default(),
-                                        websocket_session_id_clone.clone(),
-                                        state_clone.clone(),
-                                        cancel_rx.clone(),
-                                    )
-                                    .await
-                                }
-                                _ => {
-                                    println!(
-                                        "[TRACE] Unknown command type: {}",
-                                        request.command_type
-                                    );
-                                    Err("Unknown command type".to_string())
+                            let result = if !matches!(
+                                request.command_type.as_str(),
+                                "execute" | "continue" | "resume"
+                            ) {
+                                println!("[TRACE] Unknown command type: {}", request.command_type);
+                                Err("Unknown command type".to_string())
+                            } else {
+                                match ensure_provider_available(
+                                    &state_clone,
+                                    &websocket_session_id_clone,
+                                    find_claude_binary_web(),
+                                )
+                                .await
+                                {
+                                    Err(error) => Err(error),
+                                    Ok(claude_path) => match request.command_type.as_str() {
+                                        "execute" => {
+                                            println!(
+                                                "[TRACE] Calling execute_provider_session_command"
+                                            );
+                                            execute_provider_session_command(
+                                                claude_path,
+                                                request.project_path,
+                                                request.prompt,
+                                                request.model.unwrap_or_default(),
+                                                websocket_session_id_clone.clone(),
+                                                state_clone.clone(),
+                                                cancel_rx.clone(),
+                                            )
+                                            .await
+                                        }
+                                        "continue" => {
+                                            println!(
+                                                "[TRACE] Calling continue_provider_session_command"
+                                            );
+                                            continue_provider_session_command(
+                                                claude_path,
+                                                request.project_path,
+                                                request.prompt,
+                                                request.model.unwrap_or_default(),
+                                                websocket_session_id_clone.clone(),
+                                                state_clone.clone(),
+                                                cancel_rx.clone(),
+                                            )
+                                            .await
+                                        }
+                                        "resume" => {
+                                            println!(
+                                                "[TRACE] Calling resume_provider_session_command"
+                                            );
+                                            resume_provider_session_command(
+                                                claude_path,
+                                                request.project_path,
+                                                request.session_id.unwrap_or_default(),
+                                                request.prompt,
+                                                request.model.unwrap_or_default(),
+                                                websocket_session_id_clone.clone(),
+                                                state_clone.clone(),
+                                                cancel_rx.clone(),
+                                            )
+                                            .await
+                                        }
+                                        _ => unreachable!("command type is validated above"),
+                                    },
                                 }
                             };
 
@@ -570,37 +803,50 @@ async fn provider_session_websocket_handler(socket: WebSocket, state: AppState)
                                 result
                             );
 
+                            let status = completion_status_for_result(&result);
+                            let completion_session_id = resolve_provider_session_id_for_websocket(
+                                &state_clone,
+                                &websocket_session_id_clone,
+                            )
+                            .await
+                            .or_else(|| {
+                                request_session_id
+                                    .as_deref()
+                                    .map(str::trim)
+                                    .filter(|value| !value.is_empty())
+                                    .map(ToOwned::to_owned)
+                            });
+                            // Recorded even when the client has gone, so the transcript survives
+                            finish_history(
+                                &state_clone,
+                                &websocket_session_id_clone,
+                                &history_id,
+                                status,
+                                result.as_ref().err().map(String::as_str),
+                                completion_session_id.as_deref(),
+                            )
+                            .await;
+
                             // Send completion message
                             let completion_sender = {
                                 let sessions = state_clone.active_sessions.lock().await;
                                 sessions.get(&websocket_session_id_clone).cloned()
                             };
                             if let Some(sender) = completion_sender {
-                                let status = completion_status_for_result(&result).as_str();
-                                let completion_session_id =
-                                    resolve_provider_session_id_for_websocket(
-                                        &state_clone,
-                                        &websocket_session_id_clone,
-                                    )
-                                    .await
-                                    .or_else(|| {
-                                        request_session_id
-                                            .as_deref()
-                                            .map(str::trim)
-                                            .filter(|value| !value.is_empty())
-                                            .map(ToOwned::to_owned)
-                                    });
+                                let status = status.as_str();
                                 let completion_msg = match result {
                                     Ok(_) => json!({
                                         "type": "completion",
                                         "status": status,
-                                        "session_id": completion_session_id
+                                        "session_id": completion_session_id,
+                                        "history_id": history_id
                                     }),
                                     Err(e) => json!({
                                         "type": "completion",
                                         "status": status,
                                         "error": e,
-                                        "session_id": completion_session_id
+                                        "session_id": completion_session_id,
+                                        "history_id": history_id
                                     }),
                                 };
                                 println!("[TRACE] Sending completion message: {}", completion_msg);
@@ -700,6 +946,7 @@ fn spawn_provider_process_output_tasks(
     child: &mut tokio::process::Child,
     websocket_session_id: &str,
     state: &AppState,
+    activity: &Arc<tokio::sync::Notify>,
 ) -> Result<(tokio::task::JoinHandle<()>, tokio::task::JoinHandle<()>), String> {
     use tokio::io::{AsyncBufReadExt, BufReader};
 
@@ -708,9 +955,11 @@ fn spawn_provider_process_output_tasks(
 
     let websocket_session_id_stdout = websocket_session_id.to_string();
     let state_stdout = state.clone();
+    let activity_stdout = activity.clone();
     let stdout_task = tokio::spawn(async move {
         let mut lines = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = lines.next_line().await {
+            activity_stdout.notify_one();
             if let Some(provider_session_id) = extract_provider_session_id_from_stream_line(&line) {
                 register_provider_session_alias(
                     &state_stdout,
@@ -734,9 +983,11 @@ fn spawn_provider_process_output_tasks(
 
     let websocket_session_id_stderr = websocket_session_id.to_string();
     let state_stderr = state.clone();
+    let activity_stderr = activity.clone();
     let stderr_task = tokio::spawn(async move {
         let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
+            activity_stderr.notify_one();
             send_to_session(
                 &state_stderr,
                 &websocket_session_id_stderr,
@@ -753,16 +1004,63 @@ fn spawn_provider_process_output_tasks(
     Ok((stdout_task, stderr_task))
 }
 
+#[derive(Debug)]
 enum ProviderProcessOutcome {
     Exited(std::process::ExitStatus),
     Cancelled(std::process::ExitStatus),
+    /// No output arrived within the idle timeout, so the process was killed
+    IdleTimedOut(std::time::Duration),
+}
+
+impl ProviderProcessOutcome {
+    fn into_result(self) -> Result<(), String> {
+        match self {
+            Self::Cancelled(_) => Err("Provider session cancelled".to_string()),
+            Self::IdleTimedOut(idle_timeout) => Err(format!(
+                "Provider session idle for {}s without output; process killed",
+                idle_timeout.as_secs()
+            )),
+            Self::Exited(exit_status) => map_exit_status_to_result(exit_status),
+        }
+    }
+}
+
+async fn kill_provider_process(
+    child: &mut tokio::process::Child,
+) -> Result<std::process::ExitStatus, String> {
+    match child.kill().await {
+        Ok(_) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::InvalidInput => {}
+        Err(error) => {
+            return Err(format!("Failed to cancel provider session: {}", error));
+        }
+    }
+
+    child
+        .wait()
+        .await
+        .map_err(|error| format!("Failed waiting for cancelled provider session: {}", error))
 }
 
+/// Wait for the provider process to exit, be cancelled, or go quiet for longer
+/// than `idle_timeout`. `activity` is notified by the output tasks on every line.
 async fn wait_for_provider_process_completion(
     child: &mut tokio::process::Child,
     cancel_rx: &mut tokio::sync::watch::Receiver<bool>,
+    activity: &tokio::sync::Notify,
+    idle_timeout: Option<std::time::Duration>,
 ) -> Result<ProviderProcessOutcome, String> {
+    let idle_deadline = || idle_timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut deadline = idle_deadline();
+
     loop {
+        let idle = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
         tokio::select! {
             wait_result = child.wait() => {
                 let exit_status = wait_result.map_err(|error| {
@@ -778,19 +1076,21 @@ async fn wait_for_provider_process_completion(
                     continue;
                 }
 
-                match child.kill().await {
-                    Ok(_) => {}
-                    Err(error) if error.kind() == std::io::ErrorKind::InvalidInput => {}
-                    Err(error) => {
-                        return Err(format!("Failed to cancel provider session: {}", error));
-                    }
-                }
-
-                let exit_status = child.wait().await.map_err(|error| {
-                    format!("Failed waiting for cancelled provider session: {}", error)
-                })?;
+                let exit_status = kill_provider_process(child).await?;
                 return Ok(ProviderProcessOutcome::Cancelled(exit_status));
             }
+            _ = activity.notified() => {
+                deadline = idle_deadline();
+            }
+            _ = idle => {
+                let idle_timeout = idle_timeout.unwrap_or_default();
+                println!(
+                    "[TRACE] Provider process idle for {:?}; killing it",
+                    idle_timeout
+                );
+                kill_provider_process(child).await?;
+                return Ok(ProviderProcessOutcome::IdleTimedOut(idle_timeout));
+            }
         }
     }
 }
@@ -811,7 +1111,46 @@ fn map_exit_status_to_result(exit_status: std::process::ExitStatus) -> Result<()
     ))
 }
 
+/// Error sent over the WebSocket when the Claude CLI can't be found, with how to
+/// install it so clients can show actionable guidance
+fn provider_unavailable_message(error: &str) -> serde_json::Value {
+    let capabilities = crate::providers::claude::descriptor().capabilities;
+    let error = OpcodeError::ProviderUnavailable(format!(
+        "{} is not installed: {}",
+        capabilities.display_name, error
+    ));
+    json!({
+        "type": "error",
+        "code": error.code(),
+        "message": error.to_string(),
+        "install_hint": capabilities.install_command,
+        "install_url": capabilities.install_url
+    })
+}
+
+/// Check the Claude binary lookup before spawning anything, telling the client how
+/// to install the CLI when it's missing
+async fn ensure_provider_available(
+    state: &AppState,
+    websocket_session_id: &str,
+    binary: Result<String, String>,
+) -> Result<String, String> {
+    match binary {
+        Ok(claude_path) => {
+            println!("[TRACE] Found Claude binary: {}", claude_path);
+            Ok(claude_path)
+        }
+        Err(error) => {
+            println!("[TRACE] Error finding Claude binary: {}", error);
+            let message = provider_unavailable_message(&error);
+            send_to_session(state, websocket_session_id, message.to_string()).await;
+            Err(format!("Claude binary not found: {}", error))
+        }
+    }
+}
+
 async fn execute_provider_session_command(
+    claude_path: String,
     project_path: String,
     prompt: String,
     model: String,
@@ -840,15 +1179,6 @@ async fn execute_provider_session_command(
     )
     .await;
 
-    // Find Claude binary (simplified for web mode)
-    println!("[TRACE] Finding Claude binary...");
-    let claude_path = find_claude_binary_web().map_err(|e| {
-        let error = format!("Claude binary not found: {}", e);
-        println!("[TRACE] Error finding Claude binary: {}", error);
-        error
-    })?;
-    println!("[TRACE] Found Claude binary: {}", claude_path);
-
     // Create Claude command
     println!("[TRACE] Creating Claude command...");
     let mut cmd = Command::new(&claude_path);
@@ -879,11 +1209,18 @@ async fn execute_provider_session_command(
     })?;
     println!("[TRACE] Claude process spawned successfully");
 
+    let activity = Arc::new(tokio::sync::Notify::new());
     let (stdout_task, stderr_task) =
-        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state)?;
+        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state, &activity)?;
 
     println!("[TRACE] Waiting for provider process completion or cancellation...");
-    let completion = wait_for_provider_process_completion(&mut child, &mut cancel_rx).await;
+    let completion = wait_for_provider_process_completion(
+        &mut child,
+        &mut cancel_rx,
+        &activity,
+        state.provider_idle_timeout,
+    )
+    .await;
     let _ = stdout_task.await;
     let _ = stderr_task.await;
     let completion = completion?;
@@ -903,6 +1240,7 @@ async fn execute_provider_session_command(
             );
             map_exit_status_to_result(exit_status)
         }
+        idle @ ProviderProcessOutcome::IdleTimedOut(_) => idle.into_result(),
     };
 
     if let Err(error) = &result {
@@ -913,6 +1251,7 @@ async fn execute_provider_session_command(
 }
 
 async fn continue_provider_session_command(
+    claude_path: String,
     project_path: String,
     prompt: String,
     model: String,
@@ -933,10 +1272,6 @@ async fn continue_provider_session_command(
     )
     .await;
 
-    // Find Claude binary
-    let claude_path =
-        find_claude_binary_web().map_err(|e| format!("Claude binary not found: {}", e))?;
-
     // Create continue command
     let mut cmd = Command::new(&claude_path);
     let mut args = vec![
@@ -960,19 +1295,24 @@ async fn continue_provider_session_command(
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+    let activity = Arc::new(tokio::sync::Notify::new());
     let (stdout_task, stderr_task) =
-        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state)?;
-    let completion = wait_for_provider_process_completion(&mut child, &mut cancel_rx).await;
+        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state, &activity)?;
+    let completion = wait_for_provider_process_completion(
+        &mut child,
+        &mut cancel_rx,
+        &activity,
+        state.provider_idle_timeout,
+    )
+    .await;
     let _ = stdout_task.await;
     let _ = stderr_task.await;
 
-    match completion? {
-        ProviderProcessOutcome::Cancelled(_) => Err("Provider session cancelled".to_string()),
-        ProviderProcessOutcome::Exited(exit_status) => map_exit_status_to_result(exit_status),
-    }
+    completion?.into_result()
 }
 
 async fn resume_provider_session_command(
+    claude_path: String,
     project_path: String,
     provider_session_id: String,
     prompt: String,
@@ -997,12 +1337,8 @@ async fn resume_provider_session_command(
     )
     .await;
 
-    // Find Claude binary
-    println!("[resume_provider_session_command] Finding Claude binary...");
-    let claude_path =
-        find_claude_binary_web().map_err(|e| format!("Claude binary not found: {}", e))?;
     println!(
-        "[resume_provider_session_command] Found Claude binary: {}",
+        "[resume_provider_session_command] Using Claude binary: {}",
         claude_path
     );
 
@@ -1040,21 +1376,26 @@ async fn resume_provider_session_command(
         error
     })?;
     println!("[resume_provider_session_command] Process spawned successfully");
+    let activity = Arc::new(tokio::sync::Notify::new());
     let (stdout_task, stderr_task) =
-        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state)?;
-    let completion = wait_for_provider_process_completion(&mut child, &mut cancel_rx).await;
+        spawn_provider_process_output_tasks(&mut child, &websocket_session_id, &state, &activity)?;
+    let completion = wait_for_provider_process_completion(
+        &mut child,
+        &mut cancel_rx,
+        &activity,
+        state.provider_idle_timeout,
+    )
+    .await;
     let _ = stdout_task.await;
     let _ = stderr_task.await;
 
-    match completion? {
-        ProviderProcessOutcome::Cancelled(_) => Err("Provider session cancelled".to_string()),
-        ProviderProcessOutcome::Exited(exit_status) => map_exit_status_to_result(exit_status),
-    }
+    completion?.into_result()
 }
 
 async fn send_to_session(state: &AppState, session_id: &str, message: String) {
     println!("[TRACE] send_to_session called for session: {}", session_id);
     println!("[TRACE] Message: {}", message);
+    record_history_message(state, session_id, &message).await;
 
     let sender = {
         let sessions = state.active_sessions.lock().await;
@@ -1080,11 +1421,24 @@ async fn send_to_session(state: &AppState, session_id: &str, message: String) {
 }
 
 /// Create the web server
-pub async fn create_web_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn create_web_server(
+    port: u16,
+    idle_timeout_secs: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
     let state = AppState {
         active_sessions: Arc::new(Mutex::new(std::collections::HashMap::new())),
         active_cancellations: Arc::new(Mutex::new(std::collections::HashMap::new())),
         session_aliases: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        provider_idle_timeout: (idle_timeout_secs > 0)
+            .then(|| std::time::Duration::from_secs(idle_timeout_secs)),
+        history: match WebSessionStore::open(&web_data_dir()) {
+            Ok(store) => Some(Arc::new(store)),
+            Err(error) => {
+                println!("⚠️ Web session history disabled: {}", error);
+                None
+            }
+        },
+        active_history: Arc::new(Mutex::new(std::collections::HashMap::new())),
     };
 
     // CORS layer to allow requests from phone browsers
@@ -1113,6 +1467,7 @@ pub async fn create_web_server(port: u16) -> Result<(), Box<dyn std::error::Erro
         // Settings and configuration
         .route("/api/settings/claude", get(get_claude_settings))
         .route("/api/settings/claude/version", get(check_claude_version))
+        .route("/api/settings/claude/binary", get(web_claude_binary_status))
         .route(
             "/api/settings/claude/installations",
             get(list_claude_installations),
@@ -1130,6 +1485,7 @@ pub async fn create_web_server(port: u16) -> Result<(), Box<dyn std::error::Erro
             get(load_provider_session_history),
         )
         .route("/api/provider-sessions/running", get(list_running_provider_sessions))
+        .route("/api/sessions/web/{id}", get(get_web_session_transcript))
         // Claude execution endpoints (read-only in web mode)
         .route("/api/provider-sessions/execute", get(execute_provider_session))
         .route("/api/provider-sessions/continue", get(continue_provider_session))
@@ -1161,9 +1517,217 @@ pub async fn create_web_server(port: u16) -> Result<(), Box<dyn std::error::Erro
 }
 
 /// Start web server mode (alternative to Tauri GUI)
-pub async fn start_web_mode(port: Option<u16>) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn start_web_mode(
+    port: Option<u16>,
+    idle_timeout_secs: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let port = port.unwrap_or(8080);
+    let idle_timeout_secs = idle_timeout_secs.unwrap_or(DEFAULT_PROVIDER_IDLE_TIMEOUT_SECS);
 
     println!("🚀 Starting CodeInterfaceX in web server mode...");
-    create_web_server(port).await
+    create_web_server(port, idle_timeout_secs).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcode_errors_map_to_status_and_code() {
+        let cases = [
+            (OpcodeError::NotFound("x".into()), StatusCode::NOT_FOUND, "not_found"),
+            (
+                OpcodeError::ProviderUnavailable("x".into()),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "provider_unavailable",
+            ),
+            (OpcodeError::Io("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "io"),
+            (OpcodeError::Db("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "db"),
+            (OpcodeError::Cancelled, StatusCode::CONFLICT, "cancelled"),
+            (OpcodeError::Validation("x".into()), StatusCode::BAD_REQUEST, "validation"),
+            (OpcodeError::Internal("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        ];
+        for (error, status, code) in cases {
+            let (reply_status, Json(reply)) = error_reply::<()>(error.clone());
+            assert_eq!(reply_status, status, "{:?}", error);
+            assert_eq!(reply.code, Some(code));
+            assert_eq!(reply.error, Some(error.to_string()));
+            assert!(!reply.success);
+        }
+    }
+
+    #[test]
+    fn bundled_binary_resolves_relative_to_the_executable() {
+        let root = tempfile::tempdir().unwrap();
+        let exe = root.path().join("target/release/codeinterfacex-web");
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        assert_eq!(find_bundled_binary(&exe), None);
+
+        // Dev layout: src-tauri/target/<profile>/exe -> src-tauri/binaries
+        let dev_binary = root.path().join("binaries").join(BUNDLED_CLAUDE_BINARY);
+        std::fs::create_dir_all(dev_binary.parent().unwrap()).unwrap();
+        std::fs::write(&dev_binary, "").unwrap();
+        assert_eq!(find_bundled_binary(&exe), Some(dev_binary));
+
+        // A binary shipped beside the executable wins
+        let beside = exe.with_file_name(BUNDLED_CLAUDE_BINARY);
+        std::fs::write(&beside, "").unwrap();
+        assert_eq!(find_bundled_binary(&exe), Some(beside));
+    }
+
+    fn test_state(history: Option<Arc<WebSessionStore>>) -> AppState {
+        AppState {
+            active_sessions: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            active_cancellations: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            session_aliases: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            provider_idle_timeout: None,
+            history,
+            active_history: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_provider_binary_sends_structured_install_hint() {
+        let state = test_state(None);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        state
+            .active_sessions
+            .lock()
+            .await
+            .insert("ws-1".to_string(), tx);
+
+        let result = ensure_provider_available(
+            &state,
+            "ws-1",
+            Err("Claude binary not found in bundled location or system paths".to_string()),
+        )
+        .await;
+
+        assert!(result.unwrap_err().starts_with("Claude binary not found"));
+        let message: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(message["type"], "error");
+        assert_eq!(message["code"], "provider_unavailable");
+        assert_eq!(
+            message["install_hint"],
+            "npm install -g @anthropic-ai/claude-code"
+        );
+        assert!(message["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Claude Code is not installed"));
+
+        let result =
+            ensure_provider_available(&state, "ws-1", Ok("/usr/bin/claude".to_string())).await;
+        assert_eq!(result, Ok("/usr/bin/claude".to_string()));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn completed_web_session_transcript_is_retrievable_afterward() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = WebSessionStore::open(data_dir.path()).unwrap();
+        let state = test_state(Some(Arc::new(store)));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        state
+            .active_sessions
+            .lock()
+            .await
+            .insert("ws-1".to_string(), tx);
+        let request = ProviderSessionExecutionRequest {
+            project_path: "/home/me/project".to_string(),
+            prompt: "Fix the failing test".to_string(),
+            model: Some("sonnet".to_string()),
+            session_id: None,
+            command_type: "execute".to_string(),
+        };
+
+        start_history(&state, "ws-1", "history-1", &request).await;
+        send_to_session(
+            &state,
+            "ws-1",
+            json!({ "type": "output", "content": "{\"type\":\"assistant\"}" }).to_string(),
+        )
+        .await;
+        send_to_session(
+            &state,
+            "ws-1",
+            json!({ "type": "error", "message": "rate limited, retrying" }).to_string(),
+        )
+        .await;
+        finish_history(
+            &state,
+            "ws-1",
+            "history-1",
+            ProviderSessionCompletionStatus::Success,
+            None,
+            Some("provider-1"),
+        )
+        .await;
+        assert!(rx.recv().await.is_some());
+
+        // The client disconnects and the server restarts
+        remove_websocket_session_state(&state, "ws-1").await;
+        drop(state);
+        let store = WebSessionStore::open(data_dir.path()).unwrap();
+        let state = test_state(Some(Arc::new(store)));
+
+        let (status, Json(reply)) =
+            get_web_session_transcript(Path("history-1".to_string()), AxumState(state.clone()))
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        let transcript = reply.data.unwrap();
+        assert_eq!(transcript.prompt, "Fix the failing test");
+        assert_eq!(transcript.status, "success");
+        assert_eq!(
+            transcript.provider_session_id.as_deref(),
+            Some("provider-1")
+        );
+        assert!(transcript.completed_at.is_some());
+        assert_eq!(
+            transcript.messages,
+            vec![
+                json!({ "type": "output", "content": "{\"type\":\"assistant\"}" }),
+                json!({ "type": "error", "message": "rate limited, retrying" }),
+            ]
+        );
+
+        let (_, Json(reply)) =
+            get_web_session_transcript(Path("provider-1".to_string()), AxumState(state.clone()))
+                .await;
+        assert_eq!(reply.data.unwrap().id, "history-1");
+        let (status, _) =
+            get_web_session_transcript(Path("missing".to_string()), AxumState(state)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn silent_provider_process_is_killed_after_idle_timeout() {
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let (_cancel_tx, mut cancel_rx) = tokio::sync::watch::channel(false);
+        let activity = tokio::sync::Notify::new();
+        let idle_timeout = std::time::Duration::from_millis(100);
+
+        let started = std::time::Instant::now();
+        let outcome = wait_for_provider_process_completion(
+            &mut child,
+            &mut cancel_rx,
+            &activity,
+            Some(idle_timeout),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(outcome, ProviderProcessOutcome::IdleTimedOut(t) if t == idle_timeout));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert!(child.try_wait().unwrap().is_some());
+        let error = outcome.into_result().unwrap_err();
+        assert!(error.contains("idle"));
+        assert!(matches!(
+            completion_status_for_result(&Err(error)),
+            ProviderSessionCompletionStatus::Error
+        ));
+    }
 }
@@ -144,8 +144,11 @@ export const Agents: React.FC = () => {
       });
 
       if (selected) {
-        const importedAgent = await api.importAgentFromFile(selected as string);
-        setToast({ message: `Imported agent: ${importedAgent.name}`, type: 'success' });
+        const imported = await api.importAgentFromFile(selected as string);
+        const message = Array.isArray(imported)
+          ? `Imported ${imported.length} agents`
+          : `Imported agent: ${imported.name}`;
+        setToast({ message, type: 'success' });
         loadAgents();
       }
     } catch (error) {
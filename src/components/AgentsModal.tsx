@@ -160,9 +160,12 @@ export const AgentsModal: React.FC<AgentsModalProps> = ({ open, onOpenChange })
       });
       
       if (filePath) {
-        const agent = await api.importAgentFromFile(filePath as string);
+        const imported = await api.importAgentFromFile(filePath as string);
         loadAgents(); // Refresh list
-        setToast({ message: `Agent "${agent.name}" imported successfully`, type: "success" });
+        const message = Array.isArray(imported)
+          ? `${imported.length} agents imported successfully`
+          : `Agent "${imported.name}" imported successfully`;
+        setToast({ message, type: "success" });
       }
     } catch (error) {
       logger.error('ui', 'Failed to import agent:', { error: error });
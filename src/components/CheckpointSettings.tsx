@@ -44,6 +44,8 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
 }) => {
   const [autoCheckpointEnabled, setAutoCheckpointEnabled] = useState(true);
   const [checkpointStrategy, setCheckpointStrategy] = useState<CheckpointStrategy>("smart");
+  const [excludeGlobs, setExcludeGlobs] = useState("");
+  const [intervalSeconds, setIntervalSeconds] = useState(300);
   const [totalCheckpoints, setTotalCheckpoints] = useState(0);
   const [keepCount, setKeepCount] = useState(10);
   const [isLoading, setIsLoading] = useState(false);
@@ -56,6 +58,7 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
     { value: "per_prompt", label: "After Each Prompt" },
     { value: "per_tool_use", label: "After Tool Use" },
     { value: "smart", label: "Smart (Recommended)" },
+    { value: "time_interval", label: "On a Timer" },
   ];
 
   useEffect(() => {
@@ -70,6 +73,10 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
       const settings = await api.getCheckpointSettings(sessionId, projectId, projectPath);
       setAutoCheckpointEnabled(settings.auto_checkpoint_enabled);
       setCheckpointStrategy(settings.checkpoint_strategy);
+      setExcludeGlobs((settings.exclude_globs ?? []).join(", "));
+      if (settings.interval_seconds) {
+        setIntervalSeconds(settings.interval_seconds);
+      }
       setTotalCheckpoints(settings.total_checkpoints);
     } catch (err) {
       logger.error('ui', 'Failed to load checkpoint settings:', { error: err });
@@ -90,7 +97,9 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
         projectId,
         projectPath,
         autoCheckpointEnabled,
-        checkpointStrategy
+        checkpointStrategy,
+        excludeGlobs.split(",").map((pattern) => pattern.trim()).filter(Boolean),
+        checkpointStrategy === "time_interval" ? intervalSeconds : undefined
       );
       
       setSuccessMessage("Settings saved successfully");
@@ -220,6 +229,38 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
             {checkpointStrategy === "per_prompt" && "A checkpoint will be created after each user prompt"}
             {checkpointStrategy === "per_tool_use" && "A checkpoint will be created after each tool use"}
             {checkpointStrategy === "smart" && "Checkpoints will be created after destructive operations"}
+            {checkpointStrategy === "time_interval" && `A checkpoint will be created every ${intervalSeconds} seconds while the session is active`}
+          </p>
+        </div>
+
+        {checkpointStrategy === "time_interval" && (
+          <div className="space-y-2">
+            <Label htmlFor="interval-seconds" className="text-label">Interval (seconds)</Label>
+            <Input
+              id="interval-seconds"
+              type="number"
+              min="1"
+              value={intervalSeconds}
+              onChange={(e) => setIntervalSeconds(parseInt(e.target.value) || 300)}
+              disabled={isLoading || !autoCheckpointEnabled}
+              className="h-9"
+            />
+          </div>
+        )}
+
+        {/* Excluded paths */}
+        <div className="space-y-2">
+          <Label htmlFor="exclude-globs" className="text-label">Excluded Paths</Label>
+          <Input
+            id="exclude-globs"
+            value={excludeGlobs}
+            onChange={(e) => setExcludeGlobs(e.target.value)}
+            placeholder="target, node_modules, *.log"
+            disabled={isLoading}
+            className="h-9"
+          />
+          <p className="text-caption text-muted-foreground">
+            Comma-separated globs; matching files are never snapshotted or restored
           </p>
         </div>
 
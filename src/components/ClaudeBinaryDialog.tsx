@@ -46,7 +46,11 @@ export function ClaudeBinaryDialog({ open, onOpenChange, onSuccess, onError }: C
 
     setIsValidating(true);
     try {
-      await api.setClaudeBinaryPath(selectedInstallation.path);
+      const validation = await api.setClaudeBinaryPath(selectedInstallation.path);
+      if (!validation.ok) {
+        onError(validation.message);
+        return;
+      }
       onSuccess();
       onOpenChange(false);
     } catch (error) {
@@ -5,6 +5,7 @@ import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { Card } from "@/components/ui/card";
+import { Switch } from "@/components/ui/switch";
 import { Toast, ToastContainer } from "@/components/ui/toast";
 import { api, type Agent, type ProviderRuntimeStatus } from "@/lib/api";
 import { cn } from "@/lib/utils";
@@ -63,6 +64,7 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
   const [selectedIcon, setSelectedIcon] = useState<AgentIconName>((agent?.icon as AgentIconName) || "bot");
   const [systemPrompt, setSystemPrompt] = useState(agent?.system_prompt || "");
   const [defaultTask, setDefaultTask] = useState(agent?.default_task || "");
+  const [enableNetwork, setEnableNetwork] = useState(agent ? agent.enable_network ?? false : true);
   const [model, setModel] = useState(initialModel);
   const [customModelInput, setCustomModelInput] = useState(initialCustomModel);
   const [detectedProviderIds, setDetectedProviderIds] = useState<string[]>([]);
@@ -160,7 +162,12 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
           systemPrompt, 
           defaultTask || undefined, 
           providerId,
-          model
+          model,
+          undefined,
+          undefined,
+          undefined,
+          undefined,
+          enableNetwork
         );
       } else {
         await api.createAgent(
@@ -169,7 +176,12 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
           systemPrompt, 
           defaultTask || undefined, 
           providerId,
-          model
+          model,
+          undefined,
+          undefined,
+          undefined,
+          undefined,
+          enableNetwork
         );
       }
       
@@ -191,6 +203,7 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
          selectedIcon !== (agent?.icon || "bot") || 
          systemPrompt !== (agent?.system_prompt || "") ||
          defaultTask !== (agent?.default_task || "") ||
+         enableNetwork !== (agent ? agent.enable_network ?? false : true) ||
          providerId !== (agent?.provider_id || "claude") ||
          model !== (agent?.model || getDefaultModelForProvider(agent?.provider_id || "claude"))) &&
         !confirm("You have unsaved changes. Are you sure you want to leave?")) {
@@ -438,6 +451,19 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
                   This will be used as the default task placeholder when executing the agent
                 </p>
               </div>
+              <div className="flex items-center justify-between mt-4">
+                <div className="space-y-0.5 flex-1">
+                  <Label htmlFor="enable-network">Network Access</Label>
+                  <p className="text-caption text-muted-foreground">
+                    Without it the agent runs sandboxed with no network, which also blocks the provider's API unless it uses a local model
+                  </p>
+                </div>
+                <Switch
+                  id="enable-network"
+                  checked={enableNetwork}
+                  onCheckedChange={setEnableNetwork}
+                />
+              </div>
             </Card>
 
             {/* System Prompt */}
@@ -377,7 +377,10 @@ export const Settings: React.FC<SettingsProps> = ({
 
       // Save Claude binary path if changed
       if (binaryPathChanged && selectedInstallation) {
-        await api.setClaudeBinaryPath(selectedInstallation.path);
+        const validation = await api.setClaudeBinaryPath(selectedInstallation.path);
+        if (!validation.ok) {
+          throw new Error(validation.message);
+        }
         setCurrentBinaryPath(selectedInstallation.path);
         setBinaryPathChanged(false);
       }
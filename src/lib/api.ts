@@ -18,6 +18,20 @@ export interface ProcessInfo {
   model: string;
 }
 
+/** A running agent run with no output for longer than the requested threshold */
+export interface StuckProcess extends ProcessInfo {
+  last_output_at: string;
+  idle_secs: number;
+}
+
+/** CPU and memory of a running agent run's process */
+export interface ProcessResourceUsage {
+  run_id: number;
+  pid: number;
+  cpu_percent: number;
+  memory_mb: number;
+}
+
 /**
  * Represents a project in the ~/.claude/projects directory
  */
@@ -73,6 +87,28 @@ export interface ClaudeVersionStatus {
   output: string;
 }
 
+/**
+ * Disk space used by opcode for a project, in bytes
+ */
+export interface ProjectFootprint {
+  checkpoint_bytes: number;
+  attachment_bytes: number;
+  session_bytes: number;
+  total_bytes: number;
+}
+
+/**
+ * A session whose messages mention a search query
+ */
+export interface SessionSearchResult {
+  session_id: string;
+  project_id: string;
+  /** Case-insensitive matches across the session's messages */
+  match_count: number;
+  /** The first matching message, trimmed around the match */
+  first_match_snippet: string;
+}
+
 /**
  * Represents a CLAUDE.md file found in the project
  */
@@ -96,6 +132,17 @@ export interface FileEntry {
   is_directory: boolean;
   size: number;
   extension?: string;
+  /** Entries left out of a directory's recursive size; only set when sizes are computed */
+  skipped_entries?: number;
+}
+
+/**
+ * Result of validating a Claude binary path; `reason_code` is set when rejected
+ */
+export interface BinaryPathValidation {
+  ok: boolean;
+  reason_code?: 'app_bundle' | 'not_found' | 'not_a_file' | 'not_executable' | null;
+  message: string;
 }
 
 /**
@@ -122,6 +169,10 @@ export interface Agent {
   provider_id: string;
   model: string;
   hooks?: string; // JSON string of HooksConfiguration
+  first_output_timeout_secs?: number; // 0 disables the first-output timeout
+  extra_args?: string; // JSON array of strings appended to the provider invocation
+  binary_path?: string; // Claude CLI the agent is pinned to, from listClaudeInstallations
+  enable_network?: boolean; // Without it, runs are sandboxed with no network access
   created_at: string;
   updated_at: string;
 }
@@ -148,6 +199,15 @@ export interface GitHubAgentFile {
   sha: string;
 }
 
+/** An agent listed in an agent library's gallery manifest */
+export interface AgentGalleryEntry {
+  name: string;
+  description: string;
+  tags: string[];
+  download_url: string;
+  author?: string | null;
+}
+
 export interface AgentRun {
   id?: number;
   agent_id: number;
@@ -159,18 +219,56 @@ export interface AgentRun {
   project_path: string;
   session_id: string;
   output?: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'pending', 'queued', 'running', 'completed', 'failed', 'cancelled'
   pid?: number;
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  exit_code?: number; // -1 when the run was killed for producing no output
+  first_output_at?: string;
+  process_ended_at?: string;
+  retry_of_run_id?: number; // original run, set on automatic rate-limit retries
+  note?: string;
+  comparison_id?: string; // shared by runs started together by run_agents_on_task
+}
+
+/** Runs started together by `runAgentsOnTask` */
+export interface AgentComparison {
+  comparison_id: string;
+  run_ids: number[];
+  errors: string[]; // agents that failed to launch, as "<agent id>: <error>"
 }
 
 export interface AgentRunMetrics {
   duration_ms?: number;
-  total_tokens?: number;
+  total_tokens?: number; // input + output only
+  cache_creation_tokens?: number;
+  cache_read_tokens?: number;
   cost_usd?: number;
   message_count?: number;
+  time_to_first_output_ms?: number;
+  total_wall_ms?: number;
+}
+
+/** Payload of the `agent-metrics:{runId}` event, sent for each streamed usage block */
+export interface AgentRunLiveMetrics {
+  total_tokens: number;
+  cost_usd: number;
+  message_count: number;
+}
+
+/** Payload of the `file-edit:{runId}` event, normalized across providers */
+export interface FileEditEvent {
+  path: string;
+  operation: 'create' | 'modify' | 'delete';
+  additions: number;
+  deletions: number;
+}
+
+/** Payload of the `agent-raw:{runId}` event: an untransformed provider line */
+export interface RawOutputEvent {
+  stream: 'stdout' | 'stderr';
+  line: string;
 }
 
 export interface AgentRunWithMetrics {
@@ -183,7 +281,7 @@ export interface AgentRunWithMetrics {
   model: string;
   project_path: string;
   session_id: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'pending', 'queued', 'running', 'completed', 'failed', 'cancelled'
   pid?: number;
   duration_ms?: number;
   total_tokens?: number;
@@ -194,6 +292,13 @@ export interface AgentRunWithMetrics {
   output?: string; // Real-time JSONL content
 }
 
+export interface AgentSmokeTestResult {
+  succeeded: boolean;
+  output_excerpt: string;
+  duration_ms: number;
+  error?: string;
+}
+
 export interface ProviderRuntimeStatus {
   provider_id: string;
   installed: boolean;
@@ -213,6 +318,15 @@ export interface ProviderCapability {
   model_strategy: string;
 }
 
+export interface SupportedProvider {
+  provider_id: string;
+  display_name: string;
+  install_command: string;
+  install_url: string;
+  auth_env_vars: string[];
+  auth_setup_hints: string[];
+}
+
 export interface SessionStartupProbeResult {
   benchmark_kind: "startup" | "assistant" | "assistant_iterm";
   provider_id: string;
@@ -270,6 +384,8 @@ export interface MobileSyncStatus {
   tailscaleIp?: string | null;
   connectedClients: number;
   sequence: number;
+  minSupportedVersion: number;
+  maxSupportedVersion: number;
 }
 
 export interface MobileSyncPairingPayload {
@@ -278,6 +394,8 @@ export interface MobileSyncPairingPayload {
   host: string;
   port: number;
   expiresAt: string;
+  minSupportedVersion: number;
+  maxSupportedVersion: number;
 }
 
 export interface MobileSyncDevice {
@@ -288,6 +406,12 @@ export interface MobileSyncDevice {
   revoked: boolean;
 }
 
+export interface MobileSyncRevokeAllResult {
+  revokedDevices: number;
+  deletedPairingCodes: number;
+  droppedConnections: number;
+}
+
 export interface MobileSyncPublishEventInput {
   eventType: string;
   payload: any;
@@ -420,13 +544,17 @@ export interface SessionTimeline {
   currentCheckpointId?: string;
   autoCheckpointEnabled: boolean;
   checkpointStrategy: CheckpointStrategy;
+  /** Seconds between checkpoints for the `time_interval` strategy */
+  checkpointIntervalSecs?: number;
+  /** Glob patterns of paths checkpoints never snapshot or restore */
+  excludeGlobs?: string[];
   totalCheckpoints: number;
 }
 
 /**
  * Strategy for automatic checkpoint creation
  */
-export type CheckpointStrategy = 'manual' | 'per_prompt' | 'per_tool_use' | 'smart';
+export type CheckpointStrategy = 'manual' | 'per_prompt' | 'per_tool_use' | 'smart' | 'time_interval';
 
 /**
  * Result of a checkpoint operation
@@ -435,6 +563,7 @@ export interface CheckpointResult {
   checkpoint: Checkpoint;
   filesProcessed: number;
   warnings: string[];
+  diff?: CheckpointDiff; // set by restores: pre-restore working tree -> checkpoint
 }
 
 /**
@@ -483,6 +612,15 @@ export interface MCPServer {
   status: ServerStatus;
 }
 
+/**
+ * Problems found in one configured MCP server; empty when it looks correct
+ */
+export interface MCPServerValidation {
+  name: string;
+  transport: string;
+  problems: string[];
+}
+
 /**
  * Server status information
  */
@@ -626,19 +764,84 @@ export const api = {
     }
   },
 
+  /**
+   * Finds sessions whose messages mention a query, most matches first
+   * @param query - Text to search for, case-insensitively
+   * @param projectId - Optional project to restrict the search to
+   */
+  async searchSessions(query: string, projectId?: string): Promise<SessionSearchResult[]> {
+    try {
+      return await apiCall<SessionSearchResult[]>('search_sessions', { query, projectId });
+    } catch (error) {
+      logger.error("ipc", "Failed to search sessions", { error });
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes a session's transcript and todo list
+   * @param projectId - The project the session belongs to
+   * @param sessionId - The session to delete
+   * @param deleteCheckpoints - Whether to also remove the session's checkpoints
+   * @returns Promise resolving to the paths that were removed
+   */
+  async deleteSession(projectId: string, sessionId: string, deleteCheckpoints: boolean): Promise<string[]> {
+    try {
+      return await apiCall<string[]>('delete_session', { projectId, sessionId, deleteCheckpoints });
+    } catch (error) {
+      logger.error("ipc", "Failed to delete session", { error });
+      throw error;
+    }
+  },
+
+  /**
+   * Moves a project's session history to a new location after its directory was moved
+   * @param oldProjectId - The project's current ID (encoded directory name)
+   * @param newPath - The project's new path on disk
+   * @returns Promise resolving to the remapped project
+   */
+  async remapProject(oldProjectId: string, newPath: string): Promise<Project> {
+    try {
+      return await apiCall<Project>('remap_project', { oldProjectId, newPath });
+    } catch (error) {
+      logger.error("ipc", "Failed to remap project", { error });
+      throw error;
+    }
+  },
+
   /**
    * Fetch list of agents from GitHub repository
+   * @param owner - Optional repository owner; defaults to opcode's agent library
+   * @param repo - Optional repository name
+   * @param path - Optional directory within the repository
    * @returns Promise resolving to list of available agents on GitHub
    */
-  async fetchGitHubAgents(): Promise<GitHubAgentFile[]> {
+  async fetchGitHubAgents(owner?: string, repo?: string, path?: string): Promise<GitHubAgentFile[]> {
     try {
-      return await apiCall<GitHubAgentFile[]>('fetch_github_agents');
+      return await apiCall<GitHubAgentFile[]>('fetch_github_agents', { owner, repo, path });
     } catch (error) {
       logger.error("ipc", "Failed to fetch GitHub agents", { error });
       throw error;
     }
   },
 
+  /**
+   * Fetch an agent library's gallery, falling back to its agent files when the
+   * library has no `gallery.json` manifest
+   * @param owner - Optional repository owner; defaults to opcode's agent library
+   * @param repo - Optional repository name
+   * @param path - Optional directory within the repository
+   * @returns Promise resolving to the gallery entries
+   */
+  async fetchAgentGallery(owner?: string, repo?: string, path?: string): Promise<AgentGalleryEntry[]> {
+    try {
+      return await apiCall<AgentGalleryEntry[]>('fetch_agent_gallery', { owner, repo, path });
+    } catch (error) {
+      logger.error("ipc", "Failed to fetch agent gallery", { error });
+      throw error;
+    }
+  },
+
   /**
    * Fetch and preview a specific agent from GitHub
    * @param downloadUrl - The download URL for the agent file
@@ -761,11 +964,12 @@ export const api = {
   /**
    * Finds all CLAUDE.md files in a project directory
    * @param projectPath - The absolute path to the project
+   * @param respectGitignore - Skip paths excluded by the project's .gitignore files
    * @returns Promise resolving to an array of CLAUDE.md files
    */
-  async findClaudeMdFiles(projectPath: string): Promise<ClaudeMdFile[]> {
+  async findClaudeMdFiles(projectPath: string, respectGitignore?: boolean): Promise<ClaudeMdFile[]> {
     try {
-      return await apiCall<ClaudeMdFile[]>("find_claude_md_files", { projectPath });
+      return await apiCall<ClaudeMdFile[]>("find_claude_md_files", { projectPath, respectGitignore });
     } catch (error) {
       logger.error("ipc", "Failed to find CLAUDE.md files", { error });
       throw error;
@@ -836,6 +1040,10 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model (provider-specific default when omitted)
    * @param hooks - Optional hooks configuration as JSON string
+   * @param firstOutputTimeoutSecs - Optional first-output timeout in seconds (0 disables it)
+   * @param extraArgs - Optional JSON array of extra CLI arguments for the provider
+   * @param binaryPath - Optional Claude CLI path to use instead of the global one
+   * @param enableNetwork - Optional network permission; without it runs have no network access
    * @returns Promise resolving to the created agent
    */
   async createAgent(
@@ -845,7 +1053,11 @@ export const api = {
     default_task?: string, 
     providerId?: string,
     model?: string,
-    hooks?: string
+    hooks?: string,
+    firstOutputTimeoutSecs?: number,
+    extraArgs?: string,
+    binaryPath?: string,
+    enableNetwork?: boolean
   ): Promise<Agent> {
     try {
       return await apiCall<Agent>('create_agent', { 
@@ -855,7 +1067,11 @@ export const api = {
         defaultTask: default_task,
         providerId,
         model,
-        hooks
+        hooks,
+        firstOutputTimeoutSecs,
+        extraArgs,
+        binaryPath,
+        enableNetwork
       });
     } catch (error) {
       logger.error("ipc", "Failed to create agent", { error });
@@ -872,6 +1088,10 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model
    * @param hooks - Optional hooks configuration as JSON string
+   * @param firstOutputTimeoutSecs - Optional first-output timeout in seconds (0 disables it)
+   * @param extraArgs - Optional JSON array of extra CLI arguments; an empty string clears them
+   * @param binaryPath - Optional Claude CLI path; an empty string goes back to the global one
+   * @param enableNetwork - Optional network permission; without it runs have no network access
    * @returns Promise resolving to the updated agent
    */
   async updateAgent(
@@ -882,7 +1102,11 @@ export const api = {
     default_task?: string, 
     providerId?: string,
     model?: string,
-    hooks?: string
+    hooks?: string,
+    firstOutputTimeoutSecs?: number,
+    extraArgs?: string,
+    binaryPath?: string,
+    enableNetwork?: boolean
   ): Promise<Agent> {
     try {
       return await apiCall<Agent>('update_agent', { 
@@ -893,7 +1117,11 @@ export const api = {
         defaultTask: default_task,
         providerId,
         model,
-        hooks
+        hooks,
+        firstOutputTimeoutSecs,
+        extraArgs,
+        binaryPath,
+        enableNetwork
       });
     } catch (error) {
       logger.error("ipc", "Failed to update agent", { error });
@@ -945,12 +1173,12 @@ export const api = {
 
   /**
    * Imports an agent from JSON data
-   * @param jsonData - The JSON string containing the agent export
-   * @returns Promise resolving to the imported agent
+   * @param jsonData - The JSON string containing a single or bulk agent export
+   * @returns Promise resolving to the imported agent, or all agents for a bulk export
    */
-  async importAgent(jsonData: string): Promise<Agent> {
+  async importAgent(jsonData: string): Promise<Agent | Agent[]> {
     try {
-      return await apiCall<Agent>('import_agent', { jsonData });
+      return await apiCall<Agent | Agent[]>('import_agent', { jsonData });
     } catch (error) {
       logger.error("ipc", "Failed to import agent", { error });
       throw error;
@@ -960,11 +1188,11 @@ export const api = {
   /**
    * Imports an agent from a file
    * @param filePath - The path to the JSON file
-   * @returns Promise resolving to the imported agent
+   * @returns Promise resolving to the imported agent, or all agents for a bulk export
    */
-  async importAgentFromFile(filePath: string): Promise<Agent> {
+  async importAgentFromFile(filePath: string): Promise<Agent | Agent[]> {
     try {
-      return await apiCall<Agent>('import_agent_from_file', { filePath });
+      return await apiCall<Agent | Agent[]>('import_agent_from_file', { filePath });
     } catch (error) {
       logger.error("ipc", "Failed to import agent from file", { error });
       throw error;
@@ -977,6 +1205,10 @@ export const api = {
    * @param projectPath - The project path to run the agent in
    * @param task - The task description
    * @param model - Optional model override
+   * @param outputFormat - Optional provider output format; 'text' skips JSON transforms
+   * @param envOverrides - Optional env vars for this run; an empty value unsets the variable
+   * @param checkpointOnComplete - Checkpoint the session if the run succeeds; defaults to the global setting
+   * @param rawOutput - Also emit untransformed stdout/stderr lines as `agent-raw:{runId}` events
    * @returns Promise resolving to the run ID when execution starts
    */
   async executeAgent(
@@ -984,7 +1216,11 @@ export const api = {
     projectPath: string,
     task: string,
     model?: string,
-    reasoningEffort?: string
+    reasoningEffort?: string,
+    outputFormat?: 'stream-json' | 'text',
+    envOverrides?: Record<string, string>,
+    checkpointOnComplete?: boolean,
+    rawOutput?: boolean
   ): Promise<number> {
     try {
       return await apiCall<number>('execute_agent', {
@@ -993,6 +1229,10 @@ export const api = {
         task,
         model,
         reasoningEffort,
+        outputFormat,
+        envOverrides: envOverrides ? JSON.stringify(envOverrides) : undefined,
+        checkpointOnComplete,
+        rawOutput,
       });
     } catch (error) {
       logger.error("ipc", "Failed to execute agent", { error });
@@ -1001,14 +1241,58 @@ export const api = {
     }
   },
 
+  /**
+   * Runs several agents on the same task, tagging the runs with a shared comparison ID
+   * @param agentIds - Agents to compare; duplicates are dropped
+   * @param projectPath - The project path to run the agents in
+   * @param task - The task description
+   * @returns Promise resolving to the comparison, with per-agent launch errors
+   */
+  async runAgentsOnTask(
+    agentIds: number[],
+    projectPath: string,
+    task: string
+  ): Promise<AgentComparison> {
+    return apiCall<AgentComparison>('run_agents_on_task', {
+      agentIds,
+      projectPath,
+      task,
+    });
+  },
+
+  /**
+   * Builds the command line an agent run would spawn, without starting it
+   * @returns Promise resolving to the shell-quoted command (cwd, binary and args)
+   */
+  async previewAgentCommand(
+    agentId: number,
+    projectPath: string,
+    task: string,
+    model?: string,
+    reasoningEffort?: string
+  ): Promise<string> {
+    return apiCall<string>('execute_agent', {
+      agentId,
+      projectPath,
+      task,
+      model,
+      reasoningEffort,
+      dryRun: true,
+    });
+  },
+
   /**
    * Lists agent runs without metrics (basic info only)
    * @param agentId - Optional agent ID to filter runs
+   * @param filters - Optional status and RFC3339 `since`/`until` bounds on creation time
    * @returns Promise resolving to an array of agent runs
    */
-  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async listAgentRuns(
+    agentId?: number,
+    filters?: { status?: string; since?: string; until?: string }
+  ): Promise<AgentRunWithMetrics[]> {
     try {
-      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+      return await apiCall<AgentRunWithMetrics[]>('list_agent_runs', { agentId, ...filters });
     } catch (error) {
       logger.error("ipc", "Failed to list agent runs", { error });
       // Return empty array instead of throwing to prevent UI crashes
@@ -1072,6 +1356,19 @@ export const api = {
     }
   },
 
+  /**
+   * Gets CPU and memory usage for each running agent session's process
+   * @returns Promise resolving to usage for processes still alive when queried
+   */
+  async getRunningSessionResourceUsage(): Promise<ProcessResourceUsage[]> {
+    try {
+      return await apiCall<ProcessResourceUsage[]>('get_running_session_resource_usage');
+    } catch (error) {
+      logger.error("ipc", "Failed to get running session resource usage", { error });
+      throw error;
+    }
+  },
+
   /**
    * Kills a running agent session
    * @param runId - The run ID to kill
@@ -1127,6 +1424,20 @@ export const api = {
     }
   },
 
+  /**
+   * Locate a Claude run's session JSONL on disk
+   * @param runId - The run ID
+   * @returns Promise resolving to the absolute path, or null for other providers or before it exists
+   */
+  async getAgentRunJsonlPath(runId: number): Promise<string | null> {
+    try {
+      return await apiCall<string | null>('get_agent_run_jsonl_path', { runId });
+    } catch (error) {
+      logger.error("ipc", "Failed to get session file path", { error });
+      throw error;
+    }
+  },
+
   /**
    * Get live output directly from process stdout buffer
    * @param runId - The run ID to get live output for
@@ -1246,6 +1557,10 @@ export const api = {
     return apiCall("list_provider_capabilities");
   },
 
+  async listSupportedProviders(): Promise<SupportedProvider[]> {
+    return apiCall("list_supported_providers");
+  },
+
   async mobileSyncGetStatus(): Promise<MobileSyncStatus> {
     return apiCall("mobile_sync_get_status");
   },
@@ -1278,6 +1593,10 @@ export const api = {
     await apiCall("mobile_sync_revoke_device", { deviceId });
   },
 
+  async mobileSyncRevokeAllDevices(deletePairingCodes?: boolean): Promise<MobileSyncRevokeAllResult> {
+    return apiCall("mobile_sync_revoke_all_devices", { deletePairingCodes });
+  },
+
   /**
    * Starts desktop-side file watcher for automatic hot refresh.
    */
@@ -1570,23 +1889,37 @@ export const api = {
 
   /**
    * Creates a checkpoint for the current session state
+   * @param emitProgress - Emit `checkpoint-progress` events while files are snapshotted
+   * @param cancelToken - Token that lets `cancelCheckpoint` abort the checkpoint
    */
   async createCheckpoint(
     sessionId: string,
     projectId: string,
     projectPath: string,
     messageIndex?: number,
-    description?: string
+    description?: string,
+    emitProgress?: boolean,
+    cancelToken?: string
   ): Promise<CheckpointResult> {
     return apiCall("create_checkpoint", {
       sessionId,
       projectId,
       projectPath,
       messageIndex,
-      description
+      description,
+      emitProgress,
+      cancelToken
     });
   },
 
+  /**
+   * Cancels a checkpoint started with the given cancel token
+   * @returns Promise resolving to whether a checkpoint was running under that token
+   */
+  async cancelCheckpoint(cancelToken: string): Promise<boolean> {
+    return apiCall("cancel_checkpoint", { cancelToken });
+  },
+
   /**
    * Restores a session to a specific checkpoint
    */
@@ -1619,6 +1952,45 @@ export const api = {
     });
   },
 
+  /**
+   * Lists the checkpoints of every session in a project, oldest first
+   */
+  async listProjectCheckpoints(projectId: string): Promise<Checkpoint[]> {
+    return apiCall("list_project_checkpoints", { projectId });
+  },
+
+  /**
+   * Exports a checkpoint to a file as a bundle that can be imported on another machine
+   */
+  async exportCheckpointBundle(
+    sessionId: string,
+    projectId: string,
+    checkpointId: string,
+    filePath: string
+  ): Promise<void> {
+    return apiCall("export_checkpoint_bundle", {
+      sessionId,
+      projectId,
+      checkpointId,
+      filePath
+    });
+  },
+
+  /**
+   * Imports the checkpoint bundle file at `filePath` as a new checkpoint of a session
+   */
+  async importCheckpointBundle(
+    filePath: string,
+    targetSessionId: string,
+    projectId: string
+  ): Promise<Checkpoint> {
+    return apiCall("import_checkpoint_bundle", {
+      filePath,
+      targetSessionId,
+      projectId
+    });
+  },
+
   /**
    * Forks a new timeline branch from a checkpoint
    */
@@ -1663,14 +2035,18 @@ export const api = {
     projectId: string,
     projectPath: string,
     autoCheckpointEnabled: boolean,
-    checkpointStrategy: CheckpointStrategy
+    checkpointStrategy: CheckpointStrategy,
+    excludeGlobs?: string[],
+    intervalSeconds?: number
   ): Promise<void> {
     return apiCall("update_checkpoint_settings", {
       sessionId,
       projectId,
       projectPath,
       autoCheckpointEnabled,
-      checkpointStrategy
+      checkpointStrategy,
+      excludeGlobs,
+      intervalSeconds
     });
   },
 
@@ -1772,6 +2148,8 @@ export const api = {
   ): Promise<{
     auto_checkpoint_enabled: boolean;
     checkpoint_strategy: CheckpointStrategy;
+    interval_seconds?: number | null;
+    exclude_globs: string[];
     total_checkpoints: number;
     current_checkpoint_id?: string;
   }> {
@@ -1925,6 +2303,20 @@ export const api = {
     }
   },
 
+  /**
+   * Validates every MCP server in a scope without starting or connecting to them
+   * @param scope - "local", "project" or "user"
+   * @param projectPath - Project the "local" and "project" scopes belong to
+   */
+  async mcpValidateAll(scope: string, projectPath?: string): Promise<MCPServerValidation[]> {
+    try {
+      return await apiCall<MCPServerValidation[]>("mcp_validate_all", { scope, projectPath });
+    } catch (error) {
+      logger.error("ipc", "Failed to validate MCP servers", { error });
+      throw error;
+    }
+  },
+
   /**
    * Resets project-scoped server approval choices
    */
@@ -1989,11 +2381,11 @@ export const api = {
   /**
    * Set the Claude binary path in settings
    * @param path - The absolute path to the Claude binary
-   * @returns Promise resolving when the path is saved
+   * @returns Promise resolving to the validation; the path is only saved when `ok`
    */
-  async setClaudeBinaryPath(path: string): Promise<void> {
+  async setClaudeBinaryPath(path: string): Promise<BinaryPathValidation> {
     try {
-      return await apiCall<void>("set_claude_binary_path", { path });
+      return await apiCall<BinaryPathValidation>("set_claude_binary_path", { path });
     } catch (error) {
       logger.error("ipc", "Failed to set Claude binary path", { error });
       throw error;
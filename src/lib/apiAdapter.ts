@@ -63,6 +63,7 @@ interface ApiResponse<T> {
   success: boolean;
   data?: T;
   error?: string;
+  code?: string; // OpcodeError code, e.g. 'not_found' or 'provider_unavailable'
 }
 
 /**
@@ -118,7 +119,9 @@ async function restApiCall<T>(endpoint: string, params?: any): Promise<T> {
     });
 
     if (!response.ok) {
-      throw new Error(`HTTP error! status: ${response.status}`);
+      // Structured errors carry their message in the body
+      const failure: ApiResponse<T> | null = await response.json().catch(() => null);
+      throw new Error(failure?.error || `HTTP error! status: ${response.status}`);
     }
 
     const result: ApiResponse<T> = await response.json();
@@ -448,14 +451,19 @@ async function handleStreamingCommand<T>(command: string, params?: any): Promise
         } else if (message.type === 'error') {
           logger.debug('ipc', '[TRACE] Error message', { message });
           maybeUpdateActiveSessionId(message);
-          
+
+          // provider_unavailable errors carry the command that installs the CLI
+          const errorMessage = message.install_hint
+            ? `${message.message} Install it with: ${message.install_hint}`
+            : message.message || 'Unknown error';
+
           dispatchProviderSessionEvent(
             'provider-session-error',
-            message.message || 'Unknown error',
+            errorMessage,
             activeSessionId
           );
-          
-          settleReject(new Error(message.message || 'Unknown error'));
+
+          settleReject(new Error(errorMessage));
         } else {
           logger.debug('ipc', `[TRACE] Unknown message type: ${message.type}`);
         }